@@ -58,6 +58,12 @@ fi
 cp -f $MODPATH/README_CN.md $DIR/doc_cn.md
 cp -f $MODPATH/README_EN.md $DIR/doc_en.md
 
+# The vulkan loader only takes debug layers from here, and only on ro.debuggable builds
+mkdir -p /data/local/debug/vulkan
+cp -f $MODPATH/vulkan/libVkLayer_fas_rs.so /data/local/debug/vulkan/
+chmod 0755 /data/local/debug/vulkan
+chmod 0644 /data/local/debug/vulkan/libVkLayer_fas_rs.so
+
 sh $MODPATH/vtools/init_vtools.sh $(realpath $MODPATH/module.prop)
 
 set_perm_recursive $MODPATH 0 0 0755 0644
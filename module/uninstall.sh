@@ -27,4 +27,8 @@ DIR=/sdcard/Android/fas-rs
 	rm -f /data/powercfg.json
 	rm -f /data/powercfg.sh
 	rm -rf /data/adb/fas_rs
+	rm -f /data/local/debug/vulkan/libVkLayer_fas_rs.so
+	settings delete global enable_gpu_debug_layers
+	settings delete global gpu_debug_app
+	settings delete global gpu_debug_layers
 } & # do not block boot
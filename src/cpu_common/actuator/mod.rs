@@ -0,0 +1,203 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+mod msm_performance;
+mod ppm;
+
+use std::{
+    ffi::{CStr, CString, c_void},
+    fmt::{self, Debug, Formatter},
+    fs, mem,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, bail};
+use log::{debug, error, info};
+
+use super::cpu_info::Info;
+use crate::file_handler;
+use msm_performance::MsmPerformanceActuator;
+use ppm::PpmActuator;
+
+const ACTUATORS_PATH: &str = "/data/adb/fas_rs/actuators";
+const ACTUATOR_API_VERSION: u32 = 1;
+
+// Plugins are plain cdylibs exporting a C ABI:
+//
+// struct FasFreqTarget { int32_t policy; int64_t freq; };
+// uint32_t fas_actuator_api_version(void);
+// void fas_actuator_apply(const struct FasFreqTarget *targets, size_t len);
+// void fas_actuator_reset(void);
+//
+// Observe-only, ignored, dormant and mirrored policies are left out of the targets, a
+// plugin should release the ones missing
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FreqTarget {
+    pub policy: i32,
+    pub freq: i64,
+}
+
+pub trait Actuator: Debug + Send {
+    fn apply(&mut self, targets: &[FreqTarget]);
+    fn reset(&mut self);
+}
+
+type VersionFn = unsafe extern "C" fn() -> u32;
+type ApplyFn = unsafe extern "C" fn(*const FreqTarget, usize);
+type ResetFn = unsafe extern "C" fn();
+
+struct PluginActuator {
+    path: PathBuf,
+    handle: *mut c_void,
+    apply: ApplyFn,
+    reset: ResetFn,
+}
+
+// The library handle is only ever used from the thread owning the Controller.
+unsafe impl Send for PluginActuator {}
+
+impl Debug for PluginActuator {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("PluginActuator")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PluginActuator {
+    fn load(path: &Path) -> Result<Self> {
+        let path_c = CString::new(path.as_os_str().as_bytes())?;
+        let handle = unsafe { libc::dlopen(path_c.as_ptr(), libc::RTLD_NOW) };
+        if handle.is_null() {
+            bail!("dlopen failed: {}", dl_error());
+        }
+
+        let load_symbols = || -> Result<(VersionFn, ApplyFn, ResetFn)> {
+            unsafe {
+                Ok((
+                    mem::transmute::<*mut c_void, VersionFn>(symbol(
+                        handle,
+                        c"fas_actuator_api_version",
+                    )?),
+                    mem::transmute::<*mut c_void, ApplyFn>(symbol(handle, c"fas_actuator_apply")?),
+                    mem::transmute::<*mut c_void, ResetFn>(symbol(handle, c"fas_actuator_reset")?),
+                ))
+            }
+        };
+
+        let (version, apply, reset) = match load_symbols() {
+            Ok(symbols) => symbols,
+            Err(e) => {
+                unsafe { libc::dlclose(handle) };
+                return Err(e);
+            }
+        };
+
+        let version = unsafe { version() };
+        if version != ACTUATOR_API_VERSION {
+            unsafe { libc::dlclose(handle) };
+            bail!("unsupported actuator api version {version}, expected {ACTUATOR_API_VERSION}");
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            handle,
+            apply,
+            reset,
+        })
+    }
+}
+
+impl Actuator for PluginActuator {
+    fn apply(&mut self, targets: &[FreqTarget]) {
+        if file_handler::dry_run() {
+            debug!("dry run: {} <- {targets:?}", self.path.display());
+            return;
+        }
+        unsafe { (self.apply)(targets.as_ptr(), targets.len()) };
+    }
+
+    fn reset(&mut self) {
+        if file_handler::dry_run() {
+            debug!("dry run: {} reset", self.path.display());
+            return;
+        }
+        unsafe { (self.reset)() };
+    }
+}
+
+impl Drop for PluginActuator {
+    fn drop(&mut self) {
+        unsafe { libc::dlclose(self.handle) };
+    }
+}
+
+unsafe fn symbol(handle: *mut c_void, name: &CStr) -> Result<*mut c_void> {
+    let symbol = unsafe { libc::dlsym(handle, name.as_ptr()) };
+    if symbol.is_null() {
+        bail!("missing symbol {}", name.to_string_lossy());
+    }
+    Ok(symbol)
+}
+
+fn dl_error() -> String {
+    let error = unsafe { libc::dlerror() };
+    if error.is_null() {
+        String::from("unknown error")
+    } else {
+        unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+pub fn load_actuators() -> Vec<Box<dyn Actuator>> {
+    let _ = fs::create_dir_all(ACTUATORS_PATH);
+    let Ok(entries) = fs::read_dir(ACTUATORS_PATH) else {
+        return Vec::new();
+    };
+
+    let mut actuators: Vec<Box<dyn Actuator>> = Vec::new();
+    for path in entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "so"))
+    {
+        match PluginActuator::load(&path) {
+            Ok(actuator) => {
+                info!("Actuator plugin loaded: {}", path.display());
+                actuators.push(Box::new(actuator));
+            }
+            Err(e) => error!("Failed to load actuator plugin {}: {e:?}", path.display()),
+        }
+    }
+
+    actuators
+}
+
+// Vendor frequency interfaces which override cpufreq limits, picked when their nodes exist
+pub fn builtin_actuators(cpu_infos: &[Info]) -> Vec<Box<dyn Actuator>> {
+    let mut actuators: Vec<Box<dyn Actuator>> = Vec::new();
+    if let Some(actuator) = PpmActuator::detect(cpu_infos) {
+        actuators.push(Box::new(actuator));
+    }
+    if let Some(actuator) = MsmPerformanceActuator::detect(cpu_infos) {
+        actuators.push(Box::new(actuator));
+    }
+    actuators
+}
@@ -0,0 +1,109 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use log::{info, warn};
+
+use super::{Actuator, FreqTarget};
+use crate::{cpu_common::cpu_info::Info, file_handler, snapshot};
+
+const CPU_MAX_FREQ: &str = "/sys/module/msm_performance/parameters/cpu_max_freq";
+
+// msm_performance turns "<cpu>:<freq>" pairs into freq QoS requests, which the kernel
+// aggregates with the ones placed by the vendor perf HAL instead of one overwriting the other
+#[derive(Debug)]
+pub struct MsmPerformanceActuator {
+    policies: Vec<Policy>,
+    last_written: Option<String>,
+}
+
+#[derive(Debug)]
+struct Policy {
+    policy: i32,
+    cores: Vec<usize>,
+    max_freq: isize,
+}
+
+impl MsmPerformanceActuator {
+    pub fn detect(cpu_infos: &[Info]) -> Option<Self> {
+        if !Path::new(CPU_MAX_FREQ).exists() {
+            return None;
+        }
+
+        let policies = cpu_infos
+            .iter()
+            .map(|cpu| Policy {
+                policy: cpu.policy,
+                cores: cpu.cores().to_vec(),
+                max_freq: cpu.freqs.last().copied().unwrap_or_default(),
+            })
+            .collect();
+
+        info!("msm_performance detected, limiting frequencies through freq QoS");
+        Some(Self {
+            policies,
+            last_written: None,
+        })
+    }
+
+    fn write(&mut self, value: String) {
+        if self.last_written.as_ref() == Some(&value) {
+            return;
+        }
+
+        snapshot::record(CPU_MAX_FREQ);
+        match file_handler::write_direct(CPU_MAX_FREQ, &value) {
+            Ok(()) => self.last_written = Some(value),
+            Err(e) => warn!("Failed to write {CPU_MAX_FREQ}: {e}"),
+        }
+    }
+
+    fn format<F>(&self, freq_of: F) -> String
+    where
+        F: Fn(&Policy) -> i64,
+    {
+        self.policies
+            .iter()
+            .flat_map(|policy| {
+                let freq = freq_of(policy);
+                policy
+                    .cores
+                    .iter()
+                    .map(move |core| format!("{core}:{freq}"))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Actuator for MsmPerformanceActuator {
+    fn apply(&mut self, targets: &[FreqTarget]) {
+        let value = self.format(|policy| {
+            targets
+                .iter()
+                .find(|target| target.policy == policy.policy)
+                .map_or(policy.max_freq as i64, |target| target.freq)
+        });
+        self.write(value);
+    }
+
+    fn reset(&mut self) {
+        let value = self.format(|policy| policy.max_freq as i64);
+        self.write(value);
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs, path::Path};
+
+use log::{info, warn};
+
+use super::{Actuator, FreqTarget};
+use crate::{cpu_common::cpu_info::Info, file_handler, snapshot};
+
+const PPM_PATH: &str = "/proc/ppm";
+const FIX_FREQ_IDX: &str = "/proc/ppm/policy/ut_fix_freq_idx";
+const HARD_USERLIMIT_MAX: &str = "/proc/ppm/policy/hard_userlimit_max_cpu_freq";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PpmNode {
+    // "<cluster0 idx> <cluster1 idx> ...", -1 releases a cluster
+    FixFreqIdx,
+    // "<cluster> <freq>" per write, -1 releases it
+    HardUserLimit,
+}
+
+// On MediaTek kernels PPM overrides scaling_max_freq, so the limits have to go through it.
+// PPM clusters follow the order of the cpufreq policies.
+#[derive(Debug)]
+pub struct PpmActuator {
+    node: PpmNode,
+    clusters: Vec<Cluster>,
+    last_written: Option<String>,
+}
+
+#[derive(Debug)]
+struct Cluster {
+    policy: i32,
+    // Index 0 is the highest frequency, as PPM counts
+    freqs: Vec<isize>,
+}
+
+impl PpmActuator {
+    pub fn detect(cpu_infos: &[Info]) -> Option<Self> {
+        let node = if Path::new(FIX_FREQ_IDX).exists() {
+            PpmNode::FixFreqIdx
+        } else if Path::new(HARD_USERLIMIT_MAX).exists() {
+            PpmNode::HardUserLimit
+        } else {
+            return None;
+        };
+
+        let mut policies: Vec<_> = cpu_infos.iter().collect();
+        policies.sort_by_key(|cpu| cpu.policy);
+        let clusters = policies
+            .into_iter()
+            .enumerate()
+            .map(|(cluster, cpu)| Cluster {
+                policy: cpu.policy,
+                freqs: Self::dvfs_table(cluster).unwrap_or_else(|| {
+                    let mut freqs = cpu.freqs.clone();
+                    freqs.reverse();
+                    freqs
+                }),
+            })
+            .collect();
+
+        info!("MediaTek PPM detected, limiting frequencies through {node:?}");
+        Some(Self {
+            node,
+            clusters,
+            last_written: None,
+        })
+    }
+
+    fn dvfs_table(cluster: usize) -> Option<Vec<isize>> {
+        let table =
+            fs::read_to_string(format!("{PPM_PATH}/dump_cluster_{cluster}_dvfs_table")).ok()?;
+        let mut freqs: Vec<isize> = table
+            .split_whitespace()
+            .filter_map(|freq| freq.parse().ok())
+            .collect();
+        freqs.sort_unstable_by(|a, b| b.cmp(a));
+        (!freqs.is_empty()).then_some(freqs)
+    }
+
+    // The highest table entry not above the target, or the lowest one
+    fn freq_idx(cluster: &Cluster, freq: i64) -> usize {
+        cluster
+            .freqs
+            .iter()
+            .position(|f| *f as i64 <= freq)
+            .unwrap_or_else(|| cluster.freqs.len().saturating_sub(1))
+    }
+
+    fn write(&mut self, value: String) {
+        if self.last_written.as_ref() == Some(&value) {
+            return;
+        }
+
+        let path = match self.node {
+            PpmNode::FixFreqIdx => FIX_FREQ_IDX,
+            PpmNode::HardUserLimit => HARD_USERLIMIT_MAX,
+        };
+        snapshot::record(path);
+        let result = match self.node {
+            PpmNode::FixFreqIdx => file_handler::write_direct(path, &value),
+            PpmNode::HardUserLimit => value
+                .lines()
+                .try_for_each(|line| file_handler::write_direct(path, line)),
+        };
+
+        match result {
+            Ok(()) => self.last_written = Some(value),
+            Err(e) => warn!("Failed to write {path}: {e}"),
+        }
+    }
+}
+
+impl Actuator for PpmActuator {
+    fn apply(&mut self, targets: &[FreqTarget]) {
+        let values: Vec<_> = self
+            .clusters
+            .iter()
+            .enumerate()
+            .map(|(index, cluster)| {
+                let target = targets
+                    .iter()
+                    .find(|target| target.policy == cluster.policy);
+                match (self.node, target) {
+                    (PpmNode::FixFreqIdx, Some(target)) => {
+                        Self::freq_idx(cluster, target.freq).to_string()
+                    }
+                    (PpmNode::HardUserLimit, Some(target)) => {
+                        let freq = cluster.freqs[Self::freq_idx(cluster, target.freq)];
+                        format!("{index} {freq}")
+                    }
+                    (PpmNode::FixFreqIdx, None) => String::from("-1"),
+                    (PpmNode::HardUserLimit, None) => format!("{index} -1"),
+                }
+            })
+            .collect();
+
+        let separator = match self.node {
+            PpmNode::FixFreqIdx => " ",
+            PpmNode::HardUserLimit => "\n",
+        };
+        self.write(values.join(separator));
+    }
+
+    fn reset(&mut self) {
+        let value = match self.node {
+            PpmNode::FixFreqIdx => vec!["-1"; self.clusters.len()].join(" "),
+            PpmNode::HardUserLimit => (0..self.clusters.len())
+                .map(|index| format!("{index} -1"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        self.write(value);
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use std::{fs, path::Path};
+
+use log::info;
+
+use super::cpu_info::Info;
+use crate::sysfs;
+
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+// The scheduler's unit, the biggest core at its top frequency
+pub const CAPACITY_SCALE: f64 = 1024.0;
+
+// Where the capacities came from, the first one every policy has wins
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    // cpu_capacity, already normalized by the kernel
+    Kernel,
+    // capacity-dmips-mhz of the device tree times the top frequency
+    Dmips,
+    // Only the top frequencies, as if all cores did the same work per cycle
+    Frequency,
+}
+
+// Sets the capacity of every policy at its top frequency
+pub fn assign(root: &Path, cpu_infos: &mut [Info]) {
+    let cpu_dir = sysfs::resolve(root, CPU_DIR);
+    let first_core = |cpu: &Info| cpu.cores().first().copied();
+
+    let kernel: Option<Vec<f64>> = cpu_infos
+        .iter()
+        .map(|cpu| read_u32(&cpu_dir.join(format!("cpu{}/cpu_capacity", first_core(cpu)?))))
+        .map(|capacity| capacity.map(f64::from))
+        .collect();
+    let dmips: Option<Vec<f64>> = cpu_infos
+        .iter()
+        .map(|cpu| {
+            let path = cpu_dir.join(format!(
+                "cpu{}/of_node/capacity-dmips-mhz",
+                first_core(cpu)?
+            ));
+            read_be_u32(&path).map(|dmips| f64::from(dmips) * cpu.capacity_freq() as f64)
+        })
+        .collect();
+
+    let (source, raw) = match (kernel, dmips) {
+        (Some(kernel), _) => (Source::Kernel, kernel),
+        (None, Some(dmips)) => (Source::Dmips, dmips),
+        (None, None) => (
+            Source::Frequency,
+            cpu_infos
+                .iter()
+                .map(|cpu| cpu.capacity_freq() as f64)
+                .collect(),
+        ),
+    };
+
+    let max = raw.iter().copied().fold(0.0, f64::max);
+    for (cpu, raw) in cpu_infos.iter_mut().zip(raw) {
+        let capacity = if max > 0.0 {
+            raw / max * CAPACITY_SCALE
+        } else {
+            CAPACITY_SCALE
+        };
+        info!(
+            "CPU Policy{}: capacity {capacity:.0} ({source:?})",
+            cpu.policy
+        );
+        cpu.capacity = capacity;
+    }
+}
+
+fn read_u32(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+// Device tree properties are big endian cells
+fn read_be_u32(path: &Path) -> Option<u32> {
+    let bytes = fs::read(path).ok()?;
+    Some(u32::from_be_bytes(bytes.get(..4)?.try_into().ok()?))
+}
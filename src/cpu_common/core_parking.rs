@@ -0,0 +1,143 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+use super::load_sampler::LoadSampler;
+use crate::{file_handler, snapshot, sysfs};
+
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+const SAMPLE_TIME: Duration = Duration::from_secs(1);
+// Average load of the online little cores has to stay below this for PARK_TIME to park one
+const PARK_UTIL: f64 = 0.3;
+const PARK_TIME: Duration = Duration::from_secs(5);
+// Above this every parked core comes back at once
+const UNPARK_UTIL: f64 = 0.7;
+// Parking more would leave the little cluster too thin for background work
+const MAX_PARKED: usize = 2;
+
+// Offlines little cores while a light scene leaves them idle and brings them back under
+// load, games rarely need all of them on 8-core socs
+#[derive(Debug)]
+pub struct CoreParking {
+    cpu_dir: PathBuf,
+    sampler: LoadSampler,
+    cores: Vec<usize>,
+    max: usize,
+    parked: Vec<usize>,
+    sample_timer: Instant,
+    low_timer: Option<Instant>,
+}
+
+impl CoreParking {
+    // cores are the little cluster's
+    pub fn new(root: &Path, cores: &[usize]) -> Self {
+        Self {
+            cpu_dir: sysfs::resolve(root, CPU_DIR),
+            sampler: LoadSampler::new(root),
+            cores: cores.to_vec(),
+            max: 0,
+            parked: Vec::new(),
+            sample_timer: Instant::now(),
+            low_timer: None,
+        }
+    }
+
+    pub fn set_max(&mut self, max: u32) {
+        // cpu0 can't go offline and one core has to stay for background work
+        self.max = (max as usize)
+            .min(MAX_PARKED)
+            .min(self.cores.len().saturating_sub(2));
+        while self.parked.len() > self.max {
+            if let Some(core) = self.parked.pop() {
+                self.set_online(core, true);
+            }
+        }
+    }
+
+    pub fn update(&mut self) {
+        if self.max == 0 || self.sample_timer.elapsed() < SAMPLE_TIME {
+            return;
+        }
+        self.sample_timer = Instant::now();
+
+        let Some(util) = self.sample_util() else {
+            return;
+        };
+
+        if util > UNPARK_UTIL {
+            self.low_timer = None;
+            if !self.parked.is_empty() {
+                info!("Little cores busy ({util:.2}), unparking {:?}", self.parked);
+                self.reset();
+            }
+        } else if util < PARK_UTIL && self.parked.len() < self.max {
+            let low_since = *self.low_timer.get_or_insert_with(Instant::now);
+            if low_since.elapsed() < PARK_TIME {
+                return;
+            }
+
+            // Highest numbered first, lower ones tend to take interrupts
+            if let Some(core) = self
+                .cores
+                .iter()
+                .rev()
+                .copied()
+                .find(|core| *core != 0 && !self.parked.contains(core))
+                && self.set_online(core, false)
+            {
+                info!("Little cores idle ({util:.2}), parked cpu{core}");
+                self.parked.push(core);
+                self.low_timer = None;
+            }
+        } else {
+            self.low_timer = None;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for core in std::mem::take(&mut self.parked) {
+            self.set_online(core, true);
+        }
+        self.sampler.reset();
+        self.low_timer = None;
+    }
+
+    fn set_online(&self, core: usize, online: bool) -> bool {
+        let node = self.cpu_dir.join(format!("cpu{core}")).join("online");
+        snapshot::record(&node);
+        file_handler::write_direct(node, if online { "1" } else { "0" }).is_ok()
+    }
+
+    // Average load of the online little cores since the last sample, offline ones aren't in
+    // /proc/stat
+    fn sample_util(&mut self) -> Option<f64> {
+        let loads = self.sampler.sample()?;
+        let loads: Vec<f64> = self
+            .cores
+            .iter()
+            .filter_map(|core| loads.get(core).copied())
+            .collect();
+
+        (!loads.is_empty()).then(|| loads.iter().sum::<f64>() / loads.len() as f64)
+    }
+}
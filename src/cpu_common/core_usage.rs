@@ -0,0 +1,113 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use std::{collections::HashMap, path::Path};
+
+use nix::sched::CpuSet;
+
+use super::load_sampler::LoadSampler;
+
+// Decayed per-core load. A core stays critical while it keeps working even when the top
+// threads snapshot briefly misses it, so policies don't flap between the clamped and relaxed
+// write paths
+#[derive(Debug)]
+pub struct CoreUsage {
+    sampler: LoadSampler,
+    usage: HashMap<usize, f64>,
+    // 0.0 disables it
+    threshold: f64,
+    // Share of the previous usage kept on every sample
+    decay: f64,
+    // Whether non-critical policies get a floor weighted by their usage
+    weighted: bool,
+}
+
+impl CoreUsage {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            sampler: LoadSampler::new(root),
+            usage: HashMap::new(),
+            threshold: 0.0,
+            decay: 0.0,
+            weighted: false,
+        }
+    }
+
+    pub fn set(&mut self, threshold: f64, decay: f64) {
+        self.threshold = threshold.clamp(0.0, 1.0);
+        self.decay = decay.clamp(0.0, 0.99);
+        if !self.enabled() {
+            self.reset();
+        }
+    }
+
+    pub fn set_weighted(&mut self, weighted: bool) {
+        self.weighted = weighted;
+        if !self.enabled() {
+            self.reset();
+        }
+    }
+
+    pub const fn weighted(&self) -> bool {
+        self.weighted
+    }
+
+    fn enabled(&self) -> bool {
+        self.threshold > 0.0 || self.weighted
+    }
+
+    pub fn reset(&mut self) {
+        self.sampler.reset();
+        self.usage.clear();
+    }
+
+    pub fn update(&mut self) {
+        if !self.enabled() {
+            return;
+        }
+
+        if let Some(loads) = self.sampler.sample() {
+            for (core, load) in loads {
+                let usage = self.usage.entry(core).or_insert(load);
+                *usage = (*usage).mul_add(self.decay, load * (1.0 - self.decay));
+            }
+        }
+    }
+
+    // The top used cores plus every core whose usage stays above the threshold
+    pub fn critical_cores(&self, top_used_cores: CpuSet) -> CpuSet {
+        if self.threshold <= 0.0 {
+            return top_used_cores;
+        }
+
+        let mut critical_cores = top_used_cores;
+        for (core, usage) in &self.usage {
+            if *usage >= self.threshold {
+                let _ = critical_cores.set(*core);
+            }
+        }
+        critical_cores
+    }
+
+    // Usage of the busiest of the cores, 0.0 - 1.0
+    pub fn usage(&self, cores: &[usize]) -> f64 {
+        cores
+            .iter()
+            .filter_map(|core| self.usage.get(core))
+            .copied()
+            .fold(0.0, f64::max)
+    }
+}
@@ -16,32 +16,147 @@
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
 use std::{
-    fs,
+    fs, io,
+    os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
-    sync::atomic::Ordering,
+    sync::atomic::{AtomicIsize, Ordering},
     time::{Duration, Instant},
 };
 
-use anyhow::{Context, Result};
-use log::warn;
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
 use nix::sched::CpuSet;
+use serde::Serialize;
 
-use super::IGNORE_MAP;
-use crate::file_handler::FileHandler;
+use super::{IGNORE_MAP, capacity::CAPACITY_SCALE};
+use crate::{
+    file_handler::{self, FileHandler},
+    framework::{
+        config::{ClusterRole, PolicyTopology},
+        control::STATUS,
+    },
+    quirks::{self, WriteOrder},
+    sysfs::{self, PreadNode},
+    watchdog,
+};
+
+const OBSERVE_REPORT_TIME: Duration = Duration::from_secs(10);
+// How often a policy checks whether its cpus went offline or came back
+const DORMANT_PROBE_TIME: Duration = Duration::from_secs(1);
+// Verification counts are halved once this many are in, so old results fade out
+const VERIFY_WINDOW: u32 = 50;
+// A policy matching fewer of at least VERIFY_MIN_CHECKS verifications than this is taken as
+// owned by something else
+const VERIFY_MIN_CHECKS: u32 = 20;
+const CHRONIC_SUCCESS_RATIO: f64 = 0.3;
+// An automatically ignored policy is taken back after this, or when the session ends
+pub const AUTO_IGNORE_COOLDOWN: Duration = Duration::from_secs(600);
+
+#[derive(Debug)]
+struct ObserveStats {
+    fas_freq_sum: isize,
+    observed_freq_sum: isize,
+    samples: isize,
+    timer: Instant,
+}
+
+impl ObserveStats {
+    fn new() -> Self {
+        Self {
+            fas_freq_sum: 0,
+            observed_freq_sum: 0,
+            samples: 0,
+            timer: Instant::now(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PolicyJson<'a> {
+    policy: i32,
+    cpus: &'a [usize],
+    role: Option<String>,
+    capacity: f64,
+    dormant: bool,
+    cur_fas_freq: isize,
+    cur_freq: isize,
+    mirror_of: Option<i32>,
+    observe_only: bool,
+    freq_floor: Option<isize>,
+    freq_ceiling: Option<isize>,
+    slew_limit: Option<isize>,
+    usage_share: f64,
+    boost_enabled: bool,
+    verify: VerifyJson,
+}
+
+// The interval is in milliseconds
+#[derive(Debug, Serialize)]
+struct VerifyJson {
+    enabled: bool,
+    expected_freq: Option<isize>,
+    interval: f64,
+    tolerance: f64,
+    success: Option<f64>,
+}
 
 #[derive(Debug)]
 pub struct Info {
     pub policy: i32,
     path: PathBuf,
     affected_cpus: Vec<usize>,
+    // Only known when set in policy_topology, sysfs doesn't tell
+    pub role: Option<ClusterRole>,
     pub cur_fas_freq: isize,
     pub freqs: Vec<isize>,
+    // Bins above the regular table, only granted while the hardware allows it
+    boost_freqs: Vec<isize>,
+    boost_enabled: bool,
     verify_freq: Option<isize>,
     verify_timer: Instant,
+    verify_enabled: bool,
+    verify_interval: Duration,
+    // Percentage of the expected frequency the actual one may be off by
+    verify_tolerance: f64,
+    verify_checks: u32,
+    verify_matches: u32,
+    // Chronically interfered policies are handed to the system rather than fought over
+    pub verify_auto_ignore: bool,
+    // When verification handed the policy to the system
+    pub auto_ignored: Option<Instant>,
+    pub observe_only: bool,
+    observe_stats: ObserveStats,
+    pub lock_on_interference: bool,
+    // Never written below this, clamping some little clusters to their minimum causes input
+    // and audio latency
+    pub freq_floor: Option<isize>,
+    // Keeps e.g. the prime core off its thermally unsustainable top bins, wins over the floor
+    pub freq_ceiling: Option<isize>,
+    // Most the written frequency may move per update, smooths governors that ripple when
+    // requests jump
+    pub slew_limit: Option<isize>,
+    // Set when the frequency table was synthesized with this step
+    synthesized_step: Option<isize>,
+    // Recent usage relative to the busiest policy, when not critical the ceiling is scaled
+    // this far from the floor toward the written frequency
+    pub usage_share: f64,
+    // All cpus of the policy are offline, nothing is written or verified until one is back
+    dormant: bool,
+    dormant_timer: Instant,
+    // Kept open, both are read every verification or probe cycle
+    cur_freq_node: PreadNode,
+    online_nodes: Vec<PreadNode>,
+    // Last successful read of scaling_cur_freq, stands in while the node fails to read
+    last_freq: AtomicIsize,
+    // At capacity_freq, relative to the biggest core's 1024
+    pub capacity: f64,
+    capacity_freq: isize,
+    // Shares its clock with this policy, which writes for both
+    pub mirror_of: Option<i32>,
 }
 
 impl Info {
-    pub fn new<P>(path: P) -> Result<Self>
+    pub fn new<P>(path: P, freq_step: isize, topology: Option<&PolicyTopology>) -> Result<Self>
     where
         P: AsRef<Path>,
     {
@@ -55,67 +170,344 @@ impl Info {
             .parse::<i32>()
             .context("Failed to parse policy")?;
 
-        let freqs_content = fs::read_to_string(path.join("scaling_available_frequencies"))
-            .context("Failed to read frequencies")?;
-        let mut freqs: Vec<isize> = freqs_content
-            .split_whitespace()
-            .map(|f| f.parse::<isize>().context("Failed to parse frequency"))
-            .collect::<Result<_>>()?;
+        let mut synthesized_step = None;
+        let mut freqs = match Self::available_freqs(&path) {
+            Ok(freqs) => freqs,
+            Err(e) => {
+                warn!(
+                    "CPU Policy{policy}: {e:#}, synthesizing frequency table with {freq_step}khz step"
+                );
+                synthesized_step = Some(freq_step);
+                Self::synthesize_freqs(&path, freq_step)?
+            }
+        };
         freqs.sort_unstable();
+        let boost_freqs = Self::boost_freqs(&path, &freqs);
+        if !boost_freqs.is_empty() {
+            info!("CPU Policy{policy}: boost frequencies: {boost_freqs:?}");
+        }
 
-        let affected_cpus = fs::read_to_string(path.join("affected_cpus"))
-            .context("Failed to read affected_cpus")?
-            .split_whitespace()
-            .map(|core| {
-                core.parse::<usize>()
-                    .context("Failed to parse core")
-                    .unwrap()
+        let affected_cpus = if let Some(cpus) = topology.and_then(|rule| rule.cpus.clone()) {
+            info!("CPU Policy{policy}: cpus overridden to {cpus:?}");
+            cpus
+        } else {
+            sysfs::read_retrying(path.join("affected_cpus"))
+                .context("Failed to read affected_cpus")?
+                .split_whitespace()
+                .map(|core| {
+                    core.parse::<usize>()
+                        .with_context(|| format!("Failed to parse core {core:?}"))
+                })
+                .collect::<Result<_>>()?
+        };
+
+        let max_freq = *freqs.last().context("No frequencies available")?;
+        let cur_freq_node = PreadNode::new(path.join("scaling_cur_freq"));
+        // affected_cpus leaves out offline cpus, a cluster offline at startup would never
+        // look dormant
+        let related_cpus =
+            read_cpus(&path.join("related_cpus")).unwrap_or_else(|| affected_cpus.clone());
+        let online_nodes = path
+            .parent()
+            .and_then(Path::parent)
+            .map(|cpu_dir| {
+                related_cpus
+                    .iter()
+                    .map(|core| PreadNode::new(cpu_dir.join(format!("cpu{core}/online"))))
+                    .collect()
             })
-            .collect();
+            .unwrap_or_default();
 
         Ok(Self {
             policy,
             path,
             affected_cpus,
-            cur_fas_freq: *freqs.last().context("No frequencies available")?,
+            role: topology.and_then(|rule| rule.role),
+            cur_fas_freq: max_freq,
             freqs,
+            boost_freqs,
+            boost_enabled: false,
             verify_freq: None,
             verify_timer: Instant::now(),
+            verify_enabled: true,
+            verify_interval: Duration::from_secs(3),
+            verify_tolerance: 0.0,
+            verify_checks: 0,
+            verify_matches: 0,
+            verify_auto_ignore: false,
+            auto_ignored: None,
+            observe_only: false,
+            observe_stats: ObserveStats::new(),
+            lock_on_interference: false,
+            freq_floor: None,
+            freq_ceiling: None,
+            slew_limit: None,
+            synthesized_step,
+            usage_share: 1.0,
+            dormant: false,
+            dormant_timer: Instant::now(),
+            cur_freq_node,
+            online_nodes,
+            last_freq: AtomicIsize::new(0),
+            capacity: CAPACITY_SCALE,
+            capacity_freq: max_freq,
+            mirror_of: None,
         })
     }
 
-    fn verify_freq(&mut self, write_freq: isize) {
-        if self.verify_timer.elapsed() >= Duration::from_secs(3) {
+    fn available_freqs(path: &Path) -> Result<Vec<isize>> {
+        let freqs: Vec<isize> = sysfs::read_retrying(path.join("scaling_available_frequencies"))
+            .context("Failed to read frequencies")?
+            .split_whitespace()
+            .map(|f| f.parse::<isize>().context("Failed to parse frequency"))
+            .collect::<Result<_>>()?;
+        if freqs.is_empty() {
+            bail!("No frequencies available");
+        }
+        Ok(freqs)
+    }
+
+    // Custom kernels without an OPP table still expose the hardware limits
+    fn synthesize_freqs(path: &Path, freq_step: isize) -> Result<Vec<isize>> {
+        let read = |name: &str| -> Result<isize> {
+            sysfs::read_retrying(path.join(name))
+                .with_context(|| format!("Failed to read {name}"))?
+                .trim()
+                .parse::<isize>()
+                .with_context(|| format!("Failed to parse {name}"))
+        };
+        let min_freq = read("cpuinfo_min_freq")?;
+        let max_freq = read("cpuinfo_max_freq")?;
+
+        let mut freqs: Vec<isize> = (min_freq..max_freq)
+            .step_by(freq_step.max(1) as usize)
+            .collect();
+        freqs.push(max_freq);
+        Ok(freqs)
+    }
+
+    // scaling_boost_frequencies, plus cpuinfo_max_freq when an overclocked kernel raises it past
+    // the advertised table
+    fn boost_freqs(path: &Path, freqs: &[isize]) -> Vec<isize> {
+        let table_max = freqs.last().copied().unwrap_or_default();
+        let mut boost_freqs: Vec<isize> =
+            fs::read_to_string(path.join("scaling_boost_frequencies"))
+                .map(|content| {
+                    content
+                        .split_whitespace()
+                        .filter_map(|freq| freq.parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default();
+        if let Some(max_freq) = fs::read_to_string(path.join("cpuinfo_max_freq"))
+            .ok()
+            .and_then(|content| content.trim().parse().ok())
+        {
+            boost_freqs.push(max_freq);
+        }
+
+        boost_freqs.retain(|freq| *freq > table_max);
+        boost_freqs.sort_unstable();
+        boost_freqs.dedup();
+        boost_freqs
+    }
+
+    // Merges the boost bins into the table, so the controller reaches them when it asks for
+    // the top frequency
+    pub fn set_boost(&mut self, enabled: bool) -> bool {
+        if self.boost_freqs.is_empty() || self.boost_enabled == enabled {
+            return false;
+        }
+
+        self.boost_enabled = enabled;
+        if enabled {
+            self.freqs.extend_from_slice(&self.boost_freqs);
+        } else {
+            self.freqs.retain(|freq| !self.boost_freqs.contains(freq));
+        }
+        self.freqs.sort_unstable();
+        info!(
+            "CPU Policy{}: boost frequencies enabled: {enabled}",
+            self.policy
+        );
+        true
+    }
+
+    fn table_max(&self) -> isize {
+        self.freqs
+            .iter()
+            .copied()
+            .filter(|freq| !self.boost_freqs.contains(freq))
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub fn set_verify(&mut self, enabled: bool, interval: Duration, tolerance: f64) {
+        self.verify_enabled = enabled;
+        self.verify_interval = interval;
+        self.verify_tolerance = tolerance;
+    }
+
+    // Returns whether something else moved the frequency away from what fas wrote
+    fn verify_freq(&mut self, write_freq: isize) -> bool {
+        let mut interfered = false;
+        if self.verify_enabled && self.verify_timer.elapsed() >= self.verify_interval {
             self.verify_timer = Instant::now();
 
-            if let Some(verify_freq) = self.verify_freq {
-                let current_freq = self.read_freq();
-                let min_acceptable_freq = self
-                    .freqs
-                    .iter()
-                    .take_while(|freq| **freq <= verify_freq)
-                    .last()
-                    .copied()
-                    .unwrap_or(verify_freq);
-                let max_acceptable_freq = self
-                    .freqs
-                    .iter()
-                    .find(|freq| **freq >= verify_freq)
-                    .copied()
-                    .unwrap_or(verify_freq);
+            // Nothing to compare against on a failed read, verified again next interval
+            if let Some(verify_freq) = self.verify_freq
+                && let Ok(current_freq) = self.read_freq().inspect_err(|e| {
+                    watchdog::error(format!("CPU Policy{}: {e:#}", self.policy));
+                })
+            {
+                // The real OPPs may sit anywhere between the synthesized entries
+                let (min_acceptable_freq, max_acceptable_freq) =
+                    if let Some(step) = self.synthesized_step {
+                        (verify_freq - step, verify_freq + step)
+                    } else {
+                        self.acceptable_range(verify_freq)
+                    };
+                // Boost bins are a request, thermal and power budgets decide whether the
+                // hardware grants them, anything from the regular top up is fine
+                let min_acceptable_freq = if self.boost_freqs.contains(&verify_freq) {
+                    min_acceptable_freq.min(self.acceptable_range(self.table_max()).0)
+                } else {
+                    min_acceptable_freq
+                };
+                let tolerance = (verify_freq as f64 * self.verify_tolerance / 100.0) as isize;
+                let (min_acceptable_freq, max_acceptable_freq) = (
+                    min_acceptable_freq - tolerance,
+                    max_acceptable_freq + tolerance,
+                );
+                // Thermal and QoS limits lower the policy max below what fas wrote, a frequency
+                // under such a cap is the kernel's doing rather than another writer's
+                if current_freq < min_acceptable_freq
+                    && self
+                        .effective_max_freq()
+                        .is_some_and(|max| max < min_acceptable_freq && current_freq <= max)
+                {
+                    self.verify_freq = Some(write_freq);
+                    return false;
+                }
                 if !(min_acceptable_freq..=max_acceptable_freq).contains(&current_freq) {
                     warn!(
                         "CPU Policy{}: Frequency control does not meet expectations! Expected: {}-{}, Actual: {}",
                         self.policy, min_acceptable_freq, max_acceptable_freq, current_freq
                     );
+                    STATUS.lock().record_verify_mismatch(format!(
+                        "[{}] policy{}: expected {min_acceptable_freq}-{max_acceptable_freq}, actual {current_freq}",
+                        chrono::Local::now().format("%H:%M:%S%.3f"),
+                        self.policy
+                    ));
+                    interfered = true;
                 }
+                self.account_verify(!interfered);
             }
         }
 
         self.verify_freq = Some(write_freq);
+        interfered
+    }
+
+    fn account_verify(&mut self, matched: bool) {
+        if self.verify_checks >= VERIFY_WINDOW {
+            self.verify_checks /= 2;
+            self.verify_matches /= 2;
+        }
+        self.verify_checks += 1;
+        self.verify_matches += u32::from(matched);
+
+        let ratio = self.verify_success();
+        STATUS
+            .lock()
+            .record_verify_success(self.policy, ratio.unwrap_or(1.0));
+
+        if self.verify_auto_ignore
+            && self.verify_checks >= VERIFY_MIN_CHECKS
+            && ratio.is_some_and(|ratio| ratio < CHRONIC_SUCCESS_RATIO)
+        {
+            warn!(
+                "CPU Policy{}: only {}/{} verifications matched, something else owns it, ignoring the policy",
+                self.policy, self.verify_matches, self.verify_checks
+            );
+            watchdog::error(format!(
+                "CPU Policy{}: chronically interfered, ignored",
+                self.policy
+            ));
+            self.verify_checks = 0;
+            self.verify_matches = 0;
+            self.auto_ignored = Some(Instant::now());
+            super::set_policy_ignored(self.policy, true);
+        }
+    }
+
+    // Share of recent verifications where the frequency matched the request
+    pub fn verify_success(&self) -> Option<f64> {
+        (self.verify_checks > 0)
+            .then(|| f64::from(self.verify_matches) / f64::from(self.verify_checks))
     }
 
-    fn ignore_write(&self) -> Result<bool> {
+    // Logs what can be told about the other writer, then makes sure the next writes go through
+    fn counter_interference(&self, file_handler: &mut FileHandler) {
+        for path in [self.max_freq_path(), self.min_freq_path()] {
+            if let Ok(metadata) = fs::metadata(&path) {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.elapsed().ok());
+                warn!(
+                    "CPU Policy{}: {} mode {:o}, modified {modified:?} ago",
+                    self.policy,
+                    path.display(),
+                    metadata.permissions().mode() & 0o777
+                );
+            }
+
+            file_handler.forget(&path);
+            if self.lock_on_interference {
+                file_handler.lock(&path);
+            }
+        }
+
+        STATUS.lock().interference = true;
+    }
+
+    fn acceptable_range(&self, verify_freq: isize) -> (isize, isize) {
+        let min_acceptable_freq = self
+            .freqs
+            .iter()
+            .take_while(|freq| **freq <= verify_freq)
+            .last()
+            .copied()
+            .unwrap_or(verify_freq);
+        let max_acceptable_freq = self
+            .freqs
+            .iter()
+            .find(|freq| **freq >= verify_freq)
+            .copied()
+            .unwrap_or(verify_freq);
+        (min_acceptable_freq, max_acceptable_freq)
+    }
+
+    fn observe(&mut self, fas_freq: isize) {
+        let observed_freq = self.cur_freq();
+        let stats = &mut self.observe_stats;
+        stats.fas_freq_sum += fas_freq;
+        stats.observed_freq_sum += observed_freq;
+        stats.samples += 1;
+
+        if stats.timer.elapsed() >= OBSERVE_REPORT_TIME {
+            info!(
+                "CPU Policy{} (observe only): fas would set {}khz on average, actual {}khz",
+                self.policy,
+                stats.fas_freq_sum / stats.samples,
+                stats.observed_freq_sum / stats.samples
+            );
+            *stats = ObserveStats::new();
+        }
+    }
+
+    pub fn ignore_write(&self) -> Result<bool> {
         Ok(IGNORE_MAP
             .get()
             .context("IGNORE_MAP not initialized")?
@@ -124,10 +516,19 @@ impl Info {
             .load(Ordering::Acquire))
     }
 
+    // Whether the last `write_freq` reached the cpufreq nodes, vendor actuators and plugins
+    // must leave every other policy alone
+    pub fn written(&self) -> bool {
+        !self.observe_only
+            && !self.dormant
+            && self.mirror_of.is_none()
+            && !self.ignore_write().unwrap_or(true)
+    }
+
     fn critical_policy(&self, top_used_cores: CpuSet) -> bool {
         self.affected_cpus
             .iter()
-            .any(|core| top_used_cores.is_set(*core).unwrap())
+            .any(|core| top_used_cores.is_set(*core).unwrap_or(false))
     }
 
     pub fn write_freq(
@@ -136,27 +537,50 @@ impl Info {
         freq: isize,
         file_handler: &mut FileHandler,
     ) -> Result<()> {
+        if self.dormant(file_handler) {
+            return Ok(());
+        }
+
         let min_freq = *self.freqs.first().context("No frequencies available")?;
         let max_freq = *self.freqs.last().context("No frequencies available")?;
+        let max_freq = self
+            .freq_ceiling
+            .map_or(max_freq, |ceiling| ceiling.clamp(min_freq, max_freq));
+        let min_freq = self
+            .freq_floor
+            .map_or(min_freq, |floor| floor.clamp(min_freq, max_freq));
 
+        let freq = self.slew_limit.map_or(freq, |limit| {
+            freq.clamp(
+                self.cur_fas_freq.saturating_sub(limit),
+                self.cur_fas_freq.saturating_add(limit),
+            )
+        });
         let adjusted_freq = freq.clamp(min_freq, max_freq);
         self.cur_fas_freq = adjusted_freq;
 
-        if !self.ignore_write()? {
+        if self.observe_only {
+            self.observe(adjusted_freq);
+        } else if self.mirror_of.is_none() && !self.ignore_write()? {
             if self.critical_policy(top_used_cores) {
-                self.verify_freq(adjusted_freq);
+                if self.verify_freq(adjusted_freq) {
+                    self.counter_interference(file_handler);
+                }
                 let adjusted_freq = adjusted_freq.to_string();
-                file_handler.write_with_workround(self.max_freq_path(), &adjusted_freq)?;
-                file_handler.write_with_workround(self.min_freq_path(), &adjusted_freq)?;
+                self.write_limits(file_handler, &adjusted_freq, &adjusted_freq, true)?;
             } else {
-                let adjusted_freq = adjusted_freq.to_string();
-                let min_freq = self
+                // Up to the next table step, the kernel would round an in-between value anyway
+                let scaled_freq = min_freq
+                    + ((adjusted_freq - min_freq).max(0) as f64 * self.usage_share) as isize;
+                let max_freq = self
                     .freqs
-                    .first()
-                    .context("No frequencies available")?
-                    .to_string();
-                file_handler.write_with_workround(self.min_freq_path(), &min_freq)?;
-                file_handler.write_with_workround(self.max_freq_path(), &adjusted_freq)?;
+                    .iter()
+                    .copied()
+                    .find(|freq| *freq >= scaled_freq)
+                    .map_or(adjusted_freq, |freq| freq.min(adjusted_freq));
+                let max_freq = max_freq.to_string();
+                let min_freq = min_freq.to_string();
+                self.write_limits(file_handler, &min_freq, &max_freq, false)?;
             }
         }
 
@@ -175,20 +599,276 @@ impl Info {
             .context("No frequencies available")?
             .to_string();
         self.verify_freq = None;
+        self.observe_stats = ObserveStats::new();
+        if self.mirror_of.is_some() || self.dormant(file_handler) {
+            return Ok(());
+        }
 
-        file_handler.write_with_workround(self.max_freq_path(), &max_freq)?;
-        file_handler.write_with_workround(self.min_freq_path(), &min_freq)?;
+        self.write_limits(file_handler, &min_freq, &max_freq, true)
+    }
+
+    // `max_first` is the order that works without quirks, devices may force another one
+    fn write_limits(
+        &self,
+        file_handler: &mut FileHandler,
+        min_freq: &str,
+        max_freq: &str,
+        max_first: bool,
+    ) -> Result<()> {
+        let quirks = quirks::get();
+        let max_first = match quirks.write_order(self.policy) {
+            WriteOrder::Auto => max_first,
+            WriteOrder::MinFirst => false,
+            WriteOrder::MaxFirst => true,
+        };
+        let delay = quirks.write_delay(self.policy);
+
+        if max_first {
+            file_handler.write_with_workround(self.max_freq_path(), max_freq)?;
+            file_handler.write_delayed(self.min_freq_path(), min_freq, delay)?;
+        } else {
+            file_handler.write_with_workround(self.min_freq_path(), min_freq)?;
+            file_handler.write_delayed(self.max_freq_path(), max_freq, delay)?;
+        }
         Ok(())
     }
 
-    pub fn read_freq(&self) -> isize {
-        fs::read_to_string(self.path.join("scaling_cur_freq"))
-            .context("Failed to read scaling_cur_freq")
-            .unwrap()
+    // Re-probed at most every DORMANT_PROBE_TIME, the online nodes are read on every write
+    // otherwise
+    fn dormant(&mut self, file_handler: &mut FileHandler) -> bool {
+        if self.dormant_timer.elapsed() < DORMANT_PROBE_TIME {
+            return self.dormant;
+        }
+        self.dormant_timer = Instant::now();
+
+        // cpu0 usually can't go offline and has no online node
+        let dormant = !self.online_nodes.is_empty()
+            && !self
+                .online_nodes
+                .iter()
+                .any(|node| node.read().is_none_or(|online| online.trim() != "0"));
+
+        if dormant != self.dormant {
+            self.dormant = dormant;
+            if dormant {
+                info!("CPU Policy{}: all cpus offline, dormant", self.policy);
+            } else {
+                info!("CPU Policy{}: cpus back online, reactivated", self.policy);
+                // The kernel may have reset the limits meanwhile
+                file_handler.forget(self.max_freq_path());
+                file_handler.forget(self.min_freq_path());
+                self.verify_freq = None;
+                self.cur_freq_node.invalidate();
+                self.reload_freqs();
+            }
+        }
+
+        self.dormant
+    }
+
+    // The policy directory is recreated when its cpus come back, the table usually stays the
+    // same but some kernels rebuild the OPPs then
+    fn reload_freqs(&mut self) {
+        if self.synthesized_step.is_some() {
+            return;
+        }
+        let Ok(mut freqs) = Self::available_freqs(&self.path) else {
+            return;
+        };
+        freqs.sort_unstable();
+
+        let mut table: Vec<isize> = self
+            .freqs
+            .iter()
+            .copied()
+            .filter(|freq| !self.boost_freqs.contains(freq))
+            .collect();
+        table.sort_unstable();
+        if table == freqs {
+            return;
+        }
+
+        info!(
+            "CPU Policy{}: frequency table changed while offline: {freqs:?}",
+            self.policy
+        );
+        self.boost_freqs
+            .retain(|freq| *freq > *freqs.last().unwrap_or(&0));
+        if self.boost_enabled {
+            freqs.extend_from_slice(&self.boost_freqs);
+            freqs.sort_unstable();
+        }
+        self.freqs = freqs;
+    }
+
+    // Reads the limits and writes them back unchanged, whatever fails here fas can't drive.
+    // Returns the report and whether everything passed
+    pub fn self_test(&self) -> (String, bool) {
+        let mut passed = true;
+        let mut lines = vec![format!(
+            "policy{}: cpus {:?}, {} freqs{}",
+            self.policy,
+            self.affected_cpus,
+            self.freqs.len(),
+            self.synthesized_step
+                .map(|step| format!(" (synthesized with {step}khz step)"))
+                .unwrap_or_default()
+        )];
+
+        for name in ["scaling_min_freq", "scaling_max_freq"] {
+            let path = self.path.join(name);
+            let result = match fs::read_to_string(&path) {
+                Ok(value) => {
+                    let value = value.trim();
+                    match file_handler::write_direct(&path, value) {
+                        Ok(()) => match fs::read_to_string(&path) {
+                            Ok(back) if back.trim() == value => String::from("ok"),
+                            Ok(back) => format!("wrote {value}, read back {}", back.trim()),
+                            Err(e) => format!("unreadable after write ({e})"),
+                        },
+                        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                            format!(
+                                "not writable ({})",
+                                file_handler::classify_denial(&path, &e)
+                            )
+                        }
+                        Err(e) => format!("not writable ({e})"),
+                    }
+                }
+                Err(e) => format!("unreadable ({e})"),
+            };
+            passed &= result == "ok";
+            lines.push(format!("  {name}: {result}"));
+        }
+
+        let cur_freq = match self.read_freq() {
+            Ok(0) => String::from("offline"),
+            Ok(freq) => format!("{freq}khz"),
+            Err(e) => format!("unreadable ({e:#})"),
+        };
+        lines.push(format!("  scaling_cur_freq: {cur_freq}"));
+        (lines.join("\n"), passed)
+    }
+
+    // One line for diagnostics dumps
+    pub fn describe(&self) -> String {
+        let ignored = IGNORE_MAP
+            .get()
+            .and_then(|map| map.get(&self.policy))
+            .is_some_and(|ignored| ignored.load(Ordering::Acquire));
+        format!(
+            "policy{}: cpus {:?}, role {:?}, capacity {:.0}, dormant {}, fas freq {}, ignored {ignored}, mirror of {:?}, observe only {}, floor {:?}, ceiling {:?}, slew limit {:?}, boost {:?} (enabled {}), synthesized step {:?}, verified {}/{}\n  freqs {:?}",
+            self.policy,
+            self.affected_cpus,
+            self.role,
+            self.capacity,
+            self.dormant,
+            self.cur_fas_freq,
+            self.mirror_of,
+            self.observe_only,
+            self.freq_floor,
+            self.freq_ceiling,
+            self.slew_limit,
+            self.boost_freqs,
+            self.boost_enabled,
+            self.synthesized_step,
+            self.verify_matches,
+            self.verify_checks,
+            self.freqs
+        )
+    }
+
+    pub fn state_json(&self) -> PolicyJson<'_> {
+        PolicyJson {
+            policy: self.policy,
+            cpus: &self.affected_cpus,
+            role: self.role.map(|role| format!("{role:?}")),
+            capacity: self.capacity,
+            dormant: self.dormant,
+            cur_fas_freq: self.cur_fas_freq,
+            cur_freq: self.cur_freq(),
+            mirror_of: self.mirror_of,
+            observe_only: self.observe_only,
+            freq_floor: self.freq_floor,
+            freq_ceiling: self.freq_ceiling,
+            slew_limit: self.slew_limit,
+            usage_share: self.usage_share,
+            boost_enabled: self.boost_enabled,
+            verify: VerifyJson {
+                enabled: self.verify_enabled,
+                expected_freq: self.verify_freq,
+                interval: self.verify_interval.as_secs_f64() * 1000.0,
+                tolerance: self.verify_tolerance,
+                success: self.verify_success(),
+            },
+        }
+    }
+
+    pub fn cores(&self) -> &[usize] {
+        &self.affected_cpus
+    }
+
+    pub fn freq_at(&self, ratio: f64) -> isize {
+        let max_freq = self.freqs.last().copied().unwrap_or_default();
+        (max_freq as f64 * ratio) as isize
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // The cpus sharing the policy's clock, including offline ones
+    pub fn related_cpus(&self) -> Vec<usize> {
+        read_cpus(&self.path.join("related_cpus")).unwrap_or_else(|| self.affected_cpus.clone())
+    }
+
+    // The top of the regular table, what cpu_capacity describes
+    pub const fn capacity_freq(&self) -> isize {
+        self.capacity_freq
+    }
+
+    // Linear in frequency, like the scheduler's own estimate
+    pub fn capacity_at(&self, freq: isize) -> f64 {
+        self.capacity * freq as f64 / self.capacity_freq as f64
+    }
+
+    pub fn freq_for_capacity(&self, capacity: f64) -> isize {
+        let max_freq = self.freqs.last().copied().unwrap_or_default();
+        ((capacity / self.capacity * self.capacity_freq as f64) as isize).min(max_freq)
+    }
+
+    // The node is reopened once when the read fails, hotplug recreates it
+    pub fn read_freq(&self) -> Result<isize> {
+        let freq = self
+            .cur_freq_node
+            .read()
+            .context("Failed to read scaling_cur_freq")?
             .trim()
-            .parse::<isize>()
-            .context("Failed to parse scaling_cur_freq")
-            .unwrap()
+            .parse()
+            .context("Failed to parse scaling_cur_freq")?;
+        self.last_freq.store(freq, Ordering::Relaxed);
+        Ok(freq)
+    }
+
+    // 0 while the policy is dormant, the last good value while the node fails to read
+    pub fn cur_freq(&self) -> isize {
+        if self.dormant {
+            return 0;
+        }
+
+        self.read_freq().unwrap_or_else(|e| {
+            watchdog::error(format!("CPU Policy{}: {e:#}", self.policy));
+            self.last_freq.load(Ordering::Relaxed)
+        })
+    }
+
+    // scaling_max_freq reads back the max all constraints agree on, not the last write
+    fn effective_max_freq(&self) -> Option<isize> {
+        fs::read_to_string(self.max_freq_path())
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
     }
 
     fn max_freq_path(&self) -> PathBuf {
@@ -199,3 +879,97 @@ impl Info {
         self.path.join("scaling_min_freq")
     }
 }
+
+// A cpu list like "4 5 6", None when missing or empty
+fn read_cpus(path: &Path) -> Option<Vec<usize>> {
+    let cpus: Vec<usize> = fs::read_to_string(path)
+        .ok()?
+        .split_whitespace()
+        .filter_map(|core| core.parse().ok())
+        .collect();
+    (!cpus.is_empty()).then_some(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::{self, FakeRoot};
+
+    const POLICY: &str = "/sys/devices/system/cpu/cpufreq/policy4";
+
+    fn policy(root: &FakeRoot, node: &str, content: &str) {
+        root.write(&format!("{POLICY}/{node}"), content);
+    }
+
+    #[test]
+    fn reads_the_frequency_table() {
+        let root = FakeRoot::new("cpu-info-table");
+        policy(
+            &root,
+            "scaling_available_frequencies",
+            "1800000 300000 1200000 \n",
+        );
+        policy(&root, "affected_cpus", "4 5 6\n");
+
+        let info = Info::new(sysfs::resolve(root.path(), POLICY), 100_000, None).unwrap();
+        assert_eq!(info.policy, 4);
+        assert_eq!(info.freqs, [300_000, 1_200_000, 1_800_000]);
+        assert_eq!(info.cores(), [4, 5, 6]);
+        assert_eq!(info.cur_fas_freq, 1_800_000);
+        assert_eq!(info.synthesized_step, None);
+    }
+
+    #[test]
+    fn synthesizes_a_missing_table() {
+        let root = FakeRoot::new("cpu-info-synthesized");
+        policy(&root, "cpuinfo_min_freq", "300000\n");
+        policy(&root, "cpuinfo_max_freq", "1000000\n");
+        policy(&root, "affected_cpus", "0 1 2 3\n");
+
+        let info = Info::new(sysfs::resolve(root.path(), POLICY), 300_000, None).unwrap();
+        assert_eq!(info.freqs, [300_000, 600_000, 900_000, 1_000_000]);
+        assert_eq!(info.synthesized_step, Some(300_000));
+        assert!(info.boost_freqs.is_empty());
+    }
+
+    #[test]
+    fn boost_bins_only_join_while_enabled() {
+        let root = FakeRoot::new("cpu-info-boost");
+        policy(&root, "scaling_available_frequencies", "300000 1800000\n");
+        policy(&root, "scaling_boost_frequencies", "2100000\n");
+        policy(&root, "affected_cpus", "7\n");
+
+        let mut info = Info::new(sysfs::resolve(root.path(), POLICY), 100_000, None).unwrap();
+        assert_eq!(info.freqs, [300_000, 1_800_000]);
+
+        assert!(info.set_boost(true));
+        assert_eq!(info.freqs, [300_000, 1_800_000, 2_100_000]);
+        assert!(!info.set_boost(true));
+
+        assert!(info.set_boost(false));
+        assert_eq!(info.freqs, [300_000, 1_800_000]);
+    }
+
+    #[test]
+    fn offline_cluster_at_startup_is_dormant() {
+        let root = FakeRoot::new("cpu-info-dormant");
+        policy(&root, "scaling_available_frequencies", "300000 1800000\n");
+        policy(&root, "affected_cpus", "\n");
+        policy(&root, "related_cpus", "4 5\n");
+        root.write("/sys/devices/system/cpu/cpu4/online", "0\n");
+        root.write("/sys/devices/system/cpu/cpu5/online", "0\n");
+
+        let mut info = Info::new(sysfs::resolve(root.path(), POLICY), 100_000, None).unwrap();
+        let mut file_handler = FileHandler::new(root.path());
+        info.dormant_timer = Instant::now().checked_sub(DORMANT_PROBE_TIME).unwrap();
+        assert!(info.dormant(&mut file_handler));
+    }
+
+    #[test]
+    fn missing_cpus_are_an_error() {
+        let root = FakeRoot::new("cpu-info-broken");
+        policy(&root, "scaling_available_frequencies", "300000 1800000\n");
+
+        assert!(Info::new(sysfs::resolve(root.path(), POLICY), 100_000, None).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, info};
+
+use crate::{file_handler, snapshot, sysfs};
+
+// Idle states with a longer exit latency (µs) are gated
+const LATENCY_LIMIT: u64 = 100;
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+
+// Disables deep idle states of the critical cores during a session, so waking up for the
+// next frame doesn't add latency spikes. Original values are restored on reset.
+#[derive(Debug)]
+pub struct CpuIdleGate {
+    enabled: bool,
+    cpu_dir: PathBuf,
+    saved: Vec<(PathBuf, String)>,
+}
+
+impl CpuIdleGate {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            enabled: false,
+            cpu_dir: sysfs::resolve(root, CPU_DIR),
+            saved: Vec::new(),
+        }
+    }
+
+    pub const fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn gate(&mut self, cores: impl Iterator<Item = usize>) {
+        if !self.enabled || !self.saved.is_empty() {
+            return;
+        }
+
+        for core in cores {
+            let cpuidle = self.cpu_dir.join(format!("cpu{core}")).join("cpuidle");
+            let Ok(states) = fs::read_dir(cpuidle) else {
+                continue;
+            };
+
+            for state in states.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+                let deep = fs::read_to_string(state.join("latency"))
+                    .ok()
+                    .and_then(|latency| latency.trim().parse::<u64>().ok())
+                    .is_some_and(|latency| latency > LATENCY_LIMIT);
+                if !deep {
+                    continue;
+                }
+
+                let disable = state.join("disable");
+                snapshot::record(&disable);
+                if let Ok(original) = fs::read_to_string(&disable)
+                    && file_handler::write_direct(&disable, "1").is_ok()
+                {
+                    debug!("gated {}", state.display());
+                    self.saved.push((disable, original.trim().to_string()));
+                }
+            }
+        }
+
+        if !self.saved.is_empty() {
+            info!("Gated {} deep cpuidle states", self.saved.len());
+        }
+    }
+
+    pub fn restore(&mut self) {
+        for (path, original) in self.saved.drain(..) {
+            let _ = file_handler::write_direct(path, original);
+        }
+    }
+}
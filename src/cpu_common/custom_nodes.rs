@@ -0,0 +1,147 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+
+use super::cpu_info::Info;
+use crate::{file_handler::FileHandler, framework::config::CustomNode, snapshot};
+
+// Kernel tunables only, a config edit must not turn fas into a writer of arbitrary files
+const ALLOWED_ROOTS: [&str; 2] = ["/sys/", "/proc/sys/"];
+
+// Nodes declared in the config, for kernel specific knobs fas has no code for. `start` is
+// written when a game session begins, `value` on every control update and `stop` (or the
+// original value) when it ends
+#[derive(Debug, Default)]
+pub struct CustomNodes {
+    // As given by the config, `nodes` keeps the valid ones
+    configured: Vec<CustomNode>,
+    nodes: Vec<CustomNode>,
+    originals: Vec<(PathBuf, String)>,
+    active: bool,
+}
+
+impl CustomNodes {
+    pub fn set(&mut self, nodes: &[CustomNode], file_handler: &mut FileHandler) {
+        if self.configured.as_slice() == nodes {
+            return;
+        }
+
+        let active = self.active;
+        self.stop(file_handler);
+        self.configured = nodes.to_vec();
+        self.nodes = nodes
+            .iter()
+            .filter(|node| {
+                let valid = valid_path(Path::new(&node.path));
+                if !valid {
+                    warn!("Custom node {} isn't a kernel tunable, ignored", node.path);
+                }
+                valid
+            })
+            .cloned()
+            .collect();
+        self.originals.clear();
+        if !self.nodes.is_empty() {
+            info!("Custom nodes: {}", self.nodes.len());
+        }
+        if active {
+            self.start(file_handler);
+        }
+    }
+
+    pub fn start(&mut self, file_handler: &mut FileHandler) {
+        for node in &self.nodes {
+            let path = PathBuf::from(&node.path);
+            if !self.originals.iter().any(|(original, _)| *original == path)
+                && let Ok(original) = fs::read_to_string(&path)
+            {
+                snapshot::record(&path);
+                self.originals
+                    .push((path.clone(), original.trim().to_string()));
+            }
+
+            if let Some(start) = &node.start {
+                let _ = file_handler.write_with_workround(&path, start);
+            }
+        }
+        self.active = true;
+    }
+
+    pub fn update(&self, cpu_infos: &[Info], ratio: f64, file_handler: &mut FileHandler) {
+        if !self.active {
+            return;
+        }
+
+        for node in &self.nodes {
+            if let Some(template) = &node.value {
+                let value = render(template, cpu_infos, ratio);
+                let _ = file_handler.write_with_workround(&node.path, value);
+            }
+        }
+    }
+
+    pub fn stop(&mut self, file_handler: &mut FileHandler) {
+        if !self.active {
+            return;
+        }
+
+        for node in &self.nodes {
+            let path = PathBuf::from(&node.path);
+            let value = node.stop.as_ref().or_else(|| {
+                self.originals
+                    .iter()
+                    .find(|(original, _)| *original == path)
+                    .map(|(_, value)| value)
+            });
+            if let Some(value) = value {
+                let _ = file_handler.write_with_workround(&path, value);
+            }
+        }
+        self.active = false;
+    }
+}
+
+// Resolves symlinks and `..` first, so the check holds for the file actually written
+fn valid_path(path: &Path) -> bool {
+    fs::canonicalize(path).is_ok_and(|path| {
+        path.is_file()
+            && ALLOWED_ROOTS
+                .iter()
+                .any(|root| path.starts_with(Path::new(root)))
+    })
+}
+
+// {ratio} is the highest fas freq relative to the max freq (0.00-1.00), {percent} the same
+// in percent and {policyN} the fas freq of policy N (khz)
+fn render(template: &str, cpu_infos: &[Info], ratio: f64) -> String {
+    let mut value = template
+        .replace("{ratio}", &format!("{ratio:.2}"))
+        .replace("{percent}", &((ratio * 100.0).round() as u32).to_string());
+    for cpu in cpu_infos {
+        value = value.replace(
+            &format!("{{policy{}}}", cpu.policy),
+            &cpu.cur_fas_freq.to_string(),
+        );
+    }
+    value
+}
@@ -0,0 +1,164 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use log::{debug, info};
+
+use crate::{file_handler::FileHandler, sysfs};
+
+const DEVFREQ_DIR: &str = "/sys/class/devfreq";
+const BUS_KEYWORDS: &[&str] = &["ddr", "llcc", "cpubw", "bw_hwmon", "latfloor"];
+const STEP_UP_TIME: Duration = Duration::from_millis(500);
+const STEP_DOWN_TIME: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+struct BusNode {
+    path: PathBuf,
+    freqs: Vec<isize>,
+    original_min: String,
+}
+
+impl BusNode {
+    fn new(path: PathBuf) -> Result<Self> {
+        let mut freqs: Vec<isize> = fs::read_to_string(path.join("available_frequencies"))
+            .context("Failed to read available_frequencies")?
+            .split_whitespace()
+            .map(|freq| freq.parse().context("Failed to parse frequency"))
+            .collect::<Result<_>>()?;
+        freqs.sort_unstable();
+        freqs.dedup();
+
+        let original_min = fs::read_to_string(path.join("min_freq"))
+            .context("Failed to read min_freq")?
+            .trim()
+            .to_string();
+
+        Ok(Self {
+            path,
+            freqs,
+            original_min,
+        })
+    }
+
+    // Floor at the same relative level on every node, as they have different frequency tables
+    fn write_level(&self, level: usize, levels: usize, file_handler: &mut FileHandler) {
+        let index = (self.freqs.len() - 1) * level / levels;
+        let _ = file_handler
+            .write_with_workround(self.path.join("min_freq"), self.freqs[index].to_string());
+    }
+
+    fn reset(&self, file_handler: &mut FileHandler) {
+        let _ = file_handler.write_with_workround(self.path.join("min_freq"), &self.original_min);
+    }
+}
+
+// Lifts the memory bus floor when frames miss target while cpu still has headroom, and
+// drops it back once the scene is light again
+#[derive(Debug)]
+pub struct Devfreq {
+    nodes: Vec<BusNode>,
+    enabled: bool,
+    level: usize,
+    timer: Instant,
+}
+
+impl Devfreq {
+    const LEVELS: usize = 4;
+
+    pub fn new(root: &Path) -> Self {
+        let nodes: Vec<_> = fs::read_dir(sysfs::resolve(root, DEVFREQ_DIR))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| Self::is_bus(path))
+                    .filter_map(|path| BusNode::new(path).ok())
+                    .filter(|node| node.freqs.len() > 1)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for node in &nodes {
+            info!("Bus devfreq found: {}", node.path.display());
+        }
+
+        Self {
+            nodes,
+            enabled: false,
+            level: 0,
+            timer: Instant::now(),
+        }
+    }
+
+    fn is_bus(path: &Path) -> bool {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| BUS_KEYWORDS.iter().any(|keyword| name.contains(keyword)))
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool, file_handler: &mut FileHandler) {
+        if self.enabled && !enabled {
+            self.reset(file_handler);
+        }
+        self.enabled = enabled;
+    }
+
+    pub fn update(&mut self, is_janked: bool, cpu_headroom: bool, file_handler: &mut FileHandler) {
+        if !self.enabled || self.nodes.is_empty() {
+            return;
+        }
+
+        let level = if is_janked && cpu_headroom {
+            if self.timer.elapsed() < STEP_UP_TIME {
+                return;
+            }
+            (self.level + 1).min(Self::LEVELS)
+        } else if !is_janked {
+            if self.timer.elapsed() < STEP_DOWN_TIME {
+                return;
+            }
+            self.level.saturating_sub(1)
+        } else {
+            return;
+        };
+
+        self.timer = Instant::now();
+        if level != self.level {
+            debug!("bus devfreq level: {} -> {level}", self.level);
+            self.level = level;
+            for node in &self.nodes {
+                node.write_level(level, Self::LEVELS, file_handler);
+            }
+        }
+    }
+
+    pub fn reset(&mut self, file_handler: &mut FileHandler) {
+        if !self.enabled {
+            return;
+        }
+
+        self.level = 0;
+        for node in &self.nodes {
+            node.reset(file_handler);
+        }
+    }
+}
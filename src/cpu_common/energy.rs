@@ -0,0 +1,225 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+
+use super::{cpu_info::Info, load_sampler};
+use crate::{framework::config::OppPower, sysfs};
+
+const PROC_STAT: &str = "/proc/stat";
+const ENERGY_MODEL: &str = "/sys/kernel/debug/energy_model";
+// Both /proc/stat and time_in_state count in USER_HZ
+const CLOCK_TICKS: f64 = 100.0;
+// Energy models of older kernels are in milliwatts, newer ones in microwatts
+const MICROWATT_THRESHOLD: f64 = 100_000.0;
+
+#[derive(Debug)]
+struct Domain {
+    policy: i32,
+    cores: Vec<usize>,
+    time_in_state: PathBuf,
+    // (khz, watts) by frequency, empty when no power is known for the policy
+    model: Vec<(isize, f64)>,
+    power: Vec<(isize, f64)>,
+    // Residency and busy ticks of the cores at the session start
+    start: Option<(HashMap<isize, u64>, u64)>,
+}
+
+// Estimates the energy each policy spent during a game session, from how long it ran at
+// each frequency, how busy its cores were and the power each frequency draws per core at
+// full load. Power comes from the kernel energy model, or `opp_power` where it's missing
+#[derive(Debug)]
+pub struct EnergyMeter {
+    proc_stat: PathBuf,
+    domains: Vec<Domain>,
+}
+
+impl EnergyMeter {
+    pub fn new(root: &Path, cpu_infos: &[Info]) -> Self {
+        let energy_model = sysfs::resolve(root, ENERGY_MODEL);
+        let domains = cpu_infos
+            .iter()
+            .map(|cpu| {
+                let model = cpu
+                    .cores()
+                    .iter()
+                    .map(|core| read_energy_model(&energy_model.join(format!("cpu{core}"))))
+                    .find(|model| !model.is_empty())
+                    .unwrap_or_default();
+                if !model.is_empty() {
+                    info!(
+                        "CPU Policy{}: energy model with {} points",
+                        cpu.policy,
+                        model.len()
+                    );
+                }
+                Domain {
+                    policy: cpu.policy,
+                    cores: cpu.cores().to_vec(),
+                    time_in_state: cpu.path().join("stats/time_in_state"),
+                    power: model.clone(),
+                    model,
+                    start: None,
+                }
+            })
+            .collect();
+
+        Self {
+            proc_stat: sysfs::resolve(root, PROC_STAT),
+            domains,
+        }
+    }
+
+    // Configured tables win over the energy model, in milliwatts
+    pub fn set_opp_power(&mut self, rules: &[OppPower]) {
+        for domain in &mut self.domains {
+            domain.power = rules
+                .iter()
+                .find(|rule| rule.policy == domain.policy)
+                .map_or_else(
+                    || domain.model.clone(),
+                    |rule| {
+                        let mut power: Vec<_> = rule
+                            .points
+                            .iter()
+                            .map(|(freq, milliwatts)| (*freq, milliwatts / 1000.0))
+                            .collect();
+                        power.sort_unstable_by_key(|(freq, _)| *freq);
+                        power
+                    },
+                );
+        }
+    }
+
+    pub fn begin(&mut self) {
+        let busy = self.busy_ticks();
+        for domain in &mut self.domains {
+            domain.start = read_time_in_state(&domain.time_in_state).map(|residency| {
+                let busy = domain.cores.iter().filter_map(|core| busy.get(core)).sum();
+                (residency, busy)
+            });
+        }
+    }
+
+    // Joules of each policy since `begin`, policies without power or residency are left out
+    pub fn take(&mut self) -> Vec<(i32, f64)> {
+        let busy = self.busy_ticks();
+        self.domains
+            .iter_mut()
+            .filter_map(|domain| {
+                let (start_residency, start_busy) = domain.start.take()?;
+                if domain.power.is_empty() {
+                    return None;
+                }
+                let residency = read_time_in_state(&domain.time_in_state)?;
+
+                let mut ticks = 0;
+                let mut weighted = 0.0;
+                for (freq, time) in &residency {
+                    let delta =
+                        time.saturating_sub(start_residency.get(freq).copied().unwrap_or(0));
+                    ticks += delta;
+                    weighted += delta as f64 * power_at(&domain.power, *freq);
+                }
+                if ticks == 0 {
+                    return None;
+                }
+
+                let busy: u64 = domain.cores.iter().filter_map(|core| busy.get(core)).sum();
+                let busy_seconds = busy.saturating_sub(start_busy) as f64 / CLOCK_TICKS;
+                Some((domain.policy, busy_seconds * weighted / ticks as f64))
+            })
+            .collect()
+    }
+
+    // Busy ticks of each core, idle and iowait excluded
+    fn busy_ticks(&self) -> HashMap<usize, u64> {
+        let Ok(stat) = fs::read_to_string(&self.proc_stat) else {
+            return HashMap::new();
+        };
+
+        load_sampler::core_jiffies(&stat)
+            .into_iter()
+            .map(|(core, (busy, _))| (core, busy))
+            .collect()
+    }
+}
+
+// "<khz> <ticks>" per line
+fn read_time_in_state(path: &Path) -> Option<HashMap<isize, u64>> {
+    let content = fs::read_to_string(path).ok()?;
+    let residency: HashMap<_, _> = content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some((fields.next()?.parse().ok()?, fields.next()?.parse().ok()?))
+        })
+        .collect();
+    (!residency.is_empty()).then_some(residency)
+}
+
+// One "ps:<khz>" directory per performance state, each with its frequency and power
+fn read_energy_model(dir: &Path) -> Vec<(isize, f64)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let read =
+        |path: PathBuf| -> Option<f64> { fs::read_to_string(path).ok()?.trim().parse().ok() };
+
+    let mut model: Vec<(isize, f64)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("ps:"))
+        .filter_map(|entry| {
+            let freq = read(entry.path().join("frequency"))?;
+            let power = read(entry.path().join("power"))?;
+            Some((freq as isize, power))
+        })
+        .collect();
+    model.sort_unstable_by_key(|(freq, _)| *freq);
+
+    let scale = if model.iter().any(|(_, power)| *power > MICROWATT_THRESHOLD) {
+        1_000_000.0
+    } else {
+        1000.0
+    };
+    model
+        .into_iter()
+        .map(|(freq, power)| (freq, power / scale))
+        .collect()
+}
+
+// Linear between the known points, held flat outside them
+fn power_at(power: &[(isize, f64)], freq: isize) -> f64 {
+    let index = power.partition_point(|(point, _)| *point < freq);
+    match (
+        index.checked_sub(1).and_then(|i| power.get(i)),
+        power.get(index),
+    ) {
+        (Some((low_freq, low)), Some((high_freq, high))) => {
+            let ratio = (freq - low_freq) as f64 / (high_freq - low_freq).max(1) as f64;
+            (high - low).mul_add(ratio, *low)
+        }
+        (Some((_, power)), None) | (None, Some((_, power))) => *power,
+        (None, None) => 0.0,
+    }
+}
@@ -0,0 +1,223 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use log::{debug, info};
+
+use super::load_sampler::LoadSampler;
+use crate::{
+    file_handler::FileHandler,
+    framework::config::GpuProfile,
+    sysfs::{self, PreadNode},
+};
+
+const DEVFREQ_DIR: &str = "/sys/class/devfreq";
+const GPU_KEYWORDS: &[&str] = &["kgsl-3d0", "gpu", "mali"];
+const STEP_TIME: Duration = Duration::from_millis(500);
+// Below the margin by this much more, the floor steps back down
+const STEP_DOWN_HYSTERESIS: f64 = 0.2;
+// Without gpu bound janks for this long, the jank floor steps back down
+const JANK_DECAY_TIME: Duration = Duration::from_secs(2);
+
+// The gpu devfreq node, touched while the game has a gpu profile or gpu bound janks raised
+// its floor
+#[derive(Debug)]
+pub struct Gpu {
+    path: PathBuf,
+    freqs: Vec<isize>,
+    original_min: String,
+    original_max: String,
+    profile: Option<GpuProfile>,
+    // Index into freqs the floor is raised to for the margin
+    level: usize,
+    timer: Instant,
+    // Levels above the lowest frequency that gpu bound janks raised the floor by
+    jank_level: usize,
+    jank_timer: Instant,
+    cur_freq_node: PreadNode,
+}
+
+impl Gpu {
+    pub fn new(root: &Path) -> Option<Self> {
+        let path = fs::read_dir(sysfs::resolve(root, DEVFREQ_DIR))
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .find(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| GPU_KEYWORDS.iter().any(|keyword| name.contains(keyword)))
+            })?;
+
+        match Self::load(path) {
+            Ok(gpu) => {
+                info!("Gpu devfreq found: {}", gpu.path.display());
+                Some(gpu)
+            }
+            Err(e) => {
+                debug!("Unusable gpu devfreq: {e:?}");
+                None
+            }
+        }
+    }
+
+    fn load(path: PathBuf) -> Result<Self> {
+        let mut freqs: Vec<isize> = fs::read_to_string(path.join("available_frequencies"))
+            .context("Failed to read available_frequencies")?
+            .split_whitespace()
+            .map(|freq| freq.parse().context("Failed to parse frequency"))
+            .collect::<Result<_>>()?;
+        freqs.sort_unstable();
+        freqs.dedup();
+        if freqs.is_empty() {
+            bail!("No frequencies available");
+        }
+
+        let read = |node: &str| -> Result<String> {
+            Ok(fs::read_to_string(path.join(node))
+                .with_context(|| format!("Failed to read {node}"))?
+                .trim()
+                .to_string())
+        };
+        let original_min = read("min_freq")?;
+        let original_max = read("max_freq")?;
+        let cur_freq_node = PreadNode::new(path.join("cur_freq"));
+
+        Ok(Self {
+            path,
+            freqs,
+            original_min,
+            original_max,
+            profile: None,
+            level: 0,
+            timer: Instant::now(),
+            jank_level: 0,
+            jank_timer: Instant::now(),
+            cur_freq_node,
+        })
+    }
+
+    pub fn set_profile(&mut self, profile: Option<GpuProfile>, file_handler: &mut FileHandler) {
+        if let Some(profile) = &profile {
+            debug!("gpu profile: {profile:?}");
+        } else if self.profile.is_some() {
+            self.reset(file_handler);
+        }
+        self.profile = profile;
+    }
+
+    // Keeps the profile, the limits are written again on the next update
+    pub fn reset(&mut self, file_handler: &mut FileHandler) {
+        if self.profile.is_none() && self.jank_level == 0 {
+            return;
+        }
+
+        self.level = 0;
+        self.jank_level = 0;
+        let _ = file_handler.write_with_workround(self.path.join("max_freq"), &self.original_max);
+        let _ = file_handler.write_with_workround(self.path.join("min_freq"), &self.original_min);
+    }
+
+    // Whether raising the floor can still speed the gpu up
+    pub fn headroom(&self) -> bool {
+        self.cur_freq_node
+            .read()
+            .and_then(|freq| freq.trim().parse::<isize>().ok())
+            .is_some_and(|freq| self.freqs.last().is_some_and(|max| freq < *max))
+    }
+
+    // A gpu bound jank, the floor steps up once per step time
+    pub fn raise(&mut self) {
+        if self.jank_timer.elapsed() < STEP_TIME && self.jank_level > 0 {
+            return;
+        }
+
+        self.jank_timer = Instant::now();
+        self.jank_level = (self.jank_level + 1).min(self.freqs.len() - 1);
+        debug!("gpu bound jank, jank floor level: {}", self.jank_level);
+    }
+
+    fn decay_jank_level(&mut self) {
+        if self.jank_level > 0 && self.jank_timer.elapsed() >= JANK_DECAY_TIME {
+            self.jank_timer = Instant::now();
+            self.jank_level -= 1;
+        }
+    }
+
+    pub fn update(&mut self, load_sampler: &LoadSampler, file_handler: &mut FileHandler) {
+        let raised = self.jank_level > 0;
+        self.decay_jank_level();
+        let Some(profile) = &self.profile else {
+            // Only the jank floor to keep, the governor owns the rest
+            if raised {
+                let min_freq = if self.jank_level == 0 {
+                    self.original_min.clone()
+                } else {
+                    self.freqs[self.jank_level].to_string()
+                };
+                let _ = file_handler.write_with_workround(self.path.join("min_freq"), min_freq);
+            }
+            return;
+        };
+
+        let min_index = profile
+            .min_freq
+            .map_or(0, |min| self.freqs.partition_point(|freq| *freq < min))
+            .min(self.freqs.len() - 1);
+        let max_index = profile
+            .max_freq
+            .map_or(self.freqs.len() - 1, |max| {
+                self.freqs
+                    .partition_point(|freq| *freq <= max)
+                    .saturating_sub(1)
+            })
+            .max(min_index);
+
+        // The governor keeps busy below 1.0 - margin, otherwise the floor steps up
+        if let Some(margin) = profile.margin
+            && self.timer.elapsed() >= STEP_TIME
+        {
+            self.timer = Instant::now();
+            let busy = load_sampler.gpu_busy();
+            let level = if busy > 1.0 - margin {
+                self.level + 1
+            } else if busy < 1.0 - margin - STEP_DOWN_HYSTERESIS {
+                self.level.saturating_sub(1)
+            } else {
+                self.level
+            };
+            let level = level.min(max_index - min_index);
+            if level != self.level {
+                debug!(
+                    "gpu busy: {busy:.2}, floor level: {} -> {level}",
+                    self.level
+                );
+                self.level = level;
+            }
+        }
+
+        let level = self.level.max(self.jank_level);
+        let min_freq = self.freqs[(min_index + level).min(max_index)];
+        let max_freq = self.freqs[max_index];
+        let _ = file_handler.write_with_workround(self.path.join("max_freq"), max_freq.to_string());
+        let _ = file_handler.write_with_workround(self.path.join("min_freq"), min_freq.to_string());
+    }
+}
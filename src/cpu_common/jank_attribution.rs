@@ -0,0 +1,66 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+// A janked frame with the gpu this busy waited on the gpu, not on the cpu
+const GPU_BOUND_BUSY: f64 = 0.9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Cpu,
+    Gpu,
+}
+
+// Counts what each jank of the session was bound by
+#[derive(Debug, Default)]
+pub struct JankAttribution {
+    cpu: u64,
+    gpu: u64,
+    // Janked stays set for a whole slow stretch, only its start is counted
+    janked: bool,
+}
+
+impl JankAttribution {
+    // A saturated cpu can't feed the gpu, so the cpu is blamed even with a busy gpu
+    pub const fn attribute(&mut self, gpu_busy: f64, cpu_saturated: bool) -> Bound {
+        let bound = if gpu_busy >= GPU_BOUND_BUSY && !cpu_saturated {
+            Bound::Gpu
+        } else {
+            Bound::Cpu
+        };
+
+        if !self.janked {
+            self.janked = true;
+            match bound {
+                Bound::Cpu => self.cpu += 1,
+                Bound::Gpu => self.gpu += 1,
+            }
+        }
+        bound
+    }
+
+    // The slow stretch is over, the next jank counts again
+    pub const fn recover(&mut self) {
+        self.janked = false;
+    }
+
+    // (cpu bound, gpu bound) janks since the last take
+    pub fn take(&mut self) -> (u64, u64) {
+        let counts = (self.cpu, self.gpu);
+        *self = Self::default();
+        counts
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use log::debug;
+
+// After the hold, the boost falls back to the normal fas frequency over this long
+const DECAY_TIME: Duration = Duration::from_millis(300);
+
+#[derive(Debug)]
+pub struct JankBoost {
+    multiple: f64,
+    hold_time: Duration,
+    timer: Option<Instant>,
+}
+
+impl JankBoost {
+    pub const fn new() -> Self {
+        Self {
+            multiple: 0.0,
+            hold_time: Duration::ZERO,
+            timer: None,
+        }
+    }
+
+    pub fn set(&mut self, multiple: f64, hold_time: Duration) {
+        self.multiple = multiple.max(0.0);
+        self.hold_time = hold_time;
+        if self.multiple == 0.0 || hold_time.is_zero() {
+            self.timer = None;
+        }
+    }
+
+    // Starts the boost if the frame took `multiple` times longer than the target, a stutter
+    // visible to the user
+    pub fn report_frame(&mut self, frametime: Duration, target_fps: f64) {
+        if self.multiple == 0.0 || self.hold_time.is_zero() || target_fps <= 0.0 {
+            return;
+        }
+
+        let target_frametime = Duration::from_secs_f64(1.0 / target_fps);
+        if frametime.as_secs_f64() > target_frametime.as_secs_f64() * self.multiple {
+            debug!("jank burst: {frametime:?}, boosting");
+            self.timer = Some(Instant::now());
+        }
+    }
+
+    // The floor as a fraction of each critical policy's max frequency: full during the hold,
+    // then decaying linearly to nothing
+    pub fn active_floor(&mut self) -> Option<f64> {
+        let elapsed = self.timer?.elapsed();
+        if elapsed < self.hold_time {
+            return Some(1.0);
+        }
+
+        let decayed = (elapsed - self.hold_time).as_secs_f64() / DECAY_TIME.as_secs_f64();
+        if decayed >= 1.0 {
+            self.timer = None;
+            return None;
+        }
+
+        Some(1.0 - decayed)
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+use crate::sysfs::{self, PreadNode};
+
+const PROC_STAT: &str = "/proc/stat";
+// Adreno and Mali, both report a percentage
+const GPU_BUSY_NODES: [&str; 2] = [
+    "/sys/class/kgsl/kgsl-3d0/gpu_busy_percentage",
+    "/sys/kernel/gpu/gpu_busy",
+];
+const SAMPLE_TIME: Duration = Duration::from_millis(100);
+
+// Per-core and gpu load, for titles no frame source works for. Without frames the only
+// thing left to go on is how busy the hardware is
+#[derive(Debug)]
+pub struct LoadSampler {
+    proc_stat: PreadNode,
+    gpu_busy: Option<PreadNode>,
+    // (busy, total) jiffies of each core at the last sample
+    last_stat: HashMap<usize, (u64, u64)>,
+    sample_timer: Instant,
+}
+
+impl LoadSampler {
+    pub fn new(root: &Path) -> Self {
+        let gpu_busy = GPU_BUSY_NODES
+            .iter()
+            .map(|node| sysfs::resolve(root, node))
+            .find(|node| node.exists())
+            .map(PreadNode::new);
+        if let Some(node) = &gpu_busy {
+            info!("Gpu load node found: {}", node.path().display());
+        }
+
+        Self {
+            proc_stat: PreadNode::new(sysfs::resolve(root, PROC_STAT)),
+            gpu_busy,
+            last_stat: HashMap::new(),
+            sample_timer: Instant::now(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.last_stat.clear();
+    }
+
+    // Load of each core since the last sample, None until a full SAMPLE_TIME has passed
+    pub fn sample(&mut self) -> Option<HashMap<usize, f64>> {
+        if self.sample_timer.elapsed() < SAMPLE_TIME {
+            return None;
+        }
+        self.sample_timer = Instant::now();
+
+        let stat = self.proc_stat.read()?;
+        let mut loads = HashMap::new();
+
+        for (core, (busy, total)) in core_jiffies(&stat) {
+            if let Some((last_busy, last_total)) = self.last_stat.insert(core, (busy, total)) {
+                let total = total.saturating_sub(last_total);
+                if total > 0 {
+                    loads.insert(core, busy.saturating_sub(last_busy) as f64 / total as f64);
+                }
+            }
+        }
+
+        (!loads.is_empty()).then_some(loads)
+    }
+
+    pub const fn reports_gpu_busy(&self) -> bool {
+        self.gpu_busy.is_some()
+    }
+
+    // 0.0 - 1.0, 0.0 when the gpu doesn't report it
+    pub fn gpu_busy(&self) -> f64 {
+        let Some(node) = &self.gpu_busy else {
+            return 0.0;
+        };
+        let Some(content) = node.read() else {
+            return 0.0;
+        };
+
+        // "42 %" on kgsl, "42" on mali
+        content
+            .split_whitespace()
+            .next()
+            .and_then(|percentage| percentage.parse::<f64>().ok())
+            .map_or(0.0, |percentage| (percentage / 100.0).clamp(0.0, 1.0))
+    }
+}
+
+// (busy, total) jiffies of each core in /proc/stat, idle and iowait aren't busy
+pub fn core_jiffies(stat: &str) -> HashMap<usize, (u64, u64)> {
+    stat.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let core = fields.next()?.strip_prefix("cpu")?.parse::<usize>().ok()?;
+            let times: Vec<u64> = fields.filter_map(|time| time.parse().ok()).collect();
+            let total: u64 = times.iter().sum();
+            let idle = times.get(3).copied().unwrap_or(0) + times.get(4).copied().unwrap_or(0);
+            Some((core, (total.saturating_sub(idle), total)))
+        })
+        .collect()
+}
@@ -0,0 +1,63 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs;
+
+use log::info;
+
+use super::cpu_info::Info;
+
+// Pairs of (mirror, leader) policies that drive one clock domain. Some vendor kernels expose
+// a domain as two policies, by linking both to the same directory or by giving them the
+// same table with each listing the other's cpus as related. Writing both only doubles the
+// writes and lets their limits contradict each other, so the lower policy writes for both
+pub fn detect(cpu_infos: &[Info]) -> Vec<(i32, i32)> {
+    let mut mirrors = Vec::new();
+    for (index, leader) in cpu_infos.iter().enumerate() {
+        if mirrors.iter().any(|(mirror, _)| *mirror == leader.policy) {
+            continue;
+        }
+
+        for cpu in &cpu_infos[index + 1..] {
+            if mirrors.iter().any(|(mirror, _)| *mirror == cpu.policy) {
+                continue;
+            }
+
+            if same_directory(leader, cpu) || shared_domain(leader, cpu) {
+                info!(
+                    "CPU Policy{}: mirrors policy{}, written once for both",
+                    cpu.policy, leader.policy
+                );
+                mirrors.push((cpu.policy, leader.policy));
+            }
+        }
+    }
+    mirrors
+}
+
+fn same_directory(a: &Info, b: &Info) -> bool {
+    match (fs::canonicalize(a.path()), fs::canonicalize(b.path())) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn shared_domain(a: &Info, b: &Info) -> bool {
+    a.freqs == b.freqs
+        && a.related_cpus().iter().any(|core| b.cores().contains(core))
+        && b.related_cpus().iter().any(|core| a.cores().contains(core))
+}
@@ -15,39 +15,121 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
+mod actuator;
+mod capacity;
+mod core_parking;
+mod core_usage;
 mod cpu_info;
+mod cpuidle;
+mod custom_nodes;
+mod devfreq;
+mod energy;
 pub mod extra_policy;
+mod gpu;
+mod jank_attribution;
+mod jank_boost;
+mod load_sampler;
+mod mirror;
 mod process_monitor;
+mod top_app_boost;
+mod top_cores;
+mod touch_boost;
 
 use std::{
     collections::HashMap,
     fs,
-    path::Path,
-    sync::{OnceLock, atomic::AtomicBool},
+    path::{Path, PathBuf},
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result};
-use log::{debug, warn};
-use nix::{
-    sched::{CpuSet, sched_getaffinity},
-    unistd::Pid,
-};
-use parking_lot::Mutex;
+use core_parking::CoreParking;
+use core_usage::CoreUsage;
+use jank_attribution::{Bound, JankAttribution};
+use jank_boost::JankBoost;
+use load_sampler::LoadSampler;
+use log::{debug, info, warn};
+use nix::sched::CpuSet;
+use parking_lot::{Mutex, const_mutex};
 use process_monitor::ProcessMonitor;
+use serde::Serialize;
+use top_app_boost::TopAppBoost;
+use top_cores::TopCores;
+use touch_boost::TouchBoost;
+pub use touch_boost::{last_touch, watch_touch};
 
-use crate::file_handler::FileHandler;
 #[cfg(feature = "extension")]
 use crate::{
     Extension,
     api::{trigger_init_cpu_freq, trigger_reset_cpu_freq},
 };
-use cpu_info::Info;
+use crate::{
+    file_handler::{self, FileHandler},
+    framework::{
+        config::{
+            ClusterRole, CustomNode, FreqCeiling, FreqFloor, FreqSlew, GpuProfile, OppPower,
+            PolicyRatio, PolicyTopology, TopCoresStrategy,
+        },
+        control::STATUS,
+    },
+    snapshot, sysfs, watchdog,
+};
+use actuator::{Actuator, FreqTarget, builtin_actuators, load_actuators};
+use capacity::CAPACITY_SCALE;
+use cpu_info::{AUTO_IGNORE_COOLDOWN, Info, PolicyJson};
+use cpuidle::CpuIdleGate;
+use custom_nodes::CustomNodes;
+use devfreq::Devfreq;
+use energy::EnergyMeter;
 use extra_policy::ExtraPolicy;
+use gpu::Gpu;
+
+const CPUFREQ_DIR: &str = "/sys/devices/system/cpu/cpufreq";
+// Frequencies before this into a game are warm-up, not what it settles at
+const STEADY_WARMUP: Duration = Duration::from_secs(10);
+// Updates needed after the warm-up for the average to be worth remembering
+const STEADY_MIN_SAMPLES: u64 = 1800;
+// Some kernels only grant boost bins while this is set
+const CPUFREQ_BOOST: &str = "/sys/devices/system/cpu/cpufreq/boost";
+// Load control keeps each policy's busiest core at this load
+const LOAD_TARGET: f64 = 0.75;
+// Share of the top frequency a fully busy gpu keeps the cpu at, it has to be fed
+const GPU_LOAD_WEIGHT: f64 = 0.6;
+// The control is read against this top frequency in capacity units, so the same gains move a
+// device with a 2ghz top as far as one with a 3.5ghz top
+const CAPACITY_REFERENCE_FREQ: f64 = 3_000_000.0;
 
 pub static EXTRA_POLICY_MAP: OnceLock<HashMap<i32, Mutex<ExtraPolicy>>> = OnceLock::new();
 pub static IGNORE_MAP: OnceLock<HashMap<i32, AtomicBool>> = OnceLock::new();
+// Policies ignored at runtime through the control socket, released on the next update
+static RELEASE_QUEUE: Mutex<Vec<i32>> = const_mutex(Vec::new());
+
+// Returns false if the policy doesn't exist
+pub fn set_policy_ignored(policy: i32, ignored: bool) -> bool {
+    let Some(flag) = IGNORE_MAP.get().and_then(|map| map.get(&policy)) else {
+        return false;
+    };
+
+    flag.store(ignored, Ordering::Release);
+    if ignored {
+        RELEASE_QUEUE.lock().push(policy);
+    }
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct ControllerJson<'a> {
+    freq_cap: f64,
+    freq_cap_target: f64,
+    idle_cap: f64,
+    util_max: Option<f64>,
+    policies: Vec<PolicyJson<'a>>,
+}
 
 #[derive(Debug)]
 pub struct Controller {
@@ -56,12 +138,53 @@ pub struct Controller {
     file_handler: FileHandler,
     process_monitor: ProcessMonitor,
     util_max: Option<f64>,
+    actuators: Vec<Box<dyn Actuator>>,
+    touch_boost: TouchBoost,
+    jank_boost: JankBoost,
+    freq_cap: f64,
+    freq_cap_target: f64,
+    idle_cap: f64,
+    policy_ratios: Vec<PolicyRatio>,
+    devfreq: Devfreq,
+    gpu: Option<Gpu>,
+    custom_nodes: CustomNodes,
+    cpuidle_gate: CpuIdleGate,
+    top_app_boost: TopAppBoost,
+    core_parking: CoreParking,
+    load_sampler: LoadSampler,
+    core_usage: CoreUsage,
+    boost_node: PathBuf,
+    // boost_freqs of the config, only applied while a game is engaged
+    boost_freqs: bool,
+    engaged: bool,
+    // The boost node's value before the session, while boost bins are in use
+    boost_original: Option<String>,
+    // (sum, samples) of each policy's fas freq since the warm-up
+    steady_freqs: HashMap<i32, (i64, u64)>,
+    game_timer: Instant,
+    // Reason in normalized capacity rather than raw khz
+    capacity_units: bool,
+    jank_attribution: JankAttribution,
+    // Answer gpu bound janks by raising the gpu rather than the cpu
+    jank_routing: bool,
+    root: PathBuf,
+    top_cores: Box<dyn TopCores>,
+    top_cores_strategy: TopCoresStrategy,
+    // Why the looper asked for the current control, set while explain mode is on
+    explain: Option<String>,
+    // (mirror, leader) policies sharing a clock domain, only followed while detection is on
+    mirrors: Vec<(i32, i32)>,
+    energy: EnergyMeter,
 }
 
 impl Controller {
-    pub fn new() -> Result<Self> {
-        let mut cpu_infos = Self::load_cpu_infos()?;
+    // Kernel nodes are looked up under root, "/" on a device
+    pub fn new(root: &Path, freq_step: isize, topology: &[PolicyTopology]) -> Result<Self> {
+        let mut cpu_infos = Self::load_cpu_infos(root, freq_step, topology)?;
         cpu_infos.sort_by_key(|cpu| cpu.policy);
+        capacity::assign(root, &mut cpu_infos);
+        let mirrors = mirror::detect(&cpu_infos);
+        let energy = EnergyMeter::new(root, &cpu_infos);
 
         EXTRA_POLICY_MAP.get_or_init(|| {
             cpu_infos
@@ -85,19 +208,63 @@ impl Controller {
             .copied()
             .unwrap_or(0);
 
+        let little_cores = cpu_infos
+            .iter()
+            .find(|cpu| cpu.role == Some(ClusterRole::Little))
+            .or_else(|| cpu_infos.first())
+            .map_or(&[][..], |cpu| cpu.cores());
+        let core_parking = CoreParking::new(root, little_cores);
+
+        let mut actuators = builtin_actuators(&cpu_infos);
+        actuators.extend(load_actuators());
+
         Ok(Self {
             max_freq,
             cpu_infos,
-            file_handler: FileHandler::new(),
+            file_handler: FileHandler::new(root),
             process_monitor: ProcessMonitor::new(),
             util_max: None,
+            actuators,
+            touch_boost: TouchBoost::new(),
+            jank_boost: JankBoost::new(),
+            freq_cap: 1.0,
+            freq_cap_target: 1.0,
+            idle_cap: 1.0,
+            policy_ratios: Vec::new(),
+            devfreq: Devfreq::new(root),
+            gpu: Gpu::new(root),
+            custom_nodes: CustomNodes::default(),
+            cpuidle_gate: CpuIdleGate::new(root),
+            top_app_boost: TopAppBoost::new(),
+            core_parking,
+            load_sampler: LoadSampler::new(root),
+            core_usage: CoreUsage::new(root),
+            boost_node: sysfs::resolve(root, CPUFREQ_BOOST),
+            boost_freqs: false,
+            engaged: false,
+            boost_original: None,
+            steady_freqs: HashMap::new(),
+            game_timer: Instant::now(),
+            capacity_units: false,
+            jank_attribution: JankAttribution::default(),
+            jank_routing: false,
+            root: root.to_path_buf(),
+            top_cores: top_cores::strategy(root, TopCoresStrategy::Affinity),
+            top_cores_strategy: TopCoresStrategy::Affinity,
+            explain: None,
+            mirrors,
+            energy,
         })
     }
 
-    fn load_cpu_infos() -> Result<Vec<Info>> {
+    fn load_cpu_infos(
+        root: &Path,
+        freq_step: isize,
+        topology: &[PolicyTopology],
+    ) -> Result<Vec<Info>> {
         let mut cpu_infos = Vec::new();
 
-        for entry in fs::read_dir("/sys/devices/system/cpu/cpufreq")? {
+        for entry in fs::read_dir(sysfs::resolve(root, CPUFREQ_DIR))? {
             let path = match entry {
                 Ok(entry) => entry.path(),
                 Err(e) => {
@@ -118,15 +285,19 @@ impl Controller {
                 continue;
             }
 
-            cpu_infos.push(Self::retry_load_info(&path));
+            let overrides = filename
+                .get(6..)
+                .and_then(|policy| policy.parse::<i32>().ok())
+                .and_then(|policy| topology.iter().find(|rule| rule.policy == policy));
+            cpu_infos.push(Self::retry_load_info(&path, freq_step, overrides));
         }
 
         Ok(cpu_infos)
     }
 
-    fn retry_load_info(path: &Path) -> Info {
+    fn retry_load_info(path: &Path, freq_step: isize, topology: Option<&PolicyTopology>) -> Info {
         loop {
-            match Info::new(path) {
+            match Info::new(path, freq_step, topology) {
                 Ok(info) => return info,
                 Err(e) => {
                     warn!(
@@ -143,24 +314,44 @@ impl Controller {
     pub fn init_game(&mut self, pid: i32, extension: &Extension) {
         trigger_init_cpu_freq(extension);
         self.reset_all_cpu_freq();
+        self.engaged = true;
+        self.engage_boost(self.boost_freqs);
+        self.custom_nodes.start(&mut self.file_handler);
+        self.gate_cpuidle();
         self.process_monitor.set_pid(Some(pid));
         self.util_max = None;
+        self.steady_freqs.clear();
+        self.game_timer = Instant::now();
+        self.energy.begin();
     }
     #[cfg(not(feature = "extension"))]
     pub fn init_game(&mut self, pid: i32) {
         self.reset_all_cpu_freq();
+        self.engaged = true;
+        self.engage_boost(self.boost_freqs);
+        self.custom_nodes.start(&mut self.file_handler);
+        self.gate_cpuidle();
         self.process_monitor.set_pid(Some(pid));
         self.util_max = None;
+        self.steady_freqs.clear();
+        self.game_timer = Instant::now();
+        self.energy.begin();
     }
     #[cfg(feature = "extension")]
     pub fn init_default(&mut self, extension: &Extension) {
         trigger_reset_cpu_freq(extension);
+        self.restore_auto_ignored(true);
+        self.engaged = false;
+        self.engage_boost(false);
         self.reset_all_cpu_freq();
         self.process_monitor.set_pid(None);
         self.util_max = None;
     }
     #[cfg(not(feature = "extension"))]
     pub fn init_default(&mut self) {
+        self.restore_auto_ignored(true);
+        self.engaged = false;
+        self.engage_boost(false);
         self.reset_all_cpu_freq();
         self.process_monitor.set_pid(None);
         self.util_max = None;
@@ -168,39 +359,542 @@ impl Controller {
 
     pub fn fas_update_freq(&mut self, control: isize, is_janked: bool) {
         debug!("change freq: {control}");
+        self.restore_auto_ignored(false);
+        self.release_ignored();
+        let requested = (control, is_janked);
+        let (control, is_janked) = self.route_jank(control, is_janked);
+        let routed = (control, is_janked) != requested;
 
-        let fas_freqs = self.compute_target_frequencies(control, is_janked);
+        let fas_freqs = if self.capacity_units {
+            self.compute_target_capacities(control, is_janked)
+        } else {
+            self.compute_target_frequencies(control, is_janked)
+        };
         let sorted_policies = self.sort_policies_topologically();
         let fas_freqs = Self::apply_absolute_constraints(fas_freqs, &sorted_policies);
         let fas_freqs = Self::apply_relative_constraints(fas_freqs, &sorted_policies);
-        let top_used_cores = self.top_used_cores().unwrap_or_else(|| {
-            let mut all_cores = CpuSet::new();
-            for core in 0..num_cpus::get() {
-                all_cores.set(core).unwrap();
+        let fas_freqs = self.apply_policy_ratios(fas_freqs);
+        let fas_freqs = self.merge_mirrors(fas_freqs);
+        let top_used_cores = self
+            .top_cores
+            .top_used_cores(&self.process_monitor)
+            .unwrap_or_else(all_cores);
+        self.core_usage.update();
+        let top_used_cores = self.core_usage.critical_cores(top_used_cores);
+        self.update_usage_shares();
+
+        let boost_floor = self.touch_boost.active_floor();
+        let jank_floor = self.jank_boost.active_floor();
+        let freq_cap = self.step_freq_cap();
+        self.file_handler.begin_tick();
+
+        let clustered = no_extra_policy();
+        let fas_freq_max = fas_freqs.values().max().copied().unwrap_or_default();
+        let mut explained = Vec::new();
+        for cpu in &mut self.cpu_infos {
+            let Some(fas_freq) = fas_freqs.get(&cpu.policy).copied() else {
+                continue;
+            };
+            // Extra policies and capacity units already give every policy its own frequency
+            let follower = !clustered
+                || self.capacity_units
+                || self
+                    .policy_ratios
+                    .iter()
+                    .any(|ratio| ratio.policy == cpu.policy);
+            let freq = if follower {
+                fas_freq
+            } else {
+                fas_freq.clamp(
+                    fas_freq_max.saturating_sub(100_000),
+                    fas_freq_max.saturating_add(100_000),
+                )
+            };
+            let freq = boost_floor.map_or(freq, |floor| freq.max(cpu.freq_at(floor)));
+            let floored = jank_floor
+                .filter(|_| is_critical(cpu, top_used_cores))
+                .map_or(freq, |floor| freq.max(cpu.freq_at(floor)));
+            let freq = floored.min(cpu.freq_at(freq_cap));
+
+            let last_freq = cpu.cur_fas_freq;
+            report_error(
+                cpu.policy,
+                cpu.write_freq(top_used_cores, freq, &mut self.file_handler),
+            );
+            if self.explain.is_some() && cpu.cur_fas_freq != last_freq {
+                explained.push(explain_policy(
+                    cpu, last_freq, fas_freq, floored, freq, freq_cap,
+                ));
             }
-            all_cores
-        });
+        }
+        if let Some(reason) = &self.explain
+            && !explained.is_empty()
+        {
+            let routed = if routed {
+                format!(", gpu bound jank routed to the gpu, control {control}khz")
+            } else {
+                String::new()
+            };
+            info!("Explain: {reason}{routed}\n  {}", explained.join("\n  "));
+        }
 
-        if no_extra_policy() {
-            let fas_freq_max = fas_freqs.values().max().copied().unwrap();
-            for cpu in &mut self.cpu_infos {
-                if let Some(freq) = fas_freqs.get(&cpu.policy).copied() {
-                    let freq = freq.clamp(
-                        fas_freq_max.saturating_sub(100_000),
-                        fas_freq_max.saturating_add(100_000),
-                    );
-                    let _ = cpu.write_freq(top_used_cores, freq, &mut self.file_handler);
-                }
+        if !self.actuators.is_empty() {
+            let targets: Vec<_> = self
+                .cpu_infos
+                .iter()
+                .filter(|cpu| cpu.written())
+                .map(|cpu| FreqTarget {
+                    policy: cpu.policy,
+                    freq: cpu.cur_fas_freq as i64,
+                })
+                .collect();
+
+            for actuator in &mut self.actuators {
+                actuator.apply(&targets);
             }
-        } else {
-            for cpu in &mut self.cpu_infos {
-                if let Some(freq) = fas_freqs.get(&cpu.policy).copied() {
-                    let _ = cpu.write_freq(top_used_cores, freq, &mut self.file_handler);
-                }
+        }
+
+        let ratio = self.fas_freq_ratio();
+        self.custom_nodes
+            .update(&self.cpu_infos, ratio, &mut self.file_handler);
+
+        if self.game_timer.elapsed() >= STEADY_WARMUP {
+            for cpu in &self.cpu_infos {
+                let (sum, samples) = self.steady_freqs.entry(cpu.policy).or_default();
+                *sum += cpu.cur_fas_freq as i64;
+                *samples += 1;
+            }
+        }
+
+        let cpu_headroom = !self.freq_saturated();
+        self.devfreq
+            .update(is_janked, cpu_headroom, &mut self.file_handler);
+        if let Some(gpu) = &mut self.gpu {
+            gpu.update(&self.load_sampler, &mut self.file_handler);
+        }
+        let _ = self.file_handler.flush();
+        self.top_app_boost.update(is_janked);
+        self.core_parking.update();
+    }
+
+    // A gpu bound jank raises the gpu floor instead, more cpu frequency would only burn power
+    // while the frame waits on the gpu
+    fn route_jank(&mut self, control: isize, is_janked: bool) -> (isize, bool) {
+        if !is_janked {
+            self.jank_attribution.recover();
+            return (control, is_janked);
+        }
+
+        let gpu_busy = self.load_sampler.gpu_busy();
+        let cpu_saturated = self.freq_saturated();
+        let bound = self.jank_attribution.attribute(gpu_busy, cpu_saturated);
+        if bound == Bound::Gpu
+            && self.jank_routing
+            && let Some(gpu) = &mut self.gpu
+            && gpu.headroom()
+        {
+            gpu.raise();
+            return (control.min(0), false);
+        }
+
+        (control, is_janked)
+    }
+
+    // (cpu bound, gpu bound) janks since the last take
+    pub fn take_jank_attribution(&mut self) -> (u64, u64) {
+        self.jank_attribution.take()
+    }
+
+    // Estimated joules of each policy since the game started
+    pub fn take_cluster_energy(&mut self) -> Vec<(i32, f64)> {
+        self.energy.take()
+    }
+
+    // Used instead of fas_update_freq when no frame source works for the game, scales each
+    // policy with the load of its busiest core and keeps a floor for a busy gpu
+    pub fn load_update_freq(&mut self) {
+        let Some(loads) = self.load_sampler.sample() else {
+            return;
+        };
+        self.release_ignored();
+
+        let gpu_floor = self.load_sampler.gpu_busy() * GPU_LOAD_WEIGHT;
+        let top_used_cores = self
+            .top_cores
+            .top_used_cores(&self.process_monitor)
+            .unwrap_or_else(all_cores);
+        let freq_cap = self.step_freq_cap();
+        self.file_handler.begin_tick();
+
+        for cpu in &mut self.cpu_infos {
+            let load = cpu
+                .cores()
+                .iter()
+                .filter_map(|core| loads.get(core))
+                .copied()
+                .fold(0.0, f64::max);
+            debug!(
+                "policy{} load: {load:.2}, gpu floor: {gpu_floor:.2}",
+                cpu.policy
+            );
+
+            let freq = (cpu.cur_fas_freq as f64 * load / LOAD_TARGET) as isize;
+            let freq = freq.max(cpu.freq_at(gpu_floor)).min(cpu.freq_at(freq_cap));
+            report_error(
+                cpu.policy,
+                cpu.write_freq(top_used_cores, freq, &mut self.file_handler),
+            );
+        }
+
+        let _ = self.file_handler.flush();
+        self.core_parking.update();
+    }
+
+    // The average frequency of each policy once the game warmed up, taken so it's only
+    // remembered once per session. None if the session was too short to tell
+    pub fn take_steady_freqs(&mut self) -> Option<Vec<(i32, isize)>> {
+        let steady_freqs = std::mem::take(&mut self.steady_freqs);
+        if steady_freqs.is_empty()
+            || steady_freqs
+                .values()
+                .any(|(_, samples)| *samples < STEADY_MIN_SAMPLES)
+        {
+            return None;
+        }
+
+        Some(
+            steady_freqs
+                .into_iter()
+                .map(|(policy, (sum, samples))| (policy, (sum / samples as i64) as isize))
+                .collect(),
+        )
+    }
+
+    pub fn fas_freqs(&self) -> Vec<(i32, isize)> {
+        self.cpu_infos
+            .iter()
+            .map(|cpu| (cpu.policy, cpu.cur_fas_freq))
+            .collect()
+    }
+
+    // Starts the game from the frequencies it settled at last time rather than the top
+    pub fn seed_freqs(&mut self, freqs: &[(i32, isize)]) {
+        for cpu in &mut self.cpu_infos {
+            if let Some((_, freq)) = freqs.iter().find(|(policy, _)| *policy == cpu.policy)
+                && let (Some(min), Some(max)) = (cpu.freqs.first(), cpu.freqs.last())
+            {
+                cpu.cur_fas_freq = (*freq).clamp(*min, *max);
+            }
+        }
+        debug!("seeded fas freqs: {freqs:?}");
+    }
+
+    // A plain max frequency cap for non-game apps, the governor still picks below it
+    pub fn powersave_update_freq(&mut self, cap: f64) {
+        self.release_ignored();
+        let freq_cap = self.step_freq_cap().min(cap);
+        self.file_handler.begin_tick();
+
+        for cpu in &mut self.cpu_infos {
+            let freq = cpu.freq_at(freq_cap);
+            report_error(
+                cpu.policy,
+                cpu.write_freq(CpuSet::new(), freq, &mut self.file_handler),
+            );
+        }
+
+        let _ = self.file_handler.flush();
+    }
+
+    // Every policy at its (capped) top frequency, regardless of which cores the game uses
+    pub fn hold_max_freq(&mut self) {
+        self.release_ignored();
+        let freq_cap = self.step_freq_cap();
+        self.file_handler.begin_tick();
+
+        for cpu in &mut self.cpu_infos {
+            let freq = cpu.freq_at(freq_cap);
+            report_error(
+                cpu.policy,
+                cpu.write_freq(all_cores(), freq, &mut self.file_handler),
+            );
+        }
+
+        let _ = self.file_handler.flush();
+    }
+
+    // Verification only hands a policy over for a while, `force` takes every one back
+    fn restore_auto_ignored(&mut self, force: bool) {
+        for cpu in &mut self.cpu_infos {
+            if cpu
+                .auto_ignored
+                .is_some_and(|since| force || since.elapsed() >= AUTO_IGNORE_COOLDOWN)
+            {
+                info!(
+                    "CPU Policy{}: taken back after automatic ignore",
+                    cpu.policy
+                );
+                cpu.auto_ignored = None;
+                set_policy_ignored(cpu.policy, false);
+            }
+        }
+    }
+
+    fn release_ignored(&mut self) {
+        let policies: Vec<_> = RELEASE_QUEUE.lock().drain(..).collect();
+        for cpu in &mut self.cpu_infos {
+            if policies.contains(&cpu.policy) {
+                info!("CPU Policy{}: ignored, released to the system", cpu.policy);
+                report_error(cpu.policy, cpu.reset(&mut self.file_handler));
+            }
+        }
+    }
+
+    pub fn set_observe_policies(&mut self, policies: &[i32]) {
+        for cpu in &mut self.cpu_infos {
+            let observe_only = policies.contains(&cpu.policy);
+            if cpu.observe_only != observe_only {
+                info!("CPU Policy{}: observe only: {observe_only}", cpu.policy);
+                cpu.observe_only = observe_only;
+                report_error(cpu.policy, cpu.reset(&mut self.file_handler));
+            }
+        }
+    }
+
+    pub fn set_freq_floors(&mut self, floors: &[FreqFloor]) {
+        for cpu in &mut self.cpu_infos {
+            cpu.freq_floor = floors
+                .iter()
+                .find(|floor| floor.policy == cpu.policy)
+                .map(|floor| floor.freq);
+        }
+    }
+
+    pub fn set_policy_ratios(&mut self, ratios: &[PolicyRatio]) {
+        self.policy_ratios.clear();
+        self.policy_ratios.extend_from_slice(ratios);
+    }
+
+    pub fn set_freq_ceilings(&mut self, ceilings: &[FreqCeiling]) {
+        for cpu in &mut self.cpu_infos {
+            cpu.freq_ceiling = ceilings
+                .iter()
+                .find(|ceiling| ceiling.policy == cpu.policy)
+                .and_then(|ceiling| {
+                    ceiling
+                        .freq
+                        .or_else(|| ceiling.ratio.map(|ratio| cpu.freq_at(ratio)))
+                });
+        }
+    }
+
+    pub fn set_boost_freqs(&mut self, enabled: bool) {
+        self.boost_freqs = enabled;
+        if self.engaged {
+            self.engage_boost(enabled);
+        }
+    }
+
+    // Boost bins only join the tables for the session, and the boost node is put back to
+    // what it was once the game is left
+    fn engage_boost(&mut self, enabled: bool) {
+        let mut changed = false;
+        for cpu in &mut self.cpu_infos {
+            changed |= cpu.set_boost(enabled);
+        }
+        if !changed {
+            return;
+        }
+
+        self.max_freq = self
+            .cpu_infos
+            .iter()
+            .flat_map(|info| info.freqs.iter())
+            .max()
+            .copied()
+            .unwrap_or(0);
+        if !self.boost_node.exists() {
+            return;
+        }
+
+        if enabled {
+            snapshot::record(&self.boost_node);
+            self.boost_original = fs::read_to_string(&self.boost_node)
+                .ok()
+                .map(|value| value.trim().to_string());
+            let _ = file_handler::write_direct(&self.boost_node, "1");
+        } else if let Some(original) = self.boost_original.take() {
+            let _ = file_handler::write_direct(&self.boost_node, original);
+        }
+    }
+
+    pub fn set_freq_slews(&mut self, slews: &[FreqSlew]) {
+        for cpu in &mut self.cpu_infos {
+            cpu.slew_limit = slews
+                .iter()
+                .find(|slew| slew.policy == cpu.policy)
+                .map(|slew| slew.step.max(1));
+        }
+    }
+
+    pub fn set_verify(&mut self, interval: Duration, tolerance: f64, ignore_policies: &[i32]) {
+        for cpu in &mut self.cpu_infos {
+            let enabled = !ignore_policies.contains(&cpu.policy);
+            cpu.set_verify(enabled, interval, tolerance);
+        }
+    }
+
+    pub fn set_interference_lock(&mut self, enabled: bool) {
+        for cpu in &mut self.cpu_infos {
+            cpu.lock_on_interference = enabled;
+        }
+    }
+
+    pub fn set_opp_power(&mut self, rules: &[OppPower]) {
+        self.energy.set_opp_power(rules);
+    }
+
+    pub fn set_mirror_detection(&mut self, enabled: bool) {
+        for cpu in &mut self.cpu_infos {
+            let mirror_of = self
+                .mirrors
+                .iter()
+                .find(|(mirror, _)| *mirror == cpu.policy)
+                .map(|(_, leader)| *leader)
+                .filter(|_| enabled);
+            if mirror_of.is_some() && cpu.mirror_of.is_none() {
+                // Its own limits would fight the leader's from now on
+                report_error(cpu.policy, cpu.reset(&mut self.file_handler));
             }
+            cpu.mirror_of = mirror_of;
+        }
+    }
+
+    pub fn set_verify_auto_ignore(&mut self, enabled: bool) {
+        for cpu in &mut self.cpu_infos {
+            cpu.verify_auto_ignore = enabled;
+        }
+    }
+
+    pub const fn set_refresh_interval(&mut self, interval: Duration) {
+        self.file_handler.set_refresh_interval(interval);
+    }
+
+    pub fn set_top_app_boost(&mut self, max: u32) {
+        self.top_app_boost.set_max(max);
+    }
+
+    pub fn set_core_parking(&mut self, max: u32) {
+        self.core_parking.set_max(max);
+    }
+
+    pub const fn set_cpuidle_gate(&mut self, enabled: bool) {
+        self.cpuidle_gate.set_enabled(enabled);
+    }
+
+    pub fn set_devfreq_boost(&mut self, enabled: bool) {
+        self.devfreq.set_enabled(enabled, &mut self.file_handler);
+    }
+
+    pub fn set_touch_boost(&mut self, duration: Duration, floor: f64) {
+        self.touch_boost.set(duration, floor);
+    }
+
+    pub fn set_jank_boost(&mut self, multiple: f64, hold_time: Duration) {
+        self.jank_boost.set(multiple, hold_time);
+    }
+
+    pub fn set_critical_usage(&mut self, threshold: f64, decay: f64) {
+        self.core_usage.set(threshold, decay);
+    }
+
+    // Per game, None once it exits
+    pub fn set_gpu_profile(&mut self, profile: Option<GpuProfile>) {
+        if let Some(gpu) = &mut self.gpu {
+            gpu.set_profile(profile, &mut self.file_handler);
+        }
+    }
+
+    pub fn set_custom_nodes(&mut self, nodes: &[CustomNode]) {
+        self.custom_nodes.set(nodes, &mut self.file_handler);
+    }
+
+    pub fn set_dry_run(&mut self, enabled: bool) {
+        if file_handler::set_dry_run(enabled) {
+            info!("Dry run: {enabled}");
+            // What was only logged has to be really written now, and the other way around
+            self.file_handler.invalidate();
+        }
+    }
+
+    pub fn set_selinux_patch(&mut self, enabled: bool) {
+        if file_handler::set_selinux_patch(enabled) {
+            info!("Selinux patch: {enabled}");
+        }
+    }
+
+    pub fn set_capacity_units(&mut self, enabled: bool) {
+        if self.capacity_units != enabled {
+            info!("Capacity units: {enabled}");
+            self.capacity_units = enabled;
+        }
+    }
+
+    pub fn set_jank_routing(&mut self, enabled: bool) {
+        if self.jank_routing != enabled {
+            info!("Jank routing: {enabled}");
+            self.jank_routing = enabled;
         }
     }
 
+    pub fn set_top_cores(&mut self, strategy: TopCoresStrategy) {
+        if self.top_cores_strategy != strategy {
+            info!("Top used cores strategy: {strategy:?}");
+            self.top_cores = top_cores::strategy(&self.root, strategy);
+            self.top_cores_strategy = strategy;
+        }
+    }
+
+    pub fn set_usage_weighted(&mut self, weighted: bool) {
+        self.core_usage.set_weighted(weighted);
+    }
+
+    pub fn report_frame(&mut self, frametime: Duration, target_fps: f64) {
+        self.jank_boost.report_frame(frametime, target_fps);
+    }
+
+    // Caps every policy to a fraction of its max freq, reached gradually over updates
+    pub fn set_freq_cap(&mut self, cap: f64) {
+        self.freq_cap_target = cap.clamp(0.0, 1.0);
+    }
+
+    // The reason to log with each frequency change, None turns explain mode off
+    pub fn set_explain(&mut self, reason: Option<String>) {
+        self.explain = reason;
+    }
+
+    // None when the gpu doesn't report it
+    pub fn gpu_busy(&self) -> Option<f64> {
+        self.load_sampler
+            .reports_gpu_busy()
+            .then(|| self.load_sampler.gpu_busy())
+    }
+
+    // Applied on top of the freq cap without ramping, for idle scenes
+    pub const fn set_idle_cap(&mut self, cap: f64) {
+        self.idle_cap = cap.clamp(0.0, 1.0);
+    }
+
+    fn step_freq_cap(&mut self) -> f64 {
+        if self.freq_cap > self.freq_cap_target {
+            self.freq_cap = (self.freq_cap - 0.001).max(self.freq_cap_target);
+        } else {
+            self.freq_cap = (self.freq_cap + 0.001).min(self.freq_cap_target);
+        }
+
+        self.freq_cap.min(self.idle_cap)
+    }
+
     fn update_util_max(&mut self) {
         if let Some(util_max) = self.process_monitor.update() {
             self.util_max = Some(util_max);
@@ -259,6 +953,49 @@ impl Controller {
             .collect()
     }
 
+    // Same as compute_target_frequencies, but every policy is driven to the same capacity
+    // instead of the same khz
+    fn compute_target_capacities(
+        &mut self,
+        control: isize,
+        is_janked: bool,
+    ) -> HashMap<i32, isize> {
+        let cur_fas_capacity_max = self
+            .cpu_infos
+            .iter()
+            .map(|cpu| cpu.capacity_at(cpu.cur_fas_freq))
+            .fold(0.0, f64::max);
+        let cur_capacity_max = self
+            .cpu_infos
+            .iter()
+            .map(|cpu| cpu.capacity_at(cpu.cur_freq()))
+            .fold(0.0, f64::max);
+
+        if is_janked {
+            self.util_max = None;
+        } else {
+            self.update_util_max();
+        }
+
+        let control = control as f64 * CAPACITY_SCALE / CAPACITY_REFERENCE_FREQ;
+        let target = cur_fas_capacity_max + control;
+        let target = self
+            .util_max
+            .map_or(target, |util_max| {
+                let util_tracking_sugg_capacity = cur_capacity_max * util_max / 0.5; // min_util: 50%
+                debug!(
+                    "util: {util_max}, cur_capacity_max: {cur_capacity_max:.0}, util_tracking_sugg_capacity: {util_tracking_sugg_capacity:.0}"
+                );
+                target.min(util_tracking_sugg_capacity)
+            })
+            .clamp(0.0, CAPACITY_SCALE);
+
+        self.cpu_infos
+            .iter()
+            .map(|cpu| (cpu.policy, cpu.freq_for_capacity(target)))
+            .collect()
+    }
+
     fn sort_policies_topologically(&self) -> Vec<i32> {
         let mut graph: HashMap<_, Vec<_>> = HashMap::new();
         let mut indegree: HashMap<_, _> = HashMap::new();
@@ -311,48 +1048,6 @@ impl Controller {
         sorted_policies
     }
 
-    fn top_used_cores(&self) -> Option<CpuSet> {
-        let top_threads_cpu_sets: Vec<_> = self
-            .process_monitor
-            .top_threads()
-            .filter_map(|tid| sched_getaffinity(Pid::from_raw(tid)).ok())
-            .collect();
-
-        let mut counts = HashMap::new();
-        for cpu_set in top_threads_cpu_sets.iter().copied() {
-            *counts.entry(cpu_set).or_insert(0) += 1;
-        }
-
-        if counts.len() <= 1 {
-            let mut top_used_cores = CpuSet::new();
-            for cpuset in top_threads_cpu_sets {
-                for core in 0..num_cpus::get() {
-                    if cpuset.is_set(core).unwrap() {
-                        top_used_cores.set(core).unwrap();
-                    }
-                }
-            }
-
-            return Some(top_used_cores);
-        }
-
-        let (mode, _) = counts.into_iter().max_by_key(|&(_num, count)| count)?;
-
-        let mut top_used_cores = CpuSet::new();
-        for cpuset in top_threads_cpu_sets
-            .into_iter()
-            .filter(|cpu_set| *cpu_set != mode)
-        {
-            for core in 0..num_cpus::get() {
-                if cpuset.is_set(core).unwrap() {
-                    top_used_cores.set(core).unwrap();
-                }
-            }
-        }
-
-        Some(top_used_cores)
-    }
-
     fn apply_absolute_constraints(
         mut fas_freqs: HashMap<i32, isize>,
         sorted_policies: &[i32],
@@ -416,9 +1111,169 @@ impl Controller {
         fas_freqs
     }
 
+    // Followers of followers settle after as many passes as there are ratios
+    fn apply_policy_ratios(&self, mut fas_freqs: HashMap<i32, isize>) -> HashMap<i32, isize> {
+        for _ in 0..self.policy_ratios.len() {
+            for ratio in &self.policy_ratios {
+                if let Some(follow_freq) = fas_freqs.get(&ratio.follow).copied()
+                    && fas_freqs.contains_key(&ratio.policy)
+                {
+                    let freq = (follow_freq as f64 * ratio.ratio) as isize;
+                    fas_freqs.insert(ratio.policy, freq.clamp(0, self.max_freq));
+                }
+            }
+        }
+
+        fas_freqs
+    }
+
+    // A domain runs at what its most demanding policy asks for, the leader writes it
+    fn merge_mirrors(&self, mut fas_freqs: HashMap<i32, isize>) -> HashMap<i32, isize> {
+        for cpu in &self.cpu_infos {
+            let Some(leader) = cpu.mirror_of else {
+                continue;
+            };
+            if let (Some(freq), Some(leader_freq)) = (
+                fas_freqs.get(&cpu.policy).copied(),
+                fas_freqs.get_mut(&leader),
+            ) {
+                *leader_freq = (*leader_freq).max(freq);
+            }
+        }
+        for cpu in &self.cpu_infos {
+            if let Some(leader) = cpu.mirror_of
+                && let Some(leader_freq) = fas_freqs.get(&leader).copied()
+            {
+                fas_freqs.insert(cpu.policy, leader_freq);
+            }
+        }
+
+        fas_freqs
+    }
+
+    // Restores every policy regardless of what was written last, then releases the nodes
+    pub fn shutdown(&mut self) {
+        self.file_handler.invalidate();
+        self.reset_all_cpu_freq();
+    }
+
+    pub fn close_files(&mut self) {
+        self.file_handler.close();
+    }
+
     fn reset_all_cpu_freq(&mut self) {
+        self.file_handler.unlock_all();
+        STATUS.lock().interference = false;
         for cpu in &mut self.cpu_infos {
-            let _ = cpu.reset(&mut self.file_handler);
+            report_error(cpu.policy, cpu.reset(&mut self.file_handler));
+        }
+
+        for actuator in &mut self.actuators {
+            actuator.reset();
+        }
+        self.custom_nodes.stop(&mut self.file_handler);
+
+        self.devfreq.reset(&mut self.file_handler);
+        if let Some(gpu) = &mut self.gpu {
+            gpu.reset(&mut self.file_handler);
+        }
+        self.cpuidle_gate.restore();
+        self.top_app_boost.reset();
+        self.core_parking.reset();
+        self.load_sampler.reset();
+        self.core_usage.reset();
+        self.top_cores.reset();
+        for cpu in &mut self.cpu_infos {
+            cpu.usage_share = 1.0;
+        }
+    }
+
+    // Each policy's usage relative to the busiest one, scales the ceiling of non-critical
+    // policies, so idle ones drop while those doing real work keep theirs
+    fn update_usage_shares(&mut self) {
+        if !self.core_usage.weighted() {
+            for cpu in &mut self.cpu_infos {
+                cpu.usage_share = 1.0;
+            }
+            return;
+        }
+
+        let usages: Vec<_> = self
+            .cpu_infos
+            .iter()
+            .map(|cpu| self.core_usage.usage(cpu.cores()))
+            .collect();
+        let busiest = usages.iter().copied().fold(0.0, f64::max);
+
+        for (cpu, usage) in self.cpu_infos.iter_mut().zip(usages) {
+            cpu.usage_share = if busiest > 0.0 { usage / busiest } else { 1.0 };
+        }
+    }
+
+    // The little cluster (lowest policy) is left alone, games run their critical threads
+    // on the bigger ones
+    fn gate_cpuidle(&mut self) {
+        let cores = self
+            .cpu_infos
+            .iter()
+            .skip(1)
+            .flat_map(|cpu| cpu.cores().iter().copied());
+        self.cpuidle_gate.gate(cores);
+    }
+
+    // Policies with their frequency tables and state, for diagnostics dumps
+    pub fn describe(&self) -> String {
+        self.cpu_infos
+            .iter()
+            .map(Info::describe)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn self_test(&self) -> (String, bool) {
+        let (reports, results): (Vec<_>, Vec<_>) =
+            self.cpu_infos.iter().map(Info::self_test).unzip();
+        (reports.join("\n"), results.into_iter().all(|passed| passed))
+    }
+
+    pub fn state_json(&self) -> ControllerJson<'_> {
+        ControllerJson {
+            freq_cap: self.freq_cap,
+            freq_cap_target: self.freq_cap_target,
+            idle_cap: self.idle_cap,
+            util_max: self.util_max,
+            policies: self.cpu_infos.iter().map(Info::state_json).collect(),
+        }
+    }
+
+    // (policy, last written freq, freq it actually runs at) of every policy fas drives
+    pub fn freq_readback(&self) -> Vec<(i32, isize, isize)> {
+        self.cpu_infos
+            .iter()
+            .filter(|cpu| !cpu.observe_only && !cpu.ignore_write().unwrap_or(false))
+            .map(|cpu| (cpu.policy, cpu.cur_fas_freq, cpu.cur_freq()))
+            .collect()
+    }
+
+    // Every policy is already at the highest freq it may run at
+    pub fn freq_saturated(&self) -> bool {
+        self.cpu_infos
+            .iter()
+            .all(|cpu| cpu.cur_fas_freq >= cpu.freq_at(self.freq_cap))
+    }
+
+    // Highest fas freq relative to the top of the table
+    pub fn fas_freq_ratio(&self) -> f64 {
+        let fas_freq_max = self
+            .cpu_infos
+            .iter()
+            .map(|cpu| cpu.cur_fas_freq)
+            .max()
+            .unwrap_or_default();
+        if self.max_freq == 0 {
+            0.0
+        } else {
+            fas_freq_max as f64 / self.max_freq as f64
         }
     }
 
@@ -427,6 +1282,64 @@ impl Controller {
     }
 }
 
+// Policies running the game's top threads
+fn is_critical(cpu: &Info, top_used_cores: CpuSet) -> bool {
+    cpu.cores()
+        .iter()
+        .any(|core| top_used_cores.is_set(*core).unwrap_or(false))
+}
+
+// How a policy's written frequency came about, from the controller's frequency on through
+// the boost floors, the freq cap and the policy's own floor, ceiling and slew limit
+fn explain_policy(
+    cpu: &Info,
+    last_freq: isize,
+    fas_freq: isize,
+    floored: isize,
+    capped: isize,
+    freq_cap: f64,
+) -> String {
+    let mut steps = vec![format!("fas {fas_freq}khz")];
+    if floored > fas_freq {
+        steps.push(format!("boost floor {floored}khz"));
+    }
+    if capped < floored {
+        steps.push(format!("freq cap {freq_cap:.2} {capped}khz"));
+    }
+    if cpu.cur_fas_freq != capped {
+        let limit = if cpu
+            .slew_limit
+            .is_some_and(|limit| (cpu.cur_fas_freq - last_freq).abs() == limit)
+        {
+            "slew limit"
+        } else {
+            "floor, ceiling or table"
+        };
+        steps.push(format!("{limit} {}khz", cpu.cur_fas_freq));
+    }
+    format!(
+        "policy{}: {last_freq} -> {}khz ({})",
+        cpu.policy,
+        cpu.cur_fas_freq,
+        steps.join(", ")
+    )
+}
+
+// Errors of one policy don't stop the others, the watchdog keeps them for crash reports
+fn report_error(policy: i32, result: Result<()>) {
+    if let Err(e) = result {
+        watchdog::error(format!("CPU Policy{policy}: {e:#}"));
+    }
+}
+
+fn all_cores() -> CpuSet {
+    let mut all_cores = CpuSet::new();
+    for core in 0..num_cpus::get() {
+        all_cores.set(core).unwrap();
+    }
+    all_cores
+}
+
 fn no_extra_policy() -> bool {
     EXTRA_POLICY_MAP
         .get()
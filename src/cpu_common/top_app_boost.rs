@@ -0,0 +1,127 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use log::{debug, info};
+
+use crate::{file_handler, framework::CAPABILITIES, snapshot};
+
+const STUNE_BOOST: &str = "/dev/stune/top-app/schedtune.boost";
+const UCLAMP_MIN: &str = "/dev/cpuctl/top-app/cpu.uclamp.min";
+const STEP_UP_TIME: Duration = Duration::from_millis(500);
+const STEP_DOWN_TIME: Duration = Duration::from_secs(2);
+const STEP_UP: u32 = 10;
+const STEP_DOWN: u32 = 5;
+
+// A secondary knob next to frequency: boosts the top-app cgroup (schedtune or uclamp)
+// during jank bursts and lowers it again in stable scenes
+#[derive(Debug)]
+pub struct TopAppBoost {
+    node: Option<&'static str>,
+    original: Option<String>,
+    max: u32,
+    boost: u32,
+    timer: Instant,
+}
+
+impl TopAppBoost {
+    pub fn new() -> Self {
+        let node = if Path::new(STUNE_BOOST).exists() {
+            Some(STUNE_BOOST)
+        } else if CAPABILITIES.uclamp && Path::new(UCLAMP_MIN).exists() {
+            Some(UCLAMP_MIN)
+        } else {
+            None
+        };
+
+        if let Some(node) = node {
+            info!("Top-app boost node: {node}");
+        }
+
+        Self {
+            node,
+            original: None,
+            max: 0,
+            boost: 0,
+            timer: Instant::now(),
+        }
+    }
+
+    pub fn set_max(&mut self, max: u32) {
+        self.max = max.min(100);
+        if self.max == 0 {
+            self.reset();
+        } else if self.boost > self.max {
+            self.write(self.max);
+        }
+    }
+
+    pub fn update(&mut self, is_janked: bool) {
+        if self.max == 0 {
+            return;
+        }
+
+        let boost = if is_janked {
+            if self.timer.elapsed() < STEP_UP_TIME {
+                return;
+            }
+            (self.boost + STEP_UP).min(self.max)
+        } else {
+            if self.timer.elapsed() < STEP_DOWN_TIME {
+                return;
+            }
+            self.boost.saturating_sub(STEP_DOWN)
+        };
+
+        self.timer = Instant::now();
+        if boost != self.boost {
+            self.write(boost);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        if let Some(node) = self.node
+            && let Some(original) = self.original.take()
+        {
+            let _ = file_handler::write_direct(node, original);
+        }
+        self.boost = 0;
+    }
+
+    fn write(&mut self, boost: u32) {
+        let Some(node) = self.node else {
+            return;
+        };
+
+        if self.original.is_none() {
+            snapshot::record(node);
+            self.original = fs::read_to_string(node)
+                .ok()
+                .map(|value| value.trim().to_string());
+        }
+
+        if file_handler::write_direct(node, boost.to_string()).is_ok() {
+            debug!("top-app boost: {} -> {boost}", self.boost);
+            self.boost = boost;
+        }
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    path::Path,
+};
+
+use nix::{
+    sched::{CpuSet, sched_getaffinity},
+    unistd::Pid,
+};
+
+use super::{load_sampler::LoadSampler, process_monitor::ProcessMonitor};
+use crate::framework::config::TopCoresStrategy;
+
+// A core this busy relative to the busiest one runs game work
+const BUSY_SHARE: f64 = 0.6;
+// Cores below this load never count, on an idle device everything is relatively busy
+const MIN_LOAD: f64 = 0.2;
+// Load samples averaged by the windowed strategy, a second at the sampler's rate
+const WINDOW: usize = 10;
+
+// Picks the cores the game's critical work runs on, the policies owning them are clamped
+// together while the rest are left freer
+pub trait TopCores: Debug + Send {
+    // None when there is nothing to go on yet
+    fn top_used_cores(&mut self, process_monitor: &ProcessMonitor) -> Option<CpuSet>;
+    fn reset(&mut self);
+}
+
+pub fn strategy(root: &Path, strategy: TopCoresStrategy) -> Box<dyn TopCores> {
+    match strategy {
+        TopCoresStrategy::Affinity => Box::new(AffinityTopCores),
+        TopCoresStrategy::Load => Box::new(LoadTopCores::new(root)),
+        TopCoresStrategy::Windowed => Box::new(WindowedTopCores::new(root)),
+    }
+}
+
+// The cores the top threads are allowed on. Threads sharing the most common affinity are
+// left out when others are pinned apart, as they're usually the unpinned crowd.
+#[derive(Debug)]
+struct AffinityTopCores;
+
+impl TopCores for AffinityTopCores {
+    fn top_used_cores(&mut self, process_monitor: &ProcessMonitor) -> Option<CpuSet> {
+        let top_threads_cpu_sets: Vec<_> = process_monitor
+            .top_threads()
+            .filter_map(|tid| sched_getaffinity(Pid::from_raw(tid)).ok())
+            .collect();
+
+        let mut counts = HashMap::new();
+        for cpu_set in top_threads_cpu_sets.iter().copied() {
+            *counts.entry(cpu_set).or_insert(0) += 1;
+        }
+
+        if counts.len() <= 1 {
+            return Some(union(top_threads_cpu_sets));
+        }
+
+        let (mode, _) = counts.into_iter().max_by_key(|&(_num, count)| count)?;
+        Some(union(
+            top_threads_cpu_sets
+                .into_iter()
+                .filter(|cpu_set| *cpu_set != mode),
+        ))
+    }
+
+    fn reset(&mut self) {}
+}
+
+// The cores busy right now
+#[derive(Debug)]
+struct LoadTopCores {
+    sampler: LoadSampler,
+    last: Option<CpuSet>,
+}
+
+impl LoadTopCores {
+    fn new(root: &Path) -> Self {
+        Self {
+            sampler: LoadSampler::new(root),
+            last: None,
+        }
+    }
+}
+
+impl TopCores for LoadTopCores {
+    fn top_used_cores(&mut self, _: &ProcessMonitor) -> Option<CpuSet> {
+        if let Some(loads) = self.sampler.sample() {
+            self.last = busy_cores(&loads);
+        }
+        self.last
+    }
+
+    fn reset(&mut self) {
+        self.sampler.reset();
+        self.last = None;
+    }
+}
+
+// The cores busy on average over the last WINDOW samples, steadier through short bursts
+#[derive(Debug)]
+struct WindowedTopCores {
+    sampler: LoadSampler,
+    window: HashMap<usize, VecDeque<f64>>,
+    last: Option<CpuSet>,
+}
+
+impl WindowedTopCores {
+    fn new(root: &Path) -> Self {
+        Self {
+            sampler: LoadSampler::new(root),
+            window: HashMap::new(),
+            last: None,
+        }
+    }
+}
+
+impl TopCores for WindowedTopCores {
+    fn top_used_cores(&mut self, _: &ProcessMonitor) -> Option<CpuSet> {
+        if let Some(loads) = self.sampler.sample() {
+            for (core, load) in loads {
+                let samples = self.window.entry(core).or_default();
+                samples.push_front(load);
+                samples.truncate(WINDOW);
+            }
+
+            let averages: HashMap<_, _> = self
+                .window
+                .iter()
+                .map(|(core, samples)| (*core, samples.iter().sum::<f64>() / samples.len() as f64))
+                .collect();
+            self.last = busy_cores(&averages);
+        }
+        self.last
+    }
+
+    fn reset(&mut self) {
+        self.sampler.reset();
+        self.window.clear();
+        self.last = None;
+    }
+}
+
+fn busy_cores(loads: &HashMap<usize, f64>) -> Option<CpuSet> {
+    let busiest = loads.values().copied().fold(0.0, f64::max);
+    if busiest < MIN_LOAD {
+        return None;
+    }
+
+    let mut cores = CpuSet::new();
+    for (core, load) in loads {
+        if *load >= busiest * BUSY_SHARE && *load >= MIN_LOAD {
+            let _ = cores.set(*core);
+        }
+    }
+    Some(cores)
+}
+
+fn union(cpu_sets: impl IntoIterator<Item = CpuSet>) -> CpuSet {
+    let mut cores = CpuSet::new();
+    for cpu_set in cpu_sets {
+        for core in 0..num_cpus::get() {
+            if cpu_set.is_set(core).unwrap() {
+                cores.set(core).unwrap();
+            }
+        }
+    }
+    cores
+}
@@ -0,0 +1,140 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    mem,
+    path::Path,
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use parking_lot::{Mutex, const_mutex};
+
+const ABS_MT_POSITION_X: usize = 0x35;
+
+static LAST_TOUCH: Mutex<Option<Instant>> = const_mutex(None);
+static LISTENER: OnceLock<()> = OnceLock::new();
+
+#[derive(Debug)]
+pub struct TouchBoost {
+    duration: Duration,
+    floor: f64,
+}
+
+impl TouchBoost {
+    pub const fn new() -> Self {
+        Self {
+            duration: Duration::ZERO,
+            floor: 0.0,
+        }
+    }
+
+    pub fn set(&mut self, duration: Duration, floor: f64) {
+        if !duration.is_zero() {
+            watch_touch();
+        }
+
+        self.duration = duration;
+        self.floor = floor.clamp(0.0, 1.0);
+    }
+
+    // The floor as a fraction of each policy's max frequency, if a touch happened recently
+    pub fn active_floor(&self) -> Option<f64> {
+        if self.duration.is_zero() {
+            return None;
+        }
+
+        LAST_TOUCH
+            .lock()
+            .filter(|touch| touch.elapsed() < self.duration)
+            .map(|_| self.floor)
+    }
+}
+
+// Starts the touchscreen listeners without a boost, for anything else that wants to know
+// when the user last touched
+pub fn watch_touch() {
+    LISTENER.get_or_init(spawn_listeners);
+}
+
+pub fn last_touch() -> Option<Instant> {
+    *LAST_TOUCH.lock()
+}
+
+fn spawn_listeners() {
+    let Ok(entries) = fs::read_dir("/dev/input") else {
+        warn!("Failed to read /dev/input, touch input is unavailable");
+        return;
+    };
+
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        if !name.starts_with("event") || !is_touchscreen(name) {
+            continue;
+        }
+
+        info!("Listening for touches on {}", path.display());
+
+        let _ = thread::Builder::new()
+            .name("TouchBoostThread".into())
+            .spawn(move || listen(&path));
+    }
+}
+
+fn is_touchscreen(event: &str) -> bool {
+    let path = Path::new("/sys/class/input")
+        .join(event)
+        .join("device/capabilities/abs");
+    let Ok(capabilities) = fs::read_to_string(path) else {
+        return false;
+    };
+
+    // Words are printed most significant first
+    let word_bits = usize::BITS as usize;
+    capabilities
+        .split_whitespace()
+        .rev()
+        .nth(ABS_MT_POSITION_X / word_bits)
+        .and_then(|word| usize::from_str_radix(word, 16).ok())
+        .is_some_and(|word| word & (1 << (ABS_MT_POSITION_X % word_bits)) != 0)
+}
+
+fn listen(path: &Path) {
+    let mut device = match File::open(path) {
+        Ok(device) => device,
+        Err(e) => {
+            warn!("Failed to open {}: {e}", path.display());
+            return;
+        }
+    };
+
+    let mut buffer = vec![0; mem::size_of::<libc::input_event>() * 64];
+    while let Ok(len) = device.read(&mut buffer) {
+        if len == 0 {
+            break;
+        }
+
+        *LAST_TOUCH.lock() = Some(Instant::now());
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright 2024-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+mod selinux;
+mod worker;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use log::{debug, info};
+use parking_lot::Mutex;
+
+pub use selinux::{classify as classify_denial, set_selinux_patch};
+use worker::{Request, Worker};
+
+use crate::{quirks, sysfs, watchdog};
+
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(3);
+const QUEUE_SIZE: usize = 64;
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// The whole pipeline runs, but writes are only logged. For trying fas on a new device
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+// Returns whether it changed
+pub fn set_dry_run(enabled: bool) -> bool {
+    DRY_RUN.swap(enabled, Ordering::AcqRel) != enabled
+}
+
+// For changes that aren't node writes, e.g. priorities and settings
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Acquire)
+}
+
+// Nodes written once per session or on restore go straight to the file rather than through
+// the queue, dry run and the device quirks still apply
+pub fn write_direct<P, C>(path: P, content: C) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    C: AsRef<[u8]>,
+{
+    let Some(path) = quirks::get().resolve(sysfs::root(), path.as_ref()) else {
+        return Ok(());
+    };
+    let (path, content) = (path.as_path(), content.as_ref());
+    if dry_run() {
+        info!(
+            "dry run: {} <- {}",
+            path.display(),
+            String::from_utf8_lossy(content).trim()
+        );
+        return Ok(());
+    }
+    fs::write(path, content)
+}
+
+// Nodes are written on a worker thread, so a slow or busy node never stalls frame processing
+#[derive(Debug)]
+enum Backend {
+    Thread(SyncSender<Request>),
+    // The worker thread couldn't be spawned
+    Inline(Worker),
+}
+
+#[derive(Debug)]
+pub struct FileHandler {
+    // Quirk paths are placed under it, the nodes written are resolved by their owners
+    root: PathBuf,
+    backend: Backend,
+    unwritten: Arc<Mutex<Vec<PathBuf>>>,
+    last_values: HashMap<PathBuf, Vec<u8>>,
+    locked: HashSet<PathBuf>,
+    refresh_interval: Duration,
+    refresh_timer: Instant,
+    // Writes queued during a control tick, only the last value of each path is written
+    pending: Option<Vec<(PathBuf, Vec<u8>, Duration)>>,
+}
+
+impl FileHandler {
+    pub fn new(root: &Path) -> Self {
+        let unwritten = Arc::new(Mutex::new(Vec::new()));
+        Self {
+            root: root.to_path_buf(),
+            backend: spawn_backend(&unwritten),
+            unwritten,
+            last_values: HashMap::new(),
+            locked: HashSet::new(),
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            refresh_timer: Instant::now(),
+            pending: None,
+        }
+    }
+
+    pub fn begin_tick(&mut self) {
+        self.pending.get_or_insert_with(Vec::new);
+    }
+
+    // Writes what was queued since `begin_tick`, in the order the paths were first written
+    pub fn flush(&mut self) -> Result<()> {
+        let Some(pending) = self.pending.take() else {
+            return Ok(());
+        };
+
+        let mut result = Ok(());
+        for (path, content, delay) in pending {
+            if let Err(e) = self.write_delayed(&path, &content, delay) {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    // Writes of an unchanged value are skipped until the next forced refresh, which rewrites
+    // everything in case someone else overwrote the nodes meanwhile
+    pub const fn set_refresh_interval(&mut self, interval: Duration) {
+        self.refresh_interval = interval;
+    }
+
+    // Forget the written values, so the next writes go through no matter what
+    pub fn invalidate(&mut self) {
+        self.last_values.clear();
+    }
+
+    // The next write to the node goes through even if the value didn't change
+    pub fn forget<P>(&mut self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.last_values.remove(path.as_ref());
+    }
+
+    // Writes to the node make it read only for other processes from now on
+    pub fn lock<P>(&mut self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.locked.insert(path.as_ref().to_path_buf());
+    }
+
+    pub fn unlock_all(&mut self) {
+        for path in self.locked.drain() {
+            match &mut self.backend {
+                Backend::Thread(sx) => {
+                    let _ = sx.send(Request::Unlock(path));
+                }
+                Backend::Inline(worker) => worker.set_locked(path, false),
+            }
+        }
+    }
+
+    // Waits for the queued writes, so nothing lands after the caller restores the nodes
+    pub fn close(&mut self) {
+        match &mut self.backend {
+            Backend::Thread(sx) => {
+                let (ack, done) = mpsc::channel();
+                if sx.send(Request::Close(ack)).is_ok() {
+                    let _ = done.recv_timeout(CLOSE_TIMEOUT);
+                }
+            }
+            Backend::Inline(worker) => worker.close(),
+        }
+        self.last_values.clear();
+    }
+
+    pub fn write_with_workround<P, T>(&mut self, path: P, content: T) -> Result<()>
+    where
+        P: AsRef<Path>,
+        T: AsRef<[u8]>,
+    {
+        self.write_delayed(path, content, Duration::ZERO)
+    }
+
+    // The worker waits `delay` before the write, for kernels that refuse a limit written
+    // right after the other one
+    pub fn write_delayed<P, T>(&mut self, path: P, content: T, delay: Duration) -> Result<()>
+    where
+        P: AsRef<Path>,
+        T: AsRef<[u8]>,
+    {
+        if self.refresh_timer.elapsed() >= self.refresh_interval {
+            self.refresh_timer = Instant::now();
+            self.last_values.clear();
+        }
+
+        for path in self.unwritten.lock().drain(..) {
+            self.last_values.remove(&path);
+        }
+
+        // Avoided on this device, or written somewhere else
+        let Some(path) = quirks::get().resolve(&self.root, path.as_ref()) else {
+            return Ok(());
+        };
+        let path = path.as_path();
+        let content = content.as_ref();
+        if let Some(pending) = &mut self.pending {
+            match pending
+                .iter_mut()
+                .find(|(pending_path, _, _)| pending_path == path)
+            {
+                Some((_, pending_content, _)) => *pending_content = content.to_vec(),
+                None => pending.push((path.to_path_buf(), content.to_vec(), delay)),
+            }
+            return Ok(());
+        }
+
+        if self
+            .last_values
+            .get(path)
+            .is_some_and(|last| last.as_slice() == content)
+        {
+            return Ok(());
+        }
+
+        if dry_run() {
+            info!(
+                "dry run: {} <- {}",
+                path.display(),
+                String::from_utf8_lossy(content).trim()
+            );
+            self.last_values
+                .insert(path.to_path_buf(), content.to_vec());
+            return Ok(());
+        }
+
+        let lock = self.locked.contains(path);
+        match &mut self.backend {
+            Backend::Thread(sx) => {
+                match sx.try_send(Request::Write {
+                    path: path.to_path_buf(),
+                    content: content.to_vec(),
+                    lock,
+                    delay,
+                }) {
+                    Ok(()) => (),
+                    // Not remembered, so it's written again next time
+                    Err(TrySendError::Full(_)) => {
+                        debug!("write queue is full, dropped write to {}", path.display());
+                        return Ok(());
+                    }
+                    // The worker panicked, start a new one and write everything again
+                    Err(TrySendError::Disconnected(_)) => {
+                        watchdog::report("file writer worker exited");
+                        self.backend = spawn_backend(&self.unwritten);
+                        self.last_values.clear();
+                        return Err(anyhow!("write worker exited, restarted it"));
+                    }
+                }
+            }
+            Backend::Inline(worker) => worker.handle(path.to_path_buf(), content, lock, delay),
+        }
+
+        self.last_values
+            .insert(path.to_path_buf(), content.to_vec());
+        Ok(())
+    }
+}
+
+fn spawn_backend(unwritten: &Arc<Mutex<Vec<PathBuf>>>) -> Backend {
+    let (sx, rx) = mpsc::sync_channel(QUEUE_SIZE);
+    let worker = Worker::new(unwritten.clone());
+    match thread::Builder::new()
+        .name("FileWriterThread".into())
+        .spawn(move || worker.run(&rx))
+    {
+        Ok(_) => Backend::Thread(sx),
+        Err(_) => Backend::Inline(Worker::new(unwritten.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::FakeRoot;
+
+    const MAX_FREQ: &str = "/sys/devices/system/cpu/cpufreq/policy0/scaling_max_freq";
+
+    #[test]
+    fn writes_land_in_the_fake_tree() {
+        let root = FakeRoot::new("file-handler-write");
+        let path = root.write(MAX_FREQ, "1800000");
+
+        let mut file_handler = FileHandler::new(root.path());
+        file_handler.write_with_workround(&path, "1200000").unwrap();
+        file_handler.close();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1200000");
+    }
+
+    #[test]
+    fn a_tick_writes_the_last_value() {
+        let root = FakeRoot::new("file-handler-tick");
+        let path = root.write(MAX_FREQ, "1800000");
+
+        let mut file_handler = FileHandler::new(root.path());
+        file_handler.begin_tick();
+        file_handler.write_with_workround(&path, "600000").unwrap();
+        file_handler.write_with_workround(&path, "900000").unwrap();
+        file_handler.flush().unwrap();
+        file_handler.close();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "900000");
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use std::{
+    ffi::CString,
+    fs, io,
+    os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+    path::Path,
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::{info, warn};
+
+use crate::{
+    framework::control::FailureCause,
+    root_env::{self, RootImpl},
+};
+
+const SELINUX_ENFORCE: &str = "/sys/fs/selinux/enforce";
+const AVC_LOOKBACK: usize = 200;
+
+// Nodes refused by the policy get a targeted allow rule for fas' own domain
+static PATCH: AtomicBool = AtomicBool::new(false);
+
+// Returns whether it changed
+pub fn set_selinux_patch(enabled: bool) -> bool {
+    PATCH.swap(enabled, Ordering::AcqRel) != enabled
+}
+
+pub fn patch_enabled() -> bool {
+    PATCH.load(Ordering::Acquire)
+}
+
+// Root ignores the mode bits, so a refused write (EACCES) to a node with the owner write bit
+// set points at the policy, confirmed by a matching avc denial in the kernel log. Must run
+// before the mode is repaired, and an EPERM is the driver refusing the value, not a denial
+pub fn classify(path: &Path, error: &io::Error) -> FailureCause {
+    if error.raw_os_error() != Some(libc::EACCES) {
+        return FailureCause::Error(error.to_string());
+    }
+
+    let enforcing = fs::read_to_string(SELINUX_ENFORCE).is_ok_and(|enforce| enforce.trim() == "1");
+    let writable = fs::metadata(path).is_ok_and(|meta| meta.permissions().mode() & 0o200 != 0);
+    if !enforcing || !writable {
+        return FailureCause::Permission;
+    }
+
+    match context(path) {
+        Some(context) if avc_denied(path, &context) => FailureCause::Selinux(context),
+        _ => FailureCause::Permission,
+    }
+}
+
+// The denial is logged right as the write is refused, so only the tail of the log matters
+fn avc_denied(path: &Path, context: &str) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let Ok(output) = Command::new("dmesg").output() else {
+        return false;
+    };
+
+    let name = format!("name=\"{name}\"");
+    let target = format!("tcontext={context}");
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .rev()
+        .take(AVC_LOOKBACK)
+        .any(|line| {
+            line.contains("avc:")
+                && line.contains("denied")
+                && line.contains(&name)
+                && line.contains(&target)
+        })
+}
+
+fn context(path: &Path) -> Option<String> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut buffer = [0u8; 256];
+    let len = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            c"security.selinux".as_ptr(),
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+        )
+    };
+    if len <= 0 {
+        return None;
+    }
+
+    Some(
+        String::from_utf8_lossy(&buffer[..len as usize])
+            .trim_end_matches('\0')
+            .to_string(),
+    )
+}
+
+// Through the policy tool of the root implementation, e.g.
+// "allow magisk sysfs_devices_system_cpu file { open read write getattr setattr }"
+pub fn patch(context: &str) -> bool {
+    let Some(env) = root_env::get() else {
+        return false;
+    };
+    let (Some(domain), Some(target)) = (env.context.split(':').nth(2), context.split(':').nth(2))
+    else {
+        return false;
+    };
+
+    let rule = format!("allow {domain} {target} file {{ open read write getattr setattr }}");
+    let output = match env.root {
+        RootImpl::KernelSu => Command::new("ksud")
+            .args(["sepolicy", "patch", &rule])
+            .output(),
+        RootImpl::Magisk | RootImpl::APatch | RootImpl::Unknown => Command::new("magiskpolicy")
+            .args(["--live", &rule])
+            .output(),
+    };
+
+    match output {
+        Ok(output) if output.status.success() => {
+            info!("Patched selinux policy: {rule}");
+            true
+        }
+        Ok(output) => {
+            warn!(
+                "Failed to patch selinux policy ({rule}): {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            false
+        }
+        Err(e) => {
+            warn!("Failed to patch selinux policy ({rule}): {e}");
+            false
+        }
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, HashSet, hash_map::Entry},
+    fs::{self, File, set_permissions},
+    io::{self, ErrorKind, prelude::*},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use parking_lot::Mutex;
+use sys_mount::{UnmountFlags, unmount};
+
+use super::selinux;
+use crate::{
+    framework::control::{FailureCause, STATUS},
+    snapshot,
+};
+
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(10);
+// A node which kept failing is left alone for a while instead of stalling the queue
+const FAILED_BACKOFF: Duration = Duration::from_secs(5);
+
+pub enum Request {
+    Write {
+        path: PathBuf,
+        content: Vec<u8>,
+        // The node is made read only for everyone else after the write
+        lock: bool,
+        // Waited before the write
+        delay: Duration,
+    },
+    Unlock(PathBuf),
+    // Drop every open node, acknowledged once all earlier writes are done
+    Close(Sender<()>),
+}
+
+#[derive(Debug)]
+pub struct Worker {
+    files: HashMap<PathBuf, File>,
+    failed: HashMap<PathBuf, Instant>,
+    locked: HashSet<PathBuf>,
+    // Selinux contexts a policy patch was already tried for
+    patched: HashSet<String>,
+    // Writes which didn't happen, e.g. refused by the kernel (min above max mid re-ordering),
+    // handed back so the handler doesn't consider them written
+    unwritten: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl Worker {
+    pub fn new(unwritten: Arc<Mutex<Vec<PathBuf>>>) -> Self {
+        Self {
+            files: HashMap::new(),
+            failed: HashMap::new(),
+            locked: HashSet::new(),
+            patched: HashSet::new(),
+            unwritten,
+        }
+    }
+
+    pub fn run(mut self, rx: &Receiver<Request>) {
+        for request in rx {
+            match request {
+                Request::Write {
+                    path,
+                    content,
+                    lock,
+                    delay,
+                } => self.handle(path, &content, lock, delay),
+                Request::Unlock(path) => self.set_locked(path, false),
+                Request::Close(ack) => {
+                    self.close();
+                    let _ = ack.send(());
+                }
+            }
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.files.clear();
+    }
+
+    pub fn handle(&mut self, path: PathBuf, content: &[u8], lock: bool, delay: Duration) {
+        if self
+            .failed
+            .get(&path)
+            .is_some_and(|time| time.elapsed() < FAILED_BACKOFF)
+        {
+            self.unwritten.lock().push(path);
+            return;
+        }
+
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+
+        // The node may already hold it, e.g. after a forced refresh nobody interfered with.
+        // Read here rather than by the handler, nodes can be slow to read
+        if fs::read(&path).is_ok_and(|current| current.trim_ascii() == content.trim_ascii()) {
+            self.set_locked(path, lock);
+            return;
+        }
+
+        let mut attempts = 0;
+        let mut denial = None;
+        let error = loop {
+            attempts += 1;
+            let Err(e) = self.write(&path, content) else {
+                if self.failed.remove(&path).is_some() {
+                    STATUS
+                        .lock()
+                        .write_failures
+                        .retain(|(failed, _)| *failed != path);
+                }
+                self.set_locked(path, lock);
+                return;
+            };
+
+            match e.kind() {
+                ErrorKind::InvalidInput => {
+                    self.unwritten.lock().push(path);
+                    return;
+                }
+                // Someone changed the mode or owner of the node, repair it and reopen
+                // Classified before the repair, the mode bits are part of the evidence
+                ErrorKind::PermissionDenied => {
+                    self.files.remove(&path);
+                    let cause = denial.get_or_insert_with(|| selinux::classify(&path, &e));
+                    let _ = set_permissions(&path, PermissionsExt::from_mode(0o644));
+                    if let FailureCause::Selinux(context) = cause {
+                        self.try_patch(context);
+                    }
+                }
+                _ if e.raw_os_error() == Some(libc::EBUSY) => thread::sleep(RETRY_DELAY),
+                _ => break e,
+            }
+
+            if attempts >= MAX_ATTEMPTS {
+                break e;
+            }
+        };
+
+        if self.failed.insert(path.clone(), Instant::now()).is_none() {
+            let cause = denial
+                .filter(|_| error.kind() == ErrorKind::PermissionDenied)
+                .unwrap_or_else(|| FailureCause::Error(error.to_string()));
+            warn!("Failed to write {}: {error} ({cause})", path.display());
+            STATUS.lock().write_failures.push((path.clone(), cause));
+        }
+        self.unwritten.lock().push(path);
+    }
+
+    // Once per context, the write is retried right after
+    fn try_patch(&mut self, context: &str) {
+        if selinux::patch_enabled() && self.patched.insert(context.to_string()) {
+            selinux::patch(context);
+        }
+    }
+
+    // The open handle keeps working after the mode change, only later opens are refused
+    pub fn set_locked(&mut self, path: PathBuf, lock: bool) {
+        if lock {
+            let _ = set_permissions(&path, PermissionsExt::from_mode(0o444));
+            self.locked.insert(path);
+        } else if self.locked.remove(&path) {
+            let _ = set_permissions(&path, PermissionsExt::from_mode(0o644));
+        }
+    }
+
+    fn write(&mut self, path: &Path, content: &[u8]) -> io::Result<()> {
+        match self.files.entry(path.to_path_buf()) {
+            Entry::Occupied(mut entry) => {
+                entry.get_mut().write_all(content)?;
+            }
+            Entry::Vacant(entry) => {
+                let _ = unmount(path, UnmountFlags::DETACH);
+                snapshot::record(path);
+                set_permissions(path, PermissionsExt::from_mode(0o644))?;
+                let mut file = File::create(path)?;
+                file.write_all(content)?;
+                entry.insert(file);
+            }
+        }
+
+        Ok(())
+    }
+}
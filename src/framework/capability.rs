@@ -0,0 +1,82 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs, path::Path, sync::LazyLock};
+
+use log::info;
+
+pub static CAPABILITIES: LazyLock<Capabilities> = LazyLock::new(Capabilities::detect);
+
+// What the running kernel supports, evaluated once at startup so subsystems can check
+// here instead of failing at runtime when a feature is missing
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    pub kernel_version: (u32, u32),
+    pub uclamp: bool,
+    pub cgroup_v2: bool,
+    pub cpuset: bool,
+    pub energy_model: bool,
+    pub pidfd: bool,
+    pub bpf: bool,
+}
+
+impl Capabilities {
+    fn detect() -> Self {
+        let kernel_version = fs::read_to_string("/proc/sys/kernel/osrelease")
+            .ok()
+            .and_then(|release| Self::parse_kernel_version(&release))
+            .unwrap_or_default();
+
+        Self {
+            kernel_version,
+            uclamp: Path::new("/proc/sys/kernel/sched_util_clamp_min").exists()
+                || Path::new("/dev/cpuctl/top-app/cpu.uclamp.min").exists(),
+            cgroup_v2: Path::new("/sys/fs/cgroup/cgroup.controllers").exists(),
+            cpuset: Path::new("/dev/cpuset/background/cgroup.procs").exists(),
+            energy_model: Path::new("/sys/kernel/debug/energy_model").exists(),
+            // pidfd_open(2) landed in 5.3
+            pidfd: kernel_version >= (5, 3),
+            // Only present with CONFIG_BPF_SYSCALL
+            bpf: Path::new("/proc/sys/kernel/unprivileged_bpf_disabled").exists(),
+        }
+    }
+
+    fn parse_kernel_version(release: &str) -> Option<(u32, u32)> {
+        let mut version = release.trim().split(['.', '-']);
+        let major = version.next()?.parse().ok()?;
+        let minor = version.next()?.parse().ok()?;
+        Some((major, minor))
+    }
+
+    // For the self test report, one capability per line
+    pub fn describe(&self) -> String {
+        let (major, minor) = self.kernel_version;
+        format!(
+            "kernel: {major}.{minor}\nuclamp: {}\ncgroup v2: {}\ncpuset: {}\nenergy model: {}\npidfd: {}\nbpf: {}",
+            self.uclamp, self.cgroup_v2, self.cpuset, self.energy_model, self.pidfd, self.bpf
+        )
+    }
+
+    pub fn log(&self) {
+        let (major, minor) = self.kernel_version;
+        info!("Kernel version: {major}.{minor}");
+        info!(
+            "Capabilities: uclamp: {}, cgroup v2: {}, cpuset: {}, energy model: {}, pidfd: {}, bpf: {}",
+            self.uclamp, self.cgroup_v2, self.cpuset, self.energy_model, self.pidfd, self.bpf
+        );
+    }
+}
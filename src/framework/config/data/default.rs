@@ -15,7 +15,13 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use super::Config;
+use super::{
+    BackgroundThrottle, BatteryRule, BatteryTempRule, Config, ContextRule, CustomNode, EngineRule,
+    FilterWindow, FrametimeFilter, FrametimeTarget, FrametimeTargetRule, FreqCeiling, FreqFloor,
+    FreqSlew, GameMode, GamePropRule, GameRefreshRate, GameTypeRule, GpuProfile, OppPower,
+    PacingRule, PolicyRatio, PolicyTopology, PredictorMode, RtPolicy, SubProfileRule,
+    ThermalBudget, ThermalFpsStep, TopCoresStrategy,
+};
 
 impl Config {
     pub const fn default_value_keep_std() -> bool {
@@ -25,4 +31,420 @@ impl Config {
     pub const fn default_value_scene_game_list() -> bool {
         true
     }
+
+    pub const fn default_value_on_demand() -> bool {
+        false
+    }
+
+    pub const fn default_value_idle_exit_time() -> u64 {
+        300
+    }
+
+    pub const fn default_value_start_script() -> String {
+        String::new()
+    }
+
+    pub const fn default_value_stop_script() -> String {
+        String::new()
+    }
+
+    pub const fn default_value_observe_policies() -> Vec<i32> {
+        Vec::new()
+    }
+
+    pub const fn default_value_touch_boost_time() -> u64 {
+        0
+    }
+
+    pub const fn default_value_jank_boost_multiple() -> f64 {
+        0.0
+    }
+
+    pub const fn default_value_jank_boost_time() -> u64 {
+        200
+    }
+
+    pub const fn default_value_ramp_up_rate() -> f64 {
+        1.0
+    }
+
+    pub const fn default_value_ramp_down_rate() -> f64 {
+        1.0
+    }
+
+    pub const fn default_value_ramp_down_dwell() -> u64 {
+        0
+    }
+
+    pub const fn default_value_touch_boost_floor() -> f64 {
+        0.6
+    }
+
+    pub const fn default_value_refresh_pin() -> bool {
+        false
+    }
+
+    pub const fn default_value_battery_rules() -> Vec<BatteryRule> {
+        Vec::new()
+    }
+
+    pub const fn default_value_battery_max_freq() -> f64 {
+        1.0
+    }
+
+    pub const fn default_value_charging_mode() -> String {
+        String::new()
+    }
+
+    pub const fn default_value_resolution_hint() -> Vec<String> {
+        Vec::new()
+    }
+
+    pub const fn default_value_freq_refresh_interval() -> u64 {
+        3000
+    }
+
+    pub const fn default_value_devfreq_boost() -> bool {
+        false
+    }
+
+    pub const fn default_value_io_tune() -> bool {
+        false
+    }
+
+    pub const fn default_value_cpuidle_gate() -> bool {
+        false
+    }
+
+    pub const fn default_value_top_app_boost() -> u32 {
+        0
+    }
+
+    pub const fn default_value_frame_source_timeout() -> u64 {
+        3000
+    }
+
+    pub const fn default_value_freq_step() -> isize {
+        100_000
+    }
+
+    pub const fn default_value_interference_lock() -> bool {
+        false
+    }
+
+    pub const fn default_value_verify_interval() -> u64 {
+        3000
+    }
+
+    pub const fn default_value_verify_tolerance() -> f64 {
+        0.0
+    }
+
+    pub const fn default_value_verify_ignore_policies() -> Vec<i32> {
+        Vec::new()
+    }
+
+    pub const fn default_value_freq_floors() -> Vec<FreqFloor> {
+        Vec::new()
+    }
+
+    pub const fn default_value_freq_ceilings() -> Vec<FreqCeiling> {
+        Vec::new()
+    }
+
+    pub const fn default_value_policy_ratios() -> Vec<PolicyRatio> {
+        Vec::new()
+    }
+
+    pub const fn default_value_adaptive_refresh_target() -> bool {
+        false
+    }
+
+    pub const fn default_value_game_refresh_rates() -> Vec<GameRefreshRate> {
+        Vec::new()
+    }
+
+    pub const fn default_value_frametime_filter() -> FrametimeFilter {
+        FrametimeFilter::None
+    }
+
+    pub const fn default_value_frametime_filter_window() -> usize {
+        5
+    }
+
+    pub const fn default_value_frametime_filter_windows() -> Vec<FilterWindow> {
+        Vec::new()
+    }
+
+    pub const fn default_value_loading_detection() -> bool {
+        false
+    }
+
+    pub const fn default_value_rt_sched() -> RtPolicy {
+        RtPolicy::None
+    }
+
+    pub const fn default_value_rt_priority() -> i32 {
+        1
+    }
+
+    pub const fn default_value_game_modes() -> Vec<GameMode> {
+        Vec::new()
+    }
+
+    pub const fn default_value_notification() -> bool {
+        false
+    }
+
+    pub const fn default_value_thermal_fps_steps() -> Vec<ThermalFpsStep> {
+        Vec::new()
+    }
+
+    pub const fn default_value_thermal_fps_hysteresis() -> u64 {
+        3000
+    }
+
+    pub const fn default_value_core_parking() -> u32 {
+        0
+    }
+
+    pub const fn default_value_memory_tune() -> Vec<String> {
+        Vec::new()
+    }
+
+    pub const fn default_value_adpf() -> bool {
+        false
+    }
+
+    pub const fn default_value_system_game_mode() -> bool {
+        false
+    }
+
+    pub const fn default_value_load_fallback() -> bool {
+        true
+    }
+
+    pub const fn default_value_idle_scene() -> bool {
+        false
+    }
+
+    pub const fn default_value_freq_slews() -> Vec<FreqSlew> {
+        Vec::new()
+    }
+
+    pub const fn default_value_boost_freqs() -> bool {
+        false
+    }
+
+    pub const fn default_value_predictor() -> PredictorMode {
+        PredictorMode::Off
+    }
+
+    pub const fn default_value_cold_start_boost() -> u64 {
+        0
+    }
+
+    pub const fn default_value_powersave_apps() -> Vec<String> {
+        Vec::new()
+    }
+
+    pub const fn default_value_powersave_app_cap() -> f64 {
+        0.6
+    }
+
+    pub const fn default_value_policy_topology() -> Vec<PolicyTopology> {
+        Vec::new()
+    }
+
+    pub const fn default_value_critical_usage_threshold() -> f64 {
+        0.0
+    }
+
+    pub const fn default_value_critical_usage_decay() -> f64 {
+        0.8
+    }
+
+    pub const fn default_value_usage_weighted_freqs() -> bool {
+        false
+    }
+
+    pub const fn default_value_gpu_profiles() -> Vec<GpuProfile> {
+        Vec::new()
+    }
+
+    pub const fn default_value_dry_run() -> bool {
+        false
+    }
+
+    pub const fn default_value_game_cgroup() -> bool {
+        false
+    }
+
+    pub const fn default_value_game_cgroup_uclamp_min() -> u32 {
+        0
+    }
+
+    pub const fn default_value_game_cgroup_cpus() -> Vec<usize> {
+        Vec::new()
+    }
+
+    pub const fn default_value_background_throttle() -> BackgroundThrottle {
+        BackgroundThrottle::Off
+    }
+
+    pub const fn default_value_background_throttle_limit() -> f64 {
+        0.3
+    }
+
+    pub const fn default_value_compositor_boost() -> bool {
+        false
+    }
+
+    pub const fn default_value_compositor_cpus() -> Vec<usize> {
+        Vec::new()
+    }
+
+    pub const fn default_value_backpressure_detection() -> bool {
+        false
+    }
+
+    pub const fn default_value_frametime_target() -> FrametimeTarget {
+        FrametimeTarget::Last
+    }
+
+    pub const fn default_value_analysis_window() -> usize {
+        30
+    }
+
+    pub const fn default_value_frametime_targets() -> Vec<FrametimeTargetRule> {
+        Vec::new()
+    }
+
+    pub const fn default_value_battery_temp_rules() -> Vec<BatteryTempRule> {
+        Vec::new()
+    }
+
+    pub const fn default_value_custom_nodes() -> Vec<CustomNode> {
+        Vec::new()
+    }
+
+    pub const fn default_value_hook_exclude() -> Vec<String> {
+        Vec::new()
+    }
+
+    pub const fn default_value_webui() -> bool {
+        false
+    }
+
+    pub const fn default_value_webui_port() -> u16 {
+        8088
+    }
+
+    pub const fn default_value_selinux_patch() -> bool {
+        false
+    }
+
+    pub const fn default_value_capacity_units() -> bool {
+        false
+    }
+
+    pub const fn default_value_control_interval() -> u64 {
+        0
+    }
+
+    pub const fn default_value_idle_poll_interval() -> u64 {
+        500
+    }
+
+    pub const fn default_value_jank_routing() -> bool {
+        true
+    }
+
+    pub const fn default_value_launch_boost() -> u64 {
+        10
+    }
+
+    pub const fn default_value_broadcast() -> bool {
+        false
+    }
+
+    pub const fn default_value_game_types() -> Vec<GameTypeRule> {
+        Vec::new()
+    }
+
+    pub const fn default_value_thermal_budgets() -> Vec<ThermalBudget> {
+        Vec::new()
+    }
+
+    pub const fn default_value_pacing_assists() -> Vec<PacingRule> {
+        Vec::new()
+    }
+
+    pub const fn default_value_top_cores() -> TopCoresStrategy {
+        TopCoresStrategy::Affinity
+    }
+
+    pub const fn default_value_telemetry_retention() -> u32 {
+        30
+    }
+
+    pub const fn default_value_phase_drift() -> bool {
+        false
+    }
+
+    pub const fn default_value_engine_presets() -> bool {
+        true
+    }
+
+    pub const fn default_value_engines() -> Vec<EngineRule> {
+        Vec::new()
+    }
+
+    pub const fn default_value_explain() -> bool {
+        false
+    }
+
+    pub const fn default_value_game_props() -> Vec<GamePropRule> {
+        Vec::new()
+    }
+
+    pub const fn default_value_publish_props() -> bool {
+        false
+    }
+
+    pub const fn default_value_verify_auto_ignore() -> bool {
+        false
+    }
+
+    pub const fn default_value_sub_profiles() -> Vec<SubProfileRule> {
+        Vec::new()
+    }
+
+    pub const fn default_value_mirror_detection() -> bool {
+        true
+    }
+
+    pub const fn default_value_opp_power() -> Vec<OppPower> {
+        Vec::new()
+    }
+
+    pub const fn default_value_dma_latency() -> u32 {
+        0
+    }
+
+    pub const fn default_value_context_rules() -> Vec<ContextRule> {
+        Vec::new()
+    }
+
+    pub const fn default_value_control_uids() -> Vec<u32> {
+        Vec::new()
+    }
+
+    pub const fn default_value_trace_export() -> bool {
+        false
+    }
+
+    pub const fn default_value_mode_max_freq() -> f64 {
+        1.0
+    }
 }
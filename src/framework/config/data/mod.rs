@@ -17,10 +17,10 @@
 
 mod default;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use serde::{Deserialize, Serialize};
-use toml::Table;
+use toml::{Table, Value};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ConfigData {
@@ -42,12 +42,498 @@ pub struct Config {
     pub keep_std: bool,
     #[serde(default = "Config::default_value_scene_game_list")]
     pub scene_game_list: bool,
+    #[serde(default = "Config::default_value_on_demand")]
+    pub on_demand: bool,
+    #[serde(default = "Config::default_value_idle_exit_time")]
+    pub idle_exit_time: u64,
+    #[serde(default = "Config::default_value_start_script")]
+    pub start_script: String,
+    #[serde(default = "Config::default_value_stop_script")]
+    pub stop_script: String,
+    #[serde(default = "Config::default_value_observe_policies")]
+    pub observe_policies: Vec<i32>,
+    #[serde(default = "Config::default_value_touch_boost_time")]
+    pub touch_boost_time: u64,
+    #[serde(default = "Config::default_value_touch_boost_floor")]
+    pub touch_boost_floor: f64,
+    #[serde(default = "Config::default_value_jank_boost_multiple")]
+    pub jank_boost_multiple: f64,
+    #[serde(default = "Config::default_value_jank_boost_time")]
+    pub jank_boost_time: u64,
+    #[serde(default = "Config::default_value_ramp_up_rate")]
+    pub ramp_up_rate: f64,
+    #[serde(default = "Config::default_value_ramp_down_rate")]
+    pub ramp_down_rate: f64,
+    #[serde(default = "Config::default_value_ramp_down_dwell")]
+    pub ramp_down_dwell: u64,
+    #[serde(default = "Config::default_value_refresh_pin")]
+    pub refresh_pin: bool,
+    #[serde(default = "Config::default_value_battery_rules")]
+    pub battery_rules: Vec<BatteryRule>,
+    #[serde(default = "Config::default_value_charging_mode")]
+    pub charging_mode: String,
+    #[serde(default = "Config::default_value_resolution_hint")]
+    pub resolution_hint: Vec<String>,
+    #[serde(default = "Config::default_value_freq_refresh_interval")]
+    pub freq_refresh_interval: u64,
+    #[serde(default = "Config::default_value_devfreq_boost")]
+    pub devfreq_boost: bool,
+    #[serde(default = "Config::default_value_io_tune")]
+    pub io_tune: bool,
+    #[serde(default = "Config::default_value_cpuidle_gate")]
+    pub cpuidle_gate: bool,
+    #[serde(default = "Config::default_value_top_app_boost")]
+    pub top_app_boost: u32,
+    #[serde(default = "Config::default_value_frame_source_timeout")]
+    pub frame_source_timeout: u64,
+    #[serde(default = "Config::default_value_freq_step")]
+    pub freq_step: isize,
+    #[serde(default = "Config::default_value_interference_lock")]
+    pub interference_lock: bool,
+    #[serde(default = "Config::default_value_verify_interval")]
+    pub verify_interval: u64,
+    #[serde(default = "Config::default_value_verify_tolerance")]
+    pub verify_tolerance: f64,
+    #[serde(default = "Config::default_value_verify_ignore_policies")]
+    pub verify_ignore_policies: Vec<i32>,
+    #[serde(default = "Config::default_value_freq_floors")]
+    pub freq_floors: Vec<FreqFloor>,
+    #[serde(default = "Config::default_value_freq_ceilings")]
+    pub freq_ceilings: Vec<FreqCeiling>,
+    #[serde(default = "Config::default_value_policy_ratios")]
+    pub policy_ratios: Vec<PolicyRatio>,
+    #[serde(default = "Config::default_value_adaptive_refresh_target")]
+    pub adaptive_refresh_target: bool,
+    #[serde(default = "Config::default_value_game_refresh_rates")]
+    pub game_refresh_rates: Vec<GameRefreshRate>,
+    #[serde(default = "Config::default_value_frametime_filter")]
+    pub frametime_filter: FrametimeFilter,
+    #[serde(default = "Config::default_value_frametime_filter_window")]
+    pub frametime_filter_window: usize,
+    #[serde(default = "Config::default_value_frametime_filter_windows")]
+    pub frametime_filter_windows: Vec<FilterWindow>,
+    #[serde(default = "Config::default_value_loading_detection")]
+    pub loading_detection: bool,
+    #[serde(default = "Config::default_value_rt_sched")]
+    pub rt_sched: RtPolicy,
+    #[serde(default = "Config::default_value_rt_priority")]
+    pub rt_priority: i32,
+    #[serde(default = "Config::default_value_game_modes")]
+    pub game_modes: Vec<GameMode>,
+    #[serde(default = "Config::default_value_notification")]
+    pub notification: bool,
+    #[serde(default = "Config::default_value_thermal_fps_steps")]
+    pub thermal_fps_steps: Vec<ThermalFpsStep>,
+    #[serde(default = "Config::default_value_thermal_fps_hysteresis")]
+    pub thermal_fps_hysteresis: u64,
+    #[serde(default = "Config::default_value_core_parking")]
+    pub core_parking: u32,
+    #[serde(default = "Config::default_value_memory_tune")]
+    pub memory_tune: Vec<String>,
+    #[serde(default = "Config::default_value_adpf")]
+    pub adpf: bool,
+    #[serde(default = "Config::default_value_system_game_mode")]
+    pub system_game_mode: bool,
+    #[serde(default = "Config::default_value_load_fallback")]
+    pub load_fallback: bool,
+    #[serde(default = "Config::default_value_idle_scene")]
+    pub idle_scene: bool,
+    #[serde(default = "Config::default_value_freq_slews")]
+    pub freq_slews: Vec<FreqSlew>,
+    #[serde(default = "Config::default_value_boost_freqs")]
+    pub boost_freqs: bool,
+    #[serde(default = "Config::default_value_predictor")]
+    pub predictor: PredictorMode,
+    #[serde(default = "Config::default_value_cold_start_boost")]
+    pub cold_start_boost: u64,
+    #[serde(default = "Config::default_value_powersave_apps")]
+    pub powersave_apps: Vec<String>,
+    #[serde(default = "Config::default_value_powersave_app_cap")]
+    pub powersave_app_cap: f64,
+    #[serde(default = "Config::default_value_policy_topology")]
+    pub policy_topology: Vec<PolicyTopology>,
+    #[serde(default = "Config::default_value_critical_usage_threshold")]
+    pub critical_usage_threshold: f64,
+    #[serde(default = "Config::default_value_critical_usage_decay")]
+    pub critical_usage_decay: f64,
+    #[serde(default = "Config::default_value_usage_weighted_freqs")]
+    pub usage_weighted_freqs: bool,
+    #[serde(default = "Config::default_value_gpu_profiles")]
+    pub gpu_profiles: Vec<GpuProfile>,
+    #[serde(default = "Config::default_value_dry_run")]
+    pub dry_run: bool,
+    #[serde(default = "Config::default_value_game_cgroup")]
+    pub game_cgroup: bool,
+    #[serde(default = "Config::default_value_game_cgroup_uclamp_min")]
+    pub game_cgroup_uclamp_min: u32,
+    #[serde(default = "Config::default_value_game_cgroup_cpus")]
+    pub game_cgroup_cpus: Vec<usize>,
+    #[serde(default = "Config::default_value_background_throttle")]
+    pub background_throttle: BackgroundThrottle,
+    #[serde(default = "Config::default_value_background_throttle_limit")]
+    pub background_throttle_limit: f64,
+    #[serde(default = "Config::default_value_compositor_boost")]
+    pub compositor_boost: bool,
+    #[serde(default = "Config::default_value_compositor_cpus")]
+    pub compositor_cpus: Vec<usize>,
+    #[serde(default = "Config::default_value_backpressure_detection")]
+    pub backpressure_detection: bool,
+    #[serde(default = "Config::default_value_frametime_target")]
+    pub frametime_target: FrametimeTarget,
+    #[serde(default = "Config::default_value_analysis_window")]
+    pub analysis_window: usize,
+    #[serde(default = "Config::default_value_frametime_targets")]
+    pub frametime_targets: Vec<FrametimeTargetRule>,
+    #[serde(default = "Config::default_value_battery_temp_rules")]
+    pub battery_temp_rules: Vec<BatteryTempRule>,
+    #[serde(default = "Config::default_value_custom_nodes")]
+    pub custom_nodes: Vec<CustomNode>,
+    #[serde(default = "Config::default_value_hook_exclude")]
+    pub hook_exclude: Vec<String>,
+    #[serde(default = "Config::default_value_webui")]
+    pub webui: bool,
+    #[serde(default = "Config::default_value_webui_port")]
+    pub webui_port: u16,
+    #[serde(default = "Config::default_value_selinux_patch")]
+    pub selinux_patch: bool,
+    #[serde(default = "Config::default_value_capacity_units")]
+    pub capacity_units: bool,
+    #[serde(default = "Config::default_value_control_interval")]
+    pub control_interval: u64,
+    #[serde(default = "Config::default_value_idle_poll_interval")]
+    pub idle_poll_interval: u64,
+    #[serde(default = "Config::default_value_jank_routing")]
+    pub jank_routing: bool,
+    #[serde(default = "Config::default_value_launch_boost")]
+    pub launch_boost: u64,
+    #[serde(default = "Config::default_value_broadcast")]
+    pub broadcast: bool,
+    #[serde(default = "Config::default_value_game_types")]
+    pub game_types: Vec<GameTypeRule>,
+    #[serde(default = "Config::default_value_thermal_budgets")]
+    pub thermal_budgets: Vec<ThermalBudget>,
+    #[serde(default = "Config::default_value_pacing_assists")]
+    pub pacing_assists: Vec<PacingRule>,
+    #[serde(default = "Config::default_value_top_cores")]
+    pub top_cores: TopCoresStrategy,
+    #[serde(default = "Config::default_value_telemetry_retention")]
+    pub telemetry_retention: u32,
+    #[serde(default = "Config::default_value_phase_drift")]
+    pub phase_drift: bool,
+    #[serde(default = "Config::default_value_engine_presets")]
+    pub engine_presets: bool,
+    #[serde(default = "Config::default_value_engines")]
+    pub engines: Vec<EngineRule>,
+    #[serde(default = "Config::default_value_explain")]
+    pub explain: bool,
+    #[serde(default = "Config::default_value_game_props")]
+    pub game_props: Vec<GamePropRule>,
+    #[serde(default = "Config::default_value_publish_props")]
+    pub publish_props: bool,
+    #[serde(default = "Config::default_value_verify_auto_ignore")]
+    pub verify_auto_ignore: bool,
+    #[serde(default = "Config::default_value_sub_profiles")]
+    pub sub_profiles: Vec<SubProfileRule>,
+    #[serde(default = "Config::default_value_mirror_detection")]
+    pub mirror_detection: bool,
+    #[serde(default = "Config::default_value_opp_power")]
+    pub opp_power: Vec<OppPower>,
+    #[serde(default = "Config::default_value_dma_latency")]
+    pub dma_latency: u32,
+    #[serde(default = "Config::default_value_context_rules")]
+    pub context_rules: Vec<ContextRule>,
+    #[serde(default = "Config::default_value_control_uids")]
+    pub control_uids: Vec<u32>,
+    #[serde(default = "Config::default_value_trace_export")]
+    pub trace_export: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct FreqFloor {
+    pub policy: i32,
+    pub freq: isize,
+}
+
+// Wins over what sysfs reports for `policy`, some vendor kernels misreport it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolicyTopology {
+    pub policy: i32,
+    #[serde(default)]
+    pub cpus: Option<Vec<usize>>,
+    #[serde(default)]
+    pub role: Option<ClusterRole>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClusterRole {
+    Little,
+    Big,
+    Prime,
+}
+
+// step is in khz per control update
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct FreqSlew {
+    pub policy: i32,
+    pub step: isize,
+}
+
+// Either an absolute frequency or a fraction of the policy's max frequency
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct FreqCeiling {
+    pub policy: i32,
+    #[serde(default)]
+    pub freq: Option<isize>,
+    #[serde(default)]
+    pub ratio: Option<f64>,
+}
+
+// `policy` runs at `ratio` times the frequency fas picks for `follow`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct PolicyRatio {
+    pub policy: i32,
+    pub follow: i32,
+    pub ratio: f64,
+}
+
+// Overrides the global mode while `pkg` runs
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameMode {
+    pub pkg: String,
+    pub mode: String,
+}
+
+// Gpu devfreq limits (hz) while `pkg` runs, the floor is raised to keep `margin` of the gpu
+// idle
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GpuProfile {
+    pub pkg: String,
+    #[serde(default)]
+    pub min_freq: Option<isize>,
+    #[serde(default)]
+    pub max_freq: Option<isize>,
+    #[serde(default)]
+    pub margin: Option<f64>,
+}
+
+// What limits a game's frames, `stream` for cloud gaming and streaming apps that only decode
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GameType {
+    Game,
+    Stream,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameTypeRule {
+    pub pkg: String,
+    #[serde(rename = "type")]
+    pub game_type: GameType,
+}
+
+// The engine a game is built on, `custom` for anything without a preset
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    Unity,
+    Unreal,
+    Custom,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EngineRule {
+    pub pkg: String,
+    pub engine: Engine,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GamePropRule {
+    pub pkg: String,
+    pub props: BTreeMap<String, String>,
+}
+
+// A named variant of a game's settings, switched at runtime through the sub_profile node.
+// `target_fps` takes the same values as in `game_list`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubProfileRule {
+    pub pkg: String,
+    pub name: String,
+    #[serde(default)]
+    pub target_fps: Option<Value>,
+    #[serde(default)]
+    pub margin_fps: Option<f64>,
+}
+
+// Power of one core of `policy` at full load, as (khz, milliwatts) points
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OppPower {
+    pub policy: i32,
+    pub points: Vec<(isize, f64)>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameRefreshRate {
+    pub pkg: String,
+    pub refresh_rate: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FrametimeFilter {
+    None,
+    Ema,
+    Median,
+    Kalman,
+}
+
+// Which frametime of the analysis window the controller aims at the target
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FrametimeTarget {
+    Last,
+    Mean,
+    P95,
+    Worst,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RtPolicy {
+    None,
+    Fifo,
+    Rr,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundThrottle {
+    Off,
+    Uclamp,
+    Bandwidth,
+}
+
+// Only takes effect in builds with the predictor feature
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PredictorMode {
+    Off,
+    Record,
+    On,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FilterWindow {
+    pub pkg: String,
+    pub window: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrametimeTargetRule {
+    pub pkg: String,
+    pub target: FrametimeTarget,
+    #[serde(default)]
+    pub window: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct BatteryRule {
+    pub below: u32,
+    #[serde(default)]
+    pub fps_step: u32,
+    #[serde(default = "Config::default_value_battery_max_freq")]
+    pub max_freq: f64,
+}
+
+// Above `above` (0.001℃) the target fps is lowered by `fps_step` steps
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ThermalFpsStep {
+    pub above: u64,
+    pub fps_step: u32,
+}
+
+// Sustained limits while `pkg` runs, core temperature in 0.001℃ and power draw in watts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThermalBudget {
+    pub pkg: String,
+    #[serde(default)]
+    pub temp: Option<u64>,
+    #[serde(default)]
+    pub power: Option<f64>,
+}
+
+// How the cores running the game's critical work are picked
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TopCoresStrategy {
+    Affinity,
+    Load,
+    Windowed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PacingMode {
+    Off,
+    Suggest,
+    Apply,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PacingRule {
+    pub pkg: String,
+    pub mode: PacingMode,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CustomNode {
+    pub path: String,
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default)]
+    pub stop: Option<String>,
+    // Template filled in from the controller output on every update
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+// Picked at session start, every condition given has to hold. `from` and `to` are local
+// "HH:MM" and may wrap past midnight, temperatures are in 0.001℃
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContextRule {
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub core_above: Option<u64>,
+    #[serde(default)]
+    pub battery_above: Option<u64>,
+    #[serde(default)]
+    pub mode: Option<String>,
+    #[serde(default)]
+    pub sub_profile: Option<String>,
+}
+
+// Above `above` (0.001℃ of battery temperature), optionally only while charging
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct BatteryTempRule {
+    pub above: u64,
+    #[serde(default)]
+    pub charging: bool,
+    #[serde(default)]
+    pub fps_step: u32,
+    #[serde(default = "Config::default_value_battery_max_freq")]
+    pub max_freq: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ModeConfig {
     pub margin_fps: MarginFps,
     pub core_temp_thresh: TemperatureThreshold,
+    // Fraction of each policy's max freq it may run at in this mode
+    #[serde(default = "Config::default_value_mode_max_freq")]
+    pub max_freq: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
@@ -0,0 +1,147 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use dumpsys_rs::Dumpsys;
+use toml::{Table, Value};
+
+use super::{Config, ConfigData};
+use crate::framework::error::{Error, Result};
+
+// Caps games commonly offer, proposed up to the panel's top refresh rate
+const COMMON_CAPS: [u32; 6] = [30, 45, 60, 90, 120, 144];
+
+impl Config {
+    // Installed games missing from the local config's game list, each with a proposed target
+    // fps. Without the panel's refresh rates the proposal is "auto"
+    pub fn discover_games<S>(l: S) -> Result<Vec<(String, Value)>>
+    where
+        S: AsRef<str>,
+    {
+        let known: Vec<_> = Self::game_list_entries(l)?
+            .into_iter()
+            .map(|(pkg, _)| pkg)
+            .collect();
+        let packages = Dumpsys::new("package")
+            .and_then(|mut dumper| dumper.dump(&["packages"]).ok())
+            .ok_or(Error::Other("failed to dump installed packages"))?;
+        let refresh_rates = Dumpsys::new("display")
+            .and_then(|mut dumper| dumper.dump(&[]).ok())
+            .map(|dump| parse_supported_refresh_rates(&dump))
+            .unwrap_or_default();
+        let target_fps = proposed_target_fps(&refresh_rates);
+
+        Ok(parse_game_packages(&packages)
+            .into_iter()
+            .filter(|pkg| !known.contains(pkg))
+            .map(|pkg| (pkg, target_fps.clone()))
+            .collect())
+    }
+
+    // Appends the games to the game list, refusing anything fas couldn't read back
+    pub fn add_games<S>(l: S, games: &[(String, Value)]) -> Result<String>
+    where
+        S: AsRef<str>,
+    {
+        let mut local_conf: Table = toml::from_str(l.as_ref())?;
+        let game_list = local_conf
+            .entry("game_list")
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .ok_or(Error::ParseConfig)?;
+
+        for (pkg, target_fps) in games {
+            game_list
+                .entry(pkg.clone())
+                .or_insert_with(|| target_fps.clone());
+        }
+
+        let new = toml::to_string(&local_conf)?;
+        toml::from_str::<ConfigData>(&new)?;
+        Ok(new)
+    }
+}
+
+// Packages are dumped one block each, the system marks games with the game category, e.g.
+//   Package [com.example.game] (1a2b3c4):
+//     ...
+//     category=GAME
+fn parse_game_packages(dump: &str) -> Vec<String> {
+    let mut games = Vec::new();
+    let mut pkg = None;
+    for line in dump.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("Package [") {
+            pkg = name.split(']').next().map(ToString::to_string);
+        } else if (line == "category=GAME" || line == "category=0" || line.contains(" IS_GAME "))
+            && let Some(pkg) = pkg.take()
+            && !games.contains(&pkg)
+        {
+            games.push(pkg);
+        }
+    }
+    games
+}
+
+// Every mode the built-in panel supports, e.g.
+// mSupportedModes=[DisplayMode{id=1, width=1080, height=2400, fps=60.0, ...}, DisplayMode{...}]
+fn parse_supported_refresh_rates(dump: &str) -> Vec<u32> {
+    let Some(line) = dump
+        .lines()
+        .find(|line| line.contains("mSupportedModes=") || line.contains("supportedModes"))
+    else {
+        return Vec::new();
+    };
+
+    let mut rates: Vec<u32> = line
+        .split("fps=")
+        .skip(1)
+        .filter_map(|fps| {
+            fps.split([',', '}', ' '])
+                .next()?
+                .trim()
+                .parse::<f64>()
+                .ok()
+        })
+        .filter(|fps| fps.is_finite() && *fps > 0.0)
+        .map(|fps| fps.round() as u32)
+        .collect();
+    rates.sort_unstable();
+    rates.dedup();
+    rates
+}
+
+// The common caps the panel can show, plus the panel's own rates
+fn proposed_target_fps(refresh_rates: &[u32]) -> Value {
+    let Some(max) = refresh_rates.iter().max().copied() else {
+        return Value::String("auto".into());
+    };
+
+    let mut target_fps: Vec<_> = COMMON_CAPS
+        .iter()
+        .copied()
+        .filter(|cap| *cap <= max)
+        .chain(refresh_rates.iter().copied())
+        .collect();
+    target_fps.sort_unstable();
+    target_fps.dedup();
+    Value::Array(
+        target_fps
+            .into_iter()
+            .map(|fps| Value::Integer(fps.into()))
+            .collect(),
+    )
+}
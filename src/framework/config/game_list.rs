@@ -0,0 +1,101 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use toml::{Table, Value};
+
+use super::{Config, ConfigData, fps_value};
+use crate::framework::error::{Error, Result};
+
+impl Config {
+    // (package, target fps as written in toml) of every game in the local config
+    pub fn game_list_entries<S>(l: S) -> Result<Vec<(String, String)>>
+    where
+        S: AsRef<str>,
+    {
+        let local_conf: Table = toml::from_str(l.as_ref())?;
+        let game_list = local_conf
+            .get("game_list")
+            .and_then(Value::as_table)
+            .ok_or(Error::ParseConfig)?;
+
+        Ok(game_list
+            .iter()
+            .map(|(pkg, target_fps)| (pkg.clone(), target_fps.to_string()))
+            .collect())
+    }
+
+    // Sets or, without a target fps, removes a game, refusing anything fas couldn't read back
+    pub fn edit_game<S>(l: S, pkg: &str, target_fps: Option<&str>) -> Result<String>
+    where
+        S: AsRef<str>,
+    {
+        if pkg.is_empty()
+            || !pkg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_')
+        {
+            return Err(Error::Other("invalid package name"));
+        }
+
+        let mut local_conf: Table = toml::from_str(l.as_ref())?;
+        let game_list = local_conf
+            .entry("game_list")
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .ok_or(Error::ParseConfig)?;
+
+        match target_fps {
+            Some(target_fps) => {
+                let target_fps = parse_target_fps(target_fps)?;
+                game_list.insert(pkg.to_string(), target_fps);
+            }
+            None => {
+                game_list
+                    .remove(pkg)
+                    .ok_or(Error::Other("package not found in game_list"))?;
+            }
+        }
+
+        let new = toml::to_string(&local_conf)?;
+        toml::from_str::<ConfigData>(&new)?;
+        Ok(new)
+    }
+}
+
+// "auto", a single fps or a non-empty array of them
+fn parse_target_fps(target_fps: &str) -> Result<Value> {
+    let target_fps = target_fps.trim();
+    if target_fps == "auto" {
+        return Ok(Value::String(target_fps.to_string()));
+    }
+
+    let mut table: Table = toml::from_str(&format!("target_fps = {target_fps}"))?;
+    let value = table.remove("target_fps").ok_or(Error::ParseConfig)?;
+    let valid = match &value {
+        Value::String(s) => s == "auto",
+        Value::Integer(_) | Value::Float(_) => fps_value(&value).is_some(),
+        Value::Array(arr) => !arr.is_empty() && arr.iter().all(|fps| fps_value(fps).is_some()),
+        _ => false,
+    };
+
+    if valid {
+        Ok(value)
+    } else {
+        Err(Error::Other(
+            "target fps must be \"auto\", a positive fps or an array of them",
+        ))
+    }
+}
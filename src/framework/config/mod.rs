@@ -16,29 +16,59 @@
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
 mod data;
+mod discover;
+mod game_list;
 mod inner;
 mod merge;
+mod profile;
 mod read;
 
-use std::{fs, path::Path, sync::mpsc, thread};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
 
 use inner::Inner;
 use log::{error, info};
 use toml::Value;
 
-use crate::framework::{error::Result, node::Mode};
-pub use data::{ConfigData, MarginFps, ModeConfig, TemperatureThreshold};
+use crate::{
+    framework::{error::Result, node::Mode},
+    watchdog,
+};
+pub use data::{
+    BackgroundThrottle, BatteryRule, BatteryTempRule, ClusterRole, ConfigData, CustomNode, Engine,
+    FrametimeFilter, FrametimeTarget, FreqCeiling, FreqFloor, FreqSlew, GameType, GpuProfile,
+    MarginFps, ModeConfig, OppPower, PacingMode, PolicyRatio, PolicyTopology, PredictorMode,
+    RtPolicy, TemperatureThreshold, ThermalBudget, ThermalFpsStep, TopCoresStrategy,
+};
 use read::wait_and_read;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+// Games set to "auto" and scene games start out matching these, until calibrated
+const AUTO_TARGET_FPS: [f64; 8] = [15.0, 25.0, 30.0, 45.0, 60.0, 90.0, 120.0, 144.0];
+
+// Fractional, as some games cap at e.g. 59.94
+#[derive(Debug, Clone, PartialEq)]
 pub enum TargetFps {
-    Value(u32),
-    Array(Vec<u32>),
+    Value(f64),
+    Array(Vec<f64>),
+}
+
+// A game's sub-profile as it applies, unset fields keep the game's own settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubProfile {
+    pub name: String,
+    pub target_fps: Option<TargetFps>,
+    pub margin_fps: Option<f64>,
 }
 
 #[derive(Debug)]
 pub struct Config {
     inner: Inner,
+    path: PathBuf,
 }
 
 impl Config {
@@ -68,7 +98,15 @@ impl Config {
 
         info!("Config watcher started");
 
-        Ok(Self { inner })
+        Ok(Self {
+            inner,
+            path: path.to_owned(),
+        })
+    }
+
+    // The user config, as opposed to the module's standard one
+    pub fn path(&self) -> &Path {
+        &self.path
     }
 
     pub fn need_fas<S>(&mut self, pkg: S) -> bool
@@ -88,31 +126,30 @@ impl Config {
         let pkg = pkg.as_ref();
         let pkg = pkg.split(':').next()?;
 
-        self.inner.config().game_list.get(pkg).cloned().map_or_else(
+        self.inner.config().game_list.get(pkg).map_or_else(
             || {
                 if self.inner.config().scene_game_list.contains(pkg) {
-                    Some(TargetFps::Array(vec![15, 25, 30, 45, 60, 90, 120, 144]))
+                    Some(TargetFps::Array(AUTO_TARGET_FPS.to_vec()))
                 } else {
                     None
                 }
             },
-            |value| match value {
-                Value::Array(arr) => {
-                    let mut arr: Vec<_> = arr
-                        .iter()
-                        .filter_map(toml::Value::as_integer)
-                        .map(|i| i as u32)
-                        .collect();
-                    arr.sort_unstable();
-                    Some(TargetFps::Array(arr))
-                }
-                Value::Integer(i) => Some(TargetFps::Value(i as u32)),
-                _ => {
-                    error!("Find target game {pkg} in config, but meet illegal data type");
-                    error!("Sugg: try \'{pkg} = \"auto\"\'");
-                    None
-                }
-            },
+            |value| target_fps_value(pkg, value),
+        )
+    }
+
+    // No fps list is configured for the game, so its cap gets calibrated
+    pub fn auto_target_fps<S>(&mut self, pkg: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        let Some(pkg) = pkg.as_ref().split(':').next() else {
+            return false;
+        };
+
+        self.inner.config().game_list.get(pkg).map_or_else(
+            || self.inner.config().scene_game_list.contains(pkg),
+            |value| value.as_str() == Some("auto"),
         )
     }
 
@@ -126,6 +163,603 @@ impl Config {
         }
     }
 
+    pub fn on_demand(&mut self) -> bool {
+        self.inner.config().config.on_demand
+    }
+
+    pub fn idle_exit_time(&mut self) -> Duration {
+        Duration::from_secs(self.inner.config().config.idle_exit_time)
+    }
+
+    pub fn start_script(&mut self) -> String {
+        self.inner.config().config.start_script.clone()
+    }
+
+    pub fn stop_script(&mut self) -> String {
+        self.inner.config().config.stop_script.clone()
+    }
+
+    pub fn observe_policies(&mut self) -> &[i32] {
+        &self.inner.config().config.observe_policies
+    }
+
+    pub fn freq_refresh_interval(&mut self) -> Duration {
+        Duration::from_millis(self.inner.config().config.freq_refresh_interval)
+    }
+
+    pub fn freq_floors(&mut self) -> &[FreqFloor] {
+        &self.inner.config().config.freq_floors
+    }
+
+    pub fn freq_ceilings(&mut self) -> &[FreqCeiling] {
+        &self.inner.config().config.freq_ceilings
+    }
+
+    pub fn freq_slews(&mut self) -> &[FreqSlew] {
+        &self.inner.config().config.freq_slews
+    }
+
+    pub fn gpu_profile<S>(&mut self, pkg: S) -> Option<GpuProfile>
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .gpu_profiles
+            .iter()
+            .find(|profile| profile.pkg == pkg)
+            .cloned()
+    }
+
+    pub fn policy_topology(&mut self) -> &[PolicyTopology] {
+        &self.inner.config().config.policy_topology
+    }
+
+    pub fn game_refresh_rate<S>(&mut self, pkg: S) -> Option<u32>
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .game_refresh_rates
+            .iter()
+            .find(|rule| rule.pkg == pkg)
+            .map(|rule| rule.refresh_rate)
+    }
+
+    pub fn game_mode<S>(&mut self, pkg: S) -> Option<Mode>
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .game_modes
+            .iter()
+            .find(|rule| rule.pkg == pkg)
+            .and_then(|rule| rule.mode.parse().ok())
+    }
+
+    pub fn frametime_filter(&mut self) -> FrametimeFilter {
+        self.inner.config().config.frametime_filter
+    }
+
+    pub fn frametime_filter_window<S>(&mut self, pkg: S) -> usize
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        let config = &self.inner.config().config;
+        config
+            .frametime_filter_windows
+            .iter()
+            .find(|rule| rule.pkg == pkg)
+            .map_or(config.frametime_filter_window, |rule| rule.window)
+    }
+
+    pub fn game_type<S>(&mut self, pkg: S) -> GameType
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .game_types
+            .iter()
+            .find(|rule| rule.pkg == pkg)
+            .map_or(GameType::Game, |rule| rule.game_type)
+    }
+
+    // The frametime to target and the window (in frames) it's taken from. Stream games aim
+    // at the mean by default, a late frame there is network jitter more clocks can't fix
+    pub fn frametime_target<S>(&mut self, pkg: S) -> (FrametimeTarget, usize)
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        let default_target = if self.game_type(pkg) == GameType::Stream {
+            FrametimeTarget::Mean
+        } else {
+            self.inner.config().config.frametime_target
+        };
+        let config = &self.inner.config().config;
+        config
+            .frametime_targets
+            .iter()
+            .find(|rule| rule.pkg == pkg)
+            .map_or((default_target, config.analysis_window), |rule| {
+                (rule.target, rule.window.unwrap_or(config.analysis_window))
+            })
+    }
+
+    pub fn loading_detection(&mut self) -> bool {
+        self.inner.config().config.loading_detection
+    }
+
+    pub fn rt_sched(&mut self) -> RtPolicy {
+        self.inner.config().config.rt_sched
+    }
+
+    pub fn rt_priority(&mut self) -> i32 {
+        self.inner.config().config.rt_priority
+    }
+
+    pub fn notification(&mut self) -> bool {
+        self.inner.config().config.notification
+    }
+
+    pub fn thermal_fps_steps(&mut self) -> &[ThermalFpsStep] {
+        &self.inner.config().config.thermal_fps_steps
+    }
+
+    pub fn thermal_fps_hysteresis(&mut self) -> u64 {
+        self.inner.config().config.thermal_fps_hysteresis
+    }
+
+    pub fn core_parking(&mut self) -> u32 {
+        self.inner.config().config.core_parking
+    }
+
+    pub fn adpf(&mut self) -> bool {
+        self.inner.config().config.adpf
+    }
+
+    pub fn system_game_mode(&mut self) -> bool {
+        self.inner.config().config.system_game_mode
+    }
+
+    pub fn load_fallback(&mut self) -> bool {
+        self.inner.config().config.load_fallback
+    }
+
+    pub fn idle_scene(&mut self) -> bool {
+        self.inner.config().config.idle_scene
+    }
+
+    pub fn boost_freqs(&mut self) -> bool {
+        self.inner.config().config.boost_freqs
+    }
+
+    #[cfg(feature = "predictor")]
+    pub fn predictor(&mut self) -> PredictorMode {
+        self.inner.config().config.predictor
+    }
+
+    pub fn cold_start_boost(&mut self) -> Duration {
+        Duration::from_secs(self.inner.config().config.cold_start_boost)
+    }
+
+    pub fn powersave_app<S>(&mut self, pkg: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .powersave_apps
+            .iter()
+            .any(|app| app == pkg)
+    }
+
+    pub fn powersave_app_cap(&mut self) -> f64 {
+        self.inner.config().config.powersave_app_cap.clamp(0.0, 1.0)
+    }
+
+    pub fn critical_usage_threshold(&mut self) -> f64 {
+        self.inner.config().config.critical_usage_threshold
+    }
+
+    pub fn critical_usage_decay(&mut self) -> f64 {
+        self.inner.config().config.critical_usage_decay
+    }
+
+    pub fn usage_weighted_freqs(&mut self) -> bool {
+        self.inner.config().config.usage_weighted_freqs
+    }
+
+    pub fn dry_run(&mut self) -> bool {
+        self.inner.config().config.dry_run
+    }
+
+    pub fn game_cgroup(&mut self) -> bool {
+        self.inner.config().config.game_cgroup
+    }
+
+    pub fn game_cgroup_uclamp_min(&mut self) -> u32 {
+        self.inner.config().config.game_cgroup_uclamp_min
+    }
+
+    pub fn game_cgroup_cpus(&mut self) -> &[usize] {
+        &self.inner.config().config.game_cgroup_cpus
+    }
+
+    pub fn background_throttle(&mut self) -> BackgroundThrottle {
+        self.inner.config().config.background_throttle
+    }
+
+    pub fn background_throttle_limit(&mut self) -> f64 {
+        self.inner.config().config.background_throttle_limit
+    }
+
+    pub fn compositor_boost(&mut self) -> bool {
+        self.inner.config().config.compositor_boost
+    }
+
+    pub fn compositor_cpus(&mut self) -> &[usize] {
+        &self.inner.config().config.compositor_cpus
+    }
+
+    pub fn backpressure_detection(&mut self) -> bool {
+        self.inner.config().config.backpressure_detection
+    }
+
+    pub fn battery_temp_rules(&mut self) -> &[BatteryTempRule] {
+        &self.inner.config().config.battery_temp_rules
+    }
+
+    pub fn custom_nodes(&mut self) -> &[CustomNode] {
+        &self.inner.config().config.custom_nodes
+    }
+
+    pub fn hook_excluded<S>(&mut self, pkg: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .hook_exclude
+            .iter()
+            .any(|excluded| excluded == pkg)
+    }
+
+    pub fn webui(&mut self) -> bool {
+        self.inner.config().config.webui
+    }
+
+    pub fn webui_port(&mut self) -> u16 {
+        self.inner.config().config.webui_port
+    }
+
+    pub fn selinux_patch(&mut self) -> bool {
+        self.inner.config().config.selinux_patch
+    }
+
+    pub fn capacity_units(&mut self) -> bool {
+        self.inner.config().config.capacity_units
+    }
+
+    pub fn control_interval(&mut self) -> Duration {
+        Duration::from_millis(self.inner.config().config.control_interval)
+    }
+
+    // Well below the watchdog's stall time, the idle loop only feeds it once per poll
+    pub fn idle_poll_interval(&mut self) -> Duration {
+        Duration::from_millis(self.inner.config().config.idle_poll_interval)
+            .min(watchdog::STALL_TIME / 3)
+    }
+
+    pub fn jank_routing(&mut self) -> bool {
+        self.inner.config().config.jank_routing
+    }
+
+    pub fn launch_boost(&mut self) -> Duration {
+        Duration::from_secs(self.inner.config().config.launch_boost)
+    }
+
+    pub fn broadcast(&mut self) -> bool {
+        self.inner.config().config.broadcast
+    }
+
+    pub fn thermal_budget<S>(&mut self, pkg: S) -> Option<ThermalBudget>
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .thermal_budgets
+            .iter()
+            .find(|budget| budget.pkg == pkg)
+            .cloned()
+    }
+
+    pub fn pacing_assist<S>(&mut self, pkg: S) -> PacingMode
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .pacing_assists
+            .iter()
+            .find(|rule| rule.pkg == pkg)
+            .map_or(PacingMode::Off, |rule| rule.mode)
+    }
+
+    pub fn top_cores(&mut self) -> TopCoresStrategy {
+        self.inner.config().config.top_cores
+    }
+
+    pub fn telemetry_retention(&mut self) -> u32 {
+        self.inner.config().config.telemetry_retention
+    }
+
+    pub fn phase_drift(&mut self) -> bool {
+        self.inner.config().config.phase_drift
+    }
+
+    pub fn engine_presets(&mut self) -> bool {
+        self.inner.config().config.engine_presets
+    }
+
+    // Set in config, wins over the detected engine
+    pub fn engine<S>(&mut self, pkg: S) -> Option<Engine>
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .engines
+            .iter()
+            .find(|rule| rule.pkg == pkg)
+            .map(|rule| rule.engine)
+    }
+
+    pub fn explain(&mut self) -> bool {
+        self.inner.config().config.explain
+    }
+
+    pub fn game_props<S>(&mut self, pkg: S) -> Vec<(String, String)>
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .game_props
+            .iter()
+            .find(|rule| rule.pkg == pkg)
+            .map(|rule| rule.props.clone().into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn publish_props(&mut self) -> bool {
+        self.inner.config().config.publish_props
+    }
+
+    pub fn verify_auto_ignore(&mut self) -> bool {
+        self.inner.config().config.verify_auto_ignore
+    }
+
+    pub fn sub_profile<S>(&mut self, pkg: S, name: &str) -> Option<SubProfile>
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref().split(':').next()?;
+        let rule = self
+            .inner
+            .config()
+            .config
+            .sub_profiles
+            .iter()
+            .find(|rule| rule.pkg == pkg && rule.name == name)?;
+
+        Some(SubProfile {
+            name: rule.name.clone(),
+            target_fps: rule
+                .target_fps
+                .as_ref()
+                .and_then(|value| target_fps_value(pkg, value)),
+            margin_fps: rule
+                .margin_fps
+                .filter(|margin_fps| margin_fps.is_finite() && *margin_fps >= 0.0),
+        })
+    }
+
+    pub fn mirror_detection(&mut self) -> bool {
+        self.inner.config().config.mirror_detection
+    }
+
+    pub fn opp_power(&mut self) -> &[OppPower] {
+        &self.inner.config().config.opp_power
+    }
+
+    pub fn dma_latency(&mut self) -> u32 {
+        self.inner.config().config.dma_latency
+    }
+
+    pub fn control_uids(&mut self) -> &[u32] {
+        &self.inner.config().config.control_uids
+    }
+
+    pub fn trace_export(&mut self) -> bool {
+        self.inner.config().config.trace_export
+    }
+
+    pub fn adaptive_refresh_target(&mut self) -> bool {
+        self.inner.config().config.adaptive_refresh_target
+    }
+
+    pub fn policy_ratios(&mut self) -> &[PolicyRatio] {
+        &self.inner.config().config.policy_ratios
+    }
+
+    pub fn verify_interval(&mut self) -> Duration {
+        Duration::from_millis(self.inner.config().config.verify_interval)
+    }
+
+    pub fn verify_tolerance(&mut self) -> f64 {
+        self.inner.config().config.verify_tolerance
+    }
+
+    pub fn verify_ignore_policies(&mut self) -> &[i32] {
+        &self.inner.config().config.verify_ignore_policies
+    }
+
+    pub fn interference_lock(&mut self) -> bool {
+        self.inner.config().config.interference_lock
+    }
+
+    pub fn freq_step(&mut self) -> isize {
+        self.inner.config().config.freq_step
+    }
+
+    pub fn frame_source_timeout(&mut self) -> Duration {
+        Duration::from_millis(self.inner.config().config.frame_source_timeout)
+    }
+
+    pub fn devfreq_boost(&mut self) -> bool {
+        self.inner.config().config.devfreq_boost
+    }
+
+    pub fn top_app_boost(&mut self) -> u32 {
+        self.inner.config().config.top_app_boost
+    }
+
+    pub fn cpuidle_gate(&mut self) -> bool {
+        self.inner.config().config.cpuidle_gate
+    }
+
+    pub fn io_tune(&mut self) -> bool {
+        self.inner.config().config.io_tune
+    }
+
+    pub fn touch_boost_time(&mut self) -> Duration {
+        Duration::from_millis(self.inner.config().config.touch_boost_time)
+    }
+
+    pub fn touch_boost_floor(&mut self) -> f64 {
+        self.inner.config().config.touch_boost_floor
+    }
+
+    pub fn jank_boost_multiple(&mut self) -> f64 {
+        self.inner.config().config.jank_boost_multiple
+    }
+
+    pub fn jank_boost_time(&mut self) -> Duration {
+        Duration::from_millis(self.inner.config().config.jank_boost_time)
+    }
+
+    pub fn ramp_up_rate(&mut self) -> f64 {
+        self.inner.config().config.ramp_up_rate.max(0.0)
+    }
+
+    pub fn ramp_down_rate(&mut self) -> f64 {
+        self.inner.config().config.ramp_down_rate.max(0.0)
+    }
+
+    pub fn ramp_down_dwell(&mut self) -> Duration {
+        Duration::from_millis(self.inner.config().config.ramp_down_dwell)
+    }
+
+    pub fn refresh_pin(&mut self) -> bool {
+        self.inner.config().config.refresh_pin
+    }
+
+    pub fn resolution_hint<S>(&mut self, pkg: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .resolution_hint
+            .iter()
+            .any(|hint_pkg| hint_pkg == pkg)
+    }
+
+    pub fn memory_tune<S>(&mut self, pkg: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        self.inner
+            .config()
+            .config
+            .memory_tune
+            .iter()
+            .any(|tune_pkg| tune_pkg == pkg)
+    }
+
+    pub fn charging_mode(&mut self) -> Option<Mode> {
+        self.inner.config().config.charging_mode.parse().ok()
+    }
+
+    // The most severe rule the capacity falls under
+    pub fn battery_rule(&mut self, capacity: u32) -> Option<BatteryRule> {
+        self.inner
+            .config()
+            .config
+            .battery_rules
+            .iter()
+            .filter(|rule| capacity < rule.below)
+            .min_by_key(|rule| rule.below)
+            .copied()
+    }
+
+    // The first rule matching the minute of the local day and the temperatures
+    pub fn context_rule(
+        &mut self,
+        minute: u32,
+        core_temperature: u64,
+        battery_temperature: Option<u64>,
+    ) -> Option<ContextRule> {
+        self.inner
+            .config()
+            .config
+            .context_rules
+            .iter()
+            .find(|rule| {
+                in_window(minute, rule.from.as_deref(), rule.to.as_deref())
+                    && rule.core_above.is_none_or(|above| core_temperature > above)
+                    && rule.battery_above.is_none_or(|above| {
+                        battery_temperature.is_some_and(|temperature| temperature > above)
+                    })
+            })
+            .cloned()
+    }
+
+    // The config as currently parsed, for diagnostics dumps
+    pub fn dump(&mut self) -> String {
+        toml::to_string(self.inner.config())
+            .unwrap_or_else(|e| format!("failed to serialize config: {e}"))
+    }
+
     #[must_use]
     pub fn logger_level(&mut self) -> log::LevelFilter {
         match self.inner.config().logger_level.as_str() {
@@ -139,3 +773,53 @@ impl Config {
         }
     }
 }
+
+fn target_fps_value(pkg: &str, value: &Value) -> Option<TargetFps> {
+    match value {
+        Value::Array(arr) => {
+            let mut arr: Vec<_> = arr.iter().filter_map(fps_value).collect();
+            arr.sort_unstable_by(f64::total_cmp);
+            arr.dedup();
+            Some(TargetFps::Array(arr))
+        }
+        Value::Integer(_) | Value::Float(_) => fps_value(value).map(TargetFps::Value),
+        Value::String(s) if s == "auto" => Some(TargetFps::Array(AUTO_TARGET_FPS.to_vec())),
+        _ => {
+            error!("Find target game {pkg} in config, but meet illegal data type");
+            error!("Sugg: try \'{pkg} = \"auto\"\'");
+            None
+        }
+    }
+}
+
+// An open end reaches the start or the end of the day, a malformed time never matches
+fn in_window(minute: u32, from: Option<&str>, to: Option<&str>) -> bool {
+    let from = from.map_or(Some(0), minute_of_day);
+    let to = to.map_or(Some(24 * 60), minute_of_day);
+    let (Some(from), Some(to)) = (from, to) else {
+        return false;
+    };
+
+    if from <= to {
+        (from..to).contains(&minute)
+    } else {
+        minute >= from || minute < to
+    }
+}
+
+// "HH:MM"
+fn minute_of_day(time: &str) -> Option<u32> {
+    let (hour, minute) = time.trim().split_once(':')?;
+    let (hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+    (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+}
+
+// Integers and floats alike, anything not a positive finite number is skipped
+fn fps_value(value: &Value) -> Option<f64> {
+    let fps = match value {
+        Value::Integer(i) => *i as f64,
+        Value::Float(f) => *f,
+        _ => return None,
+    };
+    (fps.is_finite() && fps > 0.0).then_some(fps)
+}
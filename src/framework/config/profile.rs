@@ -0,0 +1,265 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use super::Config;
+use crate::framework::error::{Error, Result};
+
+// Lists under [config] whose entries apply to one game through their `pkg`
+const GAME_RULES: [&str; 11] = [
+    "game_modes",
+    "sub_profiles",
+    "gpu_profiles",
+    "game_types",
+    "engines",
+    "game_props",
+    "game_refresh_rates",
+    "frametime_filter_windows",
+    "frametime_targets",
+    "thermal_budgets",
+    "pacing_assists",
+];
+// Package lists under [config] a game can be part of
+const GAME_LISTS: [&str; 3] = ["hook_exclude", "resolution_hint", "memory_tune"];
+
+// Only what applies to the one game, the global tables (modes and the rest of [config]) are
+// the device's own and stay out of it
+#[derive(Deserialize, Serialize)]
+struct Profile {
+    package: String,
+    target_fps: Value,
+    // The game's entries of each list in GAME_RULES
+    #[serde(default)]
+    rules: Table,
+    // The lists of GAME_LISTS the game is in
+    #[serde(default)]
+    lists: Vec<String>,
+    // Learned on the exporting device, e.g. the calibrated fps and learned frequencies
+    #[serde(default, skip_serializing_if = "Table::is_empty")]
+    learned: Table,
+}
+
+impl Config {
+    pub fn export_profile<S>(l: S, p: S, learned: Table) -> Result<String>
+    where
+        S: AsRef<str>,
+    {
+        let local_conf: Table = toml::from_str(l.as_ref())?;
+        let package = p.as_ref();
+
+        let target_fps = local_conf
+            .get("game_list")
+            .and_then(Value::as_table)
+            .and_then(|game_list| game_list.get(package))
+            .cloned()
+            .ok_or(Error::Other("package not found in game_list"))?;
+
+        let config = local_conf.get("config").and_then(Value::as_table);
+        let rules = GAME_RULES
+            .iter()
+            .filter_map(|key| {
+                let entries = game_entries(config?.get(*key)?, package);
+                (!entries.is_empty()).then(|| ((*key).to_string(), Value::Array(entries)))
+            })
+            .collect();
+        let lists = GAME_LISTS
+            .iter()
+            .filter(|key| {
+                config
+                    .and_then(|config| config.get(**key))
+                    .and_then(Value::as_array)
+                    .is_some_and(|list| list.iter().any(|pkg| pkg.as_str() == Some(package)))
+            })
+            .map(|key| (*key).to_string())
+            .collect();
+
+        let profile = Profile {
+            package: package.to_string(),
+            target_fps,
+            rules,
+            lists,
+            learned,
+        };
+
+        Ok(toml::to_string(&profile)?)
+    }
+
+    pub fn import_profile<S, F>(l: S, p: S, mut overwrite: F) -> Result<String>
+    where
+        S: AsRef<str>,
+        F: FnMut(&str, &Value, &Value) -> bool,
+    {
+        let mut local_conf: Table = toml::from_str(l.as_ref())?;
+        let profile: Profile = toml::from_str(p.as_ref())?;
+        let package = profile.package.as_str();
+
+        let game_list = local_conf
+            .entry("game_list")
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .ok_or(Error::ParseConfig)?;
+        Self::import_value(
+            game_list,
+            package,
+            profile.target_fps,
+            &format!("game_list.\"{package}\""),
+            &mut overwrite,
+        );
+
+        let config = local_conf
+            .entry("config")
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .ok_or(Error::ParseConfig)?;
+
+        for (key, entries) in profile.rules {
+            if !GAME_RULES.contains(&key.as_str()) {
+                continue;
+            }
+            let new = game_entries(&entries, package);
+
+            let list = config
+                .entry(key.clone())
+                .or_insert_with(|| Value::Array(Vec::new()))
+                .as_array_mut()
+                .ok_or(Error::ParseConfig)?;
+            let old: Vec<_> = list
+                .iter()
+                .filter(|entry| entry_pkg(entry) == Some(package))
+                .cloned()
+                .collect();
+            if old == new
+                || (!old.is_empty()
+                    && !overwrite(
+                        &format!("config.{key}.\"{package}\""),
+                        &Value::Array(old),
+                        &Value::Array(new.clone()),
+                    ))
+            {
+                continue;
+            }
+
+            list.retain(|entry| entry_pkg(entry) != Some(package));
+            list.extend(new);
+        }
+
+        for key in profile.lists {
+            if !GAME_LISTS.contains(&key.as_str()) {
+                continue;
+            }
+
+            let list = config
+                .entry(key)
+                .or_insert_with(|| Value::Array(Vec::new()))
+                .as_array_mut()
+                .ok_or(Error::ParseConfig)?;
+            if !list.iter().any(|pkg| pkg.as_str() == Some(package)) {
+                list.push(Value::String(package.to_string()));
+            }
+        }
+
+        Ok(toml::to_string(&local_conf)?)
+    }
+
+    // The package and learned values of a profile, which go into learned.toml rather than
+    // the config
+    pub fn profile_learned<S>(p: S) -> Result<(String, Table)>
+    where
+        S: AsRef<str>,
+    {
+        let profile: Profile = toml::from_str(p.as_ref())?;
+        Ok((profile.package, profile.learned))
+    }
+
+    fn import_value<F>(table: &mut Table, key: &str, value: Value, path: &str, overwrite: &mut F)
+    where
+        F: FnMut(&str, &Value, &Value) -> bool,
+    {
+        match table.get(key) {
+            Some(old) if *old == value => (),
+            Some(old) if !overwrite(path, old, &value) => (),
+            _ => {
+                table.insert(key.to_string(), value);
+            }
+        }
+    }
+}
+
+fn entry_pkg(entry: &Value) -> Option<&str> {
+    entry.get("pkg")?.as_str()
+}
+
+// The entries of a per-game list that belong to `package`
+fn game_entries(list: &Value, package: &str) -> Vec<Value> {
+    list.as_array()
+        .map(|list| {
+            list.iter()
+                .filter(|entry| entry_pkg(entry) == Some(package))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PKG: &str = "com.example.game";
+
+    #[test]
+    fn carries_only_the_games_own_settings() {
+        let exporting = format!(
+            r#"
+[config]
+game_modes = [{{ pkg = "{PKG}", mode = "fast" }}, {{ pkg = "other", mode = "powersave" }}]
+hook_exclude = ["{PKG}"]
+
+[game_list]
+"{PKG}" = 60
+
+[balance]
+margin_fps = 1.0
+"#
+        );
+        let profile = Config::export_profile(exporting.as_str(), PKG, Table::new()).unwrap();
+        assert!(!profile.contains("balance"));
+        assert!(!profile.contains("other"));
+
+        let importing = r#"
+[config]
+game_modes = [{ pkg = "other", mode = "balance" }]
+
+[balance]
+margin_fps = 3.0
+"#;
+        let imported: Table = toml::from_str(
+            &Config::import_profile(importing, profile.as_str(), |_, _, _| true).unwrap(),
+        )
+        .unwrap();
+
+        let config = imported["config"].as_table().unwrap();
+        assert_eq!(config["game_modes"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            config["hook_exclude"].as_array().unwrap()[0].as_str(),
+            Some(PKG)
+        );
+        assert_eq!(imported["balance"]["margin_fps"].as_float(), Some(3.0));
+    }
+}
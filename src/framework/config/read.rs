@@ -18,7 +18,7 @@
 use std::{fs, path::Path, sync::mpsc::Sender, time::Duration};
 
 use inotify::{Inotify, WatchMask};
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 
 use super::data::{ConfigData, SceneAppList};
 use crate::framework::error::Result;
@@ -52,10 +52,26 @@ pub(super) fn wait_and_read(path: &Path, std_path: &Path, sx: &Sender<ConfigData
 
 fn read_config(path: &Path) -> Result<ConfigData> {
     let content = fs::read_to_string(path)?;
-    let config = toml::from_str(&content)?;
+    let mut config = toml::from_str(&content)?;
+    validate(&mut config);
     Ok(config)
 }
 
+// Entries that parse but can't work are dropped, so the rest of the config still applies
+fn validate(config: &mut ConfigData) {
+    config.config.thermal_budgets.retain(|budget| {
+        // The planner divides by it
+        let valid = budget.power.is_none_or(|power| power > 0.0);
+        if !valid {
+            warn!(
+                "Ignoring the thermal budget of {}, power must be above 0",
+                budget.pkg
+            );
+        }
+        valid
+    });
+}
+
 fn read_config_with_retry(path: &Path) -> Result<ConfigData> {
     let mut retry_count = 0;
 
@@ -0,0 +1,154 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    mem,
+    os::{
+        fd::AsRawFd,
+        unix::{
+            fs::{OpenOptionsExt, PermissionsExt},
+            net::UnixStream,
+        },
+    },
+    path::Path,
+};
+
+use log::{info, warn};
+use parking_lot::{Mutex, const_mutex};
+
+use crate::framework::error::Result;
+
+const TOKEN_FILE: &str = "/data/adb/fas_rs/control.token";
+const TOKEN_BYTES: usize = 16;
+const ROOT_UID: u32 = 0;
+
+static ALLOWED_UIDS: Mutex<Vec<u32>> = const_mutex(Vec::new());
+static TOKEN: Mutex<Option<String>> = const_mutex(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    // Status, dumps and telemetry, any app may ask
+    Read,
+    // Anything that changes what fas-rs does
+    Control,
+}
+
+// Reuses the token from earlier runs so the companion app only has to read it once
+pub fn init_token() -> Result<()> {
+    let token = match fs::read_to_string(TOKEN_FILE) {
+        Ok(token) if !token.trim().is_empty() => token.trim().to_string(),
+        _ => {
+            let token = generate_token()?;
+            let _ = fs::remove_file(TOKEN_FILE);
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(TOKEN_FILE)?;
+            writeln!(file, "{token}")?;
+            info!("Control token written to {TOKEN_FILE}");
+            token
+        }
+    };
+
+    *TOKEN.lock() = Some(token);
+    Ok(())
+}
+
+fn generate_token() -> Result<String> {
+    let mut bytes = [0; TOKEN_BYTES];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+// Any local app may connect, the commands it may run are decided per request
+pub fn open_socket(path: &Path) {
+    if let Err(e) = fs::set_permissions(path, fs::Permissions::from_mode(0o666)) {
+        warn!("Failed to open up the control socket to apps: {e}");
+    }
+}
+
+pub fn set_allowed_uids(uids: &[u32]) {
+    let mut allowed = ALLOWED_UIDS.lock();
+    if allowed.as_slice() != uids {
+        info!("Control access granted to uids {uids:?}");
+        *allowed = uids.to_vec();
+    }
+}
+
+pub fn token_matches(token: &str) -> bool {
+    TOKEN.lock().as_deref().is_some_and(|expected| {
+        // Compares every byte so the time taken doesn't tell how much of a guess was right
+        expected.len() == token.len()
+            && expected
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    })
+}
+
+pub fn uid_access(uid: Option<u32>) -> Access {
+    match uid {
+        Some(ROOT_UID) => Access::Control,
+        Some(uid) if ALLOWED_UIDS.lock().contains(&uid) => Access::Control,
+        _ => Access::Read,
+    }
+}
+
+pub fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    let mut cred: libc::ucred = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&raw mut cred).cast(),
+            &raw mut len,
+        )
+    };
+
+    (ret == 0).then_some(cred.uid)
+}
+
+// Finds who owns the other end of a loopback tcp connection, the kernel keeps it in /proc/net/tcp
+pub fn tcp_peer_uid(peer_port: u16, local_port: u16) -> Option<u32> {
+    let table = fs::read_to_string("/proc/net/tcp").ok()?;
+    let local = format!("0100007F:{peer_port:04X}");
+    let remote = format!("0100007F:{local_port:04X}");
+
+    table.lines().skip(1).find_map(|line| {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [_, l, r, _, _, _, _, uid, ..] if *l == local && *r == remote => uid.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+// The commands that change state, everything else only reads
+pub fn required_access(command: &[&str]) -> Access {
+    match command {
+        ["mode" | "profile", _] | ["log_level", _, _] | ["ignore", ..] | ["tune", _, ..] => {
+            Access::Control
+        }
+        _ => Access::Read,
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+mod auth;
+mod status;
+mod tuning;
+mod webui;
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Duration,
+};
+
+use auth::Access;
+use log::{debug, info};
+use parking_lot::{Mutex, const_mutex};
+
+use crate::{
+    cpu_common::set_policy_ignored,
+    framework::{
+        error::Result,
+        node::{Mode, Node},
+    },
+    logger,
+};
+pub use auth::set_allowed_uids;
+pub use status::{FailureCause, STATUS, Status};
+pub use tuning::{clear_tuning, tuning};
+pub use webui::WebUi;
+
+const CONTROL_SOCKET: &str = "/data/adb/fas_rs/control.sock";
+const TIMEOUT: Duration = Duration::from_secs(1);
+// Dumps are put together by the control loop, which may be sleeping while suspended
+const DUMP_TIMEOUT: Duration = Duration::from_secs(8);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static DUMP_REQUEST: Mutex<Option<(DumpFormat, Sender<String>)>> = const_mutex(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    // The human readable bug report
+    Text,
+    // The controller's internal state
+    Json,
+}
+
+pub struct Control;
+
+impl Control {
+    pub fn init() -> Result<Self> {
+        let _ = fs::remove_file(CONTROL_SOCKET);
+        let listener = UnixListener::bind(CONTROL_SOCKET)?;
+        auth::open_socket(Path::new(CONTROL_SOCKET));
+        auth::init_token()?;
+
+        thread::Builder::new()
+            .name("ControlThread".into())
+            .spawn(move || serve(&listener))?;
+
+        info!("Control socket listening on {CONTROL_SOCKET}");
+
+        Ok(Self)
+    }
+
+    pub fn shutdown() {
+        let _ = fs::remove_file(CONTROL_SOCKET);
+    }
+
+    pub fn request<S>(command: S) -> Result<String>
+    where
+        S: AsRef<str>,
+    {
+        let mut stream = UnixStream::connect(CONTROL_SOCKET)?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        writeln!(stream, "{}", command.as_ref())?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response)
+    }
+}
+
+fn serve(listener: &UnixListener) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+
+        if let Err(e) = handle(stream) {
+            debug!("Control connection failed: {e:?}");
+        }
+    }
+}
+
+fn handle(mut stream: UnixStream) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+
+    let words: Vec<_> = request.split_whitespace().collect();
+    // auth <token> <command> lets a client without an allowed uid run control commands
+    let (granted, command) = match words.as_slice() {
+        ["auth", token, command @ ..] if auth::token_matches(token) => (Access::Control, command),
+        ["auth", _, ..] => {
+            writeln!(stream, "permission denied: invalid token")?;
+            return Ok(());
+        }
+        command => (auth::uid_access(auth::peer_uid(&stream)), command),
+    };
+
+    if auth::required_access(command) > granted {
+        writeln!(
+            stream,
+            "permission denied: {} needs control access",
+            command.join(" ")
+        )?;
+        return Ok(());
+    }
+
+    let response = match command {
+        ["status"] => STATUS.lock().snapshot(),
+        ["write_failures"] => STATUS.lock().write_failures(),
+        ["threads"] => STATUS.lock().threads(),
+        ["hooks"] => STATUS.lock().hooks(),
+        ["dump"] => request_dump(DumpFormat::Text),
+        ["state"] => request_dump(DumpFormat::Json),
+        ["mode"] => STATUS.lock().mode.to_string(),
+        ["mode", mode] => switch_mode(mode),
+        ["profile"] => STATUS
+            .lock()
+            .sub_profile
+            .clone()
+            .unwrap_or_else(|| String::from("default")),
+        ["profile", name] => switch_sub_profile(name),
+        ["log_level"] => logger::module_levels(),
+        ["log_level", module, level] => logger::set_module_level(module, level),
+        ["ignore", policy, ignored] => ignore_policy(policy, ignored),
+        ["tune", args @ ..] => tuning::tune(args),
+        _ => format!("unknown command: {}", command.join(" ")),
+    };
+
+    writeln!(stream, "{response}")?;
+    Ok(())
+}
+
+// Taken by the control loop, which answers with the dump
+pub fn take_dump_request() -> Option<(DumpFormat, Sender<String>)> {
+    DUMP_REQUEST.lock().take()
+}
+
+fn request_dump(format: DumpFormat) -> String {
+    let (sx, rx) = mpsc::channel();
+    *DUMP_REQUEST.lock() = Some((format, sx));
+    rx.recv_timeout(DUMP_TIMEOUT).unwrap_or_else(|_| {
+        DUMP_REQUEST.lock().take();
+        String::from("control loop didn't answer")
+    })
+}
+
+// mode <powersave|balance|performance|fast>
+fn switch_mode(mode: &str) -> String {
+    let Ok(mode) = mode.parse::<Mode>() else {
+        return format!("invalid mode: {mode}");
+    };
+
+    match Node::set_mode(mode) {
+        Ok(()) => format!("switching to {mode}"),
+        Err(e) => format!("failed to switch mode: {e}"),
+    }
+}
+
+// profile <name|default>, names the config doesn't define for the game are ignored
+fn switch_sub_profile(name: &str) -> String {
+    let name = Some(name).filter(|name| *name != "default");
+    match Node::set_sub_profile(name) {
+        Ok(()) => format!("switching to {}", name.unwrap_or("default")),
+        Err(e) => format!("failed to switch sub-profile: {e}"),
+    }
+}
+
+// ignore <policy> <1|0>
+fn ignore_policy(policy: &str, ignored: &str) -> String {
+    let Ok(policy) = policy.parse() else {
+        return format!("invalid policy: {policy}");
+    };
+    let ignored = match ignored {
+        "1" => true,
+        "0" => false,
+        _ => return format!("invalid value: {ignored}"),
+    };
+
+    if set_policy_ignored(policy, ignored) {
+        info!("CPU Policy{policy}: ignored set to {ignored} through control socket");
+        String::from("ok")
+    } else {
+        format!("no such policy: {policy}")
+    }
+}
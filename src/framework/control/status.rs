@@ -0,0 +1,240 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::VecDeque, fmt, path::PathBuf, sync::LazyLock, time::Duration};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::framework::node::Mode;
+
+const MAX_VERIFY_MISMATCHES: usize = 32;
+
+pub static STATUS: LazyLock<Mutex<Status>> = LazyLock::new(|| Mutex::new(Status::new()));
+
+// Why a node keeps refusing writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureCause {
+    // Refused by the policy, with the node's context
+    Selinux(String),
+    Permission,
+    Error(String),
+}
+
+impl fmt::Display for FailureCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Selinux(context) => write!(f, "selinux {context}"),
+            Self::Permission => write!(f, "permission"),
+            Self::Error(e) => write!(f, "error {e}"),
+        }
+    }
+}
+
+// Temperatures in ℃, frametimes in milliseconds
+#[derive(Debug, Serialize)]
+pub struct StatusJson<'a> {
+    game: Option<&'a str>,
+    fps: f64,
+    p99_frametime: f64,
+    temperature: f64,
+    battery_temperature: Option<f64>,
+    mode: String,
+    charging: bool,
+    power: Option<f64>,
+    write_failures: Vec<String>,
+    interference: bool,
+    frametime: f64,
+    filtered_frametime: f64,
+    threads: &'a [String],
+    hooks: &'a [String],
+    loop_rate: f64,
+    loop_latency: f64,
+    verify_success: Vec<VerifySuccess>,
+    frame_source_health: &'a str,
+    sub_profile: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct VerifySuccess {
+    policy: i32,
+    ratio: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Status {
+    pub game: Option<String>,
+    pub fps: f64,
+    pub p99_frametime: Duration,
+    pub temperature: u64,
+    pub battery_temperature: Option<u64>,
+    pub mode: Mode,
+    pub charging: bool,
+    pub power: Option<f64>,
+    // Nodes the write worker keeps failing on
+    pub write_failures: Vec<(PathBuf, FailureCause)>,
+    // Another process moved the frequencies away from fas during the current game
+    pub interference: bool,
+    // The latest frametime as reported and after the frametime filter
+    pub frametime: Duration,
+    pub filtered_frametime: Duration,
+    // The game's threads as "<tid> <name> <class> <util %>", busiest first
+    pub threads: Vec<String>,
+    // "<pid> <package> <abi> <state>" of every game the frame hook was attached to
+    pub hooks: Vec<String>,
+    // Control loop iterations per second, and the smoothed time from a frame arriving to the
+    // policy being applied
+    pub loop_rate: f64,
+    pub loop_latency: Duration,
+    // The latest frequency verifications that failed, oldest first
+    pub verify_mismatches: VecDeque<String>,
+    // (policy, share of recent verifications that matched), by policy
+    pub verify_success: Vec<(i32, f64)>,
+    // "healthy", "degraded: <reason>" or "stalled" for the frame source in use
+    pub frame_source_health: String,
+    // The sub-profile the running game uses, `None` for its own settings
+    pub sub_profile: Option<String>,
+}
+
+impl Status {
+    const fn new() -> Self {
+        Self {
+            game: None,
+            fps: 0.0,
+            p99_frametime: Duration::ZERO,
+            temperature: 0,
+            battery_temperature: None,
+            mode: Mode::Balance,
+            charging: false,
+            power: None,
+            write_failures: Vec::new(),
+            interference: false,
+            frametime: Duration::ZERO,
+            filtered_frametime: Duration::ZERO,
+            threads: Vec::new(),
+            hooks: Vec::new(),
+            loop_rate: 0.0,
+            loop_latency: Duration::ZERO,
+            verify_mismatches: VecDeque::new(),
+            verify_success: Vec::new(),
+            frame_source_health: String::new(),
+            sub_profile: None,
+        }
+    }
+
+    // Fixed layout, one line: <game|-> <fps> <p99 frametime ms> <temperature ℃> <mode> <charging> <power W|-> <failing nodes> <interference> <frametime ms> <filtered frametime ms> <battery temperature ℃|->
+    pub fn snapshot(&self) -> String {
+        format!(
+            "{} {:.1} {:.1} {:.1} {} {} {} {} {} {:.1} {:.1} {}",
+            self.game.as_deref().unwrap_or("-"),
+            self.fps,
+            self.p99_frametime.as_secs_f64() * 1000.0,
+            self.temperature as f64 / 1000.0,
+            self.mode,
+            u8::from(self.charging),
+            self.power
+                .map_or_else(|| "-".to_string(), |power| format!("{power:.2}")),
+            self.write_failures.len(),
+            u8::from(self.interference),
+            self.frametime.as_secs_f64() * 1000.0,
+            self.filtered_frametime.as_secs_f64() * 1000.0,
+            self.battery_temperature.map_or_else(
+                || "-".to_string(),
+                |temperature| format!("{:.1}", temperature as f64 / 1000.0)
+            )
+        )
+    }
+
+    pub fn state_json(&self) -> StatusJson<'_> {
+        StatusJson {
+            game: self.game.as_deref(),
+            fps: self.fps,
+            p99_frametime: self.p99_frametime.as_secs_f64() * 1000.0,
+            temperature: self.temperature as f64 / 1000.0,
+            battery_temperature: self
+                .battery_temperature
+                .map(|temperature| temperature as f64 / 1000.0),
+            mode: self.mode.to_string(),
+            charging: self.charging,
+            power: self.power,
+            write_failures: self
+                .write_failures
+                .iter()
+                .map(|(path, cause)| format!("{} {cause}", path.display()))
+                .collect(),
+            interference: self.interference,
+            frametime: self.frametime.as_secs_f64() * 1000.0,
+            filtered_frametime: self.filtered_frametime.as_secs_f64() * 1000.0,
+            threads: &self.threads,
+            hooks: &self.hooks,
+            loop_rate: self.loop_rate,
+            loop_latency: self.loop_latency.as_secs_f64() * 1000.0,
+            verify_success: self
+                .verify_success
+                .iter()
+                .map(|&(policy, ratio)| VerifySuccess { policy, ratio })
+                .collect(),
+            frame_source_health: &self.frame_source_health,
+            sub_profile: self.sub_profile.as_deref(),
+        }
+    }
+
+    // "<path> <cause>" per failing node
+    pub fn write_failures(&self) -> String {
+        self.write_failures
+            .iter()
+            .map(|(path, cause)| format!("{} {cause}", path.display()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn record_verify_mismatch(&mut self, mismatch: String) {
+        if self.verify_mismatches.len() >= MAX_VERIFY_MISMATCHES {
+            self.verify_mismatches.pop_front();
+        }
+        self.verify_mismatches.push_back(mismatch);
+    }
+
+    pub fn record_verify_success(&mut self, policy: i32, ratio: f64) {
+        match self
+            .verify_success
+            .binary_search_by_key(&policy, |(policy, _)| *policy)
+        {
+            Ok(index) => self.verify_success[index].1 = ratio,
+            Err(index) => self.verify_success.insert(index, (policy, ratio)),
+        }
+    }
+
+    // "policy<n>: <matched %>" per verified policy
+    pub fn verify_success(&self) -> String {
+        self.verify_success
+            .iter()
+            .map(|(policy, ratio)| format!("policy{policy}: {:.0}%", ratio * 100.0))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // One thread per line
+    pub fn threads(&self) -> String {
+        self.threads.join("\n")
+    }
+
+    // One game per line
+    pub fn hooks(&self) -> String {
+        self.hooks.join("\n")
+    }
+}
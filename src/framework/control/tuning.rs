@@ -0,0 +1,86 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use std::fmt::Write as _;
+
+use log::info;
+use parking_lot::{Mutex, const_mutex};
+
+// Overrides of controller parameters set over the control socket while a game runs, they
+// win over the config until the session ends
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Tuning {
+    pub margin_fps: Option<f64>,
+    pub ramp_up: Option<f64>,
+    pub ramp_down: Option<f64>,
+    pub jank_multiple: Option<f64>,
+}
+
+static TUNING: Mutex<Tuning> = const_mutex(Tuning {
+    margin_fps: None,
+    ramp_up: None,
+    ramp_down: None,
+    jank_multiple: None,
+});
+
+pub fn tuning() -> Tuning {
+    *TUNING.lock()
+}
+
+pub fn clear_tuning() {
+    *TUNING.lock() = Tuning::default();
+}
+
+// tune [<margin|ramp_up|ramp_down|jank> <value|reset>] | tune reset
+pub fn tune(args: &[&str]) -> String {
+    let mut tuning = TUNING.lock();
+    match args {
+        [] => (),
+        ["reset"] => *tuning = Tuning::default(),
+        [param, value] => {
+            let value = if *value == "reset" {
+                None
+            } else {
+                match value.parse::<f64>() {
+                    Ok(value) if value.is_finite() && value >= 0.0 => Some(value),
+                    _ => return format!("invalid value: {value}"),
+                }
+            };
+            let slot = match *param {
+                "margin" => &mut tuning.margin_fps,
+                "ramp_up" => &mut tuning.ramp_up,
+                "ramp_down" => &mut tuning.ramp_down,
+                "jank" => &mut tuning.jank_multiple,
+                _ => return format!("unknown parameter: {param}"),
+            };
+            *slot = value;
+            info!("Tuned {param} to {value:?} through control socket");
+        }
+        _ => return String::from("usage: tune [<margin|ramp_up|ramp_down|jank> <value|reset>]"),
+    }
+
+    let mut response = String::new();
+    for (param, value) in [
+        ("margin", tuning.margin_fps),
+        ("ramp_up", tuning.ramp_up),
+        ("ramp_down", tuning.ramp_down),
+        ("jank", tuning.jank_multiple),
+    ] {
+        let value = value.map_or_else(|| "config".to_string(), |value| value.to_string());
+        let _ = writeln!(response, "{param}: {value}");
+    }
+    response.trim_end().to_string()
+}
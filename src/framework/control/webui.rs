@@ -0,0 +1,286 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use log::{debug, info};
+use serde::Serialize;
+
+use super::{
+    STATUS,
+    auth::{self, Access},
+};
+use crate::framework::{
+    config::Config,
+    error::{Error, Result},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+// Editing the game list never needs more
+const MAX_BODY: u64 = 64 * 1024;
+const PAGE: &str = include_str!("webui.html");
+
+struct Request {
+    method: String,
+    path: String,
+    host: Option<String>,
+    origin: Option<String>,
+    token: Option<String>,
+    body: String,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    const fn new(status: &'static str, content_type: &'static str, body: String) -> Self {
+        Self {
+            status,
+            content_type,
+            body,
+        }
+    }
+
+    fn json<T: Serialize>(body: &T) -> Self {
+        match serde_json::to_string(body) {
+            Ok(body) => Self::new("200 OK", "application/json", body),
+            Err(e) => Self::error("500 Internal Server Error", &e.to_string()),
+        }
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            error: &'a str,
+        }
+
+        Self::new(
+            status,
+            "application/json",
+            serde_json::to_string(&Body { error: message }).unwrap_or_default(),
+        )
+    }
+}
+
+// Only reachable from the device itself, serves live status and edits the user config's game list
+pub struct WebUi;
+
+impl WebUi {
+    pub fn init(port: u16, config: &Path) -> Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let config = config.to_path_buf();
+
+        thread::Builder::new()
+            .name("WebUiThread".into())
+            .spawn(move || serve(&listener, &config, port))?;
+
+        info!("WebUI listening on http://127.0.0.1:{port}");
+
+        Ok(Self)
+    }
+}
+
+fn serve(listener: &TcpListener, config: &Path, port: u16) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+
+        if let Err(e) = handle(stream, config, port) {
+            debug!("WebUI connection failed: {e:?}");
+        }
+    }
+}
+
+fn handle(mut stream: TcpStream, config: &Path, port: u16) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+
+    let request = read_request(&stream)?;
+    let response = if !trusted(&request, port) {
+        Response::error("403 Forbidden", "forbidden")
+    } else if request.method != "GET" && access(&stream, &request, port) < Access::Control {
+        Response::error("403 Forbidden", "control access required")
+    } else {
+        route(&request, config)
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    Ok(())
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(Error::Other("malformed request line"));
+    };
+    let method = method.to_string();
+    let path = path.to_string();
+
+    let mut host = None;
+    let mut origin = None;
+    let mut token = None;
+    let mut length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "host" => host = Some(value),
+            "origin" => origin = Some(value),
+            "x-fas-token" => token = Some(value),
+            "content-length" => {
+                length = value
+                    .parse()
+                    .map_err(|_| Error::Other("malformed content length"))?;
+            }
+            _ => (),
+        }
+    }
+
+    if length > MAX_BODY {
+        return Err(Error::Other("request body too large"));
+    }
+
+    let mut body = String::new();
+    reader.take(length).read_to_string(&mut body)?;
+
+    Ok(Request {
+        method,
+        path,
+        host,
+        origin,
+        token,
+        body,
+    })
+}
+
+// Rejects pages on other origins and dns rebinding, the socket already only listens on loopback
+fn trusted(request: &Request, port: u16) -> bool {
+    let hosts = [format!("127.0.0.1:{port}"), format!("localhost:{port}")];
+    let host_ok = request
+        .host
+        .as_ref()
+        .is_some_and(|host| hosts.contains(host));
+    let origin_ok = request.origin.as_ref().is_none_or(|origin| {
+        hosts
+            .iter()
+            .any(|host| origin.strip_prefix("http://") == Some(host.as_str()))
+    });
+
+    host_ok && origin_ok
+}
+
+// Reading is open to any app, edits need an allowed uid (usually the browser's) or the control token
+fn access(stream: &TcpStream, request: &Request, port: u16) -> Access {
+    if request.token.as_deref().is_some_and(auth::token_matches) {
+        return Access::Control;
+    }
+
+    let uid = stream
+        .peer_addr()
+        .ok()
+        .and_then(|peer| auth::tcp_peer_uid(peer.port(), port));
+    auth::uid_access(uid)
+}
+
+fn route(request: &Request, config: &Path) -> Response {
+    let game = request.path.strip_prefix("/api/games/");
+
+    match (request.method.as_str(), request.path.as_str(), game) {
+        ("GET", "/", _) => Response::new("200 OK", "text/html", PAGE.to_string()),
+        ("GET", "/api/status", _) => Response::json(&STATUS.lock().state_json()),
+        ("GET", "/api/games", _) => game_list(config),
+        ("PUT", _, Some(pkg)) => edit_game(config, pkg, Some(&request.body)),
+        ("DELETE", _, Some(pkg)) => edit_game(config, pkg, None),
+        _ => Response::error("404 Not Found", "not found"),
+    }
+}
+
+fn game_list(config: &Path) -> Response {
+    #[derive(Serialize)]
+    struct Game<'a> {
+        package: &'a str,
+        target_fps: &'a str,
+    }
+
+    match fs::read_to_string(config)
+        .map_err(Error::from)
+        .and_then(Config::game_list_entries)
+    {
+        Ok(games) => {
+            let games: Vec<_> = games
+                .iter()
+                .map(|(package, target_fps)| Game {
+                    package,
+                    target_fps,
+                })
+                .collect();
+            Response::json(&games)
+        }
+        Err(e) => Response::error("500 Internal Server Error", &e.to_string()),
+    }
+}
+
+// The config watcher picks the written file up like any other edit
+fn edit_game(config: &Path, pkg: &str, target_fps: Option<&str>) -> Response {
+    #[derive(Serialize)]
+    struct Edited {
+        ok: bool,
+    }
+
+    let local = match fs::read_to_string(config) {
+        Ok(local) => local,
+        Err(e) => return Response::error("500 Internal Server Error", &e.to_string()),
+    };
+
+    match Config::edit_game(&local, pkg, target_fps) {
+        Ok(new) => match fs::write(config, new) {
+            Ok(()) => {
+                info!("WebUI updated game_list.\"{pkg}\"");
+                Response::json(&Edited { ok: true })
+            }
+            Err(e) => Response::error("500 Internal Server Error", &e.to_string()),
+        },
+        Err(e) => Response::error("400 Bad Request", &e.to_string()),
+    }
+}
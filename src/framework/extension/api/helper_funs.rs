@@ -15,18 +15,28 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{
+    LazyLock,
+    atomic::{AtomicBool, Ordering},
+};
 
 use anyhow::Context;
 use log::debug;
 use log::warn;
+use parking_lot::Mutex;
 
-use crate::cpu_common::{
-    EXTRA_POLICY_MAP, IGNORE_MAP,
-    extra_policy::{AbsRangeBound, ExtraPolicy, RelRangeBound},
+use crate::{
+    cpu_common::{
+        EXTRA_POLICY_MAP, IGNORE_MAP,
+        extra_policy::{AbsRangeBound, ExtraPolicy, RelRangeBound},
+    },
+    file_handler::FileHandler,
+    sysfs,
 };
 
 static WARNING_FLAG: AtomicBool = AtomicBool::new(false);
+static FILE_HANDLER: LazyLock<Mutex<FileHandler>> =
+    LazyLock::new(|| Mutex::new(FileHandler::new(sysfs::root())));
 
 pub fn remove_extra_policy(policy: i32) {
     *EXTRA_POLICY_MAP
@@ -104,3 +114,11 @@ pub fn set_ignore_policy(policy: i32, val: bool) {
         .unwrap()
         .store(val, Ordering::Release);
 }
+
+pub fn write_node(path: &str, value: &str) -> bool {
+    FILE_HANDLER
+        .lock()
+        .write_with_workround(path, value)
+        .map_err(|e| warn!("extension failed to write {path}: {e:?}"))
+        .is_ok()
+}
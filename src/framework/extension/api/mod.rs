@@ -22,6 +22,7 @@ pub mod v1;
 pub mod v2;
 pub mod v3;
 pub mod v4;
+pub mod v5;
 
 use super::{Extension, core::ExtensionMap};
 pub use v0::ApiV0;
@@ -29,6 +30,8 @@ use v1::ApiV1;
 use v2::ApiV2;
 use v3::ApiV3;
 use v4::ApiV4;
+use v5::ApiV5;
+pub use v5::FrameWindowStats;
 
 pub trait Api: Send {
     fn handle_api(&self, ext: &ExtensionMap);
@@ -47,6 +50,7 @@ pub fn trigger_init_cpu_freq(extension: &Extension) {
     extension.trigger_extentions(ApiV2::InitCpuFreq);
     extension.trigger_extentions(ApiV3::InitCpuFreq);
     extension.trigger_extentions(ApiV4::InitCpuFreq);
+    extension.trigger_extentions(ApiV5::InitCpuFreq);
 }
 
 pub fn trigger_reset_cpu_freq(extension: &Extension) {
@@ -55,6 +59,7 @@ pub fn trigger_reset_cpu_freq(extension: &Extension) {
     extension.trigger_extentions(ApiV2::ResetCpuFreq);
     extension.trigger_extentions(ApiV3::ResetCpuFreq);
     extension.trigger_extentions(ApiV4::ResetCpuFreq);
+    extension.trigger_extentions(ApiV5::ResetCpuFreq);
 }
 
 pub fn trigger_load_fas(extension: &Extension, pid: i32, pkg: String) {
@@ -62,7 +67,8 @@ pub fn trigger_load_fas(extension: &Extension, pid: i32, pkg: String) {
     extension.trigger_extentions(ApiV1::LoadFas(pid, pkg.clone()));
     extension.trigger_extentions(ApiV2::LoadFas(pid, pkg.clone()));
     extension.trigger_extentions(ApiV3::LoadFas(pid, pkg.clone()));
-    extension.trigger_extentions(ApiV4::LoadFas(pid, pkg));
+    extension.trigger_extentions(ApiV4::LoadFas(pid, pkg.clone()));
+    extension.trigger_extentions(ApiV5::LoadFas(pid, pkg));
 }
 
 pub fn trigger_unload_fas(extension: &Extension, pid: i32, pkg: String) {
@@ -70,7 +76,8 @@ pub fn trigger_unload_fas(extension: &Extension, pid: i32, pkg: String) {
     extension.trigger_extentions(ApiV1::UnloadFas(pid, pkg.clone()));
     extension.trigger_extentions(ApiV2::UnloadFas(pid, pkg.clone()));
     extension.trigger_extentions(ApiV3::UnloadFas(pid, pkg.clone()));
-    extension.trigger_extentions(ApiV4::UnloadFas(pid, pkg));
+    extension.trigger_extentions(ApiV4::UnloadFas(pid, pkg.clone()));
+    extension.trigger_extentions(ApiV5::UnloadFas(pid, pkg));
 }
 
 pub fn trigger_start_fas(extension: &Extension) {
@@ -79,6 +86,7 @@ pub fn trigger_start_fas(extension: &Extension) {
     extension.trigger_extentions(ApiV2::StartFas);
     extension.trigger_extentions(ApiV3::StartFas);
     extension.trigger_extentions(ApiV4::StartFas);
+    extension.trigger_extentions(ApiV5::StartFas);
 }
 
 pub fn trigger_stop_fas(extension: &Extension) {
@@ -87,10 +95,24 @@ pub fn trigger_stop_fas(extension: &Extension) {
     extension.trigger_extentions(ApiV2::StopFas);
     extension.trigger_extentions(ApiV3::StopFas);
     extension.trigger_extentions(ApiV4::StopFas);
+    extension.trigger_extentions(ApiV5::StopFas);
 }
 
 pub fn trigger_target_fps_change(extension: &Extension, target_fps: u32, pkg: String) {
     extension.trigger_extentions(ApiV2::TargetFpsChange(target_fps, pkg.clone()));
     extension.trigger_extentions(ApiV3::TargetFpsChange(target_fps, pkg.clone()));
-    extension.trigger_extentions(ApiV4::TargetFpsChange(target_fps, pkg));
+    extension.trigger_extentions(ApiV4::TargetFpsChange(target_fps, pkg.clone()));
+    extension.trigger_extentions(ApiV5::TargetFpsChange(target_fps, pkg));
+}
+
+pub fn trigger_game_start(extension: &Extension, pkg: String, target_fps: Option<u32>) {
+    extension.trigger_extentions(ApiV5::GameStart(pkg, target_fps));
+}
+
+pub fn trigger_game_exit(extension: &Extension, pkg: String) {
+    extension.trigger_extentions(ApiV5::GameExit(pkg));
+}
+
+pub fn trigger_frame_window(extension: &Extension, stats: FrameWindowStats) {
+    extension.trigger_extentions(ApiV5::FrameWindow(stats));
 }
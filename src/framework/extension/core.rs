@@ -23,7 +23,10 @@ use mlua::Lua;
 
 use super::{
     EXTENSIONS_PATH,
-    api::{Api, helper_funs},
+    api::{
+        Api, helper_funs,
+        misc::{do_callback, get_api_version},
+    },
 };
 use crate::framework::error::Result;
 
@@ -137,9 +140,20 @@ fn load_extensions() -> Result<ExtensionMap> {
             })?,
         )?;
 
+        // Add in api v5
+        lua.globals().set(
+            "write_node",
+            lua.create_function(|_, (path, value): (String, String)| {
+                Ok(helper_funs::write_node(&path, &value))
+            })?,
+        )?;
+
         match lua.load(&file).exec() {
             Ok(()) => {
                 info!("Extension loaded successfully: {}", path.display());
+                if get_api_version(&lua) >= 5 {
+                    do_callback(&path, &lua, "on_load", ());
+                }
                 map.insert(path, lua);
             }
             Err(e) => {
@@ -0,0 +1,194 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    os::fd::AsRawFd,
+};
+
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use super::error::Result;
+
+const LEARNED_PATH: &str = "/data/adb/fas_rs/learned.toml";
+// Held while the file is read back and rewritten, the daemon and the command line both save it
+const LEARNED_LOCK: &str = "/data/adb/fas_rs/learned.lock";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct GameLearned {
+    #[serde(default)]
+    values: Table,
+    #[serde(skip)]
+    previous: Table,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    suspect: Option<Table>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Learned {
+    #[serde(default)]
+    games: HashMap<String, GameLearned>,
+    // Games changed by this process since the last save, the rest is taken from the file
+    #[serde(skip)]
+    dirty: HashSet<String>,
+}
+
+impl Learned {
+    pub fn load() -> Self {
+        fs::read_to_string(LEARNED_PATH)
+            .ok()
+            .and_then(|raw| toml::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&mut self) {
+        if let Err(e) = self.write() {
+            error!("Failed to save learned values: {e:?}");
+        }
+    }
+
+    // Merges with the file rather than overwriting it, so e.g. a `confirm` run from the
+    // command line during another game's session survives the daemon's next save
+    fn write(&mut self) -> Result<()> {
+        let lock = File::create(LEARNED_LOCK)?;
+        // Released when the file is closed
+        unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX) };
+
+        let mut merged = Self::load();
+        for pkg in self.dirty.drain() {
+            if let Some(game) = self.games.get(&pkg) {
+                merged.games.insert(pkg, game.clone());
+            }
+        }
+        fs::write(LEARNED_PATH, toml::to_string(&merged)?)?;
+
+        // `previous` is never saved, the session's baseline stays
+        for (pkg, game) in merged.games {
+            let entry = self.games.entry(pkg).or_default();
+            entry.values = game.values;
+            entry.suspect = game.suspect;
+        }
+        Ok(())
+    }
+
+    pub fn get<S>(&self, pkg: S, key: &str) -> Option<&Value>
+    where
+        S: AsRef<str>,
+    {
+        self.games.get(pkg.as_ref())?.values.get(key)
+    }
+
+    pub fn set<S>(&mut self, pkg: S, key: &str, value: Value)
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref().to_string();
+        self.games
+            .entry(pkg.clone())
+            .or_default()
+            .values
+            .insert(key.to_string(), value);
+        self.dirty.insert(pkg);
+        self.save();
+    }
+
+    // Reloads from disk so that confirmations made from the command line are picked up,
+    // then remembers the values the session starts with.
+    pub fn begin_session<S>(&mut self, pkg: S)
+    where
+        S: AsRef<str>,
+    {
+        *self = Self::load();
+
+        let game = self.games.entry(pkg.as_ref().to_string()).or_default();
+        game.previous = game.values.clone();
+    }
+
+    pub fn end_session<S>(&mut self, pkg: S, crashed: bool)
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+
+        if crashed
+            && let Some(game) = self.games.get_mut(pkg)
+            && game.values != game.previous
+        {
+            warn!("[{pkg}] exited abnormally, rolling back values learned in this session");
+            warn!("Run 'fas-rs-next confirm {pkg}' to keep them anyway");
+            game.suspect = Some(game.values.clone());
+            game.values = game.previous.clone();
+            self.dirty.insert(pkg.to_string());
+            self.save();
+        }
+    }
+
+    // What a shared profile carries along, e.g. the calibrated fps
+    pub fn values<S>(&self, pkg: S) -> Table
+    where
+        S: AsRef<str>,
+    {
+        self.games
+            .get(pkg.as_ref())
+            .map(|game| game.values.clone())
+            .unwrap_or_default()
+    }
+
+    // Values of an imported profile, conflicting ones only when overwrite agrees
+    pub fn import<S, F>(&mut self, pkg: S, values: Table, mut overwrite: F)
+    where
+        S: AsRef<str>,
+        F: FnMut(&str, &Value, &Value) -> bool,
+    {
+        let pkg = pkg.as_ref();
+        let game = self.games.entry(pkg.to_string()).or_default();
+
+        for (key, value) in values {
+            match game.values.get(&key) {
+                Some(old) if *old == value => (),
+                Some(old) if !overwrite(&format!("learned.\"{pkg}\".{key}"), old, &value) => (),
+                _ => {
+                    game.values.insert(key, value);
+                }
+            }
+        }
+
+        self.dirty.insert(pkg.to_string());
+        self.save();
+    }
+
+    pub fn confirm<S>(&mut self, pkg: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        let pkg = pkg.as_ref();
+        let Some(game) = self.games.get_mut(pkg) else {
+            return false;
+        };
+
+        if let Some(suspect) = game.suspect.take() {
+            game.values = suspect;
+            self.dirty.insert(pkg.to_string());
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -15,16 +15,26 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
+mod capability;
 mod config;
+mod control;
 mod error;
 #[cfg(feature = "extension")]
 mod extension;
+mod learned;
 mod node;
 mod pid_utils;
 pub mod prelude;
 mod scheduler;
+pub mod telemetry;
+mod trace;
 
+pub use capability::CAPABILITIES;
+pub use control::Control;
 pub use error::Result;
 #[cfg(feature = "extension")]
 pub use extension::{Extension, api};
-pub use scheduler::Scheduler;
+pub use learned::Learned;
+#[cfg(feature = "predictor")]
+pub use scheduler::train_predictor;
+pub use scheduler::{BenchPattern, OnDemand, Scheduler};
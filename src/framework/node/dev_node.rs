@@ -0,0 +1,115 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{ffi::OsStr, fs, path::Path, str::FromStr, sync::Arc, thread};
+
+use inotify::{Inotify, WatchMask};
+use log::{info, warn};
+use parking_lot::Mutex;
+
+use super::Mode;
+use crate::framework::error::{Error, Result};
+
+const DEV_NODE_DIR: &str = "/dev/fas_rs";
+const MODE_NODE: &str = "mode";
+
+// Last value echoed into /dev/fas_rs/mode by other modules or scripts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DevMode {
+    // The usual mode sources apply
+    Auto,
+    Mode(Mode),
+    // Fas stays off until something else is written
+    Disabled,
+}
+
+impl FromStr for DevMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "" | "auto" => Self::Auto,
+            "disabled" | "off" => Self::Disabled,
+            mode => Self::Mode(mode.parse()?),
+        })
+    }
+}
+
+// A tmpfs node other modules can write without knowing about the data dir, watched with
+// inotify so writes take effect right away
+pub struct DevNode {
+    mode: Arc<Mutex<DevMode>>,
+}
+
+impl DevNode {
+    pub fn init() -> Self {
+        let mode = Arc::new(Mutex::new(DevMode::Auto));
+        if let Err(e) = Self::spawn_watcher(mode.clone()) {
+            warn!("Failed to set up {DEV_NODE_DIR}/{MODE_NODE}: {e}");
+        }
+
+        Self { mode }
+    }
+
+    pub fn shutdown(&self) {
+        let _ = fs::remove_file(Path::new(DEV_NODE_DIR).join(MODE_NODE));
+        let _ = fs::remove_dir(DEV_NODE_DIR);
+    }
+
+    pub fn mode(&self) -> DevMode {
+        *self.mode.lock()
+    }
+
+    fn spawn_watcher(mode: Arc<Mutex<DevMode>>) -> Result<()> {
+        let _ = fs::create_dir(DEV_NODE_DIR);
+        let path = Path::new(DEV_NODE_DIR).join(MODE_NODE);
+        fs::write(&path, "auto")?;
+
+        let mut inotify = Inotify::init()?;
+        // Watch the dir, scripts may replace the file rather than write into it
+        inotify
+            .watches()
+            .add(DEV_NODE_DIR, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)?;
+
+        thread::Builder::new()
+            .name("ModeNodeThread".into())
+            .spawn(move || {
+                let mut buffer = [0; 1024];
+                while let Ok(events) = inotify.read_events_blocking(&mut buffer) {
+                    if !events
+                        .into_iter()
+                        .any(|event| event.name == Some(OsStr::new(MODE_NODE)))
+                    {
+                        continue;
+                    }
+
+                    let Ok(value) = fs::read_to_string(&path) else {
+                        continue;
+                    };
+                    match value.trim().parse() {
+                        Ok(new_mode) => {
+                            info!("Mode node set to {new_mode:?}");
+                            *mode.lock() = new_mode;
+                        }
+                        Err(_) => warn!("Invalid value in mode node: {}", value.trim()),
+                    }
+                }
+            })?;
+
+        Ok(())
+    }
+}
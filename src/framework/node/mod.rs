@@ -1,3 +1,4 @@
+mod dev_node;
 mod power_mode;
 
 use std::{
@@ -8,6 +9,7 @@ use std::{
 };
 
 use crate::framework::error::{Error, Result};
+use dev_node::{DevMode, DevNode};
 use likely_stable::unlikely;
 pub use power_mode::Mode;
 
@@ -17,6 +19,7 @@ const REFRESH_TIME: Duration = Duration::from_secs(1);
 pub struct Node {
     map: HashMap<String, String>,
     timer: Instant,
+    dev: DevNode,
 }
 
 impl Node {
@@ -26,14 +29,52 @@ impl Node {
         let mut result = Self {
             map: HashMap::new(),
             timer: Instant::now(),
+            dev: DevNode::init(),
         };
 
         let _ = result.remove_node("node");
         result.create_node("node", "balance")?;
+        let _ = result.remove_node("sub_profile");
+        result.create_node("sub_profile", "")?;
 
         Ok(result)
     }
 
+    // Only what fas created, the data dir holds more than nodes
+    pub fn shutdown(&mut self) {
+        let _ = self.remove_node("node");
+        let _ = self.remove_node("sub_profile");
+        self.dev.shutdown();
+    }
+
+    // Set by writing "disabled" into /dev/fas_rs/mode
+    pub fn fas_disabled(&self) -> bool {
+        self.dev.mode() == DevMode::Disabled
+    }
+
+    // Picked up by the control loop on its next node refresh
+    pub fn set_mode(mode: Mode) -> Result<()> {
+        fs::write(Path::new(NODE_PATH).join("mode"), mode.to_string())?;
+        Ok(())
+    }
+
+    // The sub-profile of the running game written into the sub_profile node, empty for its
+    // own settings
+    pub fn get_sub_profile(&mut self) -> Option<String> {
+        let name = self.get_node("sub_profile").ok()?;
+        let name = name.trim();
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
+    // Picked up like `set_mode`, `None` goes back to the game's own settings
+    pub fn set_sub_profile(name: Option<&str>) -> Result<()> {
+        fs::write(
+            Path::new(NODE_PATH).join("sub_profile"),
+            name.unwrap_or_default(),
+        )?;
+        Ok(())
+    }
+
     pub fn create_node<S>(&mut self, i: S, d: S) -> Result<()>
     where
         S: AsRef<str>,
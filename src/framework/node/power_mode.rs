@@ -3,7 +3,7 @@ use std::{
     str::FromStr,
 };
 
-use super::Node;
+use super::{DevMode, Node};
 use crate::framework::error::{Error, Result};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -43,6 +43,11 @@ impl Display for Mode {
 
 impl Node {
     pub fn get_mode(&mut self) -> Result<Mode> {
+        // Integrations writing /dev/fas_rs/mode take precedence
+        if let DevMode::Mode(mode) = self.dev.mode() {
+            return Ok(mode);
+        }
+
         let mode = self.get_node("mode").or(Err(Error::NodeNotFound))?;
 
         Mode::from_str(mode.trim())
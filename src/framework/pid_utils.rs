@@ -15,13 +15,98 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{fs, path::Path};
+use std::{fs, path::Path, time::Duration};
+
+use dumpsys_rs::Dumpsys;
+use libc::{_SC_CLK_TCK, sysconf};
 
 use crate::framework::Result;
 
+// ApplicationExitInfo reasons APP CRASH(EXCEPTION), APP CRASH(NATIVE) and ANR
+const ABNORMAL_EXIT_REASONS: [u32; 3] = [4, 5, 6];
+
 pub fn get_process_name(pid: i32) -> Result<String> {
     let cmdline = Path::new("/proc").join(pid.to_string()).join("cmdline");
     let cmdline = fs::read_to_string(cmdline)?;
     let cmdline = cmdline.split(':').next().unwrap_or_default();
     Ok(cmdline.trim_matches(['\0']).trim().to_string())
 }
+
+pub fn process_exists(pid: i32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+// Whether the system recorded the exit of the pid as a crash or an ANR, an exit it has no
+// record of (e.g. quitting from the game's menu) doesn't count
+pub fn exited_abnormally(pkg: &str, pid: i32) -> bool {
+    Dumpsys::new("activity")
+        .and_then(|mut dumper| dumper.dump(&["exit-info", pkg]).ok())
+        .and_then(|dump| exit_reason(&dump, pid))
+        .is_some_and(|reason| ABNORMAL_EXIT_REASONS.contains(&reason))
+}
+
+// "timestamp=... pid=<pid> ..." opens the record, "process=... reason=<n> (...)" follows
+fn exit_reason(dump: &str, pid: i32) -> Option<u32> {
+    let pid = format!(" pid={pid} ");
+    let mut lines = dump
+        .lines()
+        .skip_while(|line| !(line.contains("timestamp=") && line.contains(&pid)));
+    lines.next()?;
+    lines
+        .take_while(|line| !line.contains("timestamp="))
+        .find_map(|line| {
+            line.split_once(" reason=")?
+                .1
+                .split_whitespace()
+                .next()?
+                .parse()
+                .ok()
+        })
+}
+
+// How long ago the process started
+pub fn process_age(pid: i32) -> Option<Duration> {
+    let stat = fs::read_to_string(Path::new("/proc").join(pid.to_string()).join("stat")).ok()?;
+    // The name may contain spaces, fields after it start at state (3rd), starttime is the 22nd
+    let start_ticks: f64 = stat
+        .rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()?;
+    let uptime: f64 = fs::read_to_string("/proc/uptime")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+
+    let tick_per_sec = unsafe { sysconf(_SC_CLK_TCK) } as f64;
+    Some(Duration::from_secs_f64(
+        (uptime - start_ticks / tick_per_sec).max(0.0),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXIT_INFO: &str = "ACTIVITY MANAGER PROCESS EXIT INFO (dumpsys activity exit-info)
+  package: com.example.game
+    Historical Process Exit for uid=10123
+        ApplicationExitInfo #0:
+          timestamp=2025-05-10 13:24:56.781 pid=4321 realUid=10123 packageUid=10123 definingUid=10123 user=0
+          process=com.example.game reason=10 (USER REQUESTED) subreason=0 (UNKNOWN) status=0
+        ApplicationExitInfo #1:
+          timestamp=2025-05-10 13:02:11.004 pid=1234 realUid=10123 packageUid=10123 definingUid=10123 user=0
+          process=com.example.game reason=5 (APP CRASH(NATIVE)) subreason=0 (UNKNOWN) status=11
+";
+
+    #[test]
+    fn finds_the_exit_reason_of_the_pid() {
+        assert_eq!(exit_reason(EXIT_INFO, 4321), Some(10));
+        assert_eq!(exit_reason(EXIT_INFO, 1234), Some(5));
+        assert_eq!(exit_reason(EXIT_INFO, 123), None);
+    }
+}
@@ -15,7 +15,14 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
+#[cfg(feature = "predictor")]
+pub use super::train_predictor;
 #[cfg(feature = "extension")]
-pub use super::{Extension, Scheduler, api, config::Config, node::Mode};
+pub use super::{
+    BenchPattern, CAPABILITIES, Control, Extension, Learned, OnDemand, Scheduler, api,
+    config::Config, node::Mode,
+};
 #[cfg(not(feature = "extension"))]
-pub use super::{Scheduler, config::Config, node::Mode};
+pub use super::{
+    BenchPattern, CAPABILITIES, Control, Learned, OnDemand, Scheduler, config::Config, node::Mode,
+};
@@ -0,0 +1,190 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    ffi::{CStr, c_int, c_void},
+    mem, ptr,
+    time::Duration,
+};
+
+use libc::pid_t;
+use log::{info, warn};
+
+type GetManagerFn = unsafe extern "C" fn() -> *mut c_void;
+type CreateSessionFn = unsafe extern "C" fn(*mut c_void, *const i32, usize, i64) -> *mut c_void;
+type UpdateTargetFn = unsafe extern "C" fn(*mut c_void, i64) -> c_int;
+type ReportFn = unsafe extern "C" fn(*mut c_void, i64) -> c_int;
+type CloseFn = unsafe extern "C" fn(*mut c_void);
+
+// APerformanceHint_* from libandroid (Android 13+), resolved at runtime so older systems
+// still start
+struct Api {
+    manager: *mut c_void,
+    create_session: CreateSessionFn,
+    update_target: UpdateTargetFn,
+    report: ReportFn,
+    close: CloseFn,
+}
+
+impl Api {
+    fn load() -> Option<Self> {
+        let handle = unsafe { libc::dlopen(c"libandroid.so".as_ptr(), libc::RTLD_NOW) };
+        if handle.is_null() {
+            return None;
+        }
+
+        unsafe {
+            let get_manager = mem::transmute::<*mut c_void, GetManagerFn>(symbol(
+                handle,
+                c"APerformanceHint_getManager",
+            )?);
+            let api = Self {
+                manager: get_manager(),
+                create_session: mem::transmute::<*mut c_void, CreateSessionFn>(symbol(
+                    handle,
+                    c"APerformanceHint_createSession",
+                )?),
+                update_target: mem::transmute::<*mut c_void, UpdateTargetFn>(symbol(
+                    handle,
+                    c"APerformanceHint_updateTargetWorkDuration",
+                )?),
+                report: mem::transmute::<*mut c_void, ReportFn>(symbol(
+                    handle,
+                    c"APerformanceHint_reportActualWorkDuration",
+                )?),
+                close: mem::transmute::<*mut c_void, CloseFn>(symbol(
+                    handle,
+                    c"APerformanceHint_closeSession",
+                )?),
+            };
+            (!api.manager.is_null()).then_some(api)
+        }
+    }
+}
+
+unsafe fn symbol(handle: *mut c_void, name: &CStr) -> Option<*mut c_void> {
+    let symbol = unsafe { libc::dlsym(handle, name.as_ptr()) };
+    (!symbol.is_null()).then_some(symbol)
+}
+
+// Registers the game's critical threads with the platform's performance hint (ADPF) service
+// and reports every frame against the target frametime, so the power hal boosts the same
+// threads fas works for instead of fighting its frequency decisions.
+// Stock HintManagerService only accepts tids of the calling process (checkTidValid), so on
+// those systems no session opens for the game's threads and this does nothing; only builds
+// that relax the check for root accept it
+pub struct Adpf {
+    api: Option<Api>,
+    tried: bool,
+    // The rejection is logged once, it repeats for every new set of tids
+    rejected: bool,
+    session: *mut c_void,
+    tids: Vec<pid_t>,
+    target: Duration,
+}
+
+impl Adpf {
+    pub const fn new() -> Self {
+        Self {
+            api: None,
+            tried: false,
+            rejected: false,
+            session: ptr::null_mut(),
+            tids: Vec::new(),
+            target: Duration::ZERO,
+        }
+    }
+
+    // Opens a session for tids, reopening it when they change
+    pub fn update<I>(&mut self, tids: I, target_fps: f64)
+    where
+        I: IntoIterator<Item = pid_t>,
+    {
+        let mut tids: Vec<_> = tids.into_iter().collect();
+        tids.sort_unstable();
+        if tids.is_empty() || target_fps <= 0.0 {
+            return;
+        }
+
+        if !self.tried {
+            self.tried = true;
+            self.api = Api::load();
+            if self.api.is_none() {
+                warn!("Performance hint api is unavailable, adpf integration disabled");
+            }
+        }
+        let Some(api) = &self.api else {
+            return;
+        };
+
+        let target = Duration::from_secs_f64(1.0 / target_fps);
+        if tids != self.tids {
+            self.close();
+            let session = unsafe {
+                (api.create_session)(
+                    api.manager,
+                    tids.as_ptr(),
+                    tids.len(),
+                    target.as_nanos() as i64,
+                )
+            };
+            if session.is_null() {
+                if !self.rejected {
+                    warn!(
+                        "Performance hint session for {tids:?} was rejected, the system only accepts threads of the caller, adpf integration has no effect"
+                    );
+                    self.rejected = true;
+                }
+            } else {
+                info!("Performance hint session opened for {tids:?}");
+                self.session = session;
+            }
+            self.tids = tids;
+            self.target = target;
+        } else if target != self.target && !self.session.is_null() {
+            unsafe { (api.update_target)(self.session, target.as_nanos() as i64) };
+            self.target = target;
+        }
+    }
+
+    pub fn report(&self, frametime: Duration) {
+        if let Some(api) = &self.api
+            && !self.session.is_null()
+        {
+            unsafe { (api.report)(self.session, frametime.as_nanos() as i64) };
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.tids.clear();
+        if self.session.is_null() {
+            return;
+        }
+
+        if let Some(api) = &self.api {
+            unsafe { (api.close)(self.session) };
+            info!("Performance hint session closed");
+        }
+        self.session = ptr::null_mut();
+    }
+}
+
+impl Drop for Adpf {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
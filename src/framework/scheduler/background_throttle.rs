@@ -0,0 +1,102 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs, path::PathBuf};
+
+use log::{debug, info};
+
+use crate::{
+    file_handler,
+    framework::{CAPABILITIES, config::BackgroundThrottle},
+    snapshot,
+};
+
+const CPUCTL: &str = "/dev/cpuctl";
+const GROUPS: [&str; 2] = ["background", "system-background"];
+const CFS_PERIOD_US: u64 = 100_000;
+
+// Caps the background cgroups while a game runs, so sync and maintenance work can't take
+// big core time away from it. The original limits are written back when the session ends
+pub struct BackgroundThrottler {
+    saved: Vec<(PathBuf, String)>,
+}
+
+impl BackgroundThrottler {
+    pub const fn new() -> Self {
+        Self { saved: Vec::new() }
+    }
+
+    // `limit` is the share of cpu time (`0.0`-`1.0`) the background groups may use
+    pub fn apply(&mut self, throttle: BackgroundThrottle, limit: f64) {
+        if throttle == BackgroundThrottle::Off || !self.saved.is_empty() {
+            return;
+        }
+
+        let limit = limit.clamp(0.01, 1.0);
+        for group in GROUPS.map(|group| PathBuf::from(CPUCTL).join(group)) {
+            if !group.exists() {
+                continue;
+            }
+
+            match throttle {
+                BackgroundThrottle::Off => (),
+                BackgroundThrottle::Uclamp => {
+                    if CAPABILITIES.uclamp {
+                        let value = format!("{:.2}", limit * 100.0);
+                        self.write(group.join("cpu.uclamp.max"), &value);
+                    }
+                }
+                BackgroundThrottle::Bandwidth => {
+                    // The quota spans all cpus, a limit of 1.0 leaves every core available
+                    let quota = (CFS_PERIOD_US as f64 * limit * num_cpus::get() as f64) as u64;
+                    self.write(group.join("cpu.cfs_period_us"), &CFS_PERIOD_US.to_string());
+                    self.write(group.join("cpu.cfs_quota_us"), &quota.to_string());
+                }
+            }
+        }
+
+        if !self.saved.is_empty() {
+            info!("Background cgroups throttled to {:.0}%", limit * 100.0);
+        }
+    }
+
+    pub fn restore(&mut self) {
+        if self.saved.is_empty() {
+            return;
+        }
+
+        // Reversed, the quota has to be lifted before the period can shrink again
+        for (path, value) in self.saved.drain(..).rev() {
+            let _ = file_handler::write_direct(&path, value);
+        }
+
+        info!("Background cgroup limits restored");
+    }
+
+    fn write(&mut self, path: PathBuf, value: &str) {
+        let Ok(original) = fs::read_to_string(&path) else {
+            return;
+        };
+
+        let original = original.trim().to_string();
+        snapshot::record(&path);
+        if file_handler::write_direct(&path, value).is_ok() {
+            debug!("{}: {original} -> {value}", path.display());
+            self.saved.push((path, original));
+        }
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use log::{debug, info};
+
+const CAPACITY_NODE: &str = "/sys/class/power_supply/battery/capacity";
+const STATUS_NODE: &str = "/sys/class/power_supply/battery/status";
+const CURRENT_NODE: &str = "/sys/class/power_supply/battery/current_now";
+const VOLTAGE_NODE: &str = "/sys/class/power_supply/battery/voltage_now";
+const REFRESH_TIME: Duration = Duration::from_secs(10);
+
+struct PowerSession {
+    start: Instant,
+    power_sum: f64,
+    samples: u32,
+}
+
+pub struct Battery {
+    capacity: Option<u32>,
+    charging: bool,
+    timer: Instant,
+    target_fps_offset: f64,
+    power: Option<f64>,
+    session: Option<PowerSession>,
+}
+
+impl Battery {
+    pub fn new() -> Self {
+        Self {
+            capacity: Self::read_capacity(),
+            charging: Self::read_charging(),
+            timer: Instant::now(),
+            target_fps_offset: 0.0,
+            power: None,
+            session: None,
+        }
+    }
+
+    pub fn capacity(&mut self) -> Option<u32> {
+        self.refresh();
+        self.capacity
+    }
+
+    pub fn charging(&mut self) -> bool {
+        self.refresh();
+        self.charging
+    }
+
+    fn refresh(&mut self) {
+        if self.timer.elapsed() > REFRESH_TIME {
+            self.timer = Instant::now();
+            self.capacity = Self::read_capacity();
+
+            let charging = Self::read_charging();
+            if charging != self.charging {
+                info!(
+                    "Charger {}",
+                    if charging {
+                        "connected"
+                    } else {
+                        "disconnected"
+                    }
+                );
+            }
+            self.charging = charging;
+        }
+    }
+
+    // Power draw in watts, the sample is also added to the running session
+    pub fn sample_power(&mut self) -> Option<f64> {
+        self.power = Self::read_power();
+
+        if let Some(power) = self.power
+            && let Some(session) = &mut self.session
+        {
+            session.power_sum += power;
+            session.samples += 1;
+        }
+
+        self.power
+    }
+
+    // The last sampled power draw in watts
+    pub const fn power(&self) -> Option<f64> {
+        self.power
+    }
+
+    pub fn begin_session(&mut self) {
+        self.session = Some(PowerSession {
+            start: Instant::now(),
+            power_sum: 0.0,
+            samples: 0,
+        });
+    }
+
+    // Returns the session duration and its average power draw
+    pub fn end_session(&mut self) -> Option<(Duration, Option<f64>)> {
+        let session = self.session.take()?;
+        let average = (session.samples > 0).then(|| session.power_sum / f64::from(session.samples));
+        Some((session.start.elapsed(), average))
+    }
+
+    // Moves towards the target offset a little each time, like thermal does, so a rule
+    // kicking in lowers the target fps gradually instead of at once
+    pub fn target_fps_offset(&mut self, target: f64) -> f64 {
+        if self.target_fps_offset > target {
+            self.target_fps_offset = (self.target_fps_offset - 0.01).max(target);
+        } else {
+            self.target_fps_offset = (self.target_fps_offset + 0.01).min(target);
+        }
+
+        self.target_fps_offset
+    }
+
+    // "Full" still means the charger is plugged in
+    fn read_charging() -> bool {
+        fs::read_to_string(STATUS_NODE)
+            .is_ok_and(|status| matches!(status.trim(), "Charging" | "Full"))
+    }
+
+    // current_now is in µA and voltage_now in µV, the sign of current differs between devices
+    fn read_power() -> Option<f64> {
+        let current: f64 = fs::read_to_string(CURRENT_NODE).ok()?.trim().parse().ok()?;
+        let voltage: f64 = fs::read_to_string(VOLTAGE_NODE).ok()?.trim().parse().ok()?;
+        Some(current.abs() * voltage / 1_000_000_000_000.0)
+    }
+
+    fn read_capacity() -> Option<u32> {
+        let capacity = fs::read_to_string(CAPACITY_NODE).ok()?.trim().parse().ok();
+        if let Some(capacity) = capacity {
+            debug!("battery capacity: {capacity}%");
+        }
+        capacity
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    process::Command,
+    sync::{
+        LazyLock,
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+use log::{debug, warn};
+
+use crate::framework::node::Mode;
+
+const ACTION_PREFIX: &str = "fas_rs.intent.action";
+// FLAG_RECEIVER_INCLUDE_BACKGROUND, automation apps often listen with manifest receivers
+const INCLUDE_BACKGROUND: &str = "0x01000000";
+
+type Broadcast = (&'static str, Vec<(&'static str, String)>);
+
+// In call order on one thread, so e.g. DISENGAGED can't arrive before the ENGAGED before it
+static SENDER: LazyLock<Option<Sender<Broadcast>>> = LazyLock::new(|| {
+    let (sx, rx) = mpsc::channel::<Broadcast>();
+    thread::Builder::new()
+        .name("BroadcastThread".into())
+        .spawn(move || {
+            for (action, extras) in rx {
+                broadcast(action, &extras);
+            }
+        })
+        .ok()?;
+    Some(sx)
+});
+
+// Broadcasts state changes so automation apps (Tasker, MacroDroid...) can react without
+// polling. `am broadcast` sends them as the shell, off the control loop as it takes a while.
+fn send(action: &'static str, extras: Vec<(&'static str, String)>) {
+    let Some(sx) = SENDER.as_ref() else {
+        broadcast(action, &extras);
+        return;
+    };
+
+    if let Err(mpsc::SendError((action, extras))) = sx.send((action, extras)) {
+        broadcast(action, &extras);
+    }
+}
+
+fn broadcast(action: &str, extras: &[(&'static str, String)]) {
+    let action = format!("{ACTION_PREFIX}.{action}");
+    debug!("Broadcasting {action}: {extras:?}");
+    let mut command = Command::new("am");
+    command.args(["broadcast", "-a", &action, "-f", INCLUDE_BACKGROUND]);
+    for (key, value) in extras {
+        command.arg("--es").arg(key).arg(value);
+    }
+
+    match command.output() {
+        Ok(output) if !output.status.success() => warn!(
+            "Failed to broadcast {action}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => warn!("Failed to broadcast {action}: {e}"),
+        Ok(_) => (),
+    }
+}
+
+pub fn engaged(pkg: String, target_fps: Option<f64>, mode: Mode) {
+    let target_fps = target_fps.map_or_else(|| "auto".to_string(), |fps| fps.to_string());
+    send(
+        "ENGAGED",
+        vec![
+            ("pkg", pkg),
+            ("target_fps", target_fps),
+            ("mode", mode.to_string()),
+        ],
+    );
+}
+
+pub fn disengaged(pkg: String) {
+    send("DISENGAGED", vec![("pkg", pkg)]);
+}
+
+pub fn mode_changed(mode: Mode, pkg: Option<String>) {
+    let mut extras = vec![("mode", mode.to_string())];
+    if let Some(pkg) = pkg {
+        extras.push(("pkg", pkg));
+    }
+    send("MODE_CHANGED", extras);
+}
+
+// Sent when heat or battery temperature starts and stops holding the game back
+pub fn throttle_changed(pkg: String, throttled: bool) {
+    send(
+        "THERMAL_THROTTLE",
+        vec![("pkg", pkg), ("throttled", throttled.to_string())],
+    );
+}
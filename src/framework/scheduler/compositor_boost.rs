@@ -0,0 +1,142 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs;
+
+use libc::{PRIO_PROCESS, pid_t};
+use log::{debug, info};
+use nix::{
+    sched::{CpuSet, sched_getaffinity, sched_setaffinity},
+    unistd::Pid,
+};
+
+use crate::{file_handler, framework::pid_utils::get_process_name, snapshot};
+
+const COMPOSITOR_NICE: i32 = -10;
+// Executable names of surfaceflinger and the composer HALs (HIDL, AIDL and vendor ones)
+const COMPOSITORS: [&str; 8] = [
+    "surfaceflinger",
+    "android.hardware.graphics.composer@2.1-service",
+    "android.hardware.graphics.composer@2.2-service",
+    "android.hardware.graphics.composer@2.3-service",
+    "android.hardware.graphics.composer@2.4-service",
+    "android.hardware.graphics.composer3-service",
+    "android.hardware.composer.hwc3-service.pixel",
+    "vendor.qti.hardware.display.composer-service",
+];
+
+// Compositor scheduling delays show up as missed frames the controller would blame on
+// the game, so surfaceflinger and the hwc threads get a higher priority during sessions.
+// The original scheduling is kept in the snapshot, so a crash still restores it
+pub struct CompositorBoost {
+    boosted: usize,
+}
+
+impl CompositorBoost {
+    pub const fn new() -> Self {
+        Self { boosted: 0 }
+    }
+
+    // `cpus` empty keeps the current affinity
+    pub fn apply(&mut self, cpus: &[usize]) {
+        if self.boosted > 0 {
+            return;
+        }
+        if file_handler::dry_run() {
+            info!("dry run: compositor threads not boosted");
+            return;
+        }
+
+        let mut cpu_set = CpuSet::new();
+        for cpu in cpus {
+            let _ = cpu_set.set(*cpu);
+        }
+
+        for pid in compositor_pids() {
+            let Ok(tasks) = fs::read_dir(format!("/proc/{pid}/task")) else {
+                continue;
+            };
+
+            for tid in
+                tasks.filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<pid_t>().ok())
+            {
+                if boost(tid, (!cpus.is_empty()).then_some(&cpu_set)) {
+                    self.boosted += 1;
+                }
+            }
+        }
+
+        if self.boosted > 0 {
+            info!("Compositor threads boosted: {}", self.boosted);
+        }
+    }
+
+    pub fn restore(&mut self) {
+        if self.boosted == 0 {
+            return;
+        }
+
+        snapshot::restore_sched();
+        self.boosted = 0;
+        info!("Compositor threads scheduling restored");
+    }
+}
+
+fn compositor_pids() -> Vec<pid_t> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<pid_t>().ok())
+        .filter(|pid| {
+            get_process_name(*pid).is_ok_and(|name| {
+                let name = name.rsplit('/').next().unwrap_or_default();
+                COMPOSITORS.contains(&name)
+            })
+        })
+        .collect()
+}
+
+// Real-time threads (e.g. surfaceflinger's main thread on recent releases) keep their
+// priority, nice values don't apply to them. Returns whether anything changed
+fn boost(tid: pid_t, cpu_set: Option<&CpuSet>) -> bool {
+    let nice = (unsafe { libc::sched_getscheduler(tid) } == libc::SCHED_OTHER)
+        .then(|| unsafe { libc::getpriority(PRIO_PROCESS, tid as u32) })
+        .filter(|nice| *nice > COMPOSITOR_NICE);
+    let affinity = cpu_set.and_then(|_| sched_getaffinity(Pid::from_raw(tid)).ok());
+    if nice.is_none() && affinity.is_none() {
+        return false;
+    }
+    snapshot::record_sched(tid, nice, affinity.as_ref());
+
+    let mut boosted = false;
+    if let Some(nice) = nice
+        && unsafe { libc::setpriority(PRIO_PROCESS, tid as u32, COMPOSITOR_NICE) } == 0
+    {
+        debug!("tid {tid}: nice {nice} -> {COMPOSITOR_NICE}");
+        boosted = true;
+    }
+    if let Some(cpu_set) = cpu_set
+        && affinity.is_some()
+        && sched_setaffinity(Pid::from_raw(tid), cpu_set).is_ok()
+    {
+        boosted = true;
+    }
+
+    boosted
+}
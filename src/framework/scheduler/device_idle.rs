@@ -0,0 +1,116 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use dumpsys_rs::Dumpsys;
+use log::info;
+
+const REFRESH_TIME: Duration = Duration::from_secs(5);
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+pub struct DeviceIdleWatcher {
+    dumper: Option<Dumpsys>,
+    idle: bool,
+    last_refresh: Instant,
+    backlights: Vec<PathBuf>,
+    screen_off: bool,
+}
+
+impl DeviceIdleWatcher {
+    pub fn new() -> Self {
+        let backlights = fs::read_dir(BACKLIGHT_DIR)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path().join("brightness")))
+                    .filter(|path| path.exists())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            dumper: Dumpsys::new("deviceidle"),
+            idle: false,
+            last_refresh: Instant::now(),
+            backlights,
+            screen_off: false,
+        }
+    }
+
+    // The screen is off when every panel backlight reads zero brightness
+    pub fn screen_off(&mut self) -> bool {
+        if self.backlights.is_empty() {
+            return false;
+        }
+
+        let screen_off = self
+            .backlights
+            .iter()
+            .all(|path| fs::read_to_string(path).is_ok_and(|brightness| brightness.trim() == "0"));
+
+        if screen_off != self.screen_off {
+            if screen_off {
+                info!("Screen turned off, suspending fas");
+            } else {
+                info!("Screen turned on, resuming fas");
+            }
+        }
+
+        self.screen_off = screen_off;
+        screen_off
+    }
+
+    pub fn device_idle(&mut self) -> bool {
+        if self.last_refresh.elapsed() > REFRESH_TIME {
+            self.last_refresh = Instant::now();
+
+            let idle = self
+                .dumper
+                .as_mut()
+                .and_then(|dumper| dumper.dump(&[]).ok())
+                .is_some_and(|dump| Self::parse_idle(&dump));
+
+            if idle != self.idle {
+                if idle {
+                    info!("Device entered doze, suspending fas");
+                } else {
+                    info!("Device left doze, resuming fas");
+                }
+            }
+
+            self.idle = idle;
+        }
+
+        self.idle
+    }
+
+    // Both deep and light doze are only entered with the screen off, so either one
+    // means there is nothing for fas to schedule.
+    fn parse_idle(dump: &str) -> bool {
+        dump.split_whitespace()
+            .filter_map(|token| {
+                token
+                    .strip_prefix("mState=")
+                    .or_else(|| token.strip_prefix("mLightState="))
+            })
+            .any(|state| state.starts_with("IDLE"))
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+};
+
+use log::info;
+
+use super::refresh_pin::{get_setting, put_setting};
+use crate::snapshot;
+
+enum Request {
+    Apply(u32),
+    Restore,
+}
+
+struct Saved {
+    min: String,
+    peak: String,
+    applied: String,
+}
+
+// Locks the display refresh rate (peak_refresh_rate = min_refresh_rate) to the rate configured
+// for the game while fas works on it, and gives the user's settings back afterwards.
+pub struct DisplayRefresh {
+    sx: Option<Sender<Request>>,
+    handle: Option<JoinHandle<()>>,
+    applied: bool,
+}
+
+impl DisplayRefresh {
+    pub fn new() -> Self {
+        let (sx, rx) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("DisplayRefreshThread".into())
+            .spawn(move || {
+                let mut saved = None;
+                for request in rx {
+                    match request {
+                        Request::Apply(refresh_rate) => {
+                            if let Some(saved) = saved.take() {
+                                restore(&saved);
+                            }
+                            saved = apply(refresh_rate);
+                        }
+                        Request::Restore => {
+                            if let Some(saved) = saved.take() {
+                                restore(&saved);
+                            }
+                        }
+                    }
+                }
+            })
+            .ok();
+
+        Self {
+            sx: handle.as_ref().map(|_| sx),
+            handle,
+            applied: false,
+        }
+    }
+
+    pub fn apply(&mut self, refresh_rate: u32) {
+        self.send(Request::Apply(refresh_rate));
+        self.applied = true;
+    }
+
+    pub fn restore(&mut self) {
+        if self.applied {
+            self.send(Request::Restore);
+            self.applied = false;
+        }
+    }
+
+    // Restores and waits for the worker to finish, so the user's settings are back before exit
+    pub fn shutdown(&mut self) {
+        self.restore();
+        self.sx = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    fn send(&self, request: Request) {
+        if let Some(sx) = &self.sx {
+            let _ = sx.send(request);
+        }
+    }
+}
+
+fn apply(refresh_rate: u32) -> Option<Saved> {
+    let min = get_setting("min_refresh_rate")?;
+    let peak = get_setting("peak_refresh_rate")?;
+    // The settings take floats, e.g. 60.0
+    let applied = format!("{refresh_rate}.0");
+
+    info!(
+        "Set display refresh rate to {applied} (min_refresh_rate {min}, peak_refresh_rate {peak})"
+    );
+    snapshot::record_setting("peak_refresh_rate", &peak, &applied);
+    snapshot::record_setting("min_refresh_rate", &min, &applied);
+    put_setting("peak_refresh_rate", &applied);
+    put_setting("min_refresh_rate", &applied);
+    Some(Saved { min, peak, applied })
+}
+
+fn restore(saved: &Saved) {
+    // Someone else changed them meanwhile, leave their values alone
+    if get_setting("peak_refresh_rate").as_deref() != Some(saved.applied.as_str())
+        || get_setting("min_refresh_rate").as_deref() != Some(saved.applied.as_str())
+    {
+        info!("Display refresh rate changed by others, not restoring it");
+        snapshot::forget_setting("min_refresh_rate");
+        snapshot::forget_setting("peak_refresh_rate");
+        return;
+    }
+
+    info!(
+        "Restore display refresh rate: min_refresh_rate {}, peak_refresh_rate {}",
+        saved.min, saved.peak
+    );
+    put_setting("min_refresh_rate", &saved.min);
+    put_setting("peak_refresh_rate", &saved.peak);
+    snapshot::forget_setting("min_refresh_rate");
+    snapshot::forget_setting("peak_refresh_rate");
+}
@@ -0,0 +1,82 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+};
+
+use log::{debug, info, warn};
+
+use crate::{file_handler, sysfs};
+
+// The pm_qos request stays in force as long as the file is held open
+const CPU_DMA_LATENCY: &str = "/dev/cpu_dma_latency";
+
+// Keeps cpus out of idle states slower to exit than the bound while a game runs, a deep
+// c-state waking up late can blow a single frame. Closing the file drops the request
+pub struct DmaLatency {
+    request: Option<(File, u32)>,
+}
+
+impl DmaLatency {
+    pub const fn new() -> Self {
+        Self { request: None }
+    }
+
+    // Microseconds, 0 holds no request
+    pub fn apply(&mut self, latency: u32) {
+        if latency == 0 {
+            self.release();
+            return;
+        }
+        if self
+            .request
+            .as_ref()
+            .is_some_and(|(_, current)| *current == latency)
+        {
+            return;
+        }
+        if file_handler::dry_run() {
+            debug!("dry run: cpu dma latency not held at {latency}us");
+            return;
+        }
+
+        let path = sysfs::resolve(sysfs::root(), CPU_DMA_LATENCY);
+        let request = OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|mut file| {
+                // The kernel takes the bound as a raw s32
+                file.write_all(&i32::try_from(latency).unwrap_or(i32::MAX).to_ne_bytes())?;
+                Ok(file)
+            });
+        match request {
+            Ok(file) => {
+                info!("Cpu dma latency held at {latency}us");
+                self.request = Some((file, latency));
+            }
+            Err(e) => warn!("Failed to request cpu dma latency: {e}"),
+        }
+    }
+
+    pub fn release(&mut self) {
+        if self.request.take().is_some() {
+            info!("Cpu dma latency released");
+        }
+    }
+}
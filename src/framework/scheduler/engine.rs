@@ -0,0 +1,141 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use libc::pid_t;
+use log::info;
+
+use crate::framework::config::{Engine, TopCoresStrategy};
+
+// Engine libraries are loaded a while after the process starts
+const DETECT_TIME: Duration = Duration::from_secs(5);
+// Without a known library or thread by then the game is taken as custom
+const DETECT_ATTEMPTS: u32 = 6;
+
+// How the controller leans for an engine. The ramps scale the configured ones, the dwell is
+// a lower bound and the top cores strategy replaces the configured one
+#[derive(Debug, Clone, Copy)]
+pub struct EnginePreset {
+    pub ramp_up: f64,
+    pub ramp_down: f64,
+    pub down_dwell: Duration,
+    pub top_cores: Option<TopCoresStrategy>,
+}
+
+impl Default for EnginePreset {
+    fn default() -> Self {
+        Self {
+            ramp_up: 1.0,
+            ramp_down: 1.0,
+            down_dwell: Duration::ZERO,
+            top_cores: None,
+        }
+    }
+}
+
+// Unity games are bound by UnityMain, which migrates between cores, so misses show up at
+// once and the busiest cores matter. Unreal spreads a frame over taskgraph workers that hop
+// around, picked over a window and lowered slowly as the load shifts between them
+pub fn preset(engine: Engine) -> EnginePreset {
+    match engine {
+        Engine::Unity => EnginePreset {
+            ramp_up: 1.2,
+            ramp_down: 0.8,
+            down_dwell: Duration::from_millis(500),
+            top_cores: Some(TopCoresStrategy::Load),
+        },
+        Engine::Unreal => EnginePreset {
+            ramp_up: 1.0,
+            ramp_down: 0.7,
+            down_dwell: Duration::from_secs(1),
+            top_cores: Some(TopCoresStrategy::Windowed),
+        },
+        Engine::Custom => EnginePreset::default(),
+    }
+}
+
+// Tells the game's engine from its loaded libraries, or its thread names when they are
+// linked in under another name
+pub struct EngineDetector {
+    pid: pid_t,
+    engine: Option<Engine>,
+    attempts: u32,
+    timer: Instant,
+}
+
+impl EngineDetector {
+    pub fn new() -> Self {
+        Self {
+            pid: 0,
+            engine: None,
+            attempts: 0,
+            timer: Instant::now(),
+        }
+    }
+
+    pub fn update(&mut self, pid: pid_t) {
+        if pid != self.pid {
+            self.reset();
+            self.pid = pid;
+        } else if self.engine.is_some() || self.timer.elapsed() < DETECT_TIME {
+            return;
+        }
+        self.timer = Instant::now();
+        self.attempts += 1;
+
+        let engine =
+            detect(pid).or_else(|| (self.attempts >= DETECT_ATTEMPTS).then_some(Engine::Custom));
+        if let Some(engine) = engine {
+            info!("Game engine of pid {pid}: {engine:?}");
+            self.engine = Some(engine);
+        }
+    }
+
+    pub const fn engine(&self) -> Option<Engine> {
+        self.engine
+    }
+
+    pub fn reset(&mut self) {
+        self.pid = 0;
+        self.engine = None;
+        self.attempts = 0;
+    }
+}
+
+fn detect(pid: pid_t) -> Option<Engine> {
+    if let Ok(maps) = fs::read_to_string(format!("/proc/{pid}/maps")) {
+        if maps.contains("libunity.so") {
+            return Some(Engine::Unity);
+        }
+        if maps.contains("libUE4.so") || maps.contains("libUnreal.so") {
+            return Some(Engine::Unreal);
+        }
+    }
+
+    let entries = fs::read_dir(format!("/proc/{pid}/task")).ok()?;
+    entries
+        .filter_map(|entry| fs::read_to_string(entry.ok()?.path().join("comm")).ok())
+        .find_map(|name| match name.trim() {
+            "UnityMain" | "UnityGfxDeviceW" => Some(Engine::Unity),
+            "GameThread" | "RHIThread" | "MainThread-UE4" => Some(Engine::Unreal),
+            _ => None,
+        })
+}
@@ -0,0 +1,199 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::Read,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+// An attached hook that delivered nothing for this long is reported as not working
+const VERIFY_TIME: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Abi {
+    Bit32,
+    Bit64,
+}
+
+impl fmt::Display for Abi {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bit32 => write!(f, "32-bit"),
+            Self::Bit64 => write!(f, "64-bit"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HookState {
+    Attached(Instant),
+    Verified,
+    NoFrames,
+    // The analyzer only ships a 64-bit payload
+    Unsupported,
+    // Listed in hook_exclude, never injected
+    Excluded,
+    Failed(String),
+}
+
+impl fmt::Display for HookState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Attached(_) => write!(f, "attached"),
+            Self::Verified => write!(f, "verified"),
+            Self::NoFrames => write!(f, "no-frames"),
+            Self::Unsupported => write!(f, "unsupported-abi"),
+            Self::Excluded => write!(f, "excluded"),
+            Self::Failed(e) => write!(f, "failed: {e}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Hook {
+    pkg: String,
+    abi: Option<Abi>,
+    state: HookState,
+}
+
+// Tracks the frame hook of every game it was attached to, so a hook that can't attach or
+// stays silent is reported and the game goes straight to a fallback frame source
+#[derive(Debug, Default)]
+pub struct HookTracker {
+    hooks: HashMap<i32, Hook>,
+}
+
+impl HookTracker {
+    // Whether the hook should be attached to the game now, only once per process
+    pub fn track(&mut self, pid: i32, pkg: &str, excluded: bool) -> bool {
+        if self.hooks.contains_key(&pid) {
+            return false;
+        }
+
+        let abi = detect_abi(pid);
+        let state = if excluded {
+            info!("[{pkg}] is excluded from the frame hook, using fallback frame sources");
+            HookState::Excluded
+        } else if abi == Some(Abi::Bit32) {
+            warn!(
+                "[{pkg}] is a 32-bit process, the frame hook can't attach, using fallback frame sources"
+            );
+            HookState::Unsupported
+        } else {
+            HookState::Attached(Instant::now())
+        };
+        let attach = matches!(state, HookState::Attached(_));
+        self.hooks.insert(
+            pid,
+            Hook {
+                pkg: pkg.to_string(),
+                abi,
+                state,
+            },
+        );
+        attach
+    }
+
+    pub fn failed(&mut self, pid: i32, error: String) {
+        if let Some(hook) = self.hooks.get_mut(&pid) {
+            warn!("Failed to attach frame hook to [{}]: {error}", hook.pkg);
+            hook.state = HookState::Failed(error);
+        }
+    }
+
+    pub fn frame(&mut self, pid: i32) {
+        if let Some(hook) = self.hooks.get_mut(&pid)
+            && hook.state != HookState::Verified
+        {
+            info!("Frame hook of [{}] verified", hook.pkg);
+            hook.state = HookState::Verified;
+        }
+    }
+
+    // Drops processes that left and flags hooks that never delivered a frame
+    pub fn update(&mut self, pids: &[i32]) {
+        self.hooks.retain(|pid, _| pids.contains(pid));
+        for hook in self.hooks.values_mut() {
+            if let HookState::Attached(since) = hook.state
+                && since.elapsed() > VERIFY_TIME
+            {
+                warn!("Frame hook of [{}] delivered no frames", hook.pkg);
+                hook.state = HookState::NoFrames;
+            }
+        }
+    }
+
+    // Everything is attached again, e.g. after the analyzer was restarted
+    pub fn clear(&mut self) {
+        self.hooks.clear();
+    }
+
+    // Games the hook won't deliver frames for
+    pub fn hookless(&self) -> Vec<i32> {
+        self.hooks
+            .iter()
+            .filter(|(_, hook)| {
+                matches!(
+                    hook.state,
+                    HookState::NoFrames
+                        | HookState::Unsupported
+                        | HookState::Excluded
+                        | HookState::Failed(_)
+                )
+            })
+            .map(|(pid, _)| *pid)
+            .collect()
+    }
+
+    // "<pid> <package> <abi> <state>" per game
+    pub fn describe(&self) -> Vec<String> {
+        self.hooks
+            .iter()
+            .map(|(pid, hook)| {
+                format!(
+                    "{pid} {} {} {}",
+                    hook.pkg,
+                    hook.abi
+                        .map_or_else(|| String::from("unknown"), |abi| abi.to_string()),
+                    hook.state
+                )
+            })
+            .collect()
+    }
+}
+
+// From the ELF class of the process' executable, app_process32 or app_process64
+fn detect_abi(pid: i32) -> Option<Abi> {
+    let mut header = [0; 5];
+    File::open(format!("/proc/{pid}/exe"))
+        .and_then(|mut exe| exe.read_exact(&mut header))
+        .ok()?;
+    if &header[..4] != b"\x7fELF" {
+        return None;
+    }
+
+    match header[4] {
+        1 => Some(Abi::Bit32),
+        2 => Some(Abi::Bit64),
+        _ => None,
+    }
+}
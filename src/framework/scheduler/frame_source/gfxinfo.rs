@@ -0,0 +1,146 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
+    thread,
+    time::Duration,
+};
+
+use dumpsys_rs::Dumpsys;
+
+use super::FasData;
+
+// framestats keeps the last 120 frames
+const POLL_TIME: Duration = Duration::from_millis(500);
+const GIVE_UP_POLLS: u32 = 10;
+
+pub fn poll(pid: i32, pkg: &str, sx: &Sender<FasData>, stop: &AtomicBool) {
+    let Some(mut dumper) = Dumpsys::new("gfxinfo") else {
+        return;
+    };
+    let mut last_completed = None;
+    let mut empty_polls = 0;
+
+    while !stop.load(Ordering::Acquire) {
+        thread::sleep(POLL_TIME);
+
+        let completed = dumper
+            .dump(&[pkg, "framestats"])
+            .map(|stats| parse_completed(&stats))
+            .unwrap_or_default();
+
+        // Not rendered through HWUI, nothing to get from here
+        if completed.is_empty() {
+            empty_polls += 1;
+            if last_completed.is_none() && empty_polls >= GIVE_UP_POLLS {
+                return;
+            }
+            continue;
+        }
+
+        for completed in completed {
+            if let Some(last) = last_completed
+                && completed > last
+                && sx
+                    .send(FasData {
+                        pid,
+                        frametime: Duration::from_nanos(completed - last),
+                        low_confidence: true,
+                        production: None,
+                        present: None,
+                    })
+                    .is_err()
+            {
+                return;
+            }
+
+            if last_completed.is_none_or(|last| completed > last) {
+                last_completed = Some(completed);
+            }
+        }
+    }
+}
+
+// The PROFILEDATA section is a csv, its header names the columns. Frames with non zero
+// flags (e.g. skipped or first draw) don't reflect the game's pacing.
+fn parse_completed(stats: &str) -> Vec<u64> {
+    let mut rows = stats
+        .split("---PROFILEDATA---")
+        .nth(1)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim().is_empty());
+
+    let Some(header) = rows.next() else {
+        return Vec::new();
+    };
+    let columns: Vec<_> = header.split(',').collect();
+    let (Some(flags), Some(completed)) = (
+        columns.iter().position(|column| *column == "Flags"),
+        columns
+            .iter()
+            .position(|column| *column == "FrameCompleted"),
+    ) else {
+        return Vec::new();
+    };
+
+    let mut frames: Vec<u64> = rows
+        .map(|row| row.split(',').collect::<Vec<_>>())
+        .filter(|row| row.get(flags).is_some_and(|flags| *flags == "0"))
+        .filter_map(|row| row.get(completed)?.parse().ok())
+        .filter(|completed| *completed != 0)
+        .collect();
+    frames.sort_unstable();
+    frames
+}
+
+// Conformance corpus: raw framestats dumps under fixtures/ and the completion timestamps
+// expected from them, one per line. Add a capture for each ROM whose output breaks the parser
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CASES: [(&str, &str, &str); 3] = [
+        (
+            "gfxinfo_framestats",
+            include_str!("fixtures/gfxinfo_framestats.txt"),
+            include_str!("fixtures/gfxinfo_framestats.expected"),
+        ),
+        (
+            "gfxinfo_legacy_columns",
+            include_str!("fixtures/gfxinfo_legacy_columns.txt"),
+            include_str!("fixtures/gfxinfo_legacy_columns.expected"),
+        ),
+        (
+            "gfxinfo_not_hwui",
+            include_str!("fixtures/gfxinfo_not_hwui.txt"),
+            include_str!("fixtures/gfxinfo_not_hwui.expected"),
+        ),
+    ];
+
+    #[test]
+    fn parses_the_corpus() {
+        for (name, input, rows) in CASES {
+            let expected: Vec<u64> = rows.lines().map(|row| row.parse().unwrap()).collect();
+            assert_eq!(parse_completed(input), expected, "{name}");
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fmt, time::Duration};
+
+// Frametimes outside this range aren't real frames, a zero one is a repeated timestamp
+const MIN_FRAMETIME: Duration = Duration::from_micros(500);
+const MAX_FRAMETIME: Duration = Duration::from_secs(5);
+// Real frames never repeat to the nanosecond this many times in a row, a source doing so
+// replays a stale timestamp
+const DUPLICATE_RUN: u32 = 30;
+// Frames judged together, and how many of them may be absurd before the source is degraded
+const WINDOW: u32 = 60;
+const MAX_ABSURD: u32 = 20;
+// Silence while a game is engaged longer than this shows as stalled
+pub const STALE_TIME: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Healthy,
+    Degraded(&'static str),
+    Stalled,
+}
+
+impl fmt::Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Healthy => write!(f, "healthy"),
+            Self::Degraded(reason) => write!(f, "degraded: {reason}"),
+            Self::Stalled => write!(f, "stalled"),
+        }
+    }
+}
+
+// Judges the frames of the active source. A source replaying timestamps or delivering
+// nonsense keeps fas frozen at whatever it last decided, so it gets replaced instead
+#[derive(Debug)]
+pub struct SourceHealth {
+    last: Option<Duration>,
+    repeats: u32,
+    frames: u32,
+    absurd: u32,
+    // The latest verdict, kept for the status until the source recovers
+    degraded: Option<&'static str>,
+    // Degraded since the last `degradation`
+    pending: bool,
+}
+
+impl SourceHealth {
+    pub const fn new() -> Self {
+        Self {
+            last: None,
+            repeats: 0,
+            frames: 0,
+            absurd: 0,
+            degraded: None,
+            pending: false,
+        }
+    }
+
+    // Whether the frame is usable, `degradation` tells whether the source still is
+    pub fn check(&mut self, frametime: Duration) -> bool {
+        if self.last == Some(frametime) {
+            self.repeats += 1;
+        } else {
+            self.repeats = 0;
+        }
+        self.last = Some(frametime);
+
+        let absurd = !(MIN_FRAMETIME..=MAX_FRAMETIME).contains(&frametime);
+        self.frames += 1;
+        if absurd {
+            self.absurd += 1;
+        }
+
+        if self.repeats >= DUPLICATE_RUN {
+            self.degraded = Some("duplicate frametimes");
+            self.pending = true;
+        } else if self.absurd >= MAX_ABSURD {
+            self.degraded = Some("absurd frametimes");
+            self.pending = true;
+        } else if self.frames >= WINDOW {
+            self.frames = 0;
+            self.absurd = 0;
+            self.degraded = None;
+        }
+
+        !absurd && self.repeats < DUPLICATE_RUN
+    }
+
+    // Takes the verdict that the source has to be replaced, judging starts over
+    pub fn degradation(&mut self) -> Option<&'static str> {
+        if !self.pending {
+            return None;
+        }
+        let reason = self.degraded?;
+        self.pending = false;
+        self.last = None;
+        self.repeats = 0;
+        self.frames = 0;
+        self.absurd = 0;
+        Some(reason)
+    }
+
+    // A new source starts without a verdict
+    pub const fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    // `silence` is the time since the last frame of an engaged game
+    pub fn health(&self, silence: Option<Duration>) -> Health {
+        if let Some(reason) = self.degraded {
+            Health::Degraded(reason)
+        } else if silence.is_some_and(|silence| silence > STALE_TIME) {
+            Health::Stalled
+        } else {
+            Health::Healthy
+        }
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use dumpsys_rs::Dumpsys;
+use log::{debug, info, warn};
+
+use super::{
+    FallbackKind, FallbackSource, FasData,
+    health::{Health, SourceHealth},
+    vulkan,
+};
+
+// Every full pass that yields no frames doubles the wait before the next one, up to 64x
+// `stall_time`
+const MAX_BACKOFF_SHIFT: u32 = 6;
+
+// Picks the frame source of the game on top. The hook based analyzer always ranks first,
+// the fallback sources follow from most to least reliable, and a source that stays silent
+// for `stall_time` or delivers broken frames is replaced by the next one.
+pub struct FrameSourceManager {
+    chain: Vec<FallbackKind>,
+    active: Option<(String, FallbackSource)>,
+    frame_timer: Instant,
+    stall_time: Duration,
+    // The fallback source that last delivered frames for each package
+    working: HashMap<String, FallbackKind>,
+    // The game every source went silent for, until it delivers frames again or leaves
+    exhausted: Option<(i32, String)>,
+    // Full passes over the chain the exhausted game went through
+    failed_passes: u32,
+    // Games the hook won't deliver frames for, they skip waiting on the analyzer
+    hookless: HashSet<i32>,
+    health: SourceHealth,
+    // The analyzer degraded, its hooks have to be reinitialized
+    restart_hook: bool,
+    // The game the vulkan layer is enabled for through the gpu debug layer settings
+    layer_app: Option<String>,
+}
+
+impl FrameSourceManager {
+    pub fn new() -> Self {
+        let chain: Vec<_> = [
+            FallbackKind::Vulkan,
+            FallbackKind::SurfaceFlinger,
+            FallbackKind::GfxInfo,
+        ]
+        .into_iter()
+        .filter(|kind| Self::available(*kind))
+        .collect();
+        info!("Available fallback frame sources: {chain:?}");
+
+        Self {
+            chain,
+            active: None,
+            frame_timer: Instant::now(),
+            stall_time: Duration::from_secs(3),
+            working: HashMap::new(),
+            exhausted: None,
+            failed_passes: 0,
+            hookless: HashSet::new(),
+            health: SourceHealth::new(),
+            restart_hook: false,
+            layer_app: None,
+        }
+    }
+
+    // The fallback source in use, the analyzer otherwise
+    pub fn describe(&self) -> String {
+        let working = self
+            .working
+            .iter()
+            .map(|(pkg, kind)| format!("{pkg}: {kind:?}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let active = self.active.as_ref().map_or_else(
+            || String::from("analyzer"),
+            |(pkg, source)| format!("{:?} for {pkg}", source.kind()),
+        );
+        let exhausted = self
+            .exhausted
+            .as_ref()
+            .map_or_else(|| String::from("none"), |(_, pkg)| pkg.clone());
+        format!(
+            "active: {active}\nhealth: {}\navailable fallbacks: {:?}\nremembered: [{working}]\nexhausted: {exhausted}",
+            self.health.health(None),
+            self.chain
+        )
+    }
+
+    // For the self test report, before any manager exists
+    pub fn available_fallbacks() -> String {
+        [
+            FallbackKind::Vulkan,
+            FallbackKind::SurfaceFlinger,
+            FallbackKind::GfxInfo,
+        ]
+        .into_iter()
+        .map(|kind| format!("{kind:?}: {}", Self::available(kind)))
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+
+    fn available(kind: FallbackKind) -> bool {
+        match kind {
+            FallbackKind::Vulkan => vulkan::available(),
+            FallbackKind::SurfaceFlinger => Dumpsys::new("SurfaceFlinger").is_some(),
+            FallbackKind::GfxInfo => Dumpsys::new("gfxinfo").is_some(),
+        }
+    }
+
+    pub const fn set_stall_time(&mut self, stall_time: Duration) {
+        self.stall_time = stall_time;
+    }
+
+    pub fn set_hookless(&mut self, pids: Vec<i32>) {
+        self.hookless = pids.into_iter().collect();
+    }
+
+    pub const fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub const fn exhausted(&self) -> Option<&(i32, String)> {
+        self.exhausted.as_ref()
+    }
+
+    // The analyzer delivered a frame, nothing else is needed
+    pub fn hook_frame(&mut self) {
+        self.frame_timer = Instant::now();
+        self.clear_exhausted();
+        if self.active.take().is_some() {
+            info!("Analyzer frames are back, fallback frame source stopped");
+            self.health.reset();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.active = None;
+        self.clear_exhausted();
+        self.frame_timer = Instant::now();
+        self.health.reset();
+        self.restart_hook = false;
+    }
+
+    // Also hands the gpu debug layer settings back
+    pub fn shutdown(&mut self) {
+        self.stop();
+        if self.layer_app.take().is_some() {
+            vulkan::disable();
+        }
+    }
+
+    // Health of the source in use, an engaged game without frames shows it stalled
+    pub fn health(&self, engaged: bool) -> Health {
+        self.health
+            .health(engaged.then(|| self.frame_timer.elapsed()))
+    }
+
+    // Whether a frame is fit for the buffer. A degraded fallback source is replaced right
+    // away, a degraded analyzer is left to `take_restart_hook`
+    pub fn screen(&mut self, frametime: Duration) -> bool {
+        let usable = self.health.check(frametime);
+        if let Some(reason) = self.health.degradation() {
+            if self.active.is_some() {
+                warn!("Fallback frame source degraded, {reason}");
+                self.replace_active();
+            } else {
+                warn!("Analyzer frame source degraded, {reason}, reinitializing hooks");
+                self.restart_hook = true;
+            }
+        }
+        usable
+    }
+
+    pub const fn take_restart_hook(&mut self) -> bool {
+        std::mem::replace(&mut self.restart_hook, false)
+    }
+
+    pub fn update<F>(&mut self, topapp: &[i32], find_game: F)
+    where
+        F: FnOnce() -> Option<(i32, String)>,
+    {
+        if let Some((pkg, source)) = &self.active {
+            let pid = source.pid();
+            if !topapp.contains(&pid) {
+                self.stop();
+                return;
+            }
+
+            if !source.finished() && self.frame_timer.elapsed() <= self.stall_time {
+                return;
+            }
+
+            self.replace_active();
+            return;
+        }
+
+        let Some((pid, pkg)) = find_game() else {
+            self.frame_timer = Instant::now();
+            self.clear_exhausted();
+            return;
+        };
+        if self
+            .exhausted
+            .as_ref()
+            .is_some_and(|(exhausted, _)| *exhausted != pid)
+        {
+            self.clear_exhausted();
+        }
+
+        // A hookless game skips the wait, unless every source already failed for it
+        let wait = if self.exhausted.is_some() {
+            self.stall_time * (1 << self.failed_passes.min(MAX_BACKOFF_SHIFT))
+        } else if self.hookless.contains(&pid) {
+            Duration::ZERO
+        } else {
+            self.stall_time
+        };
+        if self.frame_timer.elapsed() > wait {
+            let Some(kind) = self
+                .working
+                .get(&pkg)
+                .copied()
+                .or_else(|| self.chain.first().copied())
+            else {
+                return;
+            };
+
+            debug!("no analyzer frames from [{pkg}], trying {kind:?} frame source");
+            self.frame_timer = Instant::now();
+            self.health.reset();
+            self.active = Some((pkg.clone(), self.start(kind, pid, pkg)));
+        }
+    }
+
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<FasData> {
+        let (pkg, source) = self.active.as_ref()?;
+        let data = source.recv_timeout(timeout)?;
+
+        self.frame_timer = Instant::now();
+        self.clear_exhausted();
+        let kind = source.kind();
+        if self.working.get(pkg) != Some(&kind) {
+            info!("[{pkg}] frames come from {kind:?} frame source");
+            self.working.insert(pkg.clone(), kind);
+        }
+
+        Some(data)
+    }
+
+    // Moves on to the fallback after the active one, which is forgotten as working
+    fn replace_active(&mut self) {
+        let Some((pkg, source)) = self.active.take() else {
+            return;
+        };
+        let pid = source.pid();
+        let kind = source.kind();
+        if self.working.get(&pkg) == Some(&kind) {
+            self.working.remove(&pkg);
+        }
+
+        self.frame_timer = Instant::now();
+        self.health.reset();
+        self.active = self.next(kind).map(|next| {
+            debug!("{kind:?} frame source failed for [{pkg}], trying {next:?}");
+            (pkg.clone(), self.start(next, pid, pkg.clone()))
+        });
+        if self.active.is_none() {
+            // Starts over from the top after a backoff that grows with every failed pass
+            self.failed_passes = self.failed_passes.saturating_add(1);
+            warn!(
+                "No fallback frame source works for [{pkg}], {} failed passes",
+                self.failed_passes
+            );
+            self.exhausted = Some((pid, pkg));
+        }
+    }
+
+    fn clear_exhausted(&mut self) {
+        self.exhausted = None;
+        self.failed_passes = 0;
+    }
+
+    fn start(&mut self, kind: FallbackKind, pid: i32, pkg: String) -> FallbackSource {
+        if kind == FallbackKind::Vulkan && self.layer_app.as_ref() != Some(&pkg) {
+            vulkan::enable_for(&pkg);
+            self.layer_app = Some(pkg.clone());
+        }
+        FallbackSource::start(kind, pid, pkg)
+    }
+
+    fn next(&self, kind: FallbackKind) -> Option<FallbackKind> {
+        let position = self.chain.iter().position(|k| *k == kind)?;
+        self.chain.get(position + 1).copied()
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+mod gfxinfo;
+mod health;
+mod manager;
+mod surfaceflinger;
+mod vulkan;
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+    time::Duration,
+};
+
+use log::{info, warn};
+
+use super::FasData;
+pub use manager::FrameSourceManager;
+
+// Frame sources used when the hook based analyzer delivers nothing for a game. Except for
+// the vulkan layer they poll system services, so precision is lower than the analyzer's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FallbackKind {
+    Vulkan,
+    SurfaceFlinger,
+    GfxInfo,
+}
+
+struct FallbackSource {
+    pid: i32,
+    kind: FallbackKind,
+    rx: Receiver<FasData>,
+    stop: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+}
+
+impl FallbackSource {
+    fn start(kind: FallbackKind, pid: i32, pkg: String) -> Self {
+        match kind {
+            FallbackKind::Vulkan => Self::vulkan(pid),
+            FallbackKind::SurfaceFlinger => Self::surfaceflinger(pid, pkg),
+            FallbackKind::GfxInfo => Self::gfxinfo(pid, pkg),
+        }
+    }
+
+    // Frames reported by the optional vulkan layer
+    fn vulkan(pid: i32) -> Self {
+        info!("Waiting for vulkan layer frames of pid {pid}");
+        Self::spawn(FallbackKind::Vulkan, pid, move |sx, stop| {
+            vulkan::poll(pid, &sx, &stop);
+        })
+    }
+
+    fn surfaceflinger(pid: i32, pkg: String) -> Self {
+        info!("Falling back to SurfaceFlinger latency frame source for [{pkg}]");
+        Self::spawn(FallbackKind::SurfaceFlinger, pid, move |sx, stop| {
+            surfaceflinger::poll(pid, &pkg, &sx, &stop);
+        })
+    }
+
+    // For apps rendered through HWUI
+    fn gfxinfo(pid: i32, pkg: String) -> Self {
+        info!("Falling back to gfxinfo framestats frame source for [{pkg}]");
+        Self::spawn(FallbackKind::GfxInfo, pid, move |sx, stop| {
+            gfxinfo::poll(pid, &pkg, &sx, &stop);
+        })
+    }
+
+    fn spawn<F>(kind: FallbackKind, pid: i32, f: F) -> Self
+    where
+        F: FnOnce(mpsc::Sender<FasData>, Arc<AtomicBool>) + Send + 'static,
+    {
+        let (sx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let finished = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread_finished = finished.clone();
+
+        let name = match kind {
+            FallbackKind::Vulkan => "VkLayerThread",
+            FallbackKind::SurfaceFlinger => "SfLatencyThread",
+            FallbackKind::GfxInfo => "GfxInfoThread",
+        };
+        if let Err(e) = thread::Builder::new().name(name.into()).spawn(move || {
+            f(sx, thread_stop);
+            thread_finished.store(true, Ordering::Release);
+        }) {
+            warn!("Failed to start fallback frame source: {e}");
+            finished.store(true, Ordering::Release);
+        }
+
+        Self {
+            pid,
+            kind,
+            rx,
+            stop,
+            finished,
+        }
+    }
+
+    const fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    pub const fn kind(&self) -> FallbackKind {
+        self.kind
+    }
+
+    // The source gave up, e.g. it couldn't find anything to read for the game
+    fn finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    fn recv_timeout(&self, timeout: Duration) -> Option<FasData> {
+        self.rx.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for FallbackSource {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+    }
+}
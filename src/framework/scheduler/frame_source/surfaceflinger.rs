@@ -0,0 +1,186 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
+    thread,
+    time::Duration,
+};
+
+use dumpsys_rs::Dumpsys;
+use log::debug;
+
+use super::FasData;
+
+// --latency keeps the last 128 frames, so this stays well within the window even at 144hz
+const POLL_TIME: Duration = Duration::from_millis(500);
+// Timestamp of a frame still pending present
+const PENDING: u64 = i64::MAX as u64;
+const GIVE_UP_POLLS: u32 = 10;
+
+pub fn poll(pid: i32, pkg: &str, sx: &Sender<FasData>, stop: &AtomicBool) {
+    let Some(mut dumper) = Dumpsys::new("SurfaceFlinger") else {
+        return;
+    };
+    let mut layer = None;
+    let mut last_present = None;
+    let mut last_ready = None;
+    let mut missing_polls = 0;
+
+    while !stop.load(Ordering::Acquire) {
+        thread::sleep(POLL_TIME);
+
+        if layer.is_none() {
+            layer = dumper
+                .dump(&["--list"])
+                .ok()
+                .and_then(|list| find_layer(&list, pkg));
+            debug!("SurfaceFlinger layer of {pkg}: {layer:?}");
+        }
+
+        let Some(name) = &layer else {
+            missing_polls += 1;
+            if last_present.is_none() && missing_polls >= GIVE_UP_POLLS {
+                return;
+            }
+            continue;
+        };
+        let Ok(latency) = dumper.dump(&["--latency", name]) else {
+            layer = None;
+            continue;
+        };
+
+        let frames = parse_frames(&latency);
+        if frames.is_empty() {
+            // The layer went away, e.g. the game recreated its surface
+            layer = None;
+            continue;
+        }
+
+        for (present, ready) in frames {
+            if let Some(last) = last_present
+                && present > last
+                && sx
+                    .send(FasData {
+                        pid,
+                        frametime: Duration::from_nanos(present - last),
+                        low_confidence: true,
+                        production: ready
+                            .zip(last_ready)
+                            .filter(|(ready, last_ready)| ready > last_ready)
+                            .map(|(ready, last_ready)| Duration::from_nanos(ready - last_ready)),
+                        present: Some(Duration::from_nanos(present)),
+                    })
+                    .is_err()
+            {
+                return;
+            }
+
+            if last_present.is_none_or(|last| present > last) {
+                last_present = Some(present);
+                last_ready = ready;
+            }
+        }
+    }
+}
+
+// The game renders into a SurfaceView when it has one, otherwise into its activity layer.
+// Mirrors of it, e.g. while casting, only repeat its frames at the receiver's pace
+fn find_layer(list: &str, pkg: &str) -> Option<String> {
+    let layers: Vec<_> = list
+        .lines()
+        .filter(|layer| layer.contains(pkg) && !layer.contains("Mirror"))
+        .collect();
+    layers
+        .iter()
+        .find(|layer| layer.starts_with("SurfaceView"))
+        .or_else(|| layers.first())
+        .map(|layer| layer.trim().to_string())
+}
+
+// Rows after the refresh period are "desired present, actual present, frame ready"
+fn parse_frames(latency: &str) -> Vec<(u64, Option<u64>)> {
+    latency
+        .lines()
+        .skip(1)
+        .filter_map(|row| {
+            let mut columns = row.split_whitespace().skip(1);
+            let present = columns.next()?.parse::<u64>().ok()?;
+            let ready = columns
+                .next()
+                .and_then(|ready| ready.parse::<u64>().ok())
+                .filter(|ready| *ready != 0 && *ready != PENDING);
+            Some((present, ready))
+        })
+        .filter(|(present, _)| *present != 0 && *present != PENDING)
+        .collect()
+}
+
+// Conformance corpus: raw dumps under fixtures/ and the frames expected from them, one
+// "present ready" row per frame, "-" for an unknown ready time. Add a capture for each ROM
+// whose output breaks the parser
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CASES: [(&str, &str, &str); 3] = [
+        (
+            "sf_latency",
+            include_str!("fixtures/sf_latency.txt"),
+            include_str!("fixtures/sf_latency.expected"),
+        ),
+        (
+            "sf_latency_two_columns",
+            include_str!("fixtures/sf_latency_two_columns.txt"),
+            include_str!("fixtures/sf_latency_two_columns.expected"),
+        ),
+        (
+            "sf_latency_empty",
+            include_str!("fixtures/sf_latency_empty.txt"),
+            include_str!("fixtures/sf_latency_empty.expected"),
+        ),
+    ];
+
+    fn expected(rows: &str) -> Vec<(u64, Option<u64>)> {
+        rows.lines()
+            .map(|row| {
+                let (present, ready) = row.split_once(' ').unwrap();
+                (present.parse().unwrap(), ready.parse().ok())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_the_corpus() {
+        for (name, input, rows) in CASES {
+            assert_eq!(parse_frames(input), expected(rows), "{name}");
+        }
+    }
+
+    #[test]
+    fn prefers_the_surface_view_over_mirrors() {
+        let list = include_str!("fixtures/sf_list.txt");
+        assert_eq!(
+            find_layer(list, "com.example.game").as_deref(),
+            Some("SurfaceView[com.example.game/com.example.game.MainActivity](BLAST)#214")
+        );
+        assert_eq!(find_layer(list, "com.example.other"), None);
+    }
+}
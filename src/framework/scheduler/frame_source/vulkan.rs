@@ -0,0 +1,174 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io, mem,
+    os::{
+        android::net::SocketAddrExt,
+        fd::AsRawFd,
+        unix::net::{SocketAddr, UnixDatagram},
+    },
+    path::Path,
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use super::FasData;
+use crate::props;
+
+// Android's loader only takes debug layers from the app itself or, on debuggable builds, from
+// this directory. customize.sh installs the layer here
+pub const LAYER_PATH: &str = "/data/local/debug/vulkan/libVkLayer_fas_rs.so";
+const LAYER_NAME: &str = "VK_LAYER_FAS_RS_frame_report";
+const LAYER_SETTINGS: [&str; 3] = [
+    "enable_gpu_debug_layers",
+    "gpu_debug_app",
+    "gpu_debug_layers",
+];
+
+// Must match the layer side in vulkan_layer/src/lib.rs
+const SOCKET_NAME: &[u8] = b"fas_rs_vk";
+const RECV_TIMEOUT: Duration = Duration::from_millis(500);
+const GIVE_UP_TIME: Duration = Duration::from_secs(5);
+
+// Non debuggable games never load it from LAYER_PATH without ro.debuggable
+pub fn available() -> bool {
+    Path::new(LAYER_PATH).exists() && props::get("ro.debuggable") == "1"
+}
+
+// The loader reads these when the app process starts, so the layer loads from the game's next
+// launch on. Only one app at a time can have debug layers
+pub fn enable_for(pkg: &str) {
+    info!("Vulkan layer enabled for [{pkg}], effective from its next launch");
+    for (key, value) in LAYER_SETTINGS.into_iter().zip(["1", pkg, LAYER_NAME]) {
+        put_global(&["put", "global", key, value]);
+    }
+}
+
+pub fn disable() {
+    for key in LAYER_SETTINGS {
+        put_global(&["delete", "global", key]);
+    }
+}
+
+fn put_global(args: &[&str]) {
+    if let Err(e) = Command::new("settings").args(args).status() {
+        warn!("Failed to run settings {}: {e}", args.join(" "));
+    }
+}
+
+// Messages from the layer are the sender pid (u32) followed by the frametime in ns (u64),
+// both little endian. Any app can send to the abstract socket, so the pid in the payload isn't
+// trusted, the kernel's SCM_CREDENTIALS tell who actually sent it
+pub fn poll(pid: i32, sx: &Sender<FasData>, stop: &AtomicBool) {
+    let socket = match SocketAddr::from_abstract_name(SOCKET_NAME)
+        .and_then(|address| UnixDatagram::bind_addr(&address))
+    {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Failed to bind vulkan layer socket: {e}");
+            return;
+        }
+    };
+    let _ = socket.set_read_timeout(Some(RECV_TIMEOUT));
+    let enable: libc::c_int = 1;
+    if unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PASSCRED,
+            (&raw const enable).cast(),
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    } != 0
+    {
+        warn!(
+            "Failed to enable credentials on the vulkan layer socket: {}",
+            io::Error::last_os_error()
+        );
+        return;
+    }
+
+    let start = Instant::now();
+    let mut received = false;
+    let mut message = [0; 12];
+
+    while !stop.load(Ordering::Acquire) {
+        let Some((12, sender)) = recv_from_pid(&socket, &mut message) else {
+            // The game doesn't load the layer
+            if !received && start.elapsed() > GIVE_UP_TIME {
+                return;
+            }
+            continue;
+        };
+
+        let frametime = u64::from_le_bytes(message[4..].try_into().unwrap());
+        if sender != pid {
+            continue;
+        }
+
+        received = true;
+        if sx
+            .send(FasData {
+                pid,
+                frametime: Duration::from_nanos(frametime),
+                low_confidence: false,
+                production: None,
+                present: None,
+            })
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+// The length received and the pid of the sending process
+fn recv_from_pid(socket: &UnixDatagram, buffer: &mut [u8]) -> Option<(usize, i32)> {
+    let mut iov = libc::iovec {
+        iov_base: buffer.as_mut_ptr().cast(),
+        iov_len: buffer.len(),
+    };
+    // u64s keep the control buffer aligned for cmsghdr
+    let mut control = [0u64; 8];
+    let mut header: libc::msghdr = unsafe { mem::zeroed() };
+    header.msg_iov = &raw mut iov;
+    header.msg_iovlen = 1;
+    header.msg_control = control.as_mut_ptr().cast();
+    header.msg_controllen = mem::size_of_val(&control) as _;
+
+    let len = unsafe { libc::recvmsg(socket.as_raw_fd(), &raw mut header, 0) };
+    let len = usize::try_from(len).ok()?;
+
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&raw const header) };
+    while !cmsg.is_null() {
+        let (level, kind) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+        if level == libc::SOL_SOCKET && kind == libc::SCM_CREDENTIALS {
+            let credentials =
+                unsafe { libc::CMSG_DATA(cmsg).cast::<libc::ucred>().read_unaligned() };
+            return Some((len, credentials.pid));
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(&raw const header, cmsg) };
+    }
+    None
+}
@@ -0,0 +1,181 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use libc::pid_t;
+use log::{debug, info, warn};
+
+use crate::{file_handler, framework::CAPABILITIES, snapshot};
+
+const ROOTS: [&str; 2] = ["/dev/cpuctl", "/dev/cpuset"];
+const GROUP: &str = "fas-rs";
+const ORIGIN: &str = "top-app";
+const SCAN_TIME: Duration = Duration::from_secs(2);
+// Copied from top-app so the game keeps the platform's settings apart from ours
+const INHERITED: [&str; 4] = [
+    "cpu.uclamp.max",
+    "cpu.uclamp.latency_sensitive",
+    "cpu.shares",
+    "mems",
+];
+
+// Moves the game out of top-app into a cgroup of its own for the session, so uclamp and
+// cpuset tweaks only touch the game and the platform's top-app settings stay untouched
+pub struct GameCgroup {
+    pid: pid_t,
+    groups: Vec<PathBuf>,
+    timer: Instant,
+}
+
+impl GameCgroup {
+    pub fn new() -> Self {
+        let mut game_cgroup = Self {
+            pid: 0,
+            groups: Vec::new(),
+            timer: Instant::now(),
+        };
+        // Left behind by a crash
+        game_cgroup.restore();
+        game_cgroup
+    }
+
+    // Rechecks now and then, threads spawned later land in top-app first
+    pub fn update(&mut self, pid: pid_t, uclamp_min: u32, cpus: &[usize]) {
+        if pid != self.pid {
+            self.restore();
+            self.setup(uclamp_min, cpus);
+            if self.groups.is_empty() {
+                return;
+            }
+            self.pid = pid;
+            info!("Game moved into the {GROUP} cgroup");
+        } else if self.timer.elapsed() < SCAN_TIME {
+            return;
+        }
+        self.timer = Instant::now();
+
+        let Ok(tasks) = fs::read_dir(format!("/proc/{pid}/task")) else {
+            return;
+        };
+        let tids: Vec<_> = tasks
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<pid_t>().ok())
+            .collect();
+
+        for group in &self.groups {
+            let origin = group.with_file_name(ORIGIN);
+            // Threads the platform already moved elsewhere (e.g. background) stay there
+            let in_origin = read_tasks(&origin);
+            let moved: Vec<_> = tids
+                .iter()
+                .copied()
+                .filter(|tid| in_origin.contains(tid))
+                .collect();
+            snapshot::record_tasks(&origin, &moved);
+            for tid in &moved {
+                if let Err(e) = file_handler::write_direct(group.join("tasks"), tid.to_string()) {
+                    debug!("Failed to move tid {tid} into {}: {e}", group.display());
+                }
+            }
+        }
+    }
+
+    // Recorded threads go back where they came from, the ones they spawned meanwhile to
+    // top-app
+    pub fn restore(&mut self) {
+        snapshot::restore_tasks();
+        for root in ROOTS {
+            let group = Path::new(root).join(GROUP);
+            if !group.exists() {
+                continue;
+            }
+
+            let origin = group.with_file_name(ORIGIN);
+            for tid in read_tasks(&group) {
+                let _ = file_handler::write_direct(origin.join("tasks"), tid.to_string());
+            }
+            if let Err(e) = fs::remove_dir(&group) {
+                warn!("Failed to remove {}: {e}", group.display());
+            }
+        }
+
+        self.groups.clear();
+        if self.pid != 0 {
+            self.pid = 0;
+            info!("Game moved back into the {ORIGIN} cgroup");
+        }
+    }
+
+    fn setup(&mut self, uclamp_min: u32, cpus: &[usize]) {
+        if file_handler::dry_run() {
+            debug!("dry run: game cgroup not set up");
+            return;
+        }
+
+        for root in ROOTS {
+            let origin = Path::new(root).join(ORIGIN);
+            let group = Path::new(root).join(GROUP);
+            if !origin.exists() || (fs::create_dir(&group).is_err() && !group.exists()) {
+                continue;
+            }
+
+            for node in INHERITED {
+                if let Ok(value) = fs::read_to_string(origin.join(node)) {
+                    let _ = file_handler::write_direct(group.join(node), value.trim());
+                }
+            }
+
+            if group.join("cpus").exists() {
+                let value = if cpus.is_empty() {
+                    fs::read_to_string(origin.join("cpus")).unwrap_or_default()
+                } else {
+                    cpus.iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                };
+                if let Err(e) = file_handler::write_direct(group.join("cpus"), value.trim()) {
+                    warn!("Failed to set cpus of {}: {e}", group.display());
+                }
+            }
+
+            if CAPABILITIES.uclamp && group.join("cpu.uclamp.min").exists() {
+                let value = if uclamp_min == 0 {
+                    fs::read_to_string(origin.join("cpu.uclamp.min")).unwrap_or_default()
+                } else {
+                    uclamp_min.min(100).to_string()
+                };
+                let _ = file_handler::write_direct(group.join("cpu.uclamp.min"), value.trim());
+            }
+
+            self.groups.push(group);
+        }
+    }
+}
+
+fn read_tasks(group: &Path) -> HashSet<pid_t> {
+    fs::read_to_string(group.join("tasks"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|tid| tid.trim().parse().ok())
+        .collect()
+}
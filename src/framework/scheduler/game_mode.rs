@@ -0,0 +1,97 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{process::Command, sync::Arc, thread, time::Duration};
+
+use log::info;
+use parking_lot::Mutex;
+
+use crate::framework::node::Mode;
+
+const POLL_TIME: Duration = Duration::from_secs(3);
+
+#[derive(Default)]
+struct Shared {
+    pkg: Option<String>,
+    mode: Option<Mode>,
+}
+
+// Follows the platform game mode (Android 12+) the user picks for the foreground game in the
+// system game panel, polled on its own thread as `cmd game` takes a while
+pub struct GameModeWatcher {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl GameModeWatcher {
+    pub fn new() -> Self {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        {
+            let shared = shared.clone();
+            let _ = thread::Builder::new()
+                .name("GameModeThread".into())
+                .spawn(move || poll(&shared));
+        }
+
+        Self { shared }
+    }
+
+    // None stops polling
+    pub fn set_package(&self, pkg: Option<&str>) {
+        let mut shared = self.shared.lock();
+        if shared.pkg.as_deref() != pkg {
+            shared.pkg = pkg.map(ToString::to_string);
+            shared.mode = None;
+        }
+    }
+
+    // The fas mode the platform game mode maps to, None for standard and custom
+    pub fn mode(&self) -> Option<Mode> {
+        self.shared.lock().mode
+    }
+}
+
+fn poll(shared: &Mutex<Shared>) {
+    loop {
+        thread::sleep(POLL_TIME);
+        let Some(pkg) = shared.lock().pkg.clone() else {
+            continue;
+        };
+
+        let mode = Command::new("cmd")
+            .args(["game", "list-modes", &pkg])
+            .output()
+            .ok()
+            .and_then(|output| parse_game_mode(&String::from_utf8_lossy(&output.stdout)));
+
+        let mut shared = shared.lock();
+        if shared.pkg.as_deref() == Some(pkg.as_str()) && shared.mode != mode {
+            info!("Platform game mode of {pkg} maps to {mode:?}");
+            shared.mode = mode;
+        }
+    }
+}
+
+// e.g. "com.example.game current mode: performance, available game modes: [...]"
+fn parse_game_mode(output: &str) -> Option<Mode> {
+    let mode = output.split("current mode:").nth(1)?;
+    match mode.split([',', '\n']).next()?.trim() {
+        "performance" => Some(Mode::Performance),
+        "battery" => Some(Mode::Powersave),
+        _ => None,
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::mpsc::Receiver;
+
+use log::{info, warn};
+
+use crate::props;
+
+// Only debug props, which apps read at runtime and nothing in the system relies on
+const ALLOWED_PREFIX: &str = "debug.";
+
+struct Applied {
+    name: String,
+    original: String,
+    changes: Receiver<String>,
+}
+
+// Per game props like debug.hwui.*, set while the game is engaged. A prop that something
+// else changed meanwhile keeps the new value instead of being restored
+pub struct GameProps {
+    applied: Vec<Applied>,
+}
+
+impl GameProps {
+    pub const fn new() -> Self {
+        Self {
+            applied: Vec::new(),
+        }
+    }
+
+    pub fn apply(&mut self, game_props: &[(String, String)]) {
+        self.restore();
+
+        for (name, value) in game_props {
+            if !name.starts_with(ALLOWED_PREFIX) {
+                warn!("Game prop {name} ignored, only {ALLOWED_PREFIX}* props may be set");
+                continue;
+            }
+
+            let original = props::get(name);
+            if !props::set(name, value) {
+                continue;
+            }
+            info!("Game prop: {name}={value} (was {original:?})");
+            self.applied.push(Applied {
+                name: name.clone(),
+                original,
+                changes: props::subscribe(name),
+            });
+        }
+    }
+
+    pub fn restore(&mut self) {
+        for applied in self.applied.drain(..) {
+            if applied.changes.try_iter().last().is_some() {
+                info!("Game prop {} changed by someone else, kept", applied.name);
+                continue;
+            }
+            props::set(&applied.name, &applied.original);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    process::Command,
+    sync::{
+        LazyLock,
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+use log::{info, warn};
+
+// Hooks run in call order on one thread, so a stop script can't overtake a slow start script
+static RUNNER: LazyLock<Option<Sender<[String; 3]>>> = LazyLock::new(|| {
+    let (sx, rx) = mpsc::channel::<[String; 3]>();
+    thread::Builder::new()
+        .name("HookThread".into())
+        .spawn(move || {
+            for args in rx {
+                run(&args);
+            }
+        })
+        .ok()?;
+    Some(sx)
+});
+
+pub fn run_hook(script: String, pkg: String, target_fps: Option<f64>) {
+    if script.is_empty() {
+        return;
+    }
+
+    let target_fps = target_fps.map(|fps| fps.to_string()).unwrap_or_default();
+    let args = [script, pkg, target_fps];
+    let Some(sx) = RUNNER.as_ref() else {
+        run(&args);
+        return;
+    };
+
+    if let Err(mpsc::SendError(args)) = sx.send(args) {
+        run(&args);
+    }
+}
+
+fn run(args: &[String; 3]) {
+    let [script, pkg, target_fps] = args;
+    info!("Running hook: {script} {pkg} {target_fps}");
+    match Command::new("sh").args(args).status() {
+        Ok(status) if !status.success() => warn!("Hook {script} exited with {status}"),
+        Err(e) => warn!("Failed to run hook {script}: {e}"),
+        Ok(_) => (),
+    }
+}
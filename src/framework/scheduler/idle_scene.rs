@@ -0,0 +1,136 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+use crate::cpu_common::{last_touch, watch_touch};
+
+// Frames the variance is measured over
+const WINDOW: usize = 60;
+// Frame-to-frame variation (stddev / mean) below this counts as a static scene
+const STATIC_VARIATION: f64 = 0.03;
+// No touches for this long, and a static scene for ENTER_TIME, to enter
+const INPUT_IDLE_TIME: Duration = Duration::from_secs(10);
+const ENTER_TIME: Duration = Duration::from_secs(5);
+// A steady pace alone is also what a well running game looks like, the scene must also run
+// this far below the target fps, as pause menus commonly drop their cap, or barely load
+// the gpu, as a frame that doesn't change is cheap to render again
+const LOW_FPS_RATIO: f64 = 0.75;
+const STATIC_GPU_BUSY: f64 = 0.15;
+// A frame this far off the window mean means the scene moved again
+const EXIT_DEVIATION: f64 = 0.15;
+// Fraction of each policy's max freq the cpu is clamped to while idle
+pub const IDLE_FREQ_CAP: f64 = 0.5;
+// Frequency control runs at most this often while idle
+pub const IDLE_POLL_TIME: Duration = Duration::from_millis(250);
+
+// Detects pause menus and AFK scenes, where the game keeps rendering the same frame at a
+// steady, low pace and nobody touches the screen, so nothing needs the performance
+pub struct IdleSceneDetector {
+    static_timer: Option<Instant>,
+    idle_since: Option<Instant>,
+    poll_timer: Instant,
+}
+
+impl IdleSceneDetector {
+    pub fn new() -> Self {
+        Self {
+            static_timer: None,
+            idle_since: None,
+            poll_timer: Instant::now(),
+        }
+    }
+
+    // frametimes are newest first, gpu_busy is None when the gpu doesn't report it
+    pub fn update(
+        &mut self,
+        frametimes: &VecDeque<Duration>,
+        target_fps: Option<f64>,
+        gpu_busy: Option<f64>,
+    ) -> bool {
+        watch_touch();
+        let Some((mean, variation)) = variation(frametimes) else {
+            self.reset();
+            return false;
+        };
+
+        if let Some(idle_since) = self.idle_since {
+            let touched = last_touch().is_some_and(|touch| touch > idle_since);
+            let moved = frametimes.front().is_some_and(|frametime| {
+                (frametime.as_secs_f64() - mean).abs() > mean * EXIT_DEVIATION
+            });
+            if touched || moved {
+                info!("Idle scene left, resuming frequency control");
+                self.reset();
+            }
+        } else if variation < STATIC_VARIATION
+            && last_touch().is_none_or(|touch| touch.elapsed() >= INPUT_IDLE_TIME)
+            && (target_fps.is_some_and(|target_fps| 1.0 / mean < target_fps * LOW_FPS_RATIO)
+                || gpu_busy.is_some_and(|busy| busy < STATIC_GPU_BUSY))
+        {
+            let timer = self.static_timer.get_or_insert_with(Instant::now);
+            if timer.elapsed() >= ENTER_TIME {
+                info!("Idle scene detected, variation: {variation:.3}, entering deep powersave");
+                self.idle_since = Some(Instant::now());
+                self.static_timer = None;
+            }
+        } else {
+            self.static_timer = None;
+        }
+
+        self.idle_since.is_some()
+    }
+
+    // Whether frequency control is due, only matters while idle
+    pub fn poll(&mut self) -> bool {
+        if self.idle_since.is_none() || self.poll_timer.elapsed() >= IDLE_POLL_TIME {
+            self.poll_timer = Instant::now();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.static_timer = None;
+        self.idle_since = None;
+    }
+}
+
+// (mean in seconds, stddev / mean) of the latest WINDOW frames
+fn variation(frametimes: &VecDeque<Duration>) -> Option<(f64, f64)> {
+    if frametimes.len() < WINDOW {
+        return None;
+    }
+
+    let window = frametimes.iter().take(WINDOW).map(Duration::as_secs_f64);
+    let mean = window.clone().sum::<f64>() / WINDOW as f64;
+    if mean <= 0.0 {
+        return None;
+    }
+    let variance = window
+        .map(|frametime| (frametime - mean).powi(2))
+        .sum::<f64>()
+        / WINDOW as f64;
+
+    Some((mean, variance.sqrt() / mean))
+}
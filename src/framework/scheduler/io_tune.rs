@@ -0,0 +1,116 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, info};
+
+use crate::{file_handler, snapshot};
+
+const BLOCK_DIR: &str = "/sys/block";
+const SCHEDULER: &str = "none";
+const READ_AHEAD_KB: &str = "128";
+
+// Switches UFS queues to latency friendly settings for a game session, the original
+// values are saved and written back when the session ends
+pub struct IoTuner {
+    saved: Vec<(PathBuf, String)>,
+}
+
+impl IoTuner {
+    pub const fn new() -> Self {
+        Self { saved: Vec::new() }
+    }
+
+    pub fn apply(&mut self) {
+        if !self.saved.is_empty() {
+            return;
+        }
+
+        let Ok(entries) = fs::read_dir(BLOCK_DIR) else {
+            return;
+        };
+
+        for queue in entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("sd"))
+            .map(|entry| entry.path().join("queue"))
+        {
+            self.tune_scheduler(&queue);
+            self.tune(queue.join("read_ahead_kb"), READ_AHEAD_KB);
+        }
+
+        info!("IO tuned for game session");
+    }
+
+    pub fn restore(&mut self) {
+        if self.saved.is_empty() {
+            return;
+        }
+
+        for (path, value) in self.saved.drain(..) {
+            let _ = file_handler::write_direct(&path, value);
+        }
+
+        info!("IO settings restored");
+    }
+
+    // Scheduler nodes read like "[mq-deadline] kyber none"
+    fn tune_scheduler(&mut self, queue: &Path) {
+        let path = queue.join("scheduler");
+        let Ok(schedulers) = fs::read_to_string(&path) else {
+            return;
+        };
+
+        let available = schedulers
+            .split_whitespace()
+            .any(|scheduler| scheduler.trim_matches(['[', ']']) == SCHEDULER);
+        let current = schedulers
+            .split_whitespace()
+            .find_map(|scheduler| scheduler.strip_prefix('[')?.strip_suffix(']'));
+
+        if let Some(current) = current
+            && available
+            && current != SCHEDULER
+        {
+            let current = current.to_string();
+            self.write(path, SCHEDULER, current);
+        }
+    }
+
+    fn tune(&mut self, path: PathBuf, value: &str) {
+        let Ok(current) = fs::read_to_string(&path) else {
+            return;
+        };
+
+        let current = current.trim().to_string();
+        if current != value {
+            self.write(path, value, current);
+        }
+    }
+
+    fn write(&mut self, path: PathBuf, value: &str, original: String) {
+        snapshot::record(&path);
+        if file_handler::write_direct(&path, value).is_ok() {
+            debug!("{}: {original} -> {value}", path.display());
+            self.saved.push((path, original));
+        }
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs, io, time::Instant};
+
+use libc::pid_t;
+use log::{debug, info};
+
+const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+const IOPRIO_CLASS_SHIFT: i32 = 13;
+const IOPRIO_CLASS_BE: i32 = 2;
+// Highest best-effort level, ahead of everything but real-time io
+const LAUNCH_IOPRIO: i32 = IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT;
+
+// A game between its process start and its first frame, its threads get raised io priority
+// while it loads, the cpu side is held at max by the looper
+pub struct LaunchBoost {
+    launch: Option<Launch>,
+}
+
+struct Launch {
+    pid: pid_t,
+    pkg: String,
+    since: Instant,
+    // (tid, original ioprio)
+    saved: Vec<(pid_t, i32)>,
+}
+
+impl LaunchBoost {
+    pub const fn new() -> Self {
+        Self { launch: None }
+    }
+
+    pub fn pid(&self) -> Option<pid_t> {
+        self.launch.as_ref().map(|launch| launch.pid)
+    }
+
+    pub fn start(&mut self, pid: pid_t, pkg: String) {
+        self.stop();
+        info!("[{pkg}] launching, boosting until its first frame");
+        self.launch = Some(Launch {
+            pid,
+            pkg,
+            since: Instant::now(),
+            saved: Vec::new(),
+        });
+        self.update();
+    }
+
+    // Loading threads keep starting during the launch
+    pub fn update(&mut self) {
+        let Some(launch) = &mut self.launch else {
+            return;
+        };
+        let Ok(entries) = fs::read_dir(format!("/proc/{}/task", launch.pid)) else {
+            return;
+        };
+
+        for tid in entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<pid_t>().ok())
+        {
+            if launch.saved.iter().any(|(saved, _)| *saved == tid) {
+                continue;
+            }
+
+            let original = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, tid) };
+            if original < 0 {
+                continue;
+            }
+            if unsafe {
+                libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, tid, LAUNCH_IOPRIO)
+            } == 0
+            {
+                launch.saved.push((tid, original as i32));
+            } else {
+                debug!(
+                    "Failed to raise io priority of tid {tid}: {}",
+                    io::Error::last_os_error()
+                );
+            }
+        }
+    }
+
+    // The first frame arrived, the game left or the launch ran too long
+    pub fn stop(&mut self) {
+        let Some(launch) = self.launch.take() else {
+            return;
+        };
+
+        for (tid, ioprio) in launch.saved {
+            unsafe {
+                libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, tid, ioprio);
+            }
+        }
+        info!(
+            "[{}] launch boost over after {:?}",
+            launch.pkg,
+            launch.since.elapsed()
+        );
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use libc::pid_t;
+use log::info;
+
+const SAMPLE_TIME: Duration = Duration::from_millis(500);
+// Fps has to stay this low with heavy reads for ENTER_TIME to count as a loading screen
+const LOADING_FPS_RATIO: f64 = 0.5;
+const LOADING_READ_RATE: f64 = 8.0 * 1024.0 * 1024.0; // bytes/s
+const ENTER_TIME: Duration = Duration::from_secs(2);
+// And back above this for LEAVE_TIME to count as the scene being stable again
+const STABLE_FPS_RATIO: f64 = 0.85;
+const LEAVE_TIME: Duration = Duration::from_secs(1);
+
+// Detects loading screens and scene changes, where the game is waiting on asset reads
+// rather than the cpu, so raising frequency only burns power.
+pub struct LoadingDetector {
+    pid: pid_t,
+    read_bytes: Option<u64>,
+    sample_timer: Instant,
+    read_rate: f64,
+    low_timer: Option<Instant>,
+    stable_timer: Option<Instant>,
+    loading: bool,
+}
+
+impl LoadingDetector {
+    pub fn new() -> Self {
+        Self {
+            pid: 0,
+            read_bytes: None,
+            sample_timer: Instant::now(),
+            read_rate: 0.0,
+            low_timer: None,
+            stable_timer: None,
+            loading: false,
+        }
+    }
+
+    pub fn update(&mut self, pid: pid_t, fps: f64, target_fps: f64) -> bool {
+        if pid != self.pid {
+            self.reset();
+            self.pid = pid;
+        }
+        self.sample_reads();
+
+        if self.loading {
+            if fps >= target_fps * STABLE_FPS_RATIO {
+                let timer = self.stable_timer.get_or_insert_with(Instant::now);
+                if timer.elapsed() >= LEAVE_TIME {
+                    info!("Scene is stable, resuming frequency control");
+                    self.loading = false;
+                    self.stable_timer = None;
+                }
+            } else {
+                self.stable_timer = None;
+            }
+        } else if fps < target_fps * LOADING_FPS_RATIO && self.read_rate >= LOADING_READ_RATE {
+            let timer = self.low_timer.get_or_insert_with(Instant::now);
+            if timer.elapsed() >= ENTER_TIME {
+                info!(
+                    "Loading screen detected, reading {:.1} MiB/s, pausing frequency boost",
+                    self.read_rate / 1024.0 / 1024.0
+                );
+                self.loading = true;
+                self.low_timer = None;
+            }
+        } else {
+            self.low_timer = None;
+        }
+
+        self.loading
+    }
+
+    pub fn reset(&mut self) {
+        self.pid = 0;
+        self.read_bytes = None;
+        self.read_rate = 0.0;
+        self.low_timer = None;
+        self.stable_timer = None;
+        self.loading = false;
+    }
+
+    fn sample_reads(&mut self) {
+        let elapsed = self.sample_timer.elapsed();
+        if self.read_bytes.is_some() && elapsed < SAMPLE_TIME {
+            return;
+        }
+        self.sample_timer = Instant::now();
+
+        let Some(read_bytes) = read_bytes(self.pid) else {
+            self.read_rate = 0.0;
+            return;
+        };
+
+        if let Some(last) = self.read_bytes.replace(read_bytes) {
+            self.read_rate = read_bytes.saturating_sub(last) as f64 / elapsed.as_secs_f64();
+        }
+    }
+}
+
+fn read_bytes(pid: pid_t) -> Option<u64> {
+    let io = fs::read_to_string(format!("/proc/{pid}/io")).ok()?;
+    io.lines()
+        .find_map(|line| line.strip_prefix("read_bytes:"))
+        .and_then(|value| value.trim().parse().ok())
+}
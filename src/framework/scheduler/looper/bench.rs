@@ -0,0 +1,287 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, process,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+use super::{ControllerState, buffer::Buffer, policy::controll::calculate_control};
+#[cfg(feature = "extension")]
+use crate::framework::Extension;
+use crate::{
+    Controller,
+    framework::{
+        config::{Config, TargetFps},
+        error::{Error, Result},
+        node::Mode,
+        scheduler::shutdown,
+    },
+};
+
+const POLL_TIME: Duration = Duration::from_millis(1);
+// A write not reflected by scaling_cur_freq within this long counts as not taking effect
+const APPLY_TIMEOUT: Duration = Duration::from_millis(500);
+// How far scaling_cur_freq may be from the written freq, governors round to the table
+const APPLY_TOLERANCE: f64 = 0.05;
+// Every this many frames spikes to SPIKE_MULTIPLE times the target frametime
+const SPIKE_INTERVAL: u64 = 30;
+const SPIKE_MULTIPLE: u32 = 3;
+// Ramping goes from RAMP_LOW to RAMP_HIGH times the target frametime and back over the run
+const RAMP_LOW: f64 = 0.7;
+const RAMP_HIGH: f64 = 1.5;
+
+#[derive(Debug, Clone, Copy)]
+pub enum BenchPattern {
+    Steady,
+    Spiky,
+    Ramping,
+}
+
+impl FromStr for BenchPattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "steady" => Ok(Self::Steady),
+            "spiky" => Ok(Self::Spiky),
+            "ramping" => Ok(Self::Ramping),
+            _ => Err(Error::Other(
+                "Unknown bench pattern, expected steady, spiky or ramping",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BenchPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Steady => write!(f, "steady"),
+            Self::Spiky => write!(f, "spiky"),
+            Self::Ramping => write!(f, "ramping"),
+        }
+    }
+}
+
+impl BenchPattern {
+    // progress is how far into the run this frame is, 0.0-1.0
+    fn frametime(self, frame: u64, progress: f64, target: Duration) -> Duration {
+        match self {
+            Self::Steady => target,
+            Self::Spiky => {
+                if frame % SPIKE_INTERVAL == SPIKE_INTERVAL - 1 {
+                    target * SPIKE_MULTIPLE
+                } else {
+                    target
+                }
+            }
+            Self::Ramping => {
+                let phase = 1.0 - 2.0f64.mul_add(progress, -1.0).abs();
+                target.mul_f64((RAMP_HIGH - RAMP_LOW).mul_add(phase, RAMP_LOW))
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct PolicyStats {
+    changes: usize,
+    missed: usize,
+    latencies: Vec<Duration>,
+}
+
+impl fmt::Display for PolicyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} changes, {} applied, {} not applied within {}ms",
+            self.changes,
+            self.latencies.len(),
+            self.missed,
+            APPLY_TIMEOUT.as_millis()
+        )?;
+
+        if self.latencies.is_empty() {
+            return Ok(());
+        }
+
+        let mut latencies = self.latencies.clone();
+        latencies.sort_unstable();
+        let percentile = |p: usize| latencies[(latencies.len() * p).div_ceil(100) - 1];
+        let avg = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+        write!(
+            f,
+            ", latency avg {:.1}ms p50 {:.1}ms p90 {:.1}ms max {:.1}ms",
+            avg.as_secs_f64() * 1000.0,
+            percentile(50).as_secs_f64() * 1000.0,
+            percentile(90).as_secs_f64() * 1000.0,
+            latencies[latencies.len() - 1].as_secs_f64() * 1000.0
+        )
+    }
+}
+
+// Times how long each frequency write takes to show up in scaling_cur_freq
+#[derive(Default)]
+struct LatencyTracker {
+    written: HashMap<i32, isize>,
+    pending: HashMap<i32, (isize, Instant)>,
+    stats: BTreeMap<i32, PolicyStats>,
+}
+
+impl LatencyTracker {
+    fn written(&mut self, controller: &Controller) {
+        for (policy, freq, _) in controller.freq_readback() {
+            if self.written.insert(policy, freq) == Some(freq) {
+                continue;
+            }
+
+            self.stats.entry(policy).or_default().changes += 1;
+            self.pending.insert(policy, (freq, Instant::now()));
+        }
+        self.poll(controller);
+    }
+
+    fn poll(&mut self, controller: &Controller) {
+        for (policy, _, cur_freq) in controller.freq_readback() {
+            let Some((freq, since)) = self.pending.get(&policy).copied() else {
+                continue;
+            };
+            let stats = self.stats.entry(policy).or_default();
+
+            if (cur_freq - freq).abs() as f64 <= freq as f64 * APPLY_TOLERANCE {
+                stats.latencies.push(since.elapsed());
+                self.pending.remove(&policy);
+            } else if since.elapsed() > APPLY_TIMEOUT {
+                stats.missed += 1;
+                self.pending.remove(&policy);
+            }
+        }
+    }
+}
+
+pub struct BenchReport {
+    pattern: BenchPattern,
+    target_fps: f64,
+    frames: u64,
+    elapsed: Duration,
+    controls: Vec<isize>,
+    stats: BTreeMap<i32, PolicyStats>,
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "pattern {}, target {}fps, {} frames in {:.1}s",
+            self.pattern,
+            self.target_fps,
+            self.frames,
+            self.elapsed.as_secs_f64()
+        )?;
+
+        if let (Some(min), Some(max)) = (self.controls.iter().min(), self.controls.iter().max()) {
+            writeln!(
+                f,
+                "control: {} decisions, {min}khz to {max}khz",
+                self.controls.len()
+            )?;
+        }
+
+        for (policy, stats) in &self.stats {
+            writeln!(f, "policy{policy}: {stats}")?;
+        }
+
+        Ok(())
+    }
+}
+
+// Feeds a synthetic frametime pattern through the same control and actuation path a game
+// would go through, so frequency writes and their latency can be checked without one
+pub fn run(
+    config: &mut Config,
+    controller: Controller,
+    #[cfg(feature = "extension")] extension: &Extension,
+    pattern: BenchPattern,
+    duration: Duration,
+    target_fps: f64,
+) -> BenchReport {
+    info!("Benchmarking with the {pattern} pattern at {target_fps}fps for {duration:?}");
+
+    let pid = process::id() as i32;
+    let mut controller_state = ControllerState::new(controller);
+    controller_state.apply_config(config);
+    #[cfg(feature = "extension")]
+    controller_state.controller.init_game(pid, extension);
+    #[cfg(not(feature = "extension"))]
+    controller_state.controller.init_game(pid);
+
+    let mut buffer = Buffer::new(TargetFps::Value(target_fps), pid, "bench".into());
+    let target = Duration::from_secs_f64(1.0 / target_fps);
+    let mut tracker = LatencyTracker::default();
+    let mut controls = Vec::new();
+    let start = Instant::now();
+    let mut frames = 0;
+
+    while start.elapsed() < duration && !shutdown::requested() {
+        let progress = start.elapsed().as_secs_f64() / duration.as_secs_f64();
+        let frametime = pattern.frametime(frames, progress, target);
+
+        // The frame "renders" while earlier writes are checked for taking effect
+        let frame_start = Instant::now();
+        while frame_start.elapsed() < frametime {
+            tracker.poll(&controller_state.controller);
+            thread::sleep(POLL_TIME);
+        }
+
+        #[cfg(feature = "extension")]
+        buffer.push_frametime(frametime, extension);
+        #[cfg(not(feature = "extension"))]
+        buffer.push_frametime(frametime);
+        frames += 1;
+
+        controller_state.apply_config(config);
+        if let Some((control, is_janked)) =
+            calculate_control(&buffer, config, Mode::Balance, &mut controller_state, 0.0)
+        {
+            controller_state
+                .controller
+                .report_frame(frametime, target_fps);
+            controller_state
+                .controller
+                .fas_update_freq(control, is_janked);
+            tracker.written(&controller_state.controller);
+            controls.push(control);
+        }
+    }
+
+    controller_state.controller.shutdown();
+    controller_state.controller.close_files();
+
+    BenchReport {
+        pattern,
+        target_fps,
+        frames,
+        elapsed: start.elapsed(),
+        controls,
+        stats: tracker.stats,
+    }
+}
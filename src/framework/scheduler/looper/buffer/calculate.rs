@@ -15,16 +15,19 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use likely_stable::unlikely;
 use log::debug;
 
 use super::Buffer;
-use crate::framework::config::TargetFps;
+use crate::framework::config::{FrametimeTarget, TargetFps};
 #[cfg(feature = "extension")]
 use crate::{Extension, api::trigger_target_fps_change};
 
+// How long the fps has to sit at a lower target before switching to it
+const TARGET_DOWN_TIME: Duration = Duration::from_secs(3);
+
 impl Buffer {
     pub fn calculate_current_fps(&mut self) {
         let avg_time_long = self.calculate_average_frametime(None);
@@ -49,6 +52,30 @@ impl Buffer {
         self.frametime_state.current_fps_short = current_fps_short;
     }
 
+    // The frametime the controller aims at the target, out of the latest `window` frames
+    pub fn target_frametime(&self, target: FrametimeTarget, window: usize) -> Duration {
+        let mut frametimes: Vec<_> = self
+            .frametime_state
+            .frametimes
+            .iter()
+            .take(window.max(1))
+            .copied()
+            .collect();
+        if frametimes.is_empty() {
+            return self.frametime_state.filtered_frametime;
+        }
+
+        match target {
+            FrametimeTarget::Last => self.frametime_state.filtered_frametime,
+            FrametimeTarget::Mean => frametimes.iter().sum::<Duration>() / frametimes.len() as u32,
+            FrametimeTarget::P95 => {
+                frametimes.sort_unstable();
+                frametimes[(frametimes.len() - 1) * 95 / 100]
+            }
+            FrametimeTarget::Worst => frametimes.iter().max().copied().unwrap_or_default(),
+        }
+    }
+
     fn calculate_average_frametime(&self, it_takes: Option<usize>) -> Duration {
         let total_time: Duration = self
             .frametime_state
@@ -70,7 +97,7 @@ impl Buffer {
     }
     #[cfg(feature = "extension")]
     pub fn calculate_target_fps(&mut self, extension: &Extension) {
-        let new_target_fps = self.target_fps();
+        let new_target_fps = self.settled_target_fps();
         if self.target_fps_state.target_fps != new_target_fps || new_target_fps.is_none() {
             self.reset_frametime_state();
             if let Some(target_fps) = new_target_fps {
@@ -83,7 +110,7 @@ impl Buffer {
 
     #[cfg(not(feature = "extension"))]
     pub fn calculate_target_fps(&mut self) {
-        let new_target_fps = self.target_fps();
+        let new_target_fps = self.settled_target_fps();
         if self.target_fps_state.target_fps != new_target_fps || new_target_fps.is_none() {
             self.reset_frametime_state();
             self.target_fps_state.target_fps = new_target_fps;
@@ -96,24 +123,87 @@ impl Buffer {
     }
 
     #[cfg(feature = "extension")]
-    fn trigger_target_fps_change(&self, extension: &Extension, target_fps: u32) {
-        trigger_target_fps_change(extension, target_fps, self.package_info.pkg.clone());
+    fn trigger_target_fps_change(&self, extension: &Extension, target_fps: f64) {
+        // Extensions have always been handed whole numbers
+        trigger_target_fps_change(
+            extension,
+            target_fps.round() as u32,
+            self.package_info.pkg.clone(),
+        );
     }
 
-    fn target_fps(&self) -> Option<u32> {
-        let target_fpses = match &self.target_fps_state.target_fps_config {
+    // The target fps `steps` entries below the current one in the configured list
+    pub fn lower_target_fps(&self, steps: u32) -> Option<f64> {
+        let target_fps = self.target_fps_state.target_fps?;
+        let TargetFps::Array(target_fpses) = self.target_fps_state.config() else {
+            return Some(target_fps);
+        };
+
+        let index = target_fpses
+            .iter()
+            .position(|fps| fps.total_cmp(&target_fps).is_eq())?;
+        Some(target_fpses[index.saturating_sub(steps as usize)])
+    }
+
+    pub const fn set_peak_refresh_rate(&mut self, refresh_rate: Option<u32>) {
+        self.target_fps_state.peak_refresh_rate = refresh_rate;
+    }
+
+    // Games switch caps with their graphics settings. Going up is unambiguous, but fps below the
+    // cap looks the same as the game lowering it, so a lower target only wins once the fps has
+    // kept matching it
+    fn settled_target_fps(&mut self) -> Option<f64> {
+        let matched = self.target_fps();
+        let state = &mut self.target_fps_state;
+
+        if let (Some(current), Some(matched)) = (state.target_fps, matched)
+            && matched < current
+            && !state
+                .peak_refresh_rate
+                .is_some_and(|refresh_rate| current > f64::from(refresh_rate) + 2.0)
+        {
+            let since = match state.pending_target_fps {
+                Some((pending, since)) if pending.total_cmp(&matched).is_eq() => since,
+                _ => {
+                    let now = Instant::now();
+                    state.pending_target_fps = Some((matched, now));
+                    now
+                }
+            };
+            if since.elapsed() < TARGET_DOWN_TIME {
+                return Some(current);
+            }
+            debug!("fps settled at {matched}, leaving target {current}");
+        }
+
+        state.pending_target_fps = None;
+        matched
+    }
+
+    fn target_fps(&self) -> Option<f64> {
+        let mut target_fpses = match self.target_fps_state.config() {
             TargetFps::Value(t) => vec![*t],
             TargetFps::Array(arr) => arr.clone(),
         };
 
+        // Follow the display right away instead of chasing an unreachable target until the fps
+        // average catches up
+        if let Some(refresh_rate) = self.target_fps_state.peak_refresh_rate {
+            let refresh_rate = f64::from(refresh_rate);
+            target_fpses.retain(|target_fps| *target_fps <= refresh_rate + 2.0);
+            if target_fpses.is_empty() {
+                target_fpses.push(refresh_rate);
+            }
+        }
+
         let current_fps = self.frametime_state.current_fps_long;
 
-        if unlikely(current_fps < (target_fpses.first()?.saturating_sub(10).max(10)).into()) {
+        if unlikely(current_fps < (target_fpses.first()? - 10.0).max(10.0)) {
             return None;
         }
 
         for &target_fps in &target_fpses {
-            if current_fps <= f64::from(target_fps) + 3.0 {
+            if current_fps <= target_fps + 3.0 {
                 debug!("Matched target_fps: current: {current_fps:.2} target_fps: {target_fps}");
                 return Some(target_fps);
             }
@@ -0,0 +1,103 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use log::info;
+
+use super::{Buffer, BufferWorkingState};
+use crate::framework::config::TargetFps;
+
+const CALIBRATION_TIME: Duration = Duration::from_secs(60);
+const SAMPLE_TIME: Duration = Duration::from_secs(1);
+const FPS_CAPS: [u32; 8] = [30, 40, 45, 60, 72, 90, 120, 144];
+
+// Observes the frame cadence of games without a configured fps list to find their cap
+#[derive(Debug)]
+pub struct Calibration {
+    timer: Instant,
+    sample_timer: Instant,
+    samples: Vec<f64>,
+}
+
+impl Calibration {
+    pub fn new() -> Self {
+        Self {
+            timer: Instant::now(),
+            sample_timer: Instant::now(),
+            samples: Vec::new(),
+        }
+    }
+}
+
+impl Buffer {
+    pub fn start_calibration(&mut self) {
+        self.calibration = Some(Calibration::new());
+    }
+
+    // Returns the detected cap once the calibration is done, it is used as target fps from then on
+    pub fn calibrate(&mut self) -> Option<u32> {
+        let calibration = self.calibration.as_mut()?;
+        if self.state.working_state != BufferWorkingState::Usable
+            || self.frametime_state.low_confidence
+        {
+            calibration.sample_timer = Instant::now();
+            return None;
+        }
+
+        if calibration.sample_timer.elapsed() >= SAMPLE_TIME {
+            calibration.sample_timer = Instant::now();
+            calibration
+                .samples
+                .push(self.frametime_state.current_fps_long);
+        }
+
+        if calibration.timer.elapsed() < CALIBRATION_TIME {
+            return None;
+        }
+
+        // The cap shows up as the top of the steady state cadence, the 90th percentile skips
+        // the odd spike
+        let mut samples = std::mem::take(&mut calibration.samples);
+        samples.sort_unstable_by(f64::total_cmp);
+        let Some(fps) = samples
+            .get((samples.len() * 9 / 10).saturating_sub(1))
+            .copied()
+        else {
+            calibration.timer = Instant::now();
+            return None;
+        };
+
+        let Some(cap) = FPS_CAPS
+            .iter()
+            .copied()
+            .find(|cap| fps <= f64::from(*cap) + 3.0 && fps >= f64::from(*cap) - 5.0)
+        else {
+            info!(
+                "[{}] no fps cap detected ({fps:.1}), calibrating again",
+                self.package_info.pkg
+            );
+            calibration.timer = Instant::now();
+            return None;
+        };
+
+        info!("[{}] calibrated target fps: {cap}", self.package_info.pkg);
+        self.calibration = None;
+        self.target_fps_state.target_fps_config = TargetFps::Value(f64::from(cap));
+        Some(cap)
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::VecDeque, time::Duration};
+
+use serde::Serialize;
+
+use crate::framework::config::FrametimeFilter;
+
+#[derive(Debug, Serialize)]
+pub struct FilterJson {
+    kind: String,
+    window: usize,
+    samples: Vec<f64>,
+    estimate: Option<f64>,
+    variance: f64,
+}
+
+// Smooths the incoming frametimes, so engines with noisy frame pacing don't make the
+// controller twitchy
+#[derive(Debug)]
+pub struct Filter {
+    kind: FrametimeFilter,
+    window: usize,
+    samples: VecDeque<Duration>,
+    estimate: Option<f64>,
+    variance: f64,
+}
+
+impl Filter {
+    pub fn new(kind: FrametimeFilter, window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            kind,
+            window,
+            samples: VecDeque::with_capacity(window),
+            estimate: None,
+            variance: 1.0,
+        }
+    }
+
+    pub fn matches(&self, kind: FrametimeFilter, window: usize) -> bool {
+        self.kind == kind && self.window == window.max(1)
+    }
+
+    pub fn state_json(&self) -> FilterJson {
+        FilterJson {
+            kind: format!("{:?}", self.kind),
+            window: self.window,
+            samples: self
+                .samples
+                .iter()
+                .map(|sample| sample.as_secs_f64() * 1000.0)
+                .collect(),
+            estimate: self.estimate.map(|estimate| estimate * 1000.0),
+            variance: self.variance,
+        }
+    }
+
+    pub fn update(&mut self, raw: Duration) -> Duration {
+        match self.kind {
+            FrametimeFilter::None => raw,
+            FrametimeFilter::Ema => self.ema(raw),
+            FrametimeFilter::Median => self.median(raw),
+            FrametimeFilter::Kalman => self.kalman(raw),
+        }
+    }
+
+    fn ema(&mut self, raw: Duration) -> Duration {
+        let alpha = 2.0 / (self.window as f64 + 1.0);
+        let raw = raw.as_secs_f64();
+        let estimate = self
+            .estimate
+            .map_or(raw, |estimate| alpha.mul_add(raw - estimate, estimate));
+        self.estimate = Some(estimate);
+        Duration::from_secs_f64(estimate)
+    }
+
+    fn median(&mut self, raw: Duration) -> Duration {
+        if self.samples.len() >= self.window {
+            self.samples.pop_back();
+        }
+        self.samples.push_front(raw);
+
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        sorted[sorted.len() / 2]
+    }
+
+    // One dimensional kalman filter on a constant frametime model, measurement noise is
+    // normalized to 1 and the window sets how slowly the estimate is allowed to drift
+    fn kalman(&mut self, raw: Duration) -> Duration {
+        let process_noise = 1.0 / (self.window * self.window) as f64;
+        let raw = raw.as_secs_f64();
+        let Some(estimate) = self.estimate else {
+            self.estimate = Some(raw);
+            return Duration::from_secs_f64(raw);
+        };
+
+        let variance = self.variance + process_noise;
+        let gain = variance / (variance + 1.0);
+        let estimate = gain.mul_add(raw - estimate, estimate);
+        self.variance = (1.0 - gain) * variance;
+        self.estimate = Some(estimate);
+        Duration::from_secs_f64(estimate)
+    }
+}
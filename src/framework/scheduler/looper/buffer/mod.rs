@@ -16,18 +16,23 @@
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
 pub mod calculate;
+mod calibrate;
+mod filter;
 
 use std::{
     collections::VecDeque,
     time::{Duration, Instant},
 };
 
+use calibrate::Calibration;
+use filter::{Filter, FilterJson};
 use libc::pid_t;
 use likely_stable::unlikely;
+use serde::Serialize;
 
 #[cfg(feature = "extension")]
 use crate::Extension;
-use crate::framework::config::TargetFps;
+use crate::framework::config::{FrametimeFilter, TargetFps};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum BufferWorkingState {
@@ -35,6 +40,26 @@ pub enum BufferWorkingState {
     Usable,
 }
 
+// Durations are in milliseconds
+#[derive(Debug, Serialize)]
+pub struct BufferJson<'a> {
+    pkg: &'a str,
+    pid: pid_t,
+    working_state: String,
+    target_fps: Option<f64>,
+    peak_refresh_rate: Option<u32>,
+    calibrating: bool,
+    current_fps_short: f64,
+    current_fps_long: f64,
+    avg_time_short: f64,
+    avg_time_long: f64,
+    additional_frametime: f64,
+    filtered_frametime: f64,
+    low_confidence: bool,
+    filter: FilterJson,
+    frametimes: Vec<f64>,
+}
+
 #[derive(Debug)]
 pub struct PackageInfo {
     pub pid: pid_t,
@@ -49,6 +74,10 @@ pub struct FrameTimeState {
     pub avg_time_short: Duration,
     pub frametimes: VecDeque<Duration>,
     pub additional_frametime: Duration,
+    pub low_confidence: bool,
+    // The latest frametime after smoothing, what the controller acts on
+    pub filtered_frametime: Duration,
+    filter: Filter,
 }
 
 impl FrameTimeState {
@@ -60,14 +89,24 @@ impl FrameTimeState {
             avg_time_short: Duration::ZERO,
             frametimes: VecDeque::with_capacity(1440),
             additional_frametime: Duration::ZERO,
+            low_confidence: false,
+            filtered_frametime: Duration::ZERO,
+            filter: Filter::new(FrametimeFilter::None, 1),
         }
     }
 }
 
 #[derive(Debug)]
 pub struct TargetFpsState {
-    pub target_fps: Option<u32>,
+    pub target_fps: Option<f64>,
     target_fps_config: TargetFps,
+    // Set by the active sub-profile, wins over the configured targets
+    target_fps_override: Option<TargetFps>,
+    // Fastest mode of the display hosting the game, targets above it are unreachable. Not the
+    // active rate, which LTPO panels lower along with the fps of the game
+    peak_refresh_rate: Option<u32>,
+    // A lower target the fps has matched, and since when
+    pending_target_fps: Option<(f64, Instant)>,
 }
 
 impl TargetFpsState {
@@ -75,6 +114,16 @@ impl TargetFpsState {
         Self {
             target_fps: None,
             target_fps_config,
+            target_fps_override: None,
+            peak_refresh_rate: None,
+            pending_target_fps: None,
+        }
+    }
+
+    const fn config(&self) -> &TargetFps {
+        match &self.target_fps_override {
+            Some(target_fps) => target_fps,
+            None => &self.target_fps_config,
         }
     }
 }
@@ -105,6 +154,7 @@ pub struct Buffer {
     pub frametime_state: FrameTimeState,
     pub target_fps_state: TargetFpsState,
     pub state: BufferState,
+    calibration: Option<Calibration>,
 }
 
 impl Buffer {
@@ -114,6 +164,7 @@ impl Buffer {
             frametime_state: FrameTimeState::new(),
             target_fps_state: TargetFpsState::new(target_fps_config),
             state: BufferState::new(),
+            calibration: None,
         }
     }
     #[cfg(feature = "extension")]
@@ -122,13 +173,14 @@ impl Buffer {
         self.state.last_update = Instant::now();
 
         while self.frametime_state.frametimes.len()
-            >= self.target_fps_state.target_fps.unwrap_or(144) as usize * 5
+            >= self.target_fps_state.target_fps.unwrap_or(144.0) as usize * 5
         {
             self.frametime_state.frametimes.pop_back();
             self.try_usable();
         }
 
         self.frametime_state.frametimes.push_front(d);
+        self.frametime_state.filtered_frametime = self.frametime_state.filter.update(d);
         self.try_calculate(extension);
     }
 
@@ -138,13 +190,14 @@ impl Buffer {
         self.state.last_update = Instant::now();
 
         while self.frametime_state.frametimes.len()
-            >= self.target_fps_state.target_fps.unwrap_or(144) as usize * 5
+            >= self.target_fps_state.target_fps.unwrap_or(144.0) as usize * 5
         {
             self.frametime_state.frametimes.pop_back();
             self.try_usable();
         }
 
         self.frametime_state.frametimes.push_front(d);
+        self.frametime_state.filtered_frametime = self.frametime_state.filter.update(d);
         self.try_calculate();
     }
 
@@ -169,6 +222,46 @@ impl Buffer {
         }
     }
 
+    pub fn state_json(&self) -> BufferJson<'_> {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let frametime_state = &self.frametime_state;
+        BufferJson {
+            pkg: &self.package_info.pkg,
+            pid: self.package_info.pid,
+            working_state: format!("{:?}", self.state.working_state),
+            target_fps: self.target_fps_state.target_fps,
+            peak_refresh_rate: self.target_fps_state.peak_refresh_rate,
+            calibrating: self.calibration.is_some(),
+            current_fps_short: frametime_state.current_fps_short,
+            current_fps_long: frametime_state.current_fps_long,
+            avg_time_short: millis(frametime_state.avg_time_short),
+            avg_time_long: millis(frametime_state.avg_time_long),
+            additional_frametime: millis(frametime_state.additional_frametime),
+            filtered_frametime: millis(frametime_state.filtered_frametime),
+            low_confidence: frametime_state.low_confidence,
+            filter: frametime_state.filter.state_json(),
+            frametimes: frametime_state
+                .frametimes
+                .iter()
+                .copied()
+                .map(millis)
+                .collect(),
+        }
+    }
+
+    pub fn set_frametime_filter(&mut self, kind: FrametimeFilter, window: usize) {
+        if !self.frametime_state.filter.matches(kind, window) {
+            self.frametime_state.filter = Filter::new(kind, window);
+        }
+    }
+
+    pub fn set_target_fps_override(&mut self, target_fps: Option<TargetFps>) {
+        if self.target_fps_state.target_fps_override != target_fps {
+            self.target_fps_state.target_fps_override = target_fps;
+            self.target_fps_state.pending_target_fps = None;
+        }
+    }
+
     pub fn try_usable(&mut self) {
         if self.state.working_state == BufferWorkingState::Unusable
             && self.state.working_state_timer.elapsed() >= Duration::from_secs(1)
@@ -26,7 +26,7 @@ use std::{
 
 use libc::{MS_BIND, MS_REC, mount, umount, umount2};
 
-use crate::framework::error::Result;
+use crate::{framework::error::Result, snapshot};
 
 fn lock_value<P, S>(path: P, value: S)
 where
@@ -40,6 +40,7 @@ where
     let mount_path = format!("/cache/mount_mask_{value}");
 
     let _ = unmount(&path_str);
+    snapshot::record(path);
     let _ = set_permissions(path, PermissionsExt::from_mode(0o644));
     let _ = fs::write(&path_str, value);
     let _ = set_permissions(path, PermissionsExt::from_mode(0o444));
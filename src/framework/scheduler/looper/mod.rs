@@ -15,43 +15,117 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
+mod bench;
 mod buffer;
 mod clean;
 mod policy;
+mod tick;
 
 use std::time::{Duration, Instant};
 
+use chrono::{Local, Timelike};
 use frame_analyzer::Analyzer;
 use likely_stable::{likely, unlikely};
-use log::{debug, info};
+use log::{debug, info, warn};
+#[cfg(feature = "predictor")]
+use policy::predictor::Predictor;
 use policy::{ControllerParams, controll::calculate_control};
+use serde::Serialize;
+use tick::LoopTicker;
 
-use super::{FasData, thermal::Thermal, topapp::TopAppsWatcher};
+use super::{
+    FasData,
+    adpf::Adpf,
+    background_throttle::BackgroundThrottler,
+    battery::Battery,
+    broadcast,
+    compositor_boost::CompositorBoost,
+    device_idle::DeviceIdleWatcher,
+    display_refresh::DisplayRefresh,
+    dma_latency::DmaLatency,
+    engine::{self, EngineDetector, EnginePreset},
+    frame_hook::HookTracker,
+    frame_source::FrameSourceManager,
+    game_cgroup::GameCgroup,
+    game_mode::GameModeWatcher,
+    game_props::GameProps,
+    hooks::run_hook,
+    idle_scene::{IDLE_FREQ_CAP, IdleSceneDetector},
+    io_tune::IoTuner,
+    launch_boost::LaunchBoost,
+    loading::LoadingDetector,
+    memory_tune::MemoryTuner,
+    notification,
+    pacing::PacingAssist,
+    refresh_pin::RefreshPin,
+    refresh_rate::RefreshRateWatcher,
+    resolution_hint::ResolutionHint,
+    rt_sched::RtScheduler,
+    self_test,
+    shutdown::{self, Shutdown},
+    state_props,
+    thermal::Thermal,
+    thermal_budget::BudgetPlanner,
+    threads::{ThreadClass, ThreadScanner},
+    topapp::TopAppsWatcher,
+    vsync_phase::{self, VsyncPhase},
+};
 use crate::{
     Controller,
+    cpu_common::ControllerJson,
     framework::{
-        config::Config,
+        Learned,
+        config::{Config, GameType, TargetFps},
+        control::{self, Control, DumpFormat, STATUS},
         error::Result,
         node::{Mode, Node},
-        pid_utils::get_process_name,
+        pid_utils::{exited_abnormally, get_process_name, process_age, process_exists},
+        telemetry::{self, Record, SessionStats},
+        trace::TraceRecorder,
     },
+    logger, props,
+    root_env::{self, RootEnv},
+    snapshot, sysfs, watchdog,
 };
 #[cfg(feature = "extension")]
 use crate::{
-    api::{trigger_load_fas, trigger_start_fas, trigger_stop_fas, trigger_unload_fas},
+    api::{
+        FrameWindowStats, trigger_frame_window, trigger_game_exit, trigger_game_start,
+        trigger_load_fas, trigger_start_fas, trigger_stop_fas, trigger_unload_fas,
+    },
     framework::Extension,
 };
-use buffer::{Buffer, BufferWorkingState};
+pub use bench::{BenchPattern, BenchReport, run as bench};
+use buffer::{Buffer, BufferJson, BufferWorkingState};
 use clean::Cleaner;
+#[cfg(feature = "predictor")]
+pub use policy::predictor::train as train_predictor;
 
 const DELAY_TIME: Duration = Duration::from_secs(3);
+const DOZE_POLL_TIME: Duration = Duration::from_secs(5);
+const SCREEN_OFF_POLL_TIME: Duration = Duration::from_secs(1);
+const STATUS_UPDATE_TIME: Duration = Duration::from_secs(1);
+// Learned key of the target fps calibrated for games without a configured fps list
+const CALIBRATED_FPS_KEY: &str = "calibrated_fps";
+// Learned key of the frequencies each policy settled at, by policy number
+const STEADY_FREQS_KEY: &str = "steady_freqs";
+// A game engaged this soon after its process started was just launched
+const COLD_START_AGE: Duration = Duration::from_secs(60);
+// A throttle state has to hold this long to be broadcast, temperatures hovering around a
+// threshold would toggle it every few updates
+const THROTTLE_HOLD_TIME: Duration = Duration::from_secs(3);
+// Share of each policy's max freq a stream game is capped at, decoding needs modest clocks
+const STREAM_FREQ_CAP: f64 = 0.7;
+const SHUTDOWN_STAGE_TIMEOUT: Duration = Duration::from_secs(2);
+#[cfg(feature = "extension")]
+const FRAME_WINDOW_TIME: Duration = Duration::from_secs(1);
 const EXCLUDE_LIST: &[&str] = &[
     "com.tungsten.fcl",
     "net.kdt.pojavlaunch",
     "com.tungsten.hmclpe",
 ];
 
-#[derive(PartialEq)]
+#[derive(Debug, PartialEq)]
 enum State {
     NotWorking,
     Waiting,
@@ -63,12 +137,47 @@ struct FasState {
     working_state: State,
     delay_timer: Instant,
     buffer: Option<Buffer>,
+    // Other games visible next to the main one (split-screen, parallel-space windows), their
+    // demand is combined with the main game's
+    others: Vec<Buffer>,
+    hook_args: Option<(String, Option<f64>)>,
+    // Pid of the game under load control, no frame source works for it
+    load_control: Option<i32>,
+    // Pid of the foreground app on the powersave list
+    powersave_app: Option<i32>,
+    // Since when a newly launched game is held at max frequency
+    cold_start: Option<Instant>,
+    // Whether heat or battery temperature holds the game back, broadcast on changes
+    throttled: bool,
+    // Since when the throttle state differs from the broadcast one
+    throttle_change: Option<Instant>,
+    session_stats: SessionStats,
+    vsync_phase: VsyncPhase,
+    standby: Option<Standby>,
+    // Frequencies of a game back from standby, skips the engage delay
+    resume_freqs: Option<Vec<(i32, isize)>>,
+    // Name of the sub-profile the game runs with
+    sub_profile: Option<String>,
+    // Picked by the context rule matching at session start, held until the session ends
+    context_mode: Option<Mode>,
+    context_sub_profile: Option<String>,
+    // The mode node when the session started, a later write through it is the user's choice
+    session_node_mode: Option<Mode>,
+}
+
+// A game sent to background but still alive, kept so fas resumes where it left off
+struct Standby {
+    buffer: Buffer,
+    freqs: Vec<(i32, isize)>,
 }
 
 struct AnalyzerState {
-    analyzer: Analyzer,
+    // None without kernel BPF support, every game then goes to the fallback sources
+    analyzer: Option<Analyzer>,
     restart_counter: u8,
     restart_timer: Instant,
+    frame_sources: FrameSourceManager,
+    hooks: HookTracker,
 }
 
 struct ControllerState {
@@ -76,6 +185,85 @@ struct ControllerState {
     params: ControllerParams,
     target_fps_offset: f64,
     usage_sample_timer: Instant,
+    upscale_timer: Instant,
+}
+
+impl ControllerState {
+    fn new(controller: Controller) -> Self {
+        Self {
+            controller,
+            params: ControllerParams::default(),
+            target_fps_offset: 0.0,
+            usage_sample_timer: Instant::now(),
+            upscale_timer: Instant::now(),
+        }
+    }
+
+    // Stream games are bound by the decoder and the network, boosts only burn power there
+    fn apply_config(&mut self, config: &mut Config, preset: EnginePreset, stream: bool) {
+        self.controller.set_dry_run(config.dry_run());
+        self.controller.set_selinux_patch(config.selinux_patch());
+        self.controller.set_capacity_units(config.capacity_units());
+        self.controller
+            .set_jank_routing(config.jank_routing() && !stream);
+        self.controller
+            .set_observe_policies(config.observe_policies());
+        self.controller
+            .set_refresh_interval(config.freq_refresh_interval());
+        self.controller.set_devfreq_boost(config.devfreq_boost());
+        self.controller.set_cpuidle_gate(config.cpuidle_gate());
+        self.controller.set_core_parking(config.core_parking());
+        self.controller.set_top_app_boost(config.top_app_boost());
+        self.controller
+            .set_interference_lock(config.interference_lock());
+        self.controller
+            .set_verify_auto_ignore(config.verify_auto_ignore());
+        self.controller
+            .set_mirror_detection(config.mirror_detection());
+        self.controller.set_opp_power(config.opp_power());
+        // Before the ceilings, ratios are taken of the table top
+        self.controller.set_boost_freqs(config.boost_freqs());
+        self.controller.set_freq_floors(config.freq_floors());
+        self.controller.set_freq_ceilings(config.freq_ceilings());
+        self.controller.set_freq_slews(config.freq_slews());
+        self.controller.set_policy_ratios(config.policy_ratios());
+        self.controller.set_custom_nodes(config.custom_nodes());
+        self.controller.set_verify(
+            config.verify_interval(),
+            config.verify_tolerance(),
+            config.verify_ignore_policies(),
+        );
+        let tuning = control::tuning();
+        if stream {
+            self.controller.set_touch_boost(Duration::ZERO, 0.0);
+            self.controller.set_jank_boost(0.0, Duration::ZERO);
+        } else {
+            self.controller
+                .set_touch_boost(config.touch_boost_time(), config.touch_boost_floor());
+            self.controller.set_jank_boost(
+                tuning
+                    .jank_multiple
+                    .unwrap_or_else(|| config.jank_boost_multiple()),
+                config.jank_boost_time(),
+            );
+        }
+        self.controller.set_critical_usage(
+            config.critical_usage_threshold(),
+            config.critical_usage_decay(),
+        );
+        self.controller
+            .set_usage_weighted(config.usage_weighted_freqs());
+        self.controller
+            .set_top_cores(preset.top_cores.unwrap_or_else(|| config.top_cores()));
+        self.params.ramp_up = tuning
+            .ramp_up
+            .unwrap_or_else(|| config.ramp_up_rate() * preset.ramp_up);
+        self.params.ramp_down = tuning
+            .ramp_down
+            .unwrap_or_else(|| config.ramp_down_rate() * preset.ramp_down);
+        self.params.down_dwell = config.ramp_down_dwell().max(preset.down_dwell);
+        self.params.margin_fps = tuning.margin_fps;
+    }
 }
 
 #[cfg(feature = "extension")]
@@ -84,11 +272,43 @@ pub struct Looper {
     config: Config,
     node: Node,
     extension: Extension,
+    frame_window_timer: Instant,
     therminal: Thermal,
+    thermal_budget: BudgetPlanner,
+    battery: Battery,
     windows_watcher: TopAppsWatcher,
+    idle_watcher: DeviceIdleWatcher,
+    game_mode_watcher: GameModeWatcher,
+    refresh_pin: RefreshPin,
+    display_refresh: DisplayRefresh,
+    game_props: GameProps,
+    refresh_rate: RefreshRateWatcher,
+    io_tuner: IoTuner,
+    launch_boost: LaunchBoost,
+    background_throttler: BackgroundThrottler,
+    compositor_boost: CompositorBoost,
+    memory_tuner: MemoryTuner,
+    dma_latency: DmaLatency,
+    trace: TraceRecorder,
+    loading: LoadingDetector,
+    idle_scene: IdleSceneDetector,
+    pacing: PacingAssist,
+    #[cfg(feature = "predictor")]
+    predictor: Predictor,
+    resolution_hint: ResolutionHint,
+    rt_sched: RtScheduler,
+    game_cgroup: GameCgroup,
+    adpf: Adpf,
+    thread_scanner: ThreadScanner,
+    engine: EngineDetector,
     cleaner: Cleaner,
     fas_state: FasState,
     controller_state: ControllerState,
+    learned: Learned,
+    idle_exit: Option<Duration>,
+    idle_timer: Instant,
+    status_timer: Instant,
+    ticker: LoopTicker,
 }
 
 #[cfg(not(feature = "extension"))]
@@ -97,84 +317,299 @@ pub struct Looper {
     config: Config,
     node: Node,
     therminal: Thermal,
+    thermal_budget: BudgetPlanner,
+    battery: Battery,
     windows_watcher: TopAppsWatcher,
+    idle_watcher: DeviceIdleWatcher,
+    game_mode_watcher: GameModeWatcher,
+    refresh_pin: RefreshPin,
+    display_refresh: DisplayRefresh,
+    game_props: GameProps,
+    refresh_rate: RefreshRateWatcher,
+    io_tuner: IoTuner,
+    launch_boost: LaunchBoost,
+    background_throttler: BackgroundThrottler,
+    compositor_boost: CompositorBoost,
+    memory_tuner: MemoryTuner,
+    dma_latency: DmaLatency,
+    trace: TraceRecorder,
+    loading: LoadingDetector,
+    idle_scene: IdleSceneDetector,
+    pacing: PacingAssist,
+    #[cfg(feature = "predictor")]
+    predictor: Predictor,
+    resolution_hint: ResolutionHint,
+    rt_sched: RtScheduler,
+    game_cgroup: GameCgroup,
+    adpf: Adpf,
+    thread_scanner: ThreadScanner,
+    engine: EngineDetector,
     cleaner: Cleaner,
     fas_state: FasState,
     controller_state: ControllerState,
+    learned: Learned,
+    idle_exit: Option<Duration>,
+    idle_timer: Instant,
+    status_timer: Instant,
+    ticker: LoopTicker,
 }
 
 impl Looper {
     #[cfg(feature = "extension")]
     pub fn new(
-        analyzer: Analyzer,
+        analyzer: Option<Analyzer>,
         config: Config,
         node: Node,
         extension: Extension,
         controller: Controller,
-    ) -> Self {
-        Self {
+    ) -> Result<Self> {
+        Ok(Self {
             analyzer_state: AnalyzerState {
                 analyzer,
                 restart_counter: 0,
                 restart_timer: Instant::now(),
+                frame_sources: FrameSourceManager::new(),
+                hooks: HookTracker::default(),
             },
             config,
             node,
             extension,
-            therminal: Thermal::new().unwrap(),
+            frame_window_timer: Instant::now(),
+            therminal: Thermal::new(sysfs::root())?,
+            thermal_budget: BudgetPlanner::new(),
+            battery: Battery::new(),
             windows_watcher: TopAppsWatcher::new(),
+            idle_watcher: DeviceIdleWatcher::new(),
+            game_mode_watcher: GameModeWatcher::new(),
+            refresh_pin: RefreshPin::new(),
+            display_refresh: DisplayRefresh::new(),
+            game_props: GameProps::new(),
+            refresh_rate: RefreshRateWatcher::new(),
+            io_tuner: IoTuner::new(),
+            launch_boost: LaunchBoost::new(),
+            background_throttler: BackgroundThrottler::new(),
+            compositor_boost: CompositorBoost::new(),
+            memory_tuner: MemoryTuner::new(),
+            dma_latency: DmaLatency::new(),
+            trace: TraceRecorder::default(),
+            loading: LoadingDetector::new(),
+            idle_scene: IdleSceneDetector::new(),
+            pacing: PacingAssist::new(),
+            #[cfg(feature = "predictor")]
+            predictor: Predictor::new(),
+            resolution_hint: ResolutionHint::new(),
+            rt_sched: RtScheduler::new(),
+            game_cgroup: GameCgroup::new(),
+            adpf: Adpf::new(),
+            thread_scanner: ThreadScanner::new(),
+            engine: EngineDetector::new(),
             cleaner: Cleaner::new(),
             fas_state: FasState {
                 mode: Mode::Balance,
                 buffer: None,
+                others: Vec::new(),
+                hook_args: None,
+                load_control: None,
+                powersave_app: None,
+                cold_start: None,
+                throttled: false,
+                throttle_change: None,
+                session_stats: SessionStats::default(),
+                vsync_phase: VsyncPhase::default(),
+                standby: None,
+                resume_freqs: None,
+                sub_profile: None,
+                context_mode: None,
+                context_sub_profile: None,
+                session_node_mode: None,
                 working_state: State::NotWorking,
                 delay_timer: Instant::now(),
             },
-            controller_state: ControllerState {
-                controller,
-                params: ControllerParams::default(),
-                target_fps_offset: 0.0,
-                usage_sample_timer: Instant::now(),
-            },
-        }
+            controller_state: ControllerState::new(controller),
+            learned: Learned::default(),
+            idle_exit: None,
+            idle_timer: Instant::now(),
+            status_timer: Instant::now(),
+            ticker: LoopTicker::new(),
+        })
     }
 
     #[cfg(not(feature = "extension"))]
-    pub fn new(analyzer: Analyzer, config: Config, node: Node, controller: Controller) -> Self {
-        Self {
+    pub fn new(
+        analyzer: Option<Analyzer>,
+        config: Config,
+        node: Node,
+        controller: Controller,
+    ) -> Result<Self> {
+        Ok(Self {
             analyzer_state: AnalyzerState {
                 analyzer,
                 restart_counter: 0,
                 restart_timer: Instant::now(),
+                frame_sources: FrameSourceManager::new(),
+                hooks: HookTracker::default(),
             },
             config,
             node,
-            therminal: Thermal::new().unwrap(),
+            therminal: Thermal::new(sysfs::root())?,
+            thermal_budget: BudgetPlanner::new(),
+            battery: Battery::new(),
             windows_watcher: TopAppsWatcher::new(),
+            idle_watcher: DeviceIdleWatcher::new(),
+            game_mode_watcher: GameModeWatcher::new(),
+            refresh_pin: RefreshPin::new(),
+            display_refresh: DisplayRefresh::new(),
+            game_props: GameProps::new(),
+            refresh_rate: RefreshRateWatcher::new(),
+            io_tuner: IoTuner::new(),
+            launch_boost: LaunchBoost::new(),
+            background_throttler: BackgroundThrottler::new(),
+            compositor_boost: CompositorBoost::new(),
+            memory_tuner: MemoryTuner::new(),
+            dma_latency: DmaLatency::new(),
+            trace: TraceRecorder::default(),
+            loading: LoadingDetector::new(),
+            idle_scene: IdleSceneDetector::new(),
+            pacing: PacingAssist::new(),
+            #[cfg(feature = "predictor")]
+            predictor: Predictor::new(),
+            resolution_hint: ResolutionHint::new(),
+            rt_sched: RtScheduler::new(),
+            game_cgroup: GameCgroup::new(),
+            adpf: Adpf::new(),
+            thread_scanner: ThreadScanner::new(),
+            engine: EngineDetector::new(),
             cleaner: Cleaner::new(),
             fas_state: FasState {
                 mode: Mode::Balance,
                 buffer: None,
+                others: Vec::new(),
+                hook_args: None,
+                load_control: None,
+                powersave_app: None,
+                cold_start: None,
+                throttled: false,
+                throttle_change: None,
+                session_stats: SessionStats::default(),
+                vsync_phase: VsyncPhase::default(),
+                standby: None,
+                resume_freqs: None,
+                sub_profile: None,
+                context_mode: None,
+                context_sub_profile: None,
+                session_node_mode: None,
                 working_state: State::NotWorking,
                 delay_timer: Instant::now(),
             },
-            controller_state: ControllerState {
-                controller,
-                params: ControllerParams::default(),
-                target_fps_offset: 0.0,
-                usage_sample_timer: Instant::now(),
-            },
-        }
+            controller_state: ControllerState::new(controller),
+            learned: Learned::default(),
+            idle_exit: None,
+            idle_timer: Instant::now(),
+            status_timer: Instant::now(),
+            ticker: LoopTicker::new(),
+        })
+    }
+
+    #[must_use]
+    pub const fn idle_exit(mut self, d: Option<Duration>) -> Self {
+        self.idle_exit = d;
+        self
     }
 
     pub fn enter_loop(&mut self) -> Result<()> {
         loop {
+            watchdog::feed();
+            self.ticker.tick();
+
+            if let Some((format, reply)) = control::take_dump_request() {
+                let _ = reply.send(match format {
+                    DumpFormat::Text => self.dump(),
+                    DumpFormat::Json => self.state_json(),
+                });
+            }
+
+            if shutdown::requested() {
+                self.shutdown();
+                return Ok(());
+            }
+
+            if self.idle_watcher.screen_off() {
+                self.suspend();
+                debug!("screen is off, fas is suspended");
+                shutdown::sleep(SCREEN_OFF_POLL_TIME);
+                continue;
+            }
+
+            if self.idle_watcher.device_idle() {
+                self.suspend();
+                debug!("device is in doze, fas is suspended");
+                shutdown::sleep(DOZE_POLL_TIME);
+                continue;
+            }
+
+            if self.node.fas_disabled() {
+                self.suspend();
+                debug!("fas is disabled through the mode node");
+                shutdown::sleep(SCREEN_OFF_POLL_TIME);
+                continue;
+            }
+
             self.switch_mode();
+            let preset = self.engine_preset();
+            let stream = self.stream_game();
+            self.controller_state
+                .apply_config(&mut self.config, preset, stream);
+            self.update_sub_profile();
+            control::set_allowed_uids(self.config.control_uids());
+            self.analyzer_state
+                .frame_sources
+                .set_stall_time(self.config.frame_source_timeout());
+            let adaptive_refresh_target = self.config.adaptive_refresh_target();
+            self.refresh_rate.set_enabled(adaptive_refresh_target);
+            self.refresh_rate.set_game(
+                self.fas_state
+                    .buffer
+                    .as_ref()
+                    .map(|buffer| buffer.package_info.pkg.as_str()),
+            );
+            if let Some(buffer) = self.fas_state.buffer.as_mut() {
+                buffer.set_peak_refresh_rate(
+                    self.refresh_rate
+                        .peak_refresh_rate()
+                        .filter(|_| adaptive_refresh_target),
+                );
+                let window = self
+                    .config
+                    .frametime_filter_window(&buffer.package_info.pkg);
+                buffer.set_frametime_filter(self.config.frametime_filter(), window);
+
+                if self.fas_state.working_state == State::Working
+                    && let Some(target_fps) = buffer.calibrate()
+                {
+                    self.learned.set(
+                        &buffer.package_info.pkg,
+                        CALIBRATED_FPS_KEY,
+                        toml::Value::Integer(target_fps.into()),
+                    );
+                }
+            }
             let _ = self.update_analyzer();
             self.retain_topapp();
-            log::set_max_level(self.config.logger_level());
 
-            if self.windows_watcher.visible_freeform_window() {
+            if self.fas_state.buffer.is_some() {
+                self.idle_timer = Instant::now();
+            } else if let Some(idle_exit) = self.idle_exit
+                && self.idle_timer.elapsed() > idle_exit
+            {
+                info!("No game for {idle_exit:?}, exiting");
+                self.shutdown();
+                return Ok(());
+            }
+
+            logger::set_level(self.config.logger_level());
+            self.update_status();
+
+            if self.windows_watcher.visible_freeform_window() && !self.game_on_screen() {
                 self.disable_fas();
                 debug!("has freedom, fas is disabled");
                 continue;
@@ -188,15 +623,33 @@ impl Looper {
                 continue;
             }
 
+            self.update_launch_boost();
+            self.update_load_control();
+            self.update_powersave_app();
+
             if let Some(data) = self.recv_message() {
+                if !self.screen_frame(&data) {
+                    continue;
+                }
+                let received = Instant::now();
                 debug!("original frametime: {:?}", data.frametime);
+                let data = self.filter_backpressure(data);
+                self.trace.frame(data.pid, data.frametime, data.present);
                 if let Some(state) = self.buffer_update(&data) {
                     match state {
-                        BufferWorkingState::Usable => self.do_policy(),
+                        BufferWorkingState::Usable => {
+                            if self.ticker.policy_due(self.config.control_interval()) {
+                                self.do_policy();
+                                self.ticker.record_latency(received.elapsed());
+                            }
+                        }
                         BufferWorkingState::Unusable => self.disable_fas(),
                     }
                 }
-            } else if let Some(buffer) = self.fas_state.buffer.as_mut() {
+            } else if let Some(buffer) = self.fas_state.buffer.as_mut()
+                // Fallback sources deliver frames in batches, silence in between isn't jank
+                && !buffer.frametime_state.low_confidence
+            {
                 debug!("janked !");
                 #[cfg(feature = "extension")]
                 buffer.additional_frametime(&self.extension);
@@ -214,14 +667,108 @@ impl Looper {
         }
     }
 
+    // Tears subsystems down in dependency order, so the device is left in stock state
+    fn shutdown(&mut self) {
+        fn restore_snapshot() {
+            snapshot::restore();
+        }
+
+        watchdog::disarm();
+        let shutdown = Shutdown::begin(&[
+            ("snapshot", restore_snapshot as fn()),
+            ("ipc", Control::shutdown),
+        ]);
+
+        shutdown.stage("controller", SHUTDOWN_STAGE_TIMEOUT, || {
+            self.disable_fas();
+            self.controller_state.controller.shutdown();
+        });
+        shutdown.stage("frame sources", SHUTDOWN_STAGE_TIMEOUT, || {
+            if let Some(analyzer) = self.analyzer_state.analyzer.as_mut() {
+                analyzer.detach_apps();
+            }
+            self.analyzer_state.frame_sources.shutdown();
+        });
+        shutdown.stage("session environment", SHUTDOWN_STAGE_TIMEOUT, || {
+            self.cleaner.undo_cleanup();
+            self.refresh_pin.shutdown();
+            self.display_refresh.shutdown();
+            self.game_props.restore();
+            self.resolution_hint.shutdown();
+            self.rt_sched.restore();
+            self.game_cgroup.restore();
+            self.adpf.close();
+            self.io_tuner.restore();
+            self.launch_boost.stop();
+            self.background_throttler.restore();
+            self.compositor_boost.restore();
+            self.memory_tuner.restore();
+            self.dma_latency.release();
+        });
+        shutdown.stage("file handler", SHUTDOWN_STAGE_TIMEOUT, || {
+            self.controller_state.controller.close_files();
+        });
+        shutdown.stage("snapshot", SHUTDOWN_STAGE_TIMEOUT, || {
+            snapshot::restore();
+        });
+        shutdown.stage("ipc", SHUTDOWN_STAGE_TIMEOUT, || {
+            Control::shutdown();
+            self.node.shutdown();
+        });
+    }
+
+    // Keeps the game buffer, so fas resumes by itself once the same game is foregrounded again
+    fn suspend(&mut self) {
+        self.end_launch_boost();
+        if self.fas_state.working_state == State::NotWorking {
+            return;
+        }
+
+        self.disable_fas();
+        if let Some(analyzer) = self.analyzer_state.analyzer.as_mut() {
+            analyzer.detach_apps();
+        }
+        self.analyzer_state.hooks.clear();
+        self.analyzer_state.frame_sources.stop();
+    }
+
     fn switch_mode(&mut self) {
-        if let Ok(new_mode) = self.node.get_mode()
-            && likely(self.fas_state.mode != new_mode)
-        {
+        let Ok(node_mode) = self.node.get_mode() else {
+            return;
+        };
+        let node_switched = self
+            .fas_state
+            .session_node_mode
+            .is_some_and(|mode| mode != node_mode);
+        if node_switched && self.fas_state.context_mode.take().is_some() {
+            info!("Mode node switched to {node_mode}, dropping the context rule's mode");
+        }
+
+        let new_mode = self.fas_state.context_mode.unwrap_or(node_mode);
+        let new_mode = self.game_mode(node_switched).unwrap_or(new_mode);
+        let new_mode = self.charging_mode().unwrap_or(new_mode);
+        if likely(self.fas_state.mode != new_mode) {
             info!("Switch mode: {} -> {}", self.fas_state.mode, new_mode);
             self.fas_state.mode = new_mode;
+            if self.config.broadcast() {
+                let pkg = self
+                    .fas_state
+                    .hook_args
+                    .as_ref()
+                    .map(|(pkg, _)| pkg.clone());
+                broadcast::mode_changed(new_mode, pkg);
+            }
+            if self.config.publish_props() {
+                state_props::mode_changed(new_mode);
+            }
 
             if self.fas_state.working_state == State::Working {
+                if self.config.notification()
+                    && let Some((pkg, target_fps)) = self.fas_state.hook_args.clone()
+                {
+                    notification::post_engaged(pkg, target_fps, new_mode);
+                }
+
                 #[cfg(feature = "extension")]
                 self.controller_state.controller.init_game(
                     self.fas_state.buffer.as_ref().unwrap().package_info.pid,
@@ -235,20 +782,270 @@ impl Looper {
         }
     }
 
+    // Time of day and temperatures at session start pick the mode and sub-profile the session
+    // runs with, game_modes still win and switching the mode node afterwards wins over both
+    fn apply_context_rule(&mut self) {
+        let now = Local::now();
+        let minute = now.hour() * 60 + now.minute();
+        let core_temperature = self.therminal.core_temperature();
+        let battery_temperature = self.therminal.battery_temperature();
+        let rule = self
+            .config
+            .context_rule(minute, core_temperature, battery_temperature);
+
+        if let Some(rule) = &rule {
+            info!("Context rule matched: {rule:?}");
+        }
+        self.fas_state.session_node_mode = self.node.get_mode().ok();
+        self.fas_state.context_mode = rule.as_ref().and_then(|rule| {
+            let mode = rule.mode.as_deref()?;
+            mode.parse()
+                .inspect_err(|_| warn!("Context rule has an invalid mode: {mode}"))
+                .ok()
+        });
+        self.fas_state.context_sub_profile = rule.and_then(|rule| rule.sub_profile);
+    }
+
+    // The platform game mode picked in the system game panel wins over game_modes, which
+    // give way to the mode node once it was switched during the session
+    fn game_mode(&mut self, node_switched: bool) -> Option<Mode> {
+        let follow_system = self.config.system_game_mode();
+        let pkg = self
+            .fas_state
+            .buffer
+            .as_ref()
+            .map(|buffer| buffer.package_info.pkg.as_str());
+        self.game_mode_watcher
+            .set_package(pkg.filter(|_| follow_system));
+
+        let pkg = pkg?;
+        self.game_mode_watcher
+            .mode()
+            .or_else(|| self.config.game_mode(pkg).filter(|_| !node_switched))
+    }
+
+    fn charging_mode(&mut self) -> Option<Mode> {
+        if self.battery.charging() {
+            self.config.charging_mode()
+        } else {
+            None
+        }
+    }
+
     fn recv_message(&mut self) -> Option<FasData> {
+        // Frames wake the loop up anyway, without a game it only has to notice one starting
+        let timeout = if self.analyzer_state.frame_sources.is_active() {
+            Duration::ZERO
+        } else if self.fas_state.buffer.is_some() {
+            Duration::from_millis(100)
+        } else {
+            self.config.idle_poll_interval()
+        };
+
+        let Some(analyzer) = self.analyzer_state.analyzer.as_mut() else {
+            shutdown::sleep(timeout);
+            return self.recv_fallback();
+        };
+        if let Some((pid, frametime)) = analyzer.recv_timeout(timeout) {
+            self.analyzer_state.frame_sources.hook_frame();
+            self.analyzer_state.hooks.frame(pid);
+            return Some(FasData {
+                pid,
+                frametime,
+                low_confidence: false,
+                production: None,
+                present: None,
+            });
+        }
+
+        self.recv_fallback()
+    }
+
+    fn recv_fallback(&mut self) -> Option<FasData> {
+        let topapp = self.windows_watcher.topapp_pids();
+        let config = &mut self.config;
+        self.analyzer_state.frame_sources.update(topapp, || {
+            topapp
+                .iter()
+                .filter_map(|pid| Some((*pid, get_process_name(*pid).ok()?)))
+                .find(|(_, pkg)| config.need_fas(pkg))
+        });
         self.analyzer_state
-            .analyzer
+            .frame_sources
             .recv_timeout(Duration::from_millis(100))
-            .map(|(pid, frametime)| FasData { pid, frametime })
+    }
+
+    // Broken frames never reach the buffer, a source that keeps delivering them is replaced,
+    // the analyzer by reinitializing its hooks
+    fn screen_frame(&mut self, data: &FasData) -> bool {
+        let usable = self.analyzer_state.frame_sources.screen(data.frametime);
+        if self.analyzer_state.frame_sources.take_restart_hook() {
+            self.restart_analyzer();
+        }
+        if !usable {
+            debug!("dropped broken frametime: {:?}", data.frametime);
+        }
+        usable
+    }
+
+    // A frame produced on time but presented late was held up by the compositor, higher
+    // cpu frequency wouldn't have helped, so only the production interval counts
+    fn filter_backpressure(&mut self, mut data: FasData) -> FasData {
+        if self.config.backpressure_detection()
+            && let Some(production) = data.production
+            && production < data.frametime
+        {
+            debug!(
+                "presented late: {:?}, produced in {production:?}",
+                data.frametime
+            );
+            data.frametime = production;
+        }
+        data
+    }
+
+    // A game between its process start and first frame is held at max frequency with raised io
+    // priority, the frame controller takes over from those frequencies once frames flow
+    fn update_launch_boost(&mut self) {
+        let limit = self.config.launch_boost();
+        let launching = if self.fas_state.buffer.is_none()
+            && self.fas_state.load_control.is_none()
+            && !limit.is_zero()
+        {
+            let config = &mut self.config;
+            self.windows_watcher
+                .topapp_pids()
+                .iter()
+                .filter(|pid| process_age(**pid).is_some_and(|age| age < limit))
+                .filter_map(|pid| Some((*pid, get_process_name(*pid).ok()?)))
+                .find(|(_, pkg)| config.need_fas(pkg) && config.game_type(pkg) != GameType::Stream)
+        } else {
+            None
+        };
+
+        if launching.as_ref().map(|(pid, _)| *pid) != self.launch_boost.pid() {
+            if let Some((pid, pkg)) = launching {
+                self.launch_boost.start(pid, pkg);
+            } else {
+                self.end_launch_boost();
+                return;
+            }
+        }
+
+        if self.launch_boost.pid().is_some() {
+            self.launch_boost.update();
+            self.controller_state.controller.hold_max_freq();
+        }
+    }
+
+    // Without frames to hand off to, the frequencies go back to the system
+    fn end_launch_boost(&mut self) {
+        if self.launch_boost.pid().is_none() {
+            return;
+        }
+
+        self.launch_boost.stop();
+        if self.fas_state.buffer.is_none() && self.fas_state.load_control.is_none() {
+            #[cfg(feature = "extension")]
+            self.controller_state
+                .controller
+                .init_default(&self.extension);
+            #[cfg(not(feature = "extension"))]
+            self.controller_state.controller.init_default();
+        }
+    }
+
+    // Keeps frequencies following load for a game no frame source works for, rather than
+    // leaving it to the system entirely
+    fn update_load_control(&mut self) {
+        let enabled = self.fas_state.buffer.is_none() && self.config.load_fallback();
+        let game = self
+            .analyzer_state
+            .frame_sources
+            .exhausted()
+            .filter(|_| enabled)
+            .cloned();
+
+        if game.as_ref().map(|(pid, _)| *pid) != self.fas_state.load_control {
+            if let Some((pid, pkg)) = &game {
+                info!("No frames from [{pkg}], following load instead");
+                #[cfg(feature = "extension")]
+                self.controller_state
+                    .controller
+                    .init_game(*pid, &self.extension);
+                #[cfg(not(feature = "extension"))]
+                self.controller_state.controller.init_game(*pid);
+            } else {
+                info!("Load control stopped");
+                #[cfg(feature = "extension")]
+                self.controller_state
+                    .controller
+                    .init_default(&self.extension);
+                #[cfg(not(feature = "extension"))]
+                self.controller_state.controller.init_default();
+            }
+            self.fas_state.load_control = game.as_ref().map(|(pid, _)| *pid);
+        }
+
+        if game.is_some() {
+            self.controller_state.controller.load_update_freq();
+        }
+    }
+
+    // Video players, readers and such get a capped max frequency, no frame monitoring
+    fn update_powersave_app(&mut self) {
+        let app = if self.fas_state.buffer.is_none() && self.fas_state.load_control.is_none() {
+            let config = &mut self.config;
+            self.windows_watcher
+                .topapp_pids()
+                .iter()
+                .filter_map(|pid| Some((*pid, get_process_name(*pid).ok()?)))
+                .find(|(_, pkg)| config.powersave_app(pkg))
+        } else {
+            None
+        };
+
+        if app.as_ref().map(|(pid, _)| *pid) != self.fas_state.powersave_app {
+            if let Some((_, pkg)) = &app {
+                info!("[{pkg}] is on the powersave list, capping frequencies");
+            } else if self.fas_state.load_control.is_none() {
+                #[cfg(feature = "extension")]
+                self.controller_state
+                    .controller
+                    .init_default(&self.extension);
+                #[cfg(not(feature = "extension"))]
+                self.controller_state.controller.init_default();
+            }
+            self.fas_state.powersave_app = app.as_ref().map(|(pid, _)| *pid);
+        }
+
+        if app.is_some() {
+            let cap = self.config.powersave_app_cap();
+            self.controller_state.controller.powersave_update_freq(cap);
+        }
     }
 
     fn update_analyzer(&mut self) -> Result<()> {
         for pid in self.windows_watcher.topapp_pids().iter().copied() {
             let pkg = get_process_name(pid)?;
             if self.config.need_fas(&pkg) {
-                self.analyzer_state.analyzer.attach_app(pid)?;
+                let excluded = self.config.hook_excluded(&pkg);
+                if !self.analyzer_state.hooks.track(pid, &pkg, excluded) {
+                    continue;
+                }
+
+                let result = self.analyzer_state.analyzer.as_mut().map_or_else(
+                    || Err(String::from("frame analyzer unavailable")),
+                    |analyzer| analyzer.attach_app(pid).map_err(|e| e.to_string()),
+                );
+                if let Err(e) = result {
+                    self.analyzer_state.hooks.failed(pid, e);
+                }
             }
         }
+
+        let hookless = self.analyzer_state.hooks.hookless();
+        self.analyzer_state.frame_sources.set_hookless(hookless);
         Ok(())
     }
 
@@ -257,7 +1054,10 @@ impl Looper {
             if self.analyzer_state.restart_timer.elapsed() >= Duration::from_secs(1) {
                 self.analyzer_state.restart_timer = Instant::now();
                 self.analyzer_state.restart_counter = 0;
-                self.analyzer_state.analyzer.detach_apps();
+                if let Some(analyzer) = self.analyzer_state.analyzer.as_mut() {
+                    analyzer.detach_apps();
+                }
+                self.analyzer_state.hooks.clear();
                 let _ = self.update_analyzer();
             }
         } else {
@@ -272,17 +1072,246 @@ impl Looper {
         }
 
         let (control, is_janked) = if let Some(buffer) = &self.fas_state.buffer {
-            let target_fps_offset = self
+            self.thread_scanner.update(buffer.package_info.pid);
+            if self.config.engine_presets() {
+                self.engine.update(buffer.package_info.pid);
+            }
+            self.rt_sched.update(
+                buffer.package_info.pid,
+                self.thread_scanner.tids(&[
+                    ThreadClass::Main,
+                    ThreadClass::Render,
+                    ThreadClass::Logic,
+                ]),
+                self.config.rt_sched(),
+                self.config.rt_priority(),
+            );
+            if self.config.game_cgroup() {
+                let uclamp_min = self.config.game_cgroup_uclamp_min();
+                self.game_cgroup.update(
+                    buffer.package_info.pid,
+                    uclamp_min,
+                    self.config.game_cgroup_cpus(),
+                );
+            } else {
+                self.game_cgroup.restore();
+            }
+            if self.config.adpf()
+                && let Some(target_fps) = buffer.target_fps_state.target_fps
+            {
+                self.adpf.update(
+                    self.thread_scanner
+                        .tids(&[ThreadClass::Main, ThreadClass::Render]),
+                    target_fps,
+                );
+            } else {
+                self.adpf.close();
+            }
+
+            let loading = self.config.loading_detection()
+                && buffer
+                    .target_fps_state
+                    .target_fps
+                    .is_some_and(|target_fps| {
+                        self.loading.update(
+                            buffer.package_info.pid,
+                            buffer.frametime_state.current_fps_short,
+                            target_fps,
+                        )
+                    });
+
+            let idle = if self.config.idle_scene() {
+                self.idle_scene.update(
+                    &buffer.frametime_state.frametimes,
+                    buffer.target_fps_state.target_fps,
+                    self.controller_state.controller.gpu_busy(),
+                )
+            } else {
+                self.idle_scene.reset();
+                false
+            };
+            // Lifted at once rather than ramped like the freq cap, the scene may need it now
+            self.controller_state
+                .controller
+                .set_idle_cap(if idle { IDLE_FREQ_CAP } else { 1.0 });
+            if !self.idle_scene.poll() {
+                return;
+            }
+
+            if !loading
+                && let Some(frametime) = buffer.frametime_state.frametimes.front().copied()
+                && let Some(target_fps) = buffer.target_fps_state.target_fps
+            {
+                self.controller_state
+                    .controller
+                    .report_frame(frametime, target_fps);
+                self.adpf.report(frametime);
+            }
+
+            let battery_rule = self
+                .battery
+                .capacity()
+                .and_then(|capacity| self.config.battery_rule(capacity));
+            let (fps_step, max_freq) =
+                battery_rule.map_or((0, 1.0), |rule| (rule.fps_step, rule.max_freq));
+            let battery_offset = buffer
+                .target_fps_state
+                .target_fps
+                .zip(buffer.lower_target_fps(fps_step))
+                .map_or(0.0, |(target_fps, lower)| lower - target_fps);
+            let hysteresis = self.config.thermal_fps_hysteresis();
+            let charging = self.battery.charging();
+            let battery_temp_rule =
+                self.therminal
+                    .battery_rule(self.config.battery_temp_rules(), charging, hysteresis);
+            let (battery_temp_step, battery_temp_max_freq) =
+                battery_temp_rule.map_or((0, 1.0), |rule| (rule.fps_step, rule.max_freq));
+            let mode_max_freq = self.config.mode_config(self.fas_state.mode).max_freq;
+            let stream_max_freq =
+                if self.config.game_type(&buffer.package_info.pkg) == GameType::Stream {
+                    STREAM_FREQ_CAP
+                } else {
+                    1.0
+                };
+            let budget = self.config.thermal_budget(&buffer.package_info.pkg);
+            let budget_max_freq = self.thermal_budget.update(
+                budget.as_ref(),
+                self.therminal.core_temperature(),
+                self.battery.power(),
+            );
+            self.controller_state.controller.set_freq_cap(
+                max_freq
+                    .min(mode_max_freq)
+                    .min(battery_temp_max_freq)
+                    .min(stream_max_freq)
+                    .min(budget_max_freq),
+            );
+
+            let thermal_offset = self
                 .therminal
                 .target_fps_offset(&mut self.config, self.fas_state.mode);
-            calculate_control(
+            // Stepped at once rather than smoothly, so heat degrades the game predictably
+            let thermal_steps = self
+                .therminal
+                .fps_steps(self.config.thermal_fps_steps(), hysteresis)
+                .max(battery_temp_step);
+            let thermal_step_offset = buffer
+                .target_fps_state
+                .target_fps
+                .zip(buffer.lower_target_fps(thermal_steps))
+                .map_or(0.0, |(target_fps, lower)| lower - target_fps);
+            let throttled =
+                thermal_offset < 0.0 || thermal_steps > 0 || battery_temp_max_freq < 1.0;
+            if throttled == self.fas_state.throttled {
+                self.fas_state.throttle_change = None;
+            } else if self
+                .fas_state
+                .throttle_change
+                .get_or_insert_with(Instant::now)
+                .elapsed()
+                >= THROTTLE_HOLD_TIME
+            {
+                self.fas_state.throttle_change = None;
+                self.fas_state.throttled = throttled;
+                info!("Thermal throttle: {throttled}");
+                if self.config.broadcast() {
+                    broadcast::throttle_changed(buffer.package_info.pkg.clone(), throttled);
+                }
+                if self.config.publish_props() {
+                    state_props::throttle_changed(throttled);
+                }
+            }
+            self.pacing
+                .set_mode(self.config.pacing_assist(&buffer.package_info.pkg));
+            let pacing_offset = buffer
+                .target_fps_state
+                .target_fps
+                .map_or(0.0, |target_fps| {
+                    let mut lower_targets: Vec<_> = (1..=8)
+                        .filter_map(|steps| buffer.lower_target_fps(steps))
+                        .filter(|fps| *fps < target_fps)
+                        .collect();
+                    lower_targets.dedup_by(|a, b| a.total_cmp(b).is_eq());
+                    self.pacing.update(
+                        &buffer.package_info.pkg,
+                        buffer.frametime_state.current_fps_long,
+                        target_fps,
+                        self.controller_state.controller.freq_saturated(),
+                        &lower_targets,
+                        self.refresh_rate.refresh_rate(),
+                    )
+                });
+            let target_fps_offset = thermal_offset
+                + thermal_step_offset
+                + pacing_offset
+                + self.battery.target_fps_offset(battery_offset);
+            let (control, is_janked) = calculate_control(
                 buffer,
                 &mut self.config,
                 self.fas_state.mode,
                 &mut self.controller_state,
                 target_fps_offset,
             )
-            .unwrap_or_default()
+            .unwrap_or_default();
+            // Presents slipping off vsync come before the fps drops, hold the frequency
+            // rather than lowering it into the miss
+            let control =
+                if self.config.phase_drift() && !is_janked && self.fas_state.vsync_phase.drifting()
+                {
+                    control.max(0)
+                } else {
+                    control
+                };
+            #[cfg(feature = "predictor")]
+            let control = if let Some(frametime) =
+                buffer.frametime_state.frametimes.front().copied()
+                && let Some(target_fps) = buffer.target_fps_state.target_fps
+            {
+                control
+                    + self.predictor.update(
+                        self.config.predictor(),
+                        &buffer.package_info.pkg,
+                        frametime,
+                        target_fps,
+                        self.controller_state.controller.fas_freq_ratio(),
+                        self.controller_state.params.kp,
+                    )
+            } else {
+                control
+            };
+
+            // Each visible game gets the frequency it needs, the busier demand wins
+            let mode = self.fas_state.mode;
+            let (control, is_janked) = self
+                .fas_state
+                .others
+                .iter()
+                .filter(|other| other.state.working_state == BufferWorkingState::Usable)
+                .filter_map(|other| {
+                    calculate_control(
+                        other,
+                        &mut self.config,
+                        mode,
+                        &mut self.controller_state,
+                        0.0,
+                    )
+                })
+                .fold((control, is_janked), |(control, is_janked), other| {
+                    (control.max(other.0), is_janked || other.1)
+                });
+
+            let reason = self
+                .config
+                .explain()
+                .then(|| explain_reason(buffer, target_fps_offset, control, is_janked, loading));
+            self.controller_state.controller.set_explain(reason);
+
+            // The game is waiting on asset reads, raising frequency wouldn't help it
+            if loading {
+                (control.min(0), false)
+            } else {
+                (control, is_janked)
+            }
         } else {
             return;
         };
@@ -290,28 +1319,413 @@ impl Looper {
         #[cfg(debug_assertions)]
         debug!("control: {control}khz");
 
+        if let Some(buffer) = &self.fas_state.buffer {
+            watchdog::record(
+                buffer
+                    .frametime_state
+                    .frametimes
+                    .front()
+                    .copied()
+                    .unwrap_or_default(),
+                buffer.target_fps_state.target_fps,
+                control,
+            );
+        }
+
+        // Only the built-in panel's refresh rate is ours to pin
+        if self.config.refresh_pin() && !self.refresh_rate.external_display() {
+            self.refresh_pin.update(is_janked);
+        } else {
+            self.refresh_pin.release();
+        }
+
+        #[cfg(feature = "extension")]
+        self.trigger_frame_window();
+
+        if self.cold_start_active() {
+            self.controller_state.controller.hold_max_freq();
+            return;
+        }
+
         self.controller_state
             .controller
             .fas_update_freq(control, is_janked);
+        if self.trace.active() {
+            let target_fps = self
+                .fas_state
+                .buffer
+                .as_ref()
+                .and_then(|buffer| buffer.target_fps_state.target_fps);
+            self.trace.decision(control, is_janked, target_fps);
+            self.trace
+                .freqs(&self.controller_state.controller.fas_freqs());
+        }
+
+        if let Some(buffer) = &self.fas_state.buffer
+            && self.config.resolution_hint(&buffer.package_info.pkg)
+        {
+            let freq_saturated = self.controller_state.controller.freq_saturated();
+            self.resolution_hint.update(is_janked, freq_saturated);
+        } else {
+            self.resolution_hint.reset();
+        }
+    }
+
+    // The engine set for the game in config, otherwise the detected one
+    fn engine_preset(&mut self) -> EnginePreset {
+        if !self.config.engine_presets() {
+            return EnginePreset::default();
+        }
+
+        self.fas_state
+            .buffer
+            .as_ref()
+            .and_then(|buffer| self.config.engine(&buffer.package_info.pkg))
+            .or_else(|| self.engine.engine())
+            .map(engine::preset)
+            .unwrap_or_default()
+    }
+
+    // The sub-profile named in the node, if the config defines it for the game on top. Session
+    // overrides from `tune` still win over its margin
+    fn update_sub_profile(&mut self) {
+        let name = self
+            .node
+            .get_sub_profile()
+            .or_else(|| self.fas_state.context_sub_profile.clone());
+        let sub_profile = self.fas_state.buffer.as_ref().and_then(|buffer| {
+            self.config
+                .sub_profile(&buffer.package_info.pkg, name.as_deref()?)
+        });
+
+        let active = sub_profile
+            .as_ref()
+            .map(|sub_profile| sub_profile.name.clone());
+        if active != self.fas_state.sub_profile {
+            info!(
+                "Sub-profile switched to {}",
+                active.as_deref().unwrap_or("default")
+            );
+            self.fas_state.sub_profile = active;
+        }
+
+        if let Some(buffer) = self.fas_state.buffer.as_mut() {
+            buffer.set_target_fps_override(
+                sub_profile
+                    .as_ref()
+                    .and_then(|sub_profile| sub_profile.target_fps.clone()),
+            );
+        }
+        let params = &mut self.controller_state.params;
+        params.margin_fps = params
+            .margin_fps
+            .or_else(|| sub_profile.and_then(|sub_profile| sub_profile.margin_fps));
+    }
+
+    fn stream_game(&mut self) -> bool {
+        self.fas_state.buffer.as_ref().is_some_and(|buffer| {
+            self.config.game_type(&buffer.package_info.pkg) == GameType::Stream
+        })
+    }
+
+    // Shader compilation and loading go faster at max frequency, until the window is over or
+    // the game already runs at its target
+    fn cold_start_active(&mut self) -> bool {
+        let Some(since) = self.fas_state.cold_start else {
+            return false;
+        };
+
+        let stable = self.fas_state.buffer.as_ref().is_some_and(|buffer| {
+            buffer.frametime_state.frametimes.len() >= 60
+                && buffer
+                    .target_fps_state
+                    .target_fps
+                    .is_some_and(|target_fps| {
+                        buffer.frametime_state.current_fps_long >= target_fps - 2.0
+                    })
+        });
+        if since.elapsed() < self.config.cold_start_boost() && !stable {
+            return true;
+        }
+
+        info!("Cold start boost over after {:?}", since.elapsed());
+        self.fas_state.cold_start = None;
+        false
+    }
+
+    #[cfg(feature = "extension")]
+    fn trigger_frame_window(&mut self) {
+        if self.frame_window_timer.elapsed() < FRAME_WINDOW_TIME {
+            return;
+        }
+        self.frame_window_timer = Instant::now();
+
+        if let Some(buffer) = &self.fas_state.buffer {
+            let frametime_state = &buffer.frametime_state;
+            let stats = FrameWindowStats {
+                fps: frametime_state.current_fps_long,
+                avg_frametime_ms: frametime_state.avg_time_long.as_secs_f64() * 1000.0,
+                max_frametime_ms: frametime_state
+                    .frametimes
+                    .iter()
+                    .max()
+                    .copied()
+                    .unwrap_or_default()
+                    .as_secs_f64()
+                    * 1000.0,
+                target_fps: buffer
+                    .target_fps_state
+                    .target_fps
+                    .map(|target_fps| target_fps.round() as u32),
+                frametime_ms: frametime_state
+                    .frametimes
+                    .front()
+                    .copied()
+                    .unwrap_or_default()
+                    .as_secs_f64()
+                    * 1000.0,
+                filtered_frametime_ms: frametime_state.filtered_frametime.as_secs_f64() * 1000.0,
+            };
+            trigger_frame_window(&self.extension, stats);
+        }
+    }
+
+    fn dump(&mut self) -> String {
+        let status = STATUS.lock();
+        let verify_mismatches = status
+            .verify_mismatches
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let sections = [
+            (
+                "version",
+                format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            ),
+            (
+                "status",
+                format!(
+                    "{}\nworking state: {:?}\nloop: {:.1}/s, latency {:.2}ms",
+                    status.snapshot(),
+                    self.fas_state.working_state,
+                    status.loop_rate,
+                    status.loop_latency.as_secs_f64() * 1000.0
+                ),
+            ),
+            (
+                "environment",
+                root_env::get().map(RootEnv::describe).unwrap_or_default(),
+            ),
+            ("policies", self.controller_state.controller.describe()),
+            ("self test", self_test::report().to_string()),
+            ("frame source", self.analyzer_state.frame_sources.describe()),
+            ("threads", status.threads()),
+            ("frame hooks", status.hooks()),
+            ("failing nodes", status.write_failures()),
+            ("verify mismatches", verify_mismatches),
+            ("verify success", status.verify_success()),
+            ("recent errors", watchdog::errors()),
+            ("vendor perf props", vendor_perf_props()),
+            (
+                "controller decisions (frametime ms, target fps, control khz)",
+                watchdog::history(),
+            ),
+            ("config", self.config.dump()),
+        ];
+        drop(status);
+
+        sections
+            .iter()
+            .map(|(title, body)| format!("== {title} ==\n{}\n", body.trim_end()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // The controller's internal state as json, for reproducing weird behavior from reports
+    fn state_json(&self) -> String {
+        #[derive(Serialize)]
+        struct State<'a> {
+            version: String,
+            working_state: String,
+            mode: String,
+            params: Params,
+            buffer: Option<BufferJson<'a>>,
+            others: Vec<BufferJson<'a>>,
+            controller: ControllerJson<'a>,
+        }
+
+        // down_dwell is in milliseconds
+        #[derive(Serialize)]
+        struct Params {
+            kp: f64,
+            ramp_up: f64,
+            ramp_down: f64,
+            down_dwell: f64,
+            margin_fps: Option<f64>,
+            target_fps_offset: f64,
+        }
+
+        let params = &self.controller_state.params;
+        let state = State {
+            version: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            working_state: format!("{:?}", self.fas_state.working_state),
+            mode: self.fas_state.mode.to_string(),
+            params: Params {
+                kp: params.kp,
+                ramp_up: params.ramp_up,
+                ramp_down: params.ramp_down,
+                down_dwell: params.down_dwell.as_secs_f64() * 1000.0,
+                margin_fps: params.margin_fps,
+                target_fps_offset: self.controller_state.target_fps_offset,
+            },
+            buffer: self.fas_state.buffer.as_ref().map(Buffer::state_json),
+            others: self
+                .fas_state
+                .others
+                .iter()
+                .map(Buffer::state_json)
+                .collect(),
+            controller: self.controller_state.controller.state_json(),
+        };
+
+        serde_json::to_string(&state).unwrap_or_default()
+    }
+
+    fn update_status(&mut self) {
+        if self.status_timer.elapsed() < STATUS_UPDATE_TIME {
+            return;
+        }
+        self.status_timer = Instant::now();
+
+        if self.fas_state.working_state != State::Working {
+            self.therminal.temperature_update();
+        }
+
+        let mut status = STATUS.lock();
+        status.mode = self.fas_state.mode;
+        status.temperature = self.therminal.core_temperature();
+        status.battery_temperature = self.therminal.battery_temperature();
+        self.analyzer_state
+            .hooks
+            .update(self.windows_watcher.topapp_pids());
+        status.hooks = self.analyzer_state.hooks.describe();
+        status.sub_profile.clone_from(&self.fas_state.sub_profile);
+        status.frame_source_health = self
+            .analyzer_state
+            .frame_sources
+            .health(self.fas_state.buffer.is_some())
+            .to_string();
+        status.charging = self.battery.charging();
+        status.power = self.battery.sample_power();
+        status.loop_rate = self.ticker.rate();
+        status.loop_latency = self.ticker.latency();
+
+        if let Some(buffer) = &self.fas_state.buffer
+            && self.fas_state.working_state == State::Working
+        {
+            let mut frametimes: Vec<_> =
+                buffer.frametime_state.frametimes.iter().copied().collect();
+            frametimes.sort_unstable();
+            let p99_index = (frametimes.len() * 99).div_ceil(100).saturating_sub(1);
+
+            status.game = Some(buffer.package_info.pkg.clone());
+            status.fps = buffer.frametime_state.current_fps_long;
+            self.fas_state.session_stats.sample(status.fps);
+            status.p99_frametime = frametimes.get(p99_index).copied().unwrap_or_default();
+            status.frametime = buffer
+                .frametime_state
+                .frametimes
+                .front()
+                .copied()
+                .unwrap_or_default();
+            status.filtered_frametime = buffer.frametime_state.filtered_frametime;
+            status.threads = self
+                .thread_scanner
+                .threads()
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+        } else {
+            status.game = None;
+            status.fps = 0.0;
+            status.p99_frametime = Duration::ZERO;
+            status.frametime = Duration::ZERO;
+            status.filtered_frametime = Duration::ZERO;
+            status.threads.clear();
+        }
+    }
+
+    // In split-screen or next to a freeform window, fas stays on the game as long as it's
+    // visible and still producing frames
+    fn game_on_screen(&mut self) -> bool {
+        self.fas_state.buffer.as_ref().is_some_and(|buffer| {
+            buffer.state.last_update.elapsed() < Duration::from_secs(1)
+                && self.windows_watcher.visible(buffer.package_info.pid)
+        })
     }
 
     pub fn retain_topapp(&mut self) {
         if let Some(buffer) = self.fas_state.buffer.as_ref()
-            && !self
-                .windows_watcher
-                .topapp_pids()
-                .contains(&buffer.package_info.pid)
+            && !self.windows_watcher.visible(buffer.package_info.pid)
         {
-            let _ = self
-                .analyzer_state
-                .analyzer
-                .detach_app(buffer.package_info.pid);
+            if let Some(analyzer) = self.analyzer_state.analyzer.as_mut() {
+                let _ = analyzer.detach_app(buffer.package_info.pid);
+            }
+            // Only a crash or an ANR the system recorded for the game counts, quitting from
+            // the game's menu also ends the process while it's on top
+            let crashed = !process_exists(buffer.package_info.pid)
+                && exited_abnormally(&buffer.package_info.pkg, buffer.package_info.pid);
+            let mut freqs = Vec::new();
+            if self.fas_state.working_state == State::Working {
+                freqs = self.controller_state.controller.fas_freqs();
+                // Before end_session, so a crash rolls these back too
+                if let Some(freqs) = self.controller_state.controller.take_steady_freqs() {
+                    self.learned.set(
+                        &buffer.package_info.pkg,
+                        STEADY_FREQS_KEY,
+                        steady_freqs_value(freqs),
+                    );
+                }
+                self.learned.end_session(&buffer.package_info.pkg, crashed);
+            }
             #[cfg(feature = "extension")]
             {
                 let pkg = buffer.package_info.pkg.clone();
-                trigger_unload_fas(&self.extension, buffer.package_info.pid, pkg);
+                trigger_unload_fas(&self.extension, buffer.package_info.pid, pkg.clone());
+                trigger_game_exit(&self.extension, pkg);
+            }
+            let buffer = self.fas_state.buffer.take().unwrap();
+            self.fas_state.resume_freqs = None;
+            if !crashed {
+                debug!(
+                    "[{}] sent to background, on standby",
+                    buffer.package_info.pkg
+                );
+                self.fas_state.standby = Some(Standby { buffer, freqs });
+            }
+        }
+
+        let windows_watcher = &mut self.windows_watcher;
+        self.fas_state.others.retain(|other| {
+            let visible = windows_watcher.visible(other.package_info.pid);
+            if !visible {
+                info!("[{}] no longer visible", other.package_info.pkg);
+                #[cfg(feature = "extension")]
+                trigger_unload_fas(
+                    &self.extension,
+                    other.package_info.pid,
+                    other.package_info.pkg.clone(),
+                );
             }
-            self.fas_state.buffer = None;
+            visible
+        });
+
+        if let Some(standby) = self.fas_state.standby.as_ref()
+            && !process_exists(standby.buffer.package_info.pid)
+        {
+            debug!("[{}] killed in background", standby.buffer.package_info.pkg);
+            self.fas_state.standby = None;
         }
 
         if self.fas_state.buffer.is_none() {
@@ -325,7 +1739,97 @@ impl Looper {
         match self.fas_state.working_state {
             State::Working => {
                 self.fas_state.working_state = State::NotWorking;
+                // Also when the option was turned off meanwhile
+                notification::cancel();
                 self.cleaner.undo_cleanup();
+                self.refresh_pin.release();
+                self.display_refresh.restore();
+                self.game_props.restore();
+                self.resolution_hint.reset();
+                self.io_tuner.restore();
+                self.background_throttler.restore();
+                self.compositor_boost.restore();
+                self.memory_tuner.restore();
+                self.dma_latency.release();
+                self.loading.reset();
+                self.idle_scene.reset();
+                self.controller_state.controller.set_idle_cap(1.0);
+                self.fas_state.cold_start = None;
+                self.fas_state.throttled = false;
+                self.fas_state.throttle_change = None;
+                self.thermal_budget.reset();
+                self.pacing.reset();
+                self.fas_state.resume_freqs = None;
+                self.controller_state.controller.set_gpu_profile(None);
+                control::clear_tuning();
+                #[cfg(feature = "predictor")]
+                self.predictor.reset();
+                self.rt_sched.restore();
+                self.game_cgroup.restore();
+                self.adpf.close();
+                self.thread_scanner.reset();
+                self.engine.reset();
+                let power_session = self.battery.end_session();
+                let phase_alignment = self.fas_state.vsync_phase.take_alignment();
+                let sub_profile = self.fas_state.sub_profile.take();
+                self.fas_state.context_mode = None;
+                self.fas_state.context_sub_profile = None;
+                self.fas_state.session_node_mode = None;
+                if let Some((pkg, target_fps)) = self.fas_state.hook_args.take() {
+                    if let Some(freqs) = self.controller_state.controller.take_steady_freqs() {
+                        self.learned
+                            .set(&pkg, STEADY_FREQS_KEY, steady_freqs_value(freqs));
+                    }
+                    if self.config.broadcast() {
+                        broadcast::disengaged(pkg.clone());
+                    }
+                    if self.config.publish_props() {
+                        state_props::disengaged();
+                    }
+                    let (cpu_bound, gpu_bound) =
+                        self.controller_state.controller.take_jank_attribution();
+                    info!(
+                        "Jank attribution: {pkg}, cpu bound: {cpu_bound}, gpu bound: {gpu_bound}"
+                    );
+                    let cluster_energy = self.controller_state.controller.take_cluster_energy();
+                    if !cluster_energy.is_empty() {
+                        info!(
+                            "Cluster energy: {pkg}, {}",
+                            telemetry::describe_energy(&cluster_energy)
+                        );
+                    }
+                    self.trace.finish(&pkg);
+                    let fps = self.fas_state.session_stats.take_fps();
+                    if let Some((duration, power)) = power_session {
+                        info!(
+                            "Session summary: {pkg}, {}s, average fps: {fps:.1}, average power: {}, vsync alignment: {}",
+                            duration.as_secs(),
+                            power.map_or_else(
+                                || "unknown".to_string(),
+                                |power| format!("{power:.2}W")
+                            ),
+                            phase_alignment.map_or_else(
+                                || "unknown".to_string(),
+                                |alignment| format!("{:.0}%", alignment * 100.0)
+                            )
+                        );
+                        let record = Record {
+                            pkg: pkg.clone(),
+                            duration,
+                            fps,
+                            target_fps,
+                            power,
+                            cpu_bound,
+                            gpu_bound,
+                            phase_alignment,
+                            sub_profile,
+                            cluster_energy,
+                        };
+                        telemetry::append(&record, self.config.telemetry_retention());
+                    }
+                    run_hook(self.config.stop_script(), pkg, target_fps);
+                }
+                self.trace.reset();
                 #[cfg(feature = "extension")]
                 self.controller_state
                     .controller
@@ -349,60 +1853,320 @@ impl Looper {
                 trigger_start_fas(&self.extension);
             }
             State::Waiting => {
-                if self.fas_state.delay_timer.elapsed() > DELAY_TIME {
+                if self.fas_state.delay_timer.elapsed() > DELAY_TIME
+                    || self.fas_state.resume_freqs.is_some()
+                {
                     self.fas_state.working_state = State::Working;
                     self.cleaner.cleanup();
                     self.controller_state.target_fps_offset = 0.0;
+                    self.apply_context_rule();
+                    let buffer = self.fas_state.buffer.as_ref().unwrap();
+                    self.learned.begin_session(&buffer.package_info.pkg);
+                    self.battery.begin_session();
+                    if self.config.io_tune() {
+                        self.io_tuner.apply();
+                    }
+                    self.background_throttler.apply(
+                        self.config.background_throttle(),
+                        self.config.background_throttle_limit(),
+                    );
+                    if self.config.compositor_boost() {
+                        self.compositor_boost.apply(self.config.compositor_cpus());
+                    }
+                    if self.config.memory_tune(&buffer.package_info.pkg) {
+                        self.memory_tuner.apply();
+                    }
+                    self.dma_latency.apply(self.config.dma_latency());
+                    if self.config.trace_export() {
+                        self.trace.begin();
+                    }
+                    if let Some(refresh_rate) =
+                        self.config.game_refresh_rate(&buffer.package_info.pkg)
+                        && !self.refresh_rate.external_display()
+                    {
+                        self.display_refresh.apply(refresh_rate);
+                    }
+                    self.game_props
+                        .apply(&self.config.game_props(&buffer.package_info.pkg));
+                    let hook_args = (
+                        buffer.package_info.pkg.clone(),
+                        buffer.target_fps_state.target_fps,
+                    );
+                    run_hook(self.config.start_script(), hook_args.0.clone(), hook_args.1);
+                    if self.config.notification() {
+                        notification::post_engaged(
+                            hook_args.0.clone(),
+                            hook_args.1,
+                            self.fas_state.mode,
+                        );
+                    }
+                    if self.config.broadcast() {
+                        broadcast::engaged(hook_args.0.clone(), hook_args.1, self.fas_state.mode);
+                    }
+                    if self.config.publish_props() {
+                        state_props::engaged(hook_args.0.clone(), hook_args.1, self.fas_state.mode);
+                    }
+                    self.fas_state.hook_args = Some(hook_args);
                     #[cfg(not(feature = "extension"))]
                     self.controller_state
                         .controller
                         .init_game(self.fas_state.buffer.as_ref().unwrap().package_info.pid);
                     #[cfg(feature = "extension")]
-                    self.controller_state.controller.init_game(
-                        self.fas_state.buffer.as_ref().unwrap().package_info.pid,
-                        &self.extension,
-                    );
+                    {
+                        let buffer = self.fas_state.buffer.as_ref().unwrap();
+                        self.controller_state
+                            .controller
+                            .init_game(buffer.package_info.pid, &self.extension);
+                        trigger_game_start(
+                            &self.extension,
+                            buffer.package_info.pkg.clone(),
+                            buffer
+                                .target_fps_state
+                                .target_fps
+                                .map(|target_fps| target_fps.round() as u32),
+                        );
+                    }
+                    let gpu_profile = self
+                        .config
+                        .gpu_profile(&self.fas_state.buffer.as_ref().unwrap().package_info.pkg);
+                    self.controller_state
+                        .controller
+                        .set_gpu_profile(gpu_profile);
+                    if let Some(freqs) = self.fas_state.resume_freqs.take() {
+                        self.controller_state.controller.seed_freqs(&freqs);
+                    } else {
+                        self.seed_steady_freqs();
+                    }
+
+                    let buffer = self.fas_state.buffer.as_ref().unwrap();
+                    if !self.config.cold_start_boost().is_zero()
+                        && self.config.game_type(&buffer.package_info.pkg) != GameType::Stream
+                        && process_age(buffer.package_info.pid)
+                            .is_some_and(|age| age < COLD_START_AGE)
+                    {
+                        info!(
+                            "[{}] newly launched, holding max frequency",
+                            buffer.package_info.pkg
+                        );
+                        self.fas_state.cold_start = Some(Instant::now());
+                    }
                 }
             }
             State::Working => (),
         }
     }
 
+    // Starts where the game settled last time instead of the top frequency, skipping the
+    // warm-up where the device runs hotter than it needs to
+    fn seed_steady_freqs(&mut self) {
+        let Some(buffer) = self.fas_state.buffer.as_ref() else {
+            return;
+        };
+        let Some(table) = self
+            .learned
+            .get(&buffer.package_info.pkg, STEADY_FREQS_KEY)
+            .and_then(toml::Value::as_table)
+        else {
+            return;
+        };
+
+        let freqs: Vec<_> = table
+            .iter()
+            .filter_map(|(policy, freq)| Some((policy.parse().ok()?, freq.as_integer()? as isize)))
+            .collect();
+        info!(
+            "[{}] starting from learned frequencies: {freqs:?}",
+            buffer.package_info.pkg
+        );
+        self.controller_state.controller.seed_freqs(&freqs);
+    }
+
     pub fn buffer_update(&mut self, data: &FasData) -> Option<BufferWorkingState> {
-        if unlikely(
-            !self.windows_watcher.topapp_pids().contains(&data.pid) || data.frametime.is_zero(),
-        ) {
+        if unlikely(!self.windows_watcher.visible(data.pid) || data.frametime.is_zero()) {
             return None;
         }
 
         let pid = data.pid;
         let frametime = data.frametime;
 
-        if let Some(buffer) = self.fas_state.buffer.as_mut() {
+        if let Some(buffer) = self.fas_state.buffer.as_mut()
+            && buffer.package_info.pid == pid
+        {
+            buffer.frametime_state.low_confidence = data.low_confidence;
             #[cfg(feature = "extension")]
             buffer.push_frametime(frametime, &self.extension);
             #[cfg(not(feature = "extension"))]
             buffer.push_frametime(frametime);
+            self.fas_state.vsync_phase.push(
+                data.present.unwrap_or_else(vsync_phase::now),
+                self.refresh_rate.refresh_rate(),
+            );
             Some(buffer.state.working_state)
-        } else {
-            let Ok(pkg) = get_process_name(data.pid) else {
-                return None;
-            };
-            let target_fps = self.config.target_fps(&pkg)?;
+        } else if self.fas_state.buffer.is_some() {
+            // Frames of the other games only feed their buffers, the main game paces the policy
+            if let Some(other) = self
+                .fas_state
+                .others
+                .iter_mut()
+                .find(|other| other.package_info.pid == pid)
+            {
+                other.frametime_state.low_confidence = data.low_confidence;
+                #[cfg(feature = "extension")]
+                other.push_frametime(frametime, &self.extension);
+                #[cfg(not(feature = "extension"))]
+                other.push_frametime(frametime);
+            } else if let Some(other) = self.new_buffer(data) {
+                info!("[{}] visible next to the main game", other.package_info.pkg);
+                self.fas_state.others.push(other);
+            }
+            None
+        } else if let Some(mut standby) = self
+            .fas_state
+            .standby
+            .take_if(|standby| standby.buffer.package_info.pid == pid)
+        {
+            info!("[{}] back from standby", standby.buffer.package_info.pkg);
+            #[cfg(feature = "extension")]
+            trigger_load_fas(
+                &self.extension,
+                pid,
+                standby.buffer.package_info.pkg.clone(),
+            );
 
-            info!("New fas buffer on: [{pkg}]");
+            standby.buffer.frametime_state.low_confidence = data.low_confidence;
             #[cfg(feature = "extension")]
-            trigger_load_fas(&self.extension, pid, pkg.clone());
+            standby.buffer.push_frametime(frametime, &self.extension);
+            #[cfg(not(feature = "extension"))]
+            standby.buffer.push_frametime(frametime);
 
-            let mut buffer = Buffer::new(target_fps, pid, pkg);
+            let working_state = standby.buffer.state.working_state;
+            self.fas_state.buffer = Some(standby.buffer);
+            if !standby.freqs.is_empty() {
+                self.fas_state.resume_freqs = Some(standby.freqs);
+            }
+
+            Some(working_state)
+        } else if let Some(index) = self
+            .fas_state
+            .others
+            .iter()
+            .position(|other| other.package_info.pid == pid)
+        {
+            // The main game left, the one still on screen takes over
+            let mut buffer = self.fas_state.others.remove(index);
+            info!("[{}] is the main game now", buffer.package_info.pkg);
+            buffer.frametime_state.low_confidence = data.low_confidence;
             #[cfg(feature = "extension")]
             buffer.push_frametime(frametime, &self.extension);
             #[cfg(not(feature = "extension"))]
             buffer.push_frametime(frametime);
 
+            let working_state = buffer.state.working_state;
+            self.fas_state.buffer = Some(buffer);
+
+            Some(working_state)
+        } else {
+            let buffer = self.new_buffer(data)?;
             self.fas_state.buffer = Some(buffer);
 
             Some(BufferWorkingState::Unusable)
         }
     }
+
+    fn new_buffer(&mut self, data: &FasData) -> Option<Buffer> {
+        let pid = data.pid;
+        let Ok(pkg) = get_process_name(pid) else {
+            return None;
+        };
+        let mut target_fps = self.config.target_fps(&pkg)?;
+        let auto_target_fps = self.config.auto_target_fps(&pkg);
+        let calibrated = Learned::load()
+            .get(&pkg, CALIBRATED_FPS_KEY)
+            .and_then(toml::Value::as_integer)
+            .filter(|_| auto_target_fps);
+        if let Some(calibrated) = calibrated {
+            info!("[{pkg}] using calibrated target fps: {calibrated}");
+            target_fps = TargetFps::Value(calibrated as f64);
+        }
+
+        info!("New fas buffer on: [{pkg}]");
+        #[cfg(feature = "extension")]
+        trigger_load_fas(&self.extension, pid, pkg.clone());
+
+        let mut buffer = Buffer::new(target_fps, pid, pkg);
+        if auto_target_fps && calibrated.is_none() {
+            buffer.start_calibration();
+        }
+        buffer.frametime_state.low_confidence = data.low_confidence;
+        #[cfg(feature = "extension")]
+        buffer.push_frametime(data.frametime, &self.extension);
+        #[cfg(not(feature = "extension"))]
+        buffer.push_frametime(data.frametime);
+
+        Some(buffer)
+    }
+}
+
+// Props of vendor perf daemons, which may fight fas over the same nodes
+fn vendor_perf_props() -> String {
+    [
+        "ro.vendor.perf",
+        "vendor.perf",
+        "persist.vendor.perf",
+        "ro.vendor.qti.perf",
+    ]
+    .iter()
+    .flat_map(|prefix| props::list(prefix))
+    .map(|(name, value)| format!("{name}={value}\n"))
+    .collect()
+}
+
+// The frame side of a frequency decision, for explain mode
+fn explain_reason(
+    buffer: &Buffer,
+    target_fps_offset: f64,
+    control: isize,
+    is_janked: bool,
+    loading: bool,
+) -> String {
+    let frametime_state = &buffer.frametime_state;
+    let frametime = frametime_state
+        .frametimes
+        .front()
+        .copied()
+        .unwrap_or_default();
+    let filtered = frametime_state.filtered_frametime;
+    let target_fps = buffer
+        .target_fps_state
+        .target_fps
+        .map(|target_fps| (target_fps + target_fps_offset).max(1.0));
+    let error = target_fps.map_or_else(
+        || "unknown".to_string(),
+        |target_fps| {
+            format!(
+                "{:+.2}ms",
+                (filtered.as_secs_f64() - 1.0 / target_fps) * 1000.0
+            )
+        },
+    );
+
+    let mut reason = format!(
+        "frametime {:.2}ms, filtered {:.2}ms, target {} (offset {target_fps_offset:+.1}), error {error}, control {control}khz, janked {is_janked}",
+        frametime.as_secs_f64() * 1000.0,
+        filtered.as_secs_f64() * 1000.0,
+        target_fps.map_or_else(|| "unknown".to_string(), |fps| format!("{fps:.1}fps")),
+    );
+    if loading {
+        reason.push_str(", loading");
+    }
+    reason
+}
+
+fn steady_freqs_value(freqs: Vec<(i32, isize)>) -> toml::Value {
+    toml::Value::Table(
+        freqs
+            .into_iter()
+            .map(|(policy, freq)| (policy.to_string(), toml::Value::Integer(freq as i64)))
+            .collect(),
+    )
 }
@@ -21,7 +21,11 @@ use likely_stable::unlikely;
 use log::debug;
 
 use super::super::buffer::Buffer;
-use crate::framework::{config::MarginFps, prelude::*, scheduler::looper::ControllerState};
+use crate::framework::{
+    config::{FrametimeTarget, MarginFps},
+    prelude::*,
+    scheduler::looper::ControllerState,
+};
 
 pub fn calculate_control(
     buffer: &Buffer,
@@ -35,20 +39,26 @@ pub fn calculate_control(
         return None;
     }
 
-    let target_fps = f64::from(buffer.target_fps_state.target_fps?);
-    let margin_fps: f64 = match &config.mode_config(mode).margin_fps {
-        MarginFps::BaseOnly(base) => target_fps / 60.0 * f64::from(*base),
-        MarginFps::Advanced { base, overrides } => overrides
-            .get(&target_fps.to_string())
-            .copied()
-            .map_or_else(|| target_fps / 60.0 * f64::from(*base), f64::from),
+    let target_fps = buffer.target_fps_state.target_fps?;
+    let margin_fps: f64 = if let Some(margin_fps) = controller_state.params.margin_fps {
+        margin_fps
+    } else {
+        match &config.mode_config(mode).margin_fps {
+            MarginFps::BaseOnly(base) => target_fps / 60.0 * f64::from(*base),
+            MarginFps::Advanced { base, overrides } => overrides
+                .get(&target_fps.to_string())
+                .copied()
+                .map_or_else(|| target_fps / 60.0 * f64::from(*base), f64::from),
+        }
     };
 
     assert!(margin_fps.is_sign_positive(), "margin_fps must be positive");
 
     let target_fps = (target_fps + target_fps_offset_thermal).clamp(0.0, target_fps);
     let adjusted_target_fps = adjust_target_fps(target_fps, controller_state) - margin_fps;
-    let adjusted_last_frame = get_normalized_last_frame(buffer, adjusted_target_fps);
+    let (frametime_target, window) = config.frametime_target(&buffer.package_info.pkg);
+    let adjusted_last_frame =
+        get_normalized_last_frame(buffer, frametime_target, window, adjusted_target_fps);
     let target_frametime = Duration::from_secs(1);
 
     debug!("adjusted_target_fps: {adjusted_target_fps}");
@@ -61,13 +71,13 @@ pub fn calculate_control(
     ))
 }
 
-fn get_normalized_last_frame(buffer: &Buffer, target_fps: f64) -> Duration {
-    let last_frame = buffer
-        .frametime_state
-        .frametimes
-        .front()
-        .copied()
-        .unwrap_or_default();
+fn get_normalized_last_frame(
+    buffer: &Buffer,
+    frametime_target: FrametimeTarget,
+    window: usize,
+    target_fps: f64,
+) -> Duration {
+    let last_frame = buffer.target_frametime(frametime_target, window);
 
     if buffer.frametime_state.additional_frametime == Duration::ZERO {
         last_frame
@@ -96,12 +106,20 @@ fn adjust_target_fps(target_fps: f64, controller_state: &mut ControllerState) ->
 }
 
 fn calculate_control_inner(
-    controller_state: &ControllerState,
+    controller_state: &mut ControllerState,
     current_frametime: Duration,
     target_frametime: Duration,
 ) -> isize {
-    let error_p = (current_frametime.as_nanos() as f64 - target_frametime.as_nanos() as f64)
-        * controller_state.params.kp;
+    let params = controller_state.params;
+    let error = current_frametime.as_nanos() as f64 - target_frametime.as_nanos() as f64;
+    let error_p = if error >= 0.0 {
+        controller_state.upscale_timer = Instant::now();
+        error * params.kp * params.ramp_up
+    } else if controller_state.upscale_timer.elapsed() < params.down_dwell {
+        0.0
+    } else {
+        error * params.kp * params.ramp_down
+    };
 
     debug!("error_p {error_p}");
 
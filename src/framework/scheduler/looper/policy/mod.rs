@@ -16,14 +16,32 @@
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
 pub mod controll;
+#[cfg(feature = "predictor")]
+pub mod predictor;
+
+use std::time::Duration;
 
 #[derive(Debug, Copy, Clone)]
 pub struct ControllerParams {
     pub kp: f64,
+    // Scales kp when raising frequency
+    pub ramp_up: f64,
+    // Scales kp when lowering frequency
+    pub ramp_down: f64,
+    // How long no raise must be requested before lowering is allowed
+    pub down_dwell: Duration,
+    // Wins over the mode's margin_fps when tuned live
+    pub margin_fps: Option<f64>,
 }
 
 impl Default for ControllerParams {
     fn default() -> Self {
-        Self { kp: 0.000_3 }
+        Self {
+            kp: 0.000_3,
+            ramp_up: 1.0,
+            ramp_down: 1.0,
+            down_dwell: Duration::ZERO,
+            margin_fps: None,
+        }
     }
 }
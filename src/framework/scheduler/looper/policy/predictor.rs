@@ -0,0 +1,304 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::framework::{
+    config::PredictorMode,
+    error::{Error, Result},
+};
+
+const MODEL_PATH: &str = "/data/adb/fas_rs/predictor.toml";
+const TRACE_DIR: &str = "/data/adb/fas_rs/traces";
+// Recent frametimes the model looks at, newest first
+const HISTORY: usize = 6;
+// The history, the frequency ratio and a bias
+const FEATURES: usize = HISTORY + 2;
+// Share of an anticipated spike acted on ahead of time, a wrong guess only costs power
+const FEED_FORWARD_GAIN: f64 = 0.5;
+// Keeps the fit stable when traces barely vary
+const RIDGE: f64 = 1e-3;
+
+// Linear model over recent normalized frametimes (frametime * target fps, 1.0 is on time)
+// and the fas frequency ratio, predicting the next frametime
+#[derive(Debug, Serialize, Deserialize)]
+struct Model {
+    weights: Vec<f64>,
+}
+
+impl Model {
+    fn load() -> Option<Self> {
+        let model: Self = toml::from_str(&fs::read_to_string(MODEL_PATH).ok()?).ok()?;
+        (model.weights.len() == FEATURES).then_some(model)
+    }
+
+    fn predict(&self, features: &[f64; FEATURES]) -> f64 {
+        self.weights
+            .iter()
+            .zip(features)
+            .map(|(weight, feature)| weight * feature)
+            .sum()
+    }
+}
+
+// Anticipates load spikes a frame or two ahead from a model trained on recorded traces,
+// and records those traces
+#[derive(Debug)]
+pub struct Predictor {
+    model: Option<Model>,
+    model_loaded: bool,
+    history: VecDeque<f64>,
+    trace: Option<(String, BufWriter<File>)>,
+}
+
+impl Predictor {
+    pub fn new() -> Self {
+        Self {
+            model: None,
+            model_loaded: false,
+            history: VecDeque::with_capacity(HISTORY + 1),
+            trace: None,
+        }
+    }
+
+    // Returns the feed-forward control in khz, added to what the controller asks for
+    pub fn update(
+        &mut self,
+        mode: PredictorMode,
+        pkg: &str,
+        frametime: Duration,
+        target_fps: f64,
+        freq_ratio: f64,
+        kp: f64,
+    ) -> isize {
+        match mode {
+            PredictorMode::Off => {
+                self.reset();
+                0
+            }
+            PredictorMode::Record => {
+                self.record(pkg, frametime, target_fps, freq_ratio);
+                0
+            }
+            PredictorMode::On => self.feed_forward(frametime, target_fps, freq_ratio, kp),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.history.clear();
+        if let Some((_, mut trace)) = self.trace.take() {
+            let _ = trace.flush();
+        }
+    }
+
+    fn feed_forward(
+        &mut self,
+        frametime: Duration,
+        target_fps: f64,
+        freq_ratio: f64,
+        kp: f64,
+    ) -> isize {
+        if !self.model_loaded {
+            self.model_loaded = true;
+            self.model = Model::load();
+            if self.model.is_none() {
+                warn!("No usable predictor model at {MODEL_PATH}, train one from traces first");
+            }
+        }
+
+        let current = frametime.as_secs_f64() * target_fps;
+        self.history.push_front(current);
+        self.history.truncate(HISTORY);
+        let Some(model) = &self.model else {
+            return 0;
+        };
+        let Some(mut features) = features(&self.history, freq_ratio) else {
+            return 0;
+        };
+
+        // The frame after next is predicted from the next one's prediction
+        let next = model.predict(&features);
+        features.copy_within(0..HISTORY - 1, 1);
+        features[0] = next;
+        let predicted = next.max(model.predict(&features));
+
+        // Same units as the controller's own error, frametime relative to one second
+        let spike = (predicted - current).max(0.0);
+        (spike * 1e9 * kp * FEED_FORWARD_GAIN) as isize
+    }
+
+    fn record(&mut self, pkg: &str, frametime: Duration, target_fps: f64, freq_ratio: f64) {
+        if self.trace.as_ref().is_none_or(|(traced, _)| traced != pkg) {
+            self.reset();
+            match open_trace(pkg) {
+                Ok(trace) => {
+                    info!("Recording frame trace of [{pkg}]");
+                    self.trace = Some((pkg.to_string(), trace));
+                }
+                Err(e) => {
+                    warn!("Failed to open frame trace of [{pkg}]: {e}");
+                    return;
+                }
+            }
+        }
+
+        if let Some((_, trace)) = &mut self.trace {
+            let _ = writeln!(
+                trace,
+                "{} {target_fps} {freq_ratio:.4}",
+                frametime.as_nanos()
+            );
+        }
+    }
+}
+
+fn open_trace(pkg: &str) -> Result<BufWriter<File>> {
+    fs::create_dir_all(TRACE_DIR)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(Path::new(TRACE_DIR).join(format!("{pkg}.trace")))?;
+    Ok(BufWriter::new(file))
+}
+
+fn features(history: &VecDeque<f64>, freq_ratio: f64) -> Option<[f64; FEATURES]> {
+    if history.len() < HISTORY {
+        return None;
+    }
+
+    let mut features = [0.0; FEATURES];
+    for (feature, frametime) in features.iter_mut().zip(history) {
+        *feature = *frametime;
+    }
+    features[HISTORY] = freq_ratio;
+    features[HISTORY + 1] = 1.0;
+    Some(features)
+}
+
+// Fits the model to recorded traces (the files under TRACE_DIR if none are given) by ridge
+// regression and saves it, returns a summary
+pub fn train(traces: &[PathBuf]) -> Result<String> {
+    let traces = if traces.is_empty() {
+        fs::read_dir(TRACE_DIR)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .collect()
+    } else {
+        traces.to_vec()
+    };
+
+    let mut samples = Vec::new();
+    for trace in &traces {
+        let content = fs::read_to_string(trace)?;
+        let mut history = VecDeque::with_capacity(HISTORY + 1);
+        for line in content.lines() {
+            let mut fields = line.split_whitespace().map(str::parse::<f64>);
+            let (Some(Ok(frametime)), Some(Ok(target_fps)), Some(Ok(freq_ratio))) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+
+            let normalized = frametime / 1e9 * target_fps;
+            if let Some(features) = features(&history, freq_ratio) {
+                samples.push((features, normalized));
+            }
+            history.push_front(normalized);
+            history.truncate(HISTORY);
+        }
+    }
+
+    if samples.len() < FEATURES * 10 {
+        return Err(Error::Other("not enough recorded frames to train on"));
+    }
+
+    let weights = fit(&samples).ok_or(Error::Other("traces are degenerate, fit failed"))?;
+    let model = Model {
+        weights: weights.to_vec(),
+    };
+
+    // How much better than assuming the next frame takes as long as the last one
+    let (model_error, naive_error) = samples.iter().fold(
+        (0.0, 0.0),
+        |(model_error, naive_error), (features, next)| {
+            (
+                model_error + (model.predict(features) - next).abs(),
+                naive_error + (features[0] - next).abs(),
+            )
+        },
+    );
+    let count = samples.len() as f64;
+
+    fs::write(MODEL_PATH, toml::to_string(&model)?)?;
+    Ok(format!(
+        "trained on {} frames from {} traces, mean error {:.4} (last frame: {:.4}), saved to {MODEL_PATH}\n",
+        samples.len(),
+        traces.len(),
+        model_error / count,
+        naive_error / count
+    ))
+}
+
+// Solves (XᵀX + λI)w = Xᵀy by gaussian elimination
+fn fit(samples: &[([f64; FEATURES], f64)]) -> Option<[f64; FEATURES]> {
+    let mut matrix = [[0.0; FEATURES + 1]; FEATURES];
+    for (features, target) in samples {
+        for (values, row_feature) in matrix.iter_mut().zip(features) {
+            for (value, feature) in values.iter_mut().zip(features) {
+                *value += row_feature * feature;
+            }
+            values[FEATURES] += row_feature * target;
+        }
+    }
+    for (row, values) in matrix.iter_mut().enumerate() {
+        values[row] += RIDGE * samples.len() as f64;
+    }
+
+    for pivot in 0..FEATURES {
+        let best = (pivot..FEATURES)
+            .max_by(|a, b| matrix[*a][pivot].abs().total_cmp(&matrix[*b][pivot].abs()))?;
+        matrix.swap(pivot, best);
+        if matrix[pivot][pivot].abs() < f64::EPSILON {
+            return None;
+        }
+
+        let pivot_row = matrix[pivot];
+        for (row, values) in matrix.iter_mut().enumerate() {
+            if row == pivot {
+                continue;
+            }
+            let factor = values[pivot] / pivot_row[pivot];
+            for (value, pivot_value) in values.iter_mut().zip(pivot_row).skip(pivot) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+
+    let mut weights = [0.0; FEATURES];
+    for (row, (weight, values)) in weights.iter_mut().zip(&matrix).enumerate() {
+        *weight = values[FEATURES] / values[row];
+    }
+    Some(weights)
+}
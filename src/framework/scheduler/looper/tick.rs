@@ -0,0 +1,79 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use std::time::{Duration, Instant};
+
+// How much of the previous average each new latency sample keeps
+const LATENCY_SMOOTHING: f64 = 0.9;
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+// Paces the control loop and measures it: iterations per second, and the time from a frame
+// arriving to the policy being applied
+#[derive(Debug)]
+pub struct LoopTicker {
+    policy_timer: Instant,
+    latency: Duration,
+    iterations: u32,
+    rate_timer: Instant,
+    rate: f64,
+}
+
+impl LoopTicker {
+    pub fn new() -> Self {
+        Self {
+            policy_timer: Instant::now(),
+            latency: Duration::ZERO,
+            iterations: 0,
+            rate_timer: Instant::now(),
+            rate: 0.0,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.iterations += 1;
+        let elapsed = self.rate_timer.elapsed();
+        if elapsed >= RATE_WINDOW {
+            self.rate = f64::from(self.iterations) / elapsed.as_secs_f64();
+            self.iterations = 0;
+            self.rate_timer = Instant::now();
+        }
+    }
+
+    // Whether a frame should run the policy, at most once per interval. Zero runs it on every
+    // frame
+    pub fn policy_due(&mut self, interval: Duration) -> bool {
+        if self.policy_timer.elapsed() < interval {
+            return false;
+        }
+        self.policy_timer = Instant::now();
+        true
+    }
+
+    pub fn record_latency(&mut self, latency: Duration) {
+        self.latency = self
+            .latency
+            .mul_f64(LATENCY_SMOOTHING)
+            .saturating_add(latency.mul_f64(1.0 - LATENCY_SMOOTHING));
+    }
+
+    pub const fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    pub const fn rate(&self) -> f64 {
+        self.rate
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs, path::PathBuf};
+
+use log::{debug, info};
+
+use crate::{file_handler, snapshot, sysfs};
+
+// Less eager to swap to zram, whose compression competes with the game for cpu
+const SWAPPINESS: (&str, &str) = ("/proc/sys/vm/swappiness", "20");
+// Wakes kswapd earlier so the game less often stalls in direct reclaim
+const WATERMARK_SCALE_FACTOR: (&str, &str) = ("/proc/sys/vm/watermark_scale_factor", "100");
+
+// Tunes reclaim for a big game's session, the original values are saved and written back
+// when the session ends
+pub struct MemoryTuner {
+    saved: Vec<(PathBuf, String)>,
+}
+
+impl MemoryTuner {
+    pub const fn new() -> Self {
+        Self { saved: Vec::new() }
+    }
+
+    pub fn apply(&mut self) {
+        if !self.saved.is_empty() {
+            return;
+        }
+
+        for (path, value) in [SWAPPINESS, WATERMARK_SCALE_FACTOR] {
+            self.tune(sysfs::resolve(sysfs::root(), path), value);
+        }
+
+        info!("Memory reclaim tuned for game session");
+    }
+
+    pub fn restore(&mut self) {
+        if self.saved.is_empty() {
+            return;
+        }
+
+        for (path, value) in self.saved.drain(..) {
+            let _ = file_handler::write_direct(&path, value);
+        }
+
+        info!("Memory reclaim settings restored");
+    }
+
+    fn tune(&mut self, path: PathBuf, value: &str) {
+        let Ok(current) = fs::read_to_string(&path) else {
+            return;
+        };
+
+        let current = current.trim().to_string();
+        if current == value {
+            return;
+        }
+
+        snapshot::record(&path);
+        if file_handler::write_direct(&path, value).is_ok() {
+            debug!("{}: {current} -> {value}", path.display());
+            self.saved.push((path, current));
+        }
+    }
+}
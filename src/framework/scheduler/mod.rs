@@ -15,33 +15,81 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
+mod adpf;
+mod background_throttle;
+mod battery;
+mod broadcast;
+mod compositor_boost;
+mod device_idle;
+mod display_refresh;
+mod dma_latency;
+mod engine;
+mod frame_hook;
+mod frame_source;
+mod game_cgroup;
+mod game_mode;
+mod game_props;
+mod hooks;
+mod idle_scene;
+mod io_tune;
+mod launch_boost;
+mod loading;
 mod looper;
+mod memory_tune;
+mod notification;
+mod on_demand;
+mod pacing;
+mod refresh_pin;
+mod refresh_rate;
+mod resolution_hint;
+mod rt_sched;
+mod self_test;
+mod shutdown;
+mod state_props;
 mod thermal;
+mod thermal_budget;
+mod threads;
 mod topapp;
+mod vsync_phase;
 
 use std::time::Duration;
 
 #[cfg(feature = "extension")]
 use super::Extension;
 use super::{
+    capability::CAPABILITIES,
     config::Config,
+    control::{Control, WebUi},
     error::{Error, Result},
     node::Node,
 };
 use crate::Controller;
 
 use frame_analyzer::Analyzer;
+use log::{error, warn};
 use looper::Looper;
+#[cfg(feature = "predictor")]
+pub use looper::train_predictor;
+pub use looper::{BenchPattern, BenchReport};
+pub use on_demand::OnDemand;
 
 #[derive(Debug, Clone, Copy)]
 pub struct FasData {
     pub pid: i32,
     pub frametime: Duration,
+    // From a polling fallback source rather than the analyzer
+    pub low_confidence: bool,
+    // How long the game took to produce the frame, when the source can tell it apart from
+    // the present interval
+    pub production: Option<Duration>,
+    // Monotonic present fence timestamp, when the source reports one
+    pub present: Option<Duration>,
 }
 
 pub struct Scheduler {
     controller: Option<Controller>,
     config: Option<Config>,
+    idle_exit: Option<Duration>,
 }
 
 impl Scheduler {
@@ -50,6 +98,7 @@ impl Scheduler {
         Self {
             controller: None,
             config: None,
+            idle_exit: None,
         }
     }
 
@@ -67,25 +116,98 @@ impl Scheduler {
         self
     }
 
+    #[must_use]
+    pub const fn idle_exit(mut self, d: Duration) -> Self {
+        self.idle_exit = Some(d);
+        self
+    }
+
+    // Drives the controller with synthetic frametimes instead of a game, see looper::bench
+    pub fn bench(
+        self,
+        pattern: BenchPattern,
+        duration: Duration,
+        target_fps: f64,
+    ) -> Result<BenchReport> {
+        // Both writing the same nodes would make the numbers meaningless
+        if Control::request("status").is_ok() {
+            return Err(Error::Other(
+                "fas-rs is running, stop it before benchmarking",
+            ));
+        }
+
+        #[cfg(feature = "extension")]
+        let extension = Extension::init()?;
+        let mut config = self.config.ok_or(Error::SchedulerMissing("Config"))?;
+        let controller = self
+            .controller
+            .ok_or(Error::SchedulerMissing("Controller"))?;
+
+        shutdown::register_signals();
+
+        #[cfg(feature = "extension")]
+        {
+            Ok(looper::bench(
+                &mut config,
+                controller,
+                &extension,
+                pattern,
+                duration,
+                target_fps,
+            ))
+        }
+        #[cfg(not(feature = "extension"))]
+        {
+            Ok(looper::bench(
+                &mut config,
+                controller,
+                pattern,
+                duration,
+                target_fps,
+            ))
+        }
+    }
+
     pub fn start_run(self) -> Result<()> {
         #[cfg(feature = "extension")]
         let extension = Extension::init()?;
-        let config = self.config.ok_or(Error::SchedulerMissing("Config"))?;
+        let mut config = self.config.ok_or(Error::SchedulerMissing("Config"))?;
 
         let controller = self
             .controller
             .ok_or(Error::SchedulerMissing("Controller"))?;
 
         let node = Node::init()?;
-        let analyzer = Analyzer::new()?;
+        Control::init()?;
+        if config.webui()
+            && let Err(e) = WebUi::init(config.webui_port(), config.path())
+        {
+            error!("Failed to start WebUI: {e}");
+        }
+        self_test::run(&controller);
+        shutdown::register_signals();
+
+        // Games are served by the fallback frame sources and load control without it
+        let analyzer = if CAPABILITIES.bpf {
+            Analyzer::new()
+                .map_err(|e| error!("Failed to start the frame analyzer: {e}"))
+                .ok()
+        } else {
+            warn!("Kernel lacks BPF support, only fallback frame sources are available");
+            None
+        };
 
         #[cfg(feature = "extension")]
         {
-            Looper::new(analyzer, config, node, extension, controller).enter_loop()
+            Looper::new(analyzer, config, node, extension, controller)?
+                .idle_exit(self.idle_exit)
+                .enter_loop()
         }
         #[cfg(not(feature = "extension"))]
         {
-            Looper::new(analyzer, config, node, controller).enter_loop()
+            Looper::new(analyzer, config, node, controller)?
+                .idle_exit(self.idle_exit)
+                .enter_loop()
         }
     }
 }
@@ -0,0 +1,101 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{process::Command, thread};
+
+use log::{debug, warn};
+use parking_lot::Mutex;
+
+use crate::framework::node::Mode;
+
+const TAG: &str = "fas_rs";
+const TITLE: &str = "fas-rs-next";
+// Milliseconds, about 24 days, until the next session unsnoozes it anyway
+const SNOOZE_TIME: &str = "2147483647";
+
+// `cmd notification` can't cancel, so the notification is snoozed once fas lets the game go.
+// The key is kept to unsnooze it before the next post, NMS would snooze the update as well
+static SNOOZED: Mutex<Option<String>> = Mutex::new(None);
+
+// Posts a notification telling the user fas is working on a game, replacing the previous one
+// as they share a tag. `cmd notification` posts it as the shell, so no app is needed.
+pub fn post_engaged(pkg: String, target_fps: Option<f64>, mode: Mode) {
+    let target_fps = target_fps.map_or_else(|| "auto".to_string(), |fps| format!("{fps}fps"));
+    let text = format!("Working on {pkg}, target {target_fps}, {mode} mode");
+
+    let _ = thread::Builder::new()
+        .name("NotificationThread".into())
+        .spawn(move || {
+            let mut snoozed = SNOOZED.lock();
+            if let Some(key) = snoozed.take() {
+                let _ = Command::new("cmd")
+                    .args(["notification", "unsnooze", &key])
+                    .output();
+            }
+
+            debug!("Posting notification: {text}");
+            match Command::new("cmd")
+                .args(["notification", "post", "-t", TITLE, TAG, &text])
+                .output()
+            {
+                Ok(output) if !output.status.success() => warn!(
+                    "Failed to post notification: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+                Err(e) => warn!("Failed to post notification: {e}"),
+                Ok(_) => (),
+            }
+        });
+}
+
+// Keys are listed like "0|com.android.shell|2020|fas_rs|2000"
+pub fn cancel() {
+    let _ = thread::Builder::new()
+        .name("NotificationThread".into())
+        .spawn(|| {
+            let mut snoozed = SNOOZED.lock();
+            if snoozed.is_some() {
+                return;
+            }
+
+            let Ok(output) = Command::new("cmd").args(["notification", "list"]).output() else {
+                return;
+            };
+            let tag = format!("|{TAG}|");
+            let Some(key) = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::trim)
+                .find(|key| key.contains(&tag))
+                .map(str::to_string)
+            else {
+                return;
+            };
+
+            debug!("Snoozing notification {key}");
+            match Command::new("cmd")
+                .args(["notification", "snooze", "--for", SNOOZE_TIME, &key])
+                .output()
+            {
+                Ok(output) if output.status.success() => *snoozed = Some(key),
+                Ok(output) => warn!(
+                    "Failed to dismiss notification: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+                Err(e) => warn!("Failed to dismiss notification: {e}"),
+            }
+        });
+}
@@ -0,0 +1,95 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    process::{Child, ExitStatus},
+    time::Duration,
+};
+
+use log::{info, warn};
+
+use super::{shutdown, topapp::TopAppsWatcher};
+use crate::framework::{config::Config, control::Control, pid_utils::get_process_name};
+
+const GAME_POLL_TIME: Duration = Duration::from_secs(1);
+const CHILD_POLL_TIME: Duration = Duration::from_millis(500);
+
+// The stub side of the on-demand mode, it waits for a game with a single watcher and owns the
+// daemon it launches, so a module disable reaches the daemon too
+pub struct OnDemand {
+    watcher: TopAppsWatcher,
+}
+
+impl OnDemand {
+    pub fn new() -> Self {
+        shutdown::register_signals();
+        Self {
+            watcher: TopAppsWatcher::new(),
+        }
+    }
+
+    pub fn shutdown_requested() -> bool {
+        shutdown::requested()
+    }
+
+    pub fn sleep(duration: Duration) {
+        shutdown::sleep(duration);
+    }
+
+    // False once a shutdown is requested. A daemon that is already running, e.g. started by
+    // hand, is left to serve the game
+    pub fn wait_for_game(&mut self, config: &mut Config) -> bool {
+        while !shutdown::requested() {
+            if self.game_on_top(config) && Control::request("status").is_err() {
+                return true;
+            }
+            shutdown::sleep(GAME_POLL_TIME);
+        }
+        false
+    }
+
+    fn game_on_top(&mut self, config: &mut Config) -> bool {
+        self.watcher
+            .topapp_pids()
+            .iter()
+            .filter_map(|pid| get_process_name(*pid).ok())
+            .any(|pkg| config.need_fas(pkg))
+    }
+
+    // Waits for the daemon to exit, a shutdown request is forwarded to it first
+    pub fn supervise(child: &mut Child) -> Option<ExitStatus> {
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) => return Some(status),
+                Ok(None) => (),
+                Err(e) => {
+                    warn!("Failed to wait for the fas daemon: {e}");
+                    return None;
+                }
+            }
+
+            if shutdown::requested() {
+                info!("Stopping the fas daemon");
+                unsafe {
+                    libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+                }
+                return child.wait().ok();
+            }
+            shutdown::sleep(CHILD_POLL_TIME);
+        }
+    }
+}
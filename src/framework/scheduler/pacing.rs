@@ -0,0 +1,148 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use log::info;
+
+use crate::framework::config::PacingMode;
+
+// The miss has to hold this long at max frequency to count as consistent
+const MISS_HOLD: Duration = Duration::from_secs(10);
+// Share of the target the fps falls short by, more than this is a heavy scene, not a cadence
+// just out of reach
+const MIN_MISS: f64 = 0.02;
+const MAX_MISS: f64 = 0.12;
+// A lower target within this of a refresh rate divisor paces evenly on vsync
+const VSYNC_TOLERANCE: f64 = 0.01;
+
+// Spots a game that keeps missing its target by a small margin with the cpu already maxed
+// out, e.g. 17.5ms frames against 16.6ms, and suggests or applies a lower target that the
+// refresh rate divides evenly, rather than burning power on a cadence it can't hold
+pub struct PacingAssist {
+    mode: PacingMode,
+    miss_since: Option<Instant>,
+    // (configured target, the target stepped down to)
+    stepped: Option<(f64, f64)>,
+    suggested: bool,
+}
+
+impl PacingAssist {
+    pub const fn new() -> Self {
+        Self {
+            mode: PacingMode::Off,
+            miss_since: None,
+            stepped: None,
+            suggested: false,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: PacingMode) {
+        if mode != self.mode {
+            self.reset();
+            self.mode = mode;
+        }
+    }
+
+    // `lower_targets` are the configured targets below the current one, nearest first. Returns
+    // the target fps offset to apply.
+    pub fn update(
+        &mut self,
+        pkg: &str,
+        fps: f64,
+        target_fps: f64,
+        saturated: bool,
+        lower_targets: &[f64],
+        refresh_rate: Option<u32>,
+    ) -> f64 {
+        if self.mode == PacingMode::Off {
+            return 0.0;
+        }
+
+        // The game switched caps on its own, judge the new one afresh
+        if let Some((stepped_from, _)) = self.stepped
+            && stepped_from.total_cmp(&target_fps).is_ne()
+        {
+            self.reset();
+        }
+        if let Some((stepped_from, stepped_to)) = self.stepped {
+            return if self.mode == PacingMode::Apply {
+                stepped_to - stepped_from
+            } else {
+                0.0
+            };
+        }
+
+        let miss = 1.0 - fps / target_fps;
+        if !saturated || !(MIN_MISS..=MAX_MISS).contains(&miss) {
+            self.miss_since = None;
+            return 0.0;
+        }
+        if self.miss_since.get_or_insert_with(Instant::now).elapsed() < MISS_HOLD {
+            return 0.0;
+        }
+
+        let Some(lower) = paced_target(target_fps, lower_targets, refresh_rate) else {
+            return 0.0;
+        };
+        if self.mode == PacingMode::Apply {
+            info!(
+                "[{pkg}] keeps missing {target_fps}fps at {fps:.1}fps, stepping down to {lower}fps"
+            );
+            self.stepped = Some((target_fps, lower));
+            return lower - target_fps;
+        }
+
+        if !self.suggested {
+            info!(
+                "[{pkg}] keeps missing {target_fps}fps at {fps:.1}fps, a {lower}fps target would pace evenly for less power"
+            );
+            self.suggested = true;
+        }
+        0.0
+    }
+
+    pub const fn reset(&mut self) {
+        self.miss_since = None;
+        self.stepped = None;
+        self.suggested = false;
+    }
+}
+
+// The nearest lower target the refresh rate divides evenly, else the nearest lower one. Without
+// a lower configured target, the refresh rate divisors themselves.
+fn paced_target(target_fps: f64, lower_targets: &[f64], refresh_rate: Option<u32>) -> Option<f64> {
+    let Some(refresh_rate) = refresh_rate.map(f64::from) else {
+        return lower_targets.first().copied();
+    };
+    let aligned = |fps: f64| {
+        let ratio = refresh_rate / fps;
+        (ratio - ratio.round()).abs() <= ratio * VSYNC_TOLERANCE
+    };
+
+    if lower_targets.is_empty() {
+        return (2..=4)
+            .map(|divisor| refresh_rate / f64::from(divisor))
+            .find(|fps| *fps < target_fps);
+    }
+
+    lower_targets
+        .iter()
+        .copied()
+        .find(|fps| aligned(*fps))
+        .or_else(|| lower_targets.first().copied())
+}
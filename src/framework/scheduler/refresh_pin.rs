@@ -0,0 +1,177 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    process::Command,
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use log::{debug, info, warn};
+
+use crate::{file_handler, snapshot};
+
+const JANK_WINDOW: Duration = Duration::from_secs(1);
+const JANK_BURST: u32 = 3;
+const STABLE_TIME: Duration = Duration::from_secs(5);
+
+enum Request {
+    Pin,
+    Release,
+}
+
+// Pins the display refresh rate (min_refresh_rate = peak_refresh_rate) while frames are
+// janking in bursts, so LTPO does not switch refresh rate mid-burst.
+pub struct RefreshPin {
+    sx: Option<Sender<Request>>,
+    handle: Option<JoinHandle<()>>,
+    pinned: bool,
+    jank_count: u32,
+    window_timer: Instant,
+    stable_timer: Instant,
+}
+
+impl RefreshPin {
+    pub fn new() -> Self {
+        let (sx, rx) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("RefreshPinThread".into())
+            .spawn(move || {
+                let mut saved = None;
+                for request in rx {
+                    match request {
+                        Request::Pin => saved = pin(),
+                        Request::Release => {
+                            if let Some((min, pinned)) = saved.take() {
+                                release(&min, &pinned);
+                            }
+                        }
+                    }
+                }
+            })
+            .ok();
+
+        Self {
+            sx: handle.as_ref().map(|_| sx),
+            handle,
+            pinned: false,
+            jank_count: 0,
+            window_timer: Instant::now(),
+            stable_timer: Instant::now(),
+        }
+    }
+
+    pub fn update(&mut self, is_janked: bool) {
+        if self.window_timer.elapsed() > JANK_WINDOW {
+            self.window_timer = Instant::now();
+            self.jank_count = 0;
+        }
+
+        if is_janked {
+            self.jank_count += 1;
+            self.stable_timer = Instant::now();
+
+            if !self.pinned && self.jank_count >= JANK_BURST {
+                debug!("jank burst, pinning refresh rate");
+                self.send(Request::Pin);
+                self.pinned = true;
+            }
+        } else if self.pinned && self.stable_timer.elapsed() > STABLE_TIME {
+            debug!("frames are stable, releasing refresh rate");
+            self.release();
+        }
+    }
+
+    pub fn release(&mut self) {
+        if self.pinned {
+            self.send(Request::Release);
+            self.pinned = false;
+        }
+    }
+
+    // Releases the pin and waits for the worker to finish, so it's restored before exit
+    pub fn shutdown(&mut self) {
+        self.release();
+        self.sx = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    fn send(&self, request: Request) {
+        if let Some(sx) = &self.sx {
+            let _ = sx.send(request);
+        }
+    }
+}
+
+pub fn get_setting(key: &str) -> Option<String> {
+    let output = Command::new("settings")
+        .args(["get", "system", key])
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn put_setting(key: &str, value: &str) {
+    if file_handler::dry_run() {
+        info!("dry run: settings system {key} <- {value}");
+        return;
+    }
+
+    let result = if value == "null" {
+        Command::new("settings")
+            .args(["delete", "system", key])
+            .status()
+    } else {
+        Command::new("settings")
+            .args(["put", "system", key, value])
+            .status()
+    };
+
+    if let Err(e) = result {
+        warn!("Failed to set {key} to {value}: {e}");
+    }
+}
+
+// Returns the previous min_refresh_rate and the value pinned to
+fn pin() -> Option<(String, String)> {
+    let min = get_setting("min_refresh_rate")?;
+    let peak = get_setting("peak_refresh_rate")?;
+    if peak == "null" || peak == min {
+        return None;
+    }
+
+    info!("Pin refresh rate: min_refresh_rate {min} -> {peak}");
+    snapshot::record_setting("min_refresh_rate", &min, &peak);
+    put_setting("min_refresh_rate", &peak);
+    Some((min, peak))
+}
+
+fn release(min: &str, pinned: &str) {
+    // Someone else (e.g. a refresh rate module) changed it meanwhile, leave their value alone
+    if get_setting("min_refresh_rate").as_deref() != Some(pinned) {
+        info!("min_refresh_rate changed by others, not restoring it");
+        snapshot::forget_setting("min_refresh_rate");
+        return;
+    }
+
+    info!("Release refresh rate: min_refresh_rate {pinned} -> {min}");
+    put_setting("min_refresh_rate", min);
+    snapshot::forget_setting("min_refresh_rate");
+}
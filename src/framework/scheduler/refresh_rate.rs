@@ -0,0 +1,240 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use dumpsys_rs::Dumpsys;
+use log::info;
+use parking_lot::Mutex;
+
+const POLL_TIME: Duration = Duration::from_secs(1);
+// The built-in panel
+const DEFAULT_DISPLAY: u32 = 0;
+
+// Tracks the display hosting the game, its active refresh rate, which changes on the fly
+// with LTPO panels, and its fastest mode, which differs entirely on external displays.
+// Polled on its own thread as dumping the display and window services takes a while.
+pub struct RefreshRateWatcher {
+    enabled: Arc<AtomicBool>,
+    refresh_rate: Arc<AtomicU32>,
+    peak_refresh_rate: Arc<AtomicU32>,
+    game: Arc<Mutex<Option<String>>>,
+    display: Arc<AtomicU32>,
+}
+
+impl RefreshRateWatcher {
+    pub fn new() -> Self {
+        let enabled = Arc::new(AtomicBool::new(false));
+        let refresh_rate = Arc::new(AtomicU32::new(0));
+        let peak_refresh_rate = Arc::new(AtomicU32::new(0));
+        let game = Arc::new(Mutex::new(None));
+        let display = Arc::new(AtomicU32::new(DEFAULT_DISPLAY));
+
+        {
+            let enabled = enabled.clone();
+            let refresh_rate = refresh_rate.clone();
+            let peak_refresh_rate = peak_refresh_rate.clone();
+            let game = game.clone();
+            let display = display.clone();
+            let _ = thread::Builder::new()
+                .name("RefreshRateThread".into())
+                .spawn(move || {
+                    poll(&enabled, &refresh_rate, &peak_refresh_rate, &game, &display);
+                });
+        }
+
+        Self {
+            enabled,
+            refresh_rate,
+            peak_refresh_rate,
+            game,
+            display,
+        }
+    }
+
+    // The game whose surface is followed across displays
+    pub fn set_game(&self, pkg: Option<&str>) {
+        let mut game = self.game.lock();
+        if game.as_deref() != pkg {
+            *game = pkg.map(str::to_string);
+            if pkg.is_none() {
+                self.display.store(DEFAULT_DISPLAY, Ordering::Release);
+                if !self.enabled.load(Ordering::Acquire) {
+                    self.refresh_rate.store(0, Ordering::Release);
+                    self.peak_refresh_rate.store(0, Ordering::Release);
+                }
+            }
+        }
+    }
+
+    // The game runs on e.g. a DisplayPort monitor or a cast display, not the built-in panel
+    pub fn external_display(&self) -> bool {
+        self.display.load(Ordering::Acquire) != DEFAULT_DISPLAY
+    }
+
+    // The refresh rate is also tracked while a game is set, regardless of this
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Release);
+    }
+
+    pub fn refresh_rate(&self) -> Option<u32> {
+        match self.refresh_rate.load(Ordering::Acquire) {
+            0 => None,
+            refresh_rate => Some(refresh_rate),
+        }
+    }
+
+    pub fn peak_refresh_rate(&self) -> Option<u32> {
+        match self.peak_refresh_rate.load(Ordering::Acquire) {
+            0 => None,
+            refresh_rate => Some(refresh_rate),
+        }
+    }
+}
+
+fn poll(
+    enabled: &AtomicBool,
+    refresh_rate: &AtomicU32,
+    peak_refresh_rate: &AtomicU32,
+    game: &Mutex<Option<String>>,
+    display: &AtomicU32,
+) {
+    let Some(mut dumper) = Dumpsys::new("display") else {
+        return;
+    };
+    let mut window_dumper = Dumpsys::new("window");
+
+    loop {
+        thread::sleep(POLL_TIME);
+
+        let pkg = game.lock().clone();
+        if let Some(pkg) = &pkg
+            && let Some(game_display) = window_dumper
+                .as_mut()
+                .and_then(|dumper| dumper.dump(&["displays"]).ok())
+                .and_then(|dump| parse_game_display(&dump, pkg))
+            && display.swap(game_display, Ordering::AcqRel) != game_display
+        {
+            info!("[{pkg}] is on display {game_display}");
+        }
+
+        if !enabled.load(Ordering::Acquire) && pkg.is_none() {
+            continue;
+        }
+
+        let Ok(dump) = dumper.dump(&[]) else {
+            continue;
+        };
+        let display = display.load(Ordering::Acquire);
+        if let Some(rate) = parse_display_refresh_rate(&dump, display) {
+            refresh_rate.store(rate, Ordering::Release);
+            let peak = parse_peak_refresh_rate(&dump, display).unwrap_or(rate);
+            peak_refresh_rate.store(peak.max(rate), Ordering::Release);
+        }
+    }
+}
+
+// Windows are listed under the display they are on, e.g.
+// Display: mDisplayId=2 (organized) rootTasks=1
+//   ...
+//     * Task{... A=10123:com.example.game ...}
+fn parse_game_display(dump: &str, pkg: &str) -> Option<u32> {
+    let mut display = None;
+    for line in dump.lines() {
+        if let Some(id) = line.trim_start().strip_prefix("Display: mDisplayId=") {
+            display = id
+                .split(|c: char| !c.is_ascii_digit())
+                .next()
+                .and_then(|id| id.parse().ok());
+        } else if line.contains(pkg) && display.is_some() {
+            return display;
+        }
+    }
+    None
+}
+
+// The built-in panel's active mode is listed on its own, other displays only through their
+// DisplayInfo, e.g.
+// DisplayInfo{"HDMI Screen", displayId 2, ..., renderFrameRate 60.0, ...}
+fn parse_display_refresh_rate(dump: &str, display: u32) -> Option<u32> {
+    if display == DEFAULT_DISPLAY {
+        return parse_refresh_rate(dump);
+    }
+
+    let id = format!("displayId {display},");
+    let info = dump
+        .lines()
+        .find(|line| line.contains("DisplayInfo{") && line.contains(&id))?;
+    let fps = info.split("renderFrameRate ").nth(1)?;
+    let fps = fps.split([',', '}', ' ']).next()?;
+    fps.trim().parse::<f64>().ok().map(|fps| fps.round() as u32)
+}
+
+// The fastest of the modes the display supports, e.g.
+// DisplayInfo{..., displayId 0, ..., supportedModes [{id=1, ..., fps=120.00001, ...},
+// {id=2, ..., fps=60.0, ...}], ...}
+fn parse_peak_refresh_rate(dump: &str, display: u32) -> Option<u32> {
+    let id = format!("displayId {display},");
+    let info = dump
+        .lines()
+        .find(|line| line.contains("DisplayInfo{") && line.contains(&id))?;
+    let modes = info.split("supportedModes [").nth(1)?;
+    // Modes carry their own lists, e.g. alternativeRefreshRates=[60.0]
+    let mut depth = 0;
+    let end = modes.find(|c| match c {
+        '[' => {
+            depth += 1;
+            false
+        }
+        ']' if depth == 0 => true,
+        ']' => {
+            depth -= 1;
+            false
+        }
+        _ => false,
+    })?;
+    modes[..end]
+        .split("fps=")
+        .skip(1)
+        .filter_map(|fps| {
+            fps.split([',', '}', ' '])
+                .next()?
+                .trim()
+                .parse::<f64>()
+                .ok()
+        })
+        .map(|fps| fps.round() as u32)
+        .max()
+}
+
+// Reads the fps of the active mode, e.g.
+// mActiveSfDisplayMode=DisplayMode{id=1, width=1080, height=2400, fps=120.00001, ...}
+fn parse_refresh_rate(dump: &str) -> Option<u32> {
+    let line = dump
+        .lines()
+        .find(|line| line.contains("mActiveSfDisplayMode=") || line.contains("mActiveMode="))?;
+    let fps = line.split("fps=").nth(1)?;
+    let fps = fps.split([',', '}', ' ']).next()?;
+    fps.trim().parse::<f64>().ok().map(|fps| fps.round() as u32)
+}
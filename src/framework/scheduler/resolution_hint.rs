@@ -0,0 +1,86 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use log::info;
+
+use crate::props;
+
+// Engines that support external dynamic resolution read the render scale from here
+const HINT_PROP: &str = "debug.fas_rs.resolution_scale";
+const STEP_TIME: Duration = Duration::from_secs(2);
+const SCALE_STEP: f64 = 0.1;
+const SCALE_MIN: f64 = 0.5;
+
+pub struct ResolutionHint {
+    scale: f64,
+    timer: Instant,
+}
+
+impl ResolutionHint {
+    pub fn new() -> Self {
+        Self {
+            scale: 1.0,
+            timer: Instant::now(),
+        }
+    }
+
+    // Scale down when frequency alone can't hold the target, and back up once there is headroom
+    pub fn update(&mut self, is_janked: bool, freq_saturated: bool) {
+        if self.timer.elapsed() < STEP_TIME {
+            return;
+        }
+
+        let scale = if is_janked && freq_saturated {
+            (self.scale - SCALE_STEP).max(SCALE_MIN)
+        } else if !is_janked && !freq_saturated {
+            (self.scale + SCALE_STEP).min(1.0)
+        } else {
+            self.scale
+        };
+
+        if (scale - self.scale).abs() > f64::EPSILON {
+            self.timer = Instant::now();
+            self.scale = scale;
+            set_hint(format!("{scale:.1}"));
+        }
+    }
+
+    pub fn reset(&mut self) {
+        if self.scale < 1.0 {
+            self.scale = 1.0;
+            set_hint(String::new());
+        }
+    }
+
+    // Like reset, but waits for the prop to be cleared
+    pub fn shutdown(&mut self) {
+        self.scale = 1.0;
+        write_hint("");
+    }
+}
+
+fn set_hint(value: String) {
+    info!("Resolution hint: {HINT_PROP}={value}");
+    props::set_async(HINT_PROP.to_string(), value);
+}
+
+fn write_hint(value: &str) {
+    info!("Resolution hint: {HINT_PROP}={value}");
+    props::set(HINT_PROP, value);
+}
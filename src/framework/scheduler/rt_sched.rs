@@ -0,0 +1,151 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use libc::{PRIO_PROCESS, pid_t, sched_param};
+use log::{debug, info, warn};
+
+use crate::{file_handler, framework::config::RtPolicy};
+
+const SCAN_TIME: Duration = Duration::from_secs(2);
+// Nice value used when the kernel refuses real-time policies
+const FALLBACK_NICE: i32 = -10;
+
+#[derive(Debug, Clone, Copy)]
+struct Saved {
+    tid: pid_t,
+    policy: i32,
+    priority: i32,
+    nice: i32,
+}
+
+// Promotes the game's main and render threads to a real-time policy during fas sessions,
+// which keeps frametimes stable on busy systems, and demotes them again afterwards
+pub struct RtScheduler {
+    pid: pid_t,
+    saved: Vec<Saved>,
+    timer: Instant,
+}
+
+impl RtScheduler {
+    pub fn new() -> Self {
+        Self {
+            pid: 0,
+            saved: Vec::new(),
+            timer: Instant::now(),
+        }
+    }
+
+    // Rechecks now and then, render threads can start after the game does
+    pub fn update<I>(&mut self, pid: pid_t, tids: I, policy: RtPolicy, priority: i32)
+    where
+        I: IntoIterator<Item = pid_t>,
+    {
+        let policy = match policy {
+            RtPolicy::None => {
+                self.restore();
+                return;
+            }
+            RtPolicy::Fifo => libc::SCHED_FIFO,
+            RtPolicy::Rr => libc::SCHED_RR,
+        };
+
+        if pid != self.pid {
+            self.restore();
+            self.pid = pid;
+        } else if self.timer.elapsed() < SCAN_TIME {
+            return;
+        }
+        self.timer = Instant::now();
+        if file_handler::dry_run() {
+            debug!("dry run: render threads of pid {pid} not promoted");
+            return;
+        }
+
+        let priority = priority.clamp(1, 99);
+        for tid in tids {
+            if self.saved.iter().any(|saved| saved.tid == tid) {
+                continue;
+            }
+
+            if let Some(saved) = promote(tid, policy, priority) {
+                self.saved.push(saved);
+            }
+        }
+    }
+
+    pub fn restore(&mut self) {
+        if self.saved.is_empty() {
+            return;
+        }
+
+        for saved in self.saved.drain(..) {
+            let param = sched_param {
+                sched_priority: saved.priority,
+            };
+            unsafe {
+                libc::sched_setscheduler(saved.tid, saved.policy, &raw const param);
+                libc::setpriority(PRIO_PROCESS, saved.tid as u32, saved.nice);
+            }
+        }
+
+        self.pid = 0;
+        info!("Render threads scheduling restored");
+    }
+}
+
+fn promote(tid: pid_t, policy: i32, priority: i32) -> Option<Saved> {
+    let mut param = sched_param { sched_priority: 0 };
+    let saved = unsafe {
+        let old_policy = libc::sched_getscheduler(tid);
+        if old_policy < 0 || libc::sched_getparam(tid, &raw mut param) < 0 {
+            return None;
+        }
+
+        let nice = libc::getpriority(PRIO_PROCESS, tid as u32);
+        Saved {
+            tid,
+            policy: old_policy,
+            priority: param.sched_priority,
+            nice,
+        }
+    };
+
+    let param = sched_param {
+        sched_priority: priority,
+    };
+    if unsafe { libc::sched_setscheduler(tid, policy, &raw const param) } == 0 {
+        debug!("tid {tid}: real-time priority {priority}");
+        return Some(saved);
+    }
+
+    // E.g. rt throttling or a kernel without rt group budget for the cgroup
+    let e = io::Error::last_os_error();
+    if unsafe { libc::setpriority(PRIO_PROCESS, tid as u32, FALLBACK_NICE) } == 0 {
+        warn!(
+            "Failed to set real-time policy for tid {tid}: {e}, fell back to nice {FALLBACK_NICE}"
+        );
+        Some(saved)
+    } else {
+        warn!("Failed to raise priority of tid {tid}: {e}");
+        None
+    }
+}
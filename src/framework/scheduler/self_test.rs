@@ -0,0 +1,73 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use std::{fs, sync::OnceLock};
+
+use log::{info, warn};
+
+use super::frame_source::FrameSourceManager;
+use crate::{
+    Controller,
+    framework::capability::CAPABILITIES,
+    root_env::{self, RootEnv},
+};
+
+const REPORT_PATH: &str = "/sdcard/Android/fas-rs/self_test.txt";
+
+static REPORT: OnceLock<String> = OnceLock::new();
+
+// Runs on every start, so "it does nothing on my device" comes with the reason. Limits are
+// only written back with the values they already hold
+pub fn run(controller: &Controller) {
+    let (policies, policies_passed) = controller.self_test();
+    let analyzer = if CAPABILITIES.bpf {
+        "analyzer: true"
+    } else {
+        "analyzer: false (kernel lacks bpf)"
+    };
+    let sections = [
+        (
+            "environment",
+            root_env::get().map(RootEnv::describe).unwrap_or_default(),
+        ),
+        ("capabilities", CAPABILITIES.describe()),
+        ("policies", policies),
+        (
+            "frame sources",
+            format!("{analyzer}\n{}", FrameSourceManager::available_fallbacks()),
+        ),
+    ];
+    let report = sections
+        .iter()
+        .map(|(title, body)| format!("== {title} ==\n{}\n", body.trim_end()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if policies_passed && CAPABILITIES.bpf {
+        info!("Self test passed");
+    } else {
+        warn!("Self test found problems, see {REPORT_PATH}");
+    }
+    if let Err(e) = fs::write(REPORT_PATH, &report) {
+        warn!("Failed to write {REPORT_PATH}: {e}");
+    }
+
+    let _ = REPORT.set(report);
+}
+
+pub fn report() -> &'static str {
+    REPORT.get().map_or("", String::as_str)
+}
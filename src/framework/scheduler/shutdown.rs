@@ -0,0 +1,125 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    process,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{error, info, warn};
+use parking_lot::Mutex;
+
+const WATCHDOG_POLL_TIME: Duration = Duration::from_millis(50);
+// Longest a sleeping control loop takes to notice a shutdown request
+const SLEEP_SLICE: Duration = Duration::from_millis(100);
+
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_shutdown(_: libc::c_int) {
+    SHUTDOWN.store(true, Ordering::Release);
+}
+
+// Module disable, `kill` and a closed adb shell all end up in the full teardown
+pub fn register_signals() {
+    let handler = request_shutdown as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGHUP, handler);
+        libc::signal(libc::SIGQUIT, handler);
+    }
+}
+
+pub fn requested() -> bool {
+    SHUTDOWN.load(Ordering::Acquire)
+}
+
+// Like thread::sleep, but returns early once a shutdown is requested, so the sender of the
+// signal doesn't give up waiting and kill the process before the teardown
+pub fn sleep(duration: Duration) {
+    let start = Instant::now();
+    while !requested() {
+        let Some(left) = duration.checked_sub(start.elapsed()) else {
+            return;
+        };
+        thread::sleep(left.min(SLEEP_SLICE));
+    }
+}
+
+type Stage = Option<(&'static str, Instant, Duration)>;
+// Stages that need nothing from the control loop, the watchdog can run them on its own
+type Standalone = Vec<(&'static str, fn())>;
+
+// Runs teardown stages in order under a watchdog. Stages borrowing the control loop can't
+// run past a stuck one, so when a stage overruns its timeout the watchdog skips it, runs the
+// standalone stages still left (the snapshot restore puts every node back) and then exits
+pub struct Shutdown {
+    stage: Arc<Mutex<Stage>>,
+    standalone: Arc<Mutex<Standalone>>,
+}
+
+impl Shutdown {
+    pub fn begin(standalone: &[(&'static str, fn())]) -> Self {
+        info!("Shutting down");
+
+        let stage: Arc<Mutex<Stage>> = Arc::new(Mutex::new(None));
+        let standalone = Arc::new(Mutex::new(standalone.to_vec()));
+        let watched = stage.clone();
+        let left = standalone.clone();
+        let _ = thread::Builder::new()
+            .name("ShutdownWatchdog".into())
+            .spawn(move || {
+                loop {
+                    thread::sleep(WATCHDOG_POLL_TIME);
+                    let Some((name, start, timeout)) = *watched.lock() else {
+                        continue;
+                    };
+                    if start.elapsed() <= timeout {
+                        continue;
+                    }
+
+                    error!("Shutdown stage {name} is stuck after {timeout:?}, skipping it");
+                    let stages: Vec<_> = left.lock().drain(..).collect();
+                    for (next, f) in stages.into_iter().filter(|(next, _)| *next != name) {
+                        warn!("Shutdown stage: {next}, run by the watchdog");
+                        f();
+                    }
+                    process::exit(1);
+                }
+            });
+
+        Self { stage, standalone }
+    }
+
+    pub fn stage<F>(&self, name: &'static str, timeout: Duration, f: F)
+    where
+        F: FnOnce(),
+    {
+        info!("Shutdown stage: {name}");
+        self.standalone
+            .lock()
+            .retain(|(standalone, _)| *standalone != name);
+        *self.stage.lock() = Some((name, Instant::now(), timeout));
+        f();
+        *self.stage.lock() = None;
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{framework::node::Mode, props};
+
+const GAME_PROP: &str = "debug.fas_rs.game";
+const TARGET_FPS_PROP: &str = "debug.fas_rs.target_fps";
+const MODE_PROP: &str = "debug.fas_rs.mode";
+const THROTTLED_PROP: &str = "debug.fas_rs.throttled";
+
+// Publishes fas state as system properties, for modules and scripts that would rather read
+// a prop than listen for broadcasts. Empty values while no game is engaged
+pub fn engaged(pkg: String, target_fps: Option<f64>, mode: Mode) {
+    let target_fps = target_fps.map_or_else(|| "auto".to_string(), |fps| fps.to_string());
+    props::set_async(GAME_PROP.to_string(), pkg);
+    props::set_async(TARGET_FPS_PROP.to_string(), target_fps);
+    props::set_async(MODE_PROP.to_string(), mode.to_string());
+}
+
+pub fn disengaged() {
+    for prop in [GAME_PROP, TARGET_FPS_PROP, THROTTLED_PROP] {
+        props::set_async(prop.to_string(), String::new());
+    }
+}
+
+pub fn mode_changed(mode: Mode) {
+    props::set_async(MODE_PROP.to_string(), mode.to_string());
+}
+
+pub fn throttle_changed(throttled: bool) {
+    props::set_async(THROTTLED_PROP.to_string(), throttled.to_string());
+}
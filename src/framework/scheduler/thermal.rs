@@ -15,23 +15,42 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
-use log::debug;
+use anyhow::{Context, Result};
+use log::{debug, info};
 
-use crate::{Config, Mode, framework::config::TemperatureThreshold};
+use crate::{
+    Config, Mode,
+    framework::config::{BatteryTempRule, TemperatureThreshold, ThermalFpsStep},
+    sysfs,
+};
+
+const THERMAL_DIR: &str = "/sys/devices/virtual/thermal";
+// In 0.1℃, unlike thermal zones
+const BATTERY_TEMP: &str = "/sys/class/power_supply/battery/temp";
 
 pub struct Thermal {
     target_fps_offset: f64,
     core_temperature: u64,
+    fps_steps: u32,
     nodes: Vec<PathBuf>,
+    // Tracked apart from the soc zones, battery heat limits are far below junction temps
+    battery_temperature: Option<u64>,
+    battery_node: PathBuf,
+    // Threshold of the battery temperature rule in effect
+    battery_rule_above: Option<u64>,
 }
 
 impl Thermal {
-    pub fn new() -> Result<Self> {
+    pub fn new(root: &Path) -> Result<Self> {
         let mut nodes = Vec::new();
-        for device in fs::read_dir("/sys/devices/virtual/thermal")? {
+        for device in fs::read_dir(sysfs::resolve(root, THERMAL_DIR))
+            .with_context(|| format!("Failed to read {THERMAL_DIR}"))?
+        {
             let device = device?;
             let device_type = device.path().join("type");
             let Ok(device_type) = fs::read_to_string(device_type) else {
@@ -48,7 +67,11 @@ impl Thermal {
         Ok(Self {
             target_fps_offset: 0.0,
             core_temperature: 0,
+            fps_steps: 0,
             nodes,
+            battery_temperature: None,
+            battery_node: sysfs::resolve(root, BATTERY_TEMP),
+            battery_rule_above: None,
         })
     }
 
@@ -72,7 +95,80 @@ impl Thermal {
         self.target_fps_offset
     }
 
-    fn temperature_update(&mut self) {
+    // How many steps the target fps list is lowered by. Stepping back up waits until the
+    // temperature is `hysteresis` below the threshold, so the target doesn't flap around it
+    pub fn fps_steps(&mut self, rules: &[ThermalFpsStep], hysteresis: u64) -> u32 {
+        let steps_above = |temperature: u64| {
+            rules
+                .iter()
+                .filter(|rule| temperature > rule.above)
+                .map(|rule| rule.fps_step)
+                .max()
+                .unwrap_or(0)
+        };
+        let hot = steps_above(self.core_temperature);
+        let cooled = steps_above(self.core_temperature.saturating_add(hysteresis));
+
+        if hot > self.fps_steps || cooled < self.fps_steps {
+            let fps_steps = if hot > self.fps_steps { hot } else { cooled };
+            info!(
+                "Core temperature {}, target fps steps {} -> {fps_steps}",
+                self.core_temperature, self.fps_steps
+            );
+            self.fps_steps = fps_steps;
+        }
+
+        self.fps_steps
+    }
+
+    // The hottest rule the battery is above, with the same hysteresis as `fps_steps`.
+    // Rules marked `charging` only apply while charging
+    pub fn battery_rule(
+        &mut self,
+        rules: &[BatteryTempRule],
+        charging: bool,
+        hysteresis: u64,
+    ) -> Option<BatteryTempRule> {
+        let temperature = self.battery_temperature?;
+        let hottest = |temperature: u64| {
+            rules
+                .iter()
+                .filter(|rule| (charging || !rule.charging) && temperature > rule.above)
+                .map(|rule| rule.above)
+                .max()
+        };
+        let hot = hottest(temperature);
+        let cooled = hottest(temperature.saturating_add(hysteresis));
+
+        if hot > self.battery_rule_above || cooled < self.battery_rule_above {
+            let above = if hot > self.battery_rule_above {
+                hot
+            } else {
+                cooled
+            };
+            info!(
+                "Battery temperature {temperature}, rule above {:?} -> {above:?}",
+                self.battery_rule_above
+            );
+            self.battery_rule_above = above;
+        }
+
+        rules
+            .iter()
+            .filter(|rule| charging || !rule.charging)
+            .find(|rule| Some(rule.above) == self.battery_rule_above)
+            .copied()
+    }
+
+    pub const fn core_temperature(&self) -> u64 {
+        self.core_temperature
+    }
+
+    pub const fn battery_temperature(&self) -> Option<u64> {
+        self.battery_temperature
+    }
+
+    pub fn temperature_update(&mut self) {
         self.core_temperature = self
             .nodes
             .iter()
@@ -80,5 +176,70 @@ impl Thermal {
             .map(|temp| temp.trim().parse::<u64>().unwrap_or_default())
             .max()
             .unwrap_or_default();
+        self.battery_temperature = fs::read_to_string(&self.battery_node)
+            .ok()
+            .and_then(|temp| temp.trim().parse::<u64>().ok())
+            .map(|temp| temp * 100);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::FakeRoot;
+
+    fn zone(root: &FakeRoot, zone: u32, device_type: &str, temp: u64) {
+        root.write(
+            &format!("{THERMAL_DIR}/thermal_zone{zone}/type"),
+            device_type,
+        );
+        root.write(
+            &format!("{THERMAL_DIR}/thermal_zone{zone}/temp"),
+            &temp.to_string(),
+        );
+    }
+
+    #[test]
+    fn reads_the_hottest_cpu_zone() {
+        let root = FakeRoot::new("thermal-zones");
+        zone(&root, 0, "cpu-0-0-usr", 55_000);
+        zone(&root, 1, "cpu-1-0-usr", 61_000);
+        zone(&root, 2, "battery", 90_000);
+        root.write(BATTERY_TEMP, "385");
+
+        let mut thermal = Thermal::new(root.path()).unwrap();
+        assert_eq!(thermal.nodes.len(), 2);
+
+        thermal.temperature_update();
+        assert_eq!(thermal.core_temperature, 61_000);
+        assert_eq!(thermal.battery_temperature, Some(38_500));
+    }
+
+    #[test]
+    fn steps_down_with_hysteresis() {
+        let root = FakeRoot::new("thermal-steps");
+        zone(&root, 0, "soc_max", 80_000);
+        let rules = [ThermalFpsStep {
+            above: 75_000,
+            fps_step: 1,
+        }];
+
+        let mut thermal = Thermal::new(root.path()).unwrap();
+        thermal.temperature_update();
+        assert_eq!(thermal.fps_steps(&rules, 3_000), 1);
+
+        zone(&root, 0, "soc_max", 73_000);
+        thermal.temperature_update();
+        assert_eq!(thermal.fps_steps(&rules, 3_000), 1);
+
+        zone(&root, 0, "soc_max", 70_000);
+        thermal.temperature_update();
+        assert_eq!(thermal.fps_steps(&rules, 3_000), 0);
+    }
+
+    #[test]
+    fn missing_thermal_dir_is_an_error() {
+        let root = FakeRoot::new("thermal-missing");
+        assert!(Thermal::new(root.path()).is_err());
     }
 }
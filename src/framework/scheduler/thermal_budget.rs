@@ -0,0 +1,118 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+
+use crate::framework::config::ThermalBudget;
+
+const PLAN_TIME: Duration = Duration::from_secs(5);
+// How far ahead the temperature trend is projected, in seconds
+const HORIZON: f64 = 60.0;
+const TREND_SMOOTHING: f64 = 0.3;
+const POWER_SMOOTHING: f64 = 0.2;
+// Errors are normalized against these, a full error moves the cap by one step
+const TEMP_SCALE: f64 = 5000.0;
+const POWER_SCALE: f64 = 0.2;
+// Down faster than up, so the game settles below the budget instead of oscillating around it
+const STEP_DOWN: f64 = 0.05;
+const STEP_UP: f64 = 0.02;
+const MIN_CAP: f64 = 0.4;
+
+// Plans the frequency cap over minutes for a game with a sustained budget. It runs at full
+// speed while there is headroom and eases down as the projected temperature or the power
+// draw nears the budget, settling at a level the device holds instead of sawtoothing
+// against the kernel throttler.
+pub struct BudgetPlanner {
+    cap: f64,
+    timer: Instant,
+    // (temperature in 0.001℃, when it was read)
+    last_temp: Option<(f64, Instant)>,
+    // 0.001℃ per second
+    trend: f64,
+    power: Option<f64>,
+}
+
+impl BudgetPlanner {
+    pub fn new() -> Self {
+        Self {
+            cap: 1.0,
+            timer: Instant::now(),
+            last_temp: None,
+            trend: 0.0,
+            power: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    // Returns the cap as a fraction of each policy's max freq
+    pub fn update(&mut self, budget: Option<&ThermalBudget>, temp: u64, power: Option<f64>) -> f64 {
+        let Some(budget) = budget else {
+            return 1.0;
+        };
+        if self.timer.elapsed() < PLAN_TIME {
+            return self.cap;
+        }
+        self.timer = Instant::now();
+
+        let temp = temp as f64;
+        if let Some((last, at)) = self.last_temp {
+            let slope = (temp - last) / at.elapsed().as_secs_f64();
+            self.trend += (slope - self.trend) * TREND_SMOOTHING;
+        }
+        self.last_temp = Some((temp, Instant::now()));
+        if let Some(power) = power {
+            self.power = Some(self.power.map_or(power, |smoothed| {
+                smoothed + (power - smoothed) * POWER_SMOOTHING
+            }));
+        }
+
+        // Positive is headroom, the tighter of the two budgets wins
+        let temp_error = budget.temp.map(|limit| {
+            let projected = temp + self.trend.max(0.0) * HORIZON;
+            (limit as f64 - projected) / TEMP_SCALE
+        });
+        let power_error = budget
+            .power
+            .zip(self.power)
+            .map(|(limit, power)| (limit - power) / limit / POWER_SCALE);
+        let Some(error) = [temp_error, power_error]
+            .into_iter()
+            .flatten()
+            .min_by(f64::total_cmp)
+        else {
+            return self.cap;
+        };
+
+        let error = error.clamp(-1.0, 1.0);
+        let step = if error < 0.0 { STEP_DOWN } else { STEP_UP };
+        let cap = error.mul_add(step, self.cap).clamp(MIN_CAP, 1.0);
+        debug!(
+            "thermal budget: temp {temp}, trend {:.1}/s, power {:?}, cap {:.2} -> {cap:.2}",
+            self.trend, self.power, self.cap
+        );
+        if (cap < 1.0) != (self.cap < 1.0) {
+            info!("Thermal budget cap: {cap:.2}");
+        }
+        self.cap = cap;
+        self.cap
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    time::{Duration, Instant},
+};
+
+use libc::{_SC_CLK_TCK, pid_t, sysconf};
+use log::debug;
+
+const SCAN_TIME: Duration = Duration::from_secs(2);
+// Unnamed threads busier than these are taken for game logic and workers
+const LOGIC_UTIL: f64 = 0.5;
+const WORKER_UTIL: f64 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadClass {
+    // The process main thread, the java ui thread on android
+    Main,
+    Render,
+    Logic,
+    Worker,
+    Other,
+}
+
+impl fmt::Display for ThreadClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let class = match self {
+            Self::Main => "main",
+            Self::Render => "render",
+            Self::Logic => "logic",
+            Self::Worker => "worker",
+            Self::Other => "other",
+        };
+        write!(f, "{class}")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GameThread {
+    pub tid: pid_t,
+    pub name: String,
+    pub class: ThreadClass,
+    pub util: f64,
+}
+
+impl fmt::Display for GameThread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {:.1}",
+            self.tid,
+            self.name,
+            self.class,
+            self.util * 100.0
+        )
+    }
+}
+
+// Classifies the game's threads by name and how busy they are, for the priority subsystems
+// and for debugging
+pub struct ThreadScanner {
+    pid: pid_t,
+    timer: Instant,
+    cputimes: HashMap<pid_t, u64>,
+    threads: Vec<GameThread>,
+}
+
+impl ThreadScanner {
+    pub fn new() -> Self {
+        Self {
+            pid: 0,
+            timer: Instant::now(),
+            cputimes: HashMap::new(),
+            threads: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, pid: pid_t) {
+        if pid != self.pid {
+            self.reset();
+            self.pid = pid;
+        } else if self.timer.elapsed() < SCAN_TIME {
+            return;
+        }
+
+        let ticks = self.timer.elapsed().as_secs_f64() * unsafe { sysconf(_SC_CLK_TCK) } as f64;
+        self.timer = Instant::now();
+
+        let Ok(entries) = fs::read_dir(format!("/proc/{pid}/task")) else {
+            return;
+        };
+
+        let mut cputimes = HashMap::new();
+        let mut threads = Vec::new();
+        for tid in entries.filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok()) {
+            let Some((name, cputime)) = read_thread(pid, tid) else {
+                continue;
+            };
+
+            let util = self
+                .cputimes
+                .get(&tid)
+                .filter(|_| ticks > 0.0)
+                .map_or(0.0, |last| cputime.saturating_sub(*last) as f64 / ticks);
+            cputimes.insert(tid, cputime);
+            threads.push(GameThread {
+                tid,
+                class: classify(pid, tid, &name, util),
+                name,
+                util,
+            });
+        }
+
+        threads.sort_by(|a, b| b.util.total_cmp(&a.util));
+        for thread in threads
+            .iter()
+            .filter(|thread| thread.class != ThreadClass::Other)
+        {
+            debug!("game thread: {thread}");
+        }
+
+        self.cputimes = cputimes;
+        self.threads = threads;
+    }
+
+    pub fn reset(&mut self) {
+        self.pid = 0;
+        self.cputimes.clear();
+        self.threads.clear();
+    }
+
+    // Sorted by utilization, busiest first
+    pub fn threads(&self) -> &[GameThread] {
+        &self.threads
+    }
+
+    pub fn tids(&self, classes: &[ThreadClass]) -> impl Iterator<Item = pid_t> {
+        self.threads
+            .iter()
+            .filter(|thread| classes.contains(&thread.class))
+            .map(|thread| thread.tid)
+    }
+}
+
+fn classify(pid: pid_t, tid: pid_t, name: &str, util: f64) -> ThreadClass {
+    if tid == pid {
+        return ThreadClass::Main;
+    }
+
+    match name {
+        "RenderThread" | "UnityGfxDeviceW" | "RHIThread" | "GLThread" | "VkThread" => {
+            return ThreadClass::Render;
+        }
+        "UnityMain" | "GameThread" | "MainThread-UE4" | "Cocos2dxThread" => {
+            return ThreadClass::Logic;
+        }
+        _ => (),
+    }
+
+    if name.starts_with("RenderThread") || name.starts_with("RHI") || name.starts_with("UnityGfx") {
+        ThreadClass::Render
+    } else if name.starts_with("Worker Thread")
+        || name.starts_with("Job.Worker")
+        || name.starts_with("UnityWorker")
+        || name.starts_with("UnrealWorker")
+        || name.starts_with("TaskGraph")
+        || name.starts_with("Background Worker")
+        || name.starts_with("PoolThread")
+    {
+        ThreadClass::Worker
+    } else if util >= LOGIC_UTIL && name.starts_with("Thread-") {
+        ThreadClass::Logic
+    } else if util >= WORKER_UTIL {
+        ThreadClass::Worker
+    } else {
+        ThreadClass::Other
+    }
+}
+
+// The comm may contain spaces and parentheses, so fields are counted after the last ')'
+fn read_thread(pid: pid_t, tid: pid_t) -> Option<(String, u64)> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/task/{tid}/stat")).ok()?;
+    let (head, fields) = stat.rsplit_once(')')?;
+    let name = head.split_once('(')?.1.to_string();
+    let fields: Vec<_> = fields.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some((name, utime + stime))
+}
@@ -15,11 +15,35 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use std::time::{Duration, Instant};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use dumpsys_rs::Dumpsys;
+use inotify::{Inotify, WatchMask};
+use log::{info, warn};
 
+// Without a top-app event, the foreground apps are polled at this interval
 const REFRESH_TIME: Duration = Duration::from_millis(500);
+// Shortest gap between two dumps, a burst of events leads to one dump
+const EVENT_DEBOUNCE_TIME: Duration = Duration::from_millis(80);
+// The system moves the new foreground app into these, writes to them wake the watcher
+const TOP_APP_PROCS: [&str; 4] = [
+    "/dev/cpuset/top-app/cgroup.procs",
+    "/dev/cpuset/top-app/tasks",
+    "/dev/cpuctl/top-app/cgroup.procs",
+    "/dev/cpuctl/top-app/tasks",
+];
+// Split-screen and freeform windows make apps drop out of single dumps while they're still on
+// screen, so a pid counts as visible until it's been missing for this long
+const VISIBLE_GRACE: Duration = Duration::from_millis(1500);
 
 #[derive(Default)]
 struct WindowsInfo {
@@ -54,6 +78,8 @@ pub struct TopAppsWatcher {
     windows_dumper: Dumpsys,
     cache: WindowsInfo,
     last_refresh: Instant,
+    last_seen: HashMap<i32, Instant>,
+    changed: Option<Arc<AtomicBool>>,
 }
 
 impl TopAppsWatcher {
@@ -69,6 +95,8 @@ impl TopAppsWatcher {
             windows_dumper,
             cache: WindowsInfo::default(),
             last_refresh: Instant::now(),
+            last_seen: HashMap::new(),
+            changed: spawn_cgroup_watcher(),
         }
     }
 
@@ -80,8 +108,26 @@ impl TopAppsWatcher {
         self.cache().visible_freeform_window
     }
 
+    // Whether the pid is on screen, tolerating brief gaps while windows are rearranged
+    pub fn visible(&mut self, pid: i32) -> bool {
+        self.cache();
+        self.last_seen
+            .get(&pid)
+            .is_some_and(|seen| seen.elapsed() < VISIBLE_GRACE)
+    }
+
+    // Threads moving in and out of top-app fire events all the time, a pending one waits for
+    // EVENT_DEBOUNCE_TIME since the last dump rather than dumping on every call
     fn cache(&mut self) -> &WindowsInfo {
-        if self.last_refresh.elapsed() > REFRESH_TIME {
+        let elapsed = self.last_refresh.elapsed();
+        let need_refresh = elapsed > REFRESH_TIME
+            || (elapsed > EVENT_DEBOUNCE_TIME
+                && self
+                    .changed
+                    .as_ref()
+                    .is_some_and(|changed| changed.swap(false, Ordering::AcqRel)));
+
+        if need_refresh {
             let dump = loop {
                 match self.windows_dumper.dump(&["visible-apps"]) {
                     Ok(dump) => break dump,
@@ -93,9 +139,50 @@ impl TopAppsWatcher {
             };
             self.cache = WindowsInfo::new(&dump);
 
-            self.last_refresh = Instant::now();
+            let now = Instant::now();
+            self.last_seen
+                .retain(|_, seen| seen.elapsed() < VISIBLE_GRACE);
+            for pid in &self.cache.pids {
+                self.last_seen.insert(*pid, now);
+            }
+
+            self.last_refresh = now;
         }
 
         &self.cache
     }
 }
+
+// Watches writes to the top-app cgroup, so a launched game is picked up within
+// EVENT_DEBOUNCE_TIME instead of on the next poll. None if no cgroup can be watched.
+fn spawn_cgroup_watcher() -> Option<Arc<AtomicBool>> {
+    let mut inotify = Inotify::init().ok()?;
+    let mut watched = false;
+    for path in TOP_APP_PROCS.iter().filter(|path| Path::new(path).exists()) {
+        match inotify.watches().add(path, WatchMask::MODIFY) {
+            Ok(_) => watched = true,
+            Err(e) => warn!("Failed to watch {path}: {e}"),
+        }
+    }
+
+    if !watched {
+        info!("No top-app cgroup to watch, polling foreground apps");
+        return None;
+    }
+
+    let changed = Arc::new(AtomicBool::new(true));
+    {
+        let changed = changed.clone();
+        thread::Builder::new()
+            .name("TopAppWatchThread".into())
+            .spawn(move || {
+                let mut buffer = [0; 1024];
+                while inotify.read_events_blocking(&mut buffer).is_ok() {
+                    changed.store(true, Ordering::Release);
+                }
+            })
+            .ok()?;
+    }
+
+    Some(changed)
+}
@@ -0,0 +1,142 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+// Weight of the newest frame in the running phase error
+const SMOOTHING: f64 = 0.05;
+// Presents this far off the vsync grid on average, halfway between two vsyncs being 1.0
+const DRIFT_THRESHOLD: f64 = 0.3;
+// Drift held this long is taken as frames about to miss their vsync
+const DRIFT_TIME: Duration = Duration::from_millis(500);
+// The display driver's last vsync, "VSYNC=<monotonic ns>", first one found is used
+const VSYNC_NODES: [&str; 3] = [
+    "/sys/class/drm/card0/sde-crtc-0/vsync_event",
+    "/sys/class/drm/sde-crtc-0/vsync_event",
+    "/sys/class/graphics/fb0/vsync_event",
+];
+// Re-read so the grid doesn't walk away from the real vsync through rounding of the period
+const ANCHOR_TIME: Duration = Duration::from_secs(1);
+
+// How close presents land to vsync. Each present timestamp is placed on the grid spanned by
+// the last vsync and the refresh period. One landing between two grid lines means the frame
+// was late for its slot and waited for the next one, which shows up well before whole frames
+// drop
+#[derive(Debug, Default)]
+pub struct VsyncPhase {
+    error: f64,
+    error_sum: f64,
+    samples: u64,
+    drift_since: Option<Instant>,
+    grid: Option<Grid>,
+}
+
+#[derive(Debug)]
+struct Grid {
+    // A vsync timestamp, on the monotonic clock
+    anchor: Duration,
+    refresh_rate: u32,
+    // Anchored by a real vsync rather than the first present
+    vsync: bool,
+    since: Instant,
+}
+
+impl VsyncPhase {
+    // Nothing to align to while the refresh rate is unknown. The present is a monotonic
+    // timestamp, the present fence when the frame source knows it
+    pub fn push(&mut self, present: Duration, refresh_rate: Option<u32>) {
+        let Some(refresh_rate) = refresh_rate.filter(|refresh_rate| *refresh_rate > 0) else {
+            return;
+        };
+
+        let grid = self.grid(present, refresh_rate);
+        let period = 1.0 / f64::from(refresh_rate);
+        let offset = present.as_secs_f64() - grid.anchor.as_secs_f64();
+        let phase = offset.rem_euclid(period) / period;
+        let error = (phase.min(1.0 - phase) * 2.0).min(1.0);
+        self.error = self.error.mul_add(1.0 - SMOOTHING, error * SMOOTHING);
+        self.error_sum += error;
+        self.samples += 1;
+
+        if self.error > DRIFT_THRESHOLD {
+            self.drift_since.get_or_insert_with(Instant::now);
+        } else {
+            self.drift_since = None;
+        }
+    }
+
+    // Without a vsync node the first present after a refresh rate change stands in for a
+    // vsync, so only drift against it is seen
+    fn grid(&mut self, present: Duration, refresh_rate: u32) -> &Grid {
+        let stale = self.grid.as_ref().is_none_or(|grid| {
+            grid.refresh_rate != refresh_rate || (grid.vsync && grid.since.elapsed() >= ANCHOR_TIME)
+        });
+
+        if stale {
+            let vsync = last_vsync();
+            self.grid = Some(Grid {
+                anchor: vsync.unwrap_or(present),
+                refresh_rate,
+                vsync: vsync.is_some(),
+                since: Instant::now(),
+            });
+        }
+
+        self.grid.as_ref().unwrap()
+    }
+
+    pub fn drifting(&self) -> bool {
+        self.drift_since
+            .is_some_and(|since| since.elapsed() >= DRIFT_TIME)
+    }
+
+    // 1.0 when every present landed on a vsync
+    pub fn alignment(&self) -> Option<f64> {
+        (self.samples > 0).then(|| 1.0 - self.error_sum / self.samples as f64)
+    }
+
+    // The alignment of the whole session, starting over for the next one
+    pub fn take_alignment(&mut self) -> Option<f64> {
+        let alignment = self.alignment();
+        *self = Self::default();
+        alignment
+    }
+}
+
+// The receive time, for frame sources without a present timestamp
+pub fn now() -> Duration {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &raw mut time);
+    }
+
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+fn last_vsync() -> Option<Duration> {
+    VSYNC_NODES.iter().find_map(|node| {
+        let event = fs::read_to_string(node).ok()?;
+        let nanos = event.trim().strip_prefix("VSYNC=")?.parse::<u64>().ok()?;
+        (nanos > 0).then(|| Duration::from_nanos(nanos))
+    })
+}
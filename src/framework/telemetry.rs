@@ -0,0 +1,310 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::Write as _,
+    path::Path,
+    time::Duration,
+};
+
+use chrono::{Local, NaiveDate, TimeDelta};
+use log::{debug, error};
+
+use super::error::Result;
+
+const TELEMETRY_DIR: &str = "/data/adb/fas_rs/telemetry";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+// Sessions shorter than this are app switches, not play
+const MIN_DURATION: Duration = Duration::from_secs(30);
+
+// One finished game session, appended to the file of the day as a tab separated line
+#[derive(Debug)]
+pub struct Record {
+    pub pkg: String,
+    pub duration: Duration,
+    pub fps: f64,
+    pub target_fps: Option<f64>,
+    pub power: Option<f64>,
+    pub cpu_bound: u64,
+    pub gpu_bound: u64,
+    // Share of presents that landed on vsync, 1.0 being all of them
+    pub phase_alignment: Option<f64>,
+    // The sub-profile active when the session ended
+    pub sub_profile: Option<String>,
+    // Estimated joules of each policy
+    pub cluster_energy: Vec<(i32, f64)>,
+}
+
+// Averages the fps of a running session, sampled once per status update
+#[derive(Debug, Default)]
+pub struct SessionStats {
+    fps_sum: f64,
+    samples: u32,
+}
+
+impl SessionStats {
+    pub fn sample(&mut self, fps: f64) {
+        self.fps_sum += fps;
+        self.samples += 1;
+    }
+
+    pub fn take_fps(&mut self) -> f64 {
+        let fps = if self.samples > 0 {
+            self.fps_sum / f64::from(self.samples)
+        } else {
+            0.0
+        };
+        *self = Self::default();
+        fps
+    }
+}
+
+// Files older than `retention_days` are removed on every append, 0 keeps nothing at all
+pub fn append(record: &Record, retention_days: u32) {
+    if retention_days == 0 || record.duration < MIN_DURATION {
+        return;
+    }
+
+    if let Err(e) = write(record) {
+        error!("Failed to record telemetry: {e:?}");
+    }
+    prune(Path::new(TELEMETRY_DIR), retention_days);
+}
+
+fn write(record: &Record) -> Result<()> {
+    fs::create_dir_all(TELEMETRY_DIR)?;
+    let now = Local::now();
+    let path = Path::new(TELEMETRY_DIR).join(format!("{}.tsv", now.format(DATE_FORMAT)));
+    let optional =
+        |value: Option<f64>| value.map_or_else(|| "-".to_string(), |v| format!("{v:.2}"));
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{}\t{}\t{}\t{:.2}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        now.format("%H:%M:%S"),
+        record.pkg,
+        record.duration.as_secs(),
+        record.fps,
+        optional(record.target_fps),
+        optional(record.power),
+        record.cpu_bound,
+        record.gpu_bound,
+        optional(record.phase_alignment),
+        record.sub_profile.as_deref().unwrap_or("-"),
+        energy_field(&record.cluster_energy),
+    )?;
+    Ok(())
+}
+
+// "<policy>:<joules>" comma separated, "-" without any
+fn energy_field(cluster_energy: &[(i32, f64)]) -> String {
+    if cluster_energy.is_empty() {
+        return "-".to_string();
+    }
+    cluster_energy
+        .iter()
+        .map(|(policy, joules)| format!("{policy}:{joules:.1}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_energy_field(field: &str) -> Vec<(i32, f64)> {
+    field
+        .split(',')
+        .filter_map(|entry| {
+            let (policy, joules) = entry.split_once(':')?;
+            Some((policy.parse().ok()?, joules.parse().ok()?))
+        })
+        .collect()
+}
+
+// "policy<n>: <joules>J (<share %>)" per policy, for the session summary
+pub fn describe_energy(cluster_energy: &[(i32, f64)]) -> String {
+    let total: f64 = cluster_energy.iter().map(|(_, joules)| joules).sum();
+    cluster_energy
+        .iter()
+        .map(|(policy, joules)| {
+            format!(
+                "policy{policy}: {joules:.1}J ({:.0}%)",
+                joules / total.max(f64::EPSILON) * 100.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn prune(dir: &Path, retention_days: u32) {
+    let oldest = Local::now().date_naive() - TimeDelta::days(i64::from(retention_days) - 1);
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if file_date(&path).is_some_and(|date| date < oldest) {
+            debug!("Removing expired telemetry {}", path.display());
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn file_date(path: &Path) -> Option<NaiveDate> {
+    let stem = path.file_stem()?.to_str()?;
+    NaiveDate::parse_from_str(stem, DATE_FORMAT).ok()
+}
+
+#[derive(Default)]
+struct Summary {
+    sessions: u32,
+    seconds: u64,
+    // Weighted by duration
+    fps_sum: f64,
+    power_sum: f64,
+    power_seconds: u64,
+    cpu_bound: u64,
+    gpu_bound: u64,
+    alignment_sum: f64,
+    alignment_seconds: u64,
+    // Joules per policy, over the seconds of the sessions that recorded energy
+    cluster_energy: BTreeMap<i32, f64>,
+    energy_seconds: u64,
+}
+
+// Per game summary of the sessions in the last `days` days, optionally of a single game
+pub fn query(days: u32, pkg: Option<&str>) -> String {
+    let oldest = Local::now().date_naive() - TimeDelta::days(i64::from(days.max(1)) - 1);
+    let mut summaries: BTreeMap<String, Summary> = BTreeMap::new();
+
+    let files: Vec<_> = fs::read_dir(TELEMETRY_DIR)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| file_date(path).is_some_and(|date| date >= oldest))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let contents: Vec<_> = files
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .collect();
+    for line in contents.iter().flat_map(|content| content.lines()) {
+        let fields: Vec<_> = line.split('\t').collect();
+        // Older records lack the phase alignment, sub-profile and cluster energy columns
+        let [
+            _,
+            game,
+            seconds,
+            fps,
+            _,
+            power,
+            cpu_bound,
+            gpu_bound,
+            ref extra @ ..,
+        ] = fields[..]
+        else {
+            continue;
+        };
+        if pkg.is_some_and(|pkg| pkg != game) {
+            continue;
+        }
+        // Sessions of each sub-profile are summarized apart from the game's own
+        let key = match extra.get(1) {
+            Some(sub_profile) if *sub_profile != "-" => format!("{game} ({sub_profile})"),
+            _ => game.to_string(),
+        };
+        let (Ok(seconds), Ok(fps)) = (seconds.parse::<u64>(), fps.parse::<f64>()) else {
+            continue;
+        };
+
+        let summary = summaries.entry(key).or_default();
+        summary.sessions += 1;
+        summary.seconds += seconds;
+        summary.fps_sum += fps * seconds as f64;
+        if let Ok(power) = power.parse::<f64>() {
+            summary.power_sum += power * seconds as f64;
+            summary.power_seconds += seconds;
+        }
+        summary.cpu_bound += cpu_bound.parse::<u64>().unwrap_or_default();
+        summary.gpu_bound += gpu_bound.parse::<u64>().unwrap_or_default();
+        if let Some(Ok(alignment)) = extra.first().map(|alignment| alignment.parse::<f64>()) {
+            summary.alignment_sum += alignment * seconds as f64;
+            summary.alignment_seconds += seconds;
+        }
+        let cluster_energy = extra
+            .get(2)
+            .map_or_else(Vec::new, |field| parse_energy_field(field));
+        if !cluster_energy.is_empty() {
+            for (policy, joules) in cluster_energy {
+                *summary.cluster_energy.entry(policy).or_default() += joules;
+            }
+            summary.energy_seconds += seconds;
+        }
+    }
+
+    if summaries.is_empty() {
+        return format!("No sessions recorded in the last {days} days\n");
+    }
+
+    let mut output = format!("Last {days} days:\n");
+    for (game, summary) in summaries {
+        let seconds = summary.seconds.max(1) as f64;
+        let power = if summary.power_seconds > 0 {
+            format!("{:.2}W", summary.power_sum / summary.power_seconds as f64)
+        } else {
+            "unknown".to_string()
+        };
+        let alignment = if summary.alignment_seconds > 0 {
+            format!(
+                "{:.0}%",
+                summary.alignment_sum / summary.alignment_seconds as f64 * 100.0
+            )
+        } else {
+            "unknown".to_string()
+        };
+        let cluster_power = if summary.energy_seconds > 0 {
+            summary
+                .cluster_energy
+                .iter()
+                .map(|(policy, joules)| {
+                    format!(
+                        "policy{policy} {:.2}W",
+                        joules / summary.energy_seconds as f64
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        } else {
+            "unknown".to_string()
+        };
+        let _ = writeln!(
+            output,
+            "{game}: {} sessions, {}h{:02}m, average fps: {:.1}, average power: {power}, cluster power: {cluster_power}, vsync alignment: {alignment}, janks cpu bound: {}, gpu bound: {}",
+            summary.sessions,
+            summary.seconds / 3600,
+            summary.seconds % 3600 / 60,
+            summary.fps_sum / seconds,
+            summary.cpu_bound,
+            summary.gpu_bound,
+        );
+    }
+    output
+}
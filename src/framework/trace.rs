@@ -0,0 +1,385 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process, thread,
+    time::Duration,
+};
+
+use chrono::Local;
+use log::{error, info, warn};
+use serde::Serialize;
+
+use super::error::Result;
+
+const TRACE_DIR: &str = "/data/adb/fas_rs/traces";
+// Older traces are removed past this many
+const MAX_TRACES: usize = 10;
+// About an hour of 120fps with a decision per frame, the rest of the session is dropped
+const MAX_EVENTS: usize = 1_000_000;
+// The controller and frequency tracks, frames go on the track of the game's pid
+const CONTROLLER_TID: i32 = 0;
+
+#[derive(Debug)]
+enum Event {
+    Frame {
+        at: Duration,
+        pid: i32,
+        frametime: Duration,
+    },
+    Freq {
+        at: Duration,
+        policy: i32,
+        freq: isize,
+    },
+    Decision {
+        at: Duration,
+        control: isize,
+        janked: bool,
+        target_fps: Option<f64>,
+    },
+}
+
+// One entry of the trace event format, fields a phase doesn't use are left out
+#[derive(Debug, Serialize)]
+struct TraceEvent<'a> {
+    name: &'a str,
+    ph: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    s: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+    pid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tid: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<Args<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Args<'a> {
+    Name { name: &'a str },
+    Frame { frametime_ms: f64 },
+    Freq { khz: isize },
+    Control { khz: isize, target_fps: Option<f64> },
+}
+
+// Captures one game session and writes it as a trace event json file, which perfetto and
+// chrome://tracing open directly
+#[derive(Debug, Default)]
+pub struct TraceRecorder {
+    // Session start, on CLOCK_MONOTONIC like the present timestamps and in CLOCK_BOOTTIME
+    // microseconds so the events line up with a system trace taken at the same time
+    start: Option<(Duration, f64)>,
+    events: Vec<Event>,
+    freqs: Vec<(i32, isize)>,
+    truncated: bool,
+}
+
+impl TraceRecorder {
+    pub fn begin(&mut self) {
+        self.reset();
+        self.start = Some((monotonic(), boottime_us()));
+    }
+
+    pub const fn active(&self) -> bool {
+        self.start.is_some()
+    }
+
+    // Stamped at `present` (CLOCK_MONOTONIC) when the frame source reports it, so the frame
+    // slices end where the frames were shown rather than where fas-rs received them
+    pub fn frame(&mut self, pid: i32, frametime: Duration, present: Option<Duration>) {
+        let Some((start, _)) = self.start else {
+            return;
+        };
+
+        let at = present.unwrap_or_else(monotonic).saturating_sub(start);
+        self.push(Event::Frame { at, pid, frametime });
+    }
+
+    // Only the changes are kept, the counter tracks hold their value in between
+    pub fn freqs(&mut self, freqs: &[(i32, isize)]) {
+        let Some(at) = self.elapsed() else {
+            return;
+        };
+
+        for &(policy, freq) in freqs {
+            if self.freqs.contains(&(policy, freq)) {
+                continue;
+            }
+            self.freqs.retain(|(p, _)| *p != policy);
+            self.freqs.push((policy, freq));
+            self.push(Event::Freq { at, policy, freq });
+        }
+    }
+
+    pub fn decision(&mut self, control: isize, janked: bool, target_fps: Option<f64>) {
+        if let Some(at) = self.elapsed() {
+            self.push(Event::Decision {
+                at,
+                control,
+                janked,
+                target_fps,
+            });
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // Writes the captured session of `pkg` on a background thread, the control loop doesn't
+    // wait for up to a million events to be formatted. A no-op when nothing was being captured
+    pub fn finish(&mut self, pkg: &str) {
+        let Some((_, start_us)) = self.start else {
+            return;
+        };
+
+        let session = Session {
+            pkg: pkg.to_string(),
+            start_us,
+            events: std::mem::take(&mut self.events),
+            truncated: self.truncated,
+        };
+        self.reset();
+
+        let _ = thread::Builder::new()
+            .name("TraceWriterThread".into())
+            .spawn(move || {
+                match session.write() {
+                    Ok(path) => {
+                        info!(
+                            "Frame timeline of {} written to {}{}",
+                            session.pkg,
+                            path.display(),
+                            if session.truncated { ", truncated" } else { "" }
+                        );
+                    }
+                    Err(e) => error!("Failed to write frame timeline: {e:?}"),
+                }
+                prune(Path::new(TRACE_DIR));
+            });
+    }
+
+    fn elapsed(&self) -> Option<Duration> {
+        self.start
+            .map(|(start, _)| monotonic().saturating_sub(start))
+    }
+
+    fn push(&mut self, event: Event) {
+        if self.events.len() < MAX_EVENTS {
+            self.events.push(event);
+        } else if !self.truncated {
+            warn!("Frame timeline is full, the rest of the session isn't captured");
+            self.truncated = true;
+        }
+    }
+}
+
+// A finished session handed over to the writer thread
+struct Session {
+    pkg: String,
+    start_us: f64,
+    events: Vec<Event>,
+    truncated: bool,
+}
+
+impl Session {
+    fn write(&self) -> Result<PathBuf> {
+        let (pkg, start_us) = (self.pkg.as_str(), self.start_us);
+        fs::create_dir_all(TRACE_DIR)?;
+        let path = Path::new(TRACE_DIR).join(format!(
+            "{pkg}-{}.json",
+            Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        let mut file = BufWriter::new(File::create(&path)?);
+        let pid = process::id();
+
+        // Streamed one event at a time, a long session doesn't fit in memory twice
+        write!(file, "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[")?;
+        let process_name = format!("fas-rs ({pkg})");
+        serde_json::to_writer(
+            &mut file,
+            &metadata("process_name", pid, CONTROLLER_TID, &process_name),
+        )?;
+        write!(file, ",")?;
+        serde_json::to_writer(
+            &mut file,
+            &metadata("thread_name", pid, CONTROLLER_TID, "controller"),
+        )?;
+
+        let us = |at: Duration| start_us + at.as_secs_f64() * 1_000_000.0;
+        let mut framed_pids = Vec::new();
+        for event in &self.events {
+            match *event {
+                Event::Frame {
+                    at,
+                    pid: game,
+                    frametime,
+                } => {
+                    if !framed_pids.contains(&game) {
+                        framed_pids.push(game);
+                        let name = format!("frames (pid {game})");
+                        write!(file, ",")?;
+                        serde_json::to_writer(
+                            &mut file,
+                            &metadata("thread_name", pid, game, &name),
+                        )?;
+                    }
+                    // The frame ends at its present, so back to back frames don't overlap
+                    let duration = frametime.as_secs_f64() * 1_000_000.0;
+                    write!(file, ",")?;
+                    serde_json::to_writer(
+                        &mut file,
+                        &TraceEvent {
+                            name: "frame",
+                            ph: "X",
+                            s: None,
+                            ts: Some(us(at) - duration),
+                            dur: Some(duration),
+                            pid,
+                            tid: Some(game),
+                            args: Some(Args::Frame {
+                                frametime_ms: frametime.as_secs_f64() * 1000.0,
+                            }),
+                        },
+                    )?;
+                }
+                Event::Freq { at, policy, freq } => {
+                    let name = format!("policy{policy} freq");
+                    write!(file, ",")?;
+                    serde_json::to_writer(
+                        &mut file,
+                        &counter(&name, pid, us(at), Args::Freq { khz: freq }),
+                    )?;
+                }
+                Event::Decision {
+                    at,
+                    control,
+                    janked,
+                    target_fps,
+                } => {
+                    if janked {
+                        write!(file, ",")?;
+                        serde_json::to_writer(
+                            &mut file,
+                            &TraceEvent {
+                                name: "jank",
+                                ph: "i",
+                                s: Some("t"),
+                                ts: Some(us(at)),
+                                dur: None,
+                                pid,
+                                tid: Some(CONTROLLER_TID),
+                                args: None,
+                            },
+                        )?;
+                    }
+                    write!(file, ",")?;
+                    serde_json::to_writer(
+                        &mut file,
+                        &counter(
+                            "control",
+                            pid,
+                            us(at),
+                            Args::Control {
+                                khz: control,
+                                target_fps,
+                            },
+                        ),
+                    )?;
+                }
+            }
+        }
+        writeln!(file, "]}}")?;
+        file.flush()?;
+
+        Ok(path)
+    }
+}
+
+const fn metadata<'a>(name: &'a str, pid: u32, tid: i32, value: &'a str) -> TraceEvent<'a> {
+    TraceEvent {
+        name,
+        ph: "M",
+        s: None,
+        ts: None,
+        dur: None,
+        pid,
+        tid: Some(tid),
+        args: Some(Args::Name { name: value }),
+    }
+}
+
+const fn counter<'a>(name: &'a str, pid: u32, ts: f64, args: Args<'a>) -> TraceEvent<'a> {
+    TraceEvent {
+        name,
+        ph: "C",
+        s: None,
+        ts: Some(ts),
+        dur: None,
+        pid,
+        tid: None,
+        args: Some(args),
+    }
+}
+
+fn monotonic() -> Duration {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &raw mut time);
+    }
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+// Perfetto's default clock for system traces
+fn boottime_us() -> f64 {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_BOOTTIME, &raw mut time);
+    }
+    time.tv_sec as f64 * 1_000_000.0 + time.tv_nsec as f64 / 1000.0
+}
+
+fn prune(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut traces: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .collect();
+    traces.sort_by(|a, b| b.0.cmp(&a.0));
+
+    for (_, path) in traces.into_iter().skip(MAX_TRACES) {
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    env,
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+use parking_lot::{Mutex, const_mutex};
+
+const LOG_DIR: &str = "log";
+const LOG_FILE: &str = "fas_rs.log";
+const MAX_SIZE: u64 = 1024 * 1024;
+// Rotated files kept next to the current one, fas_rs.log.1 is the newest
+const MAX_ROTATED: usize = 2;
+
+static LOGGER: Logger = Logger {
+    levels: const_mutex(Levels {
+        global: LevelFilter::Info,
+        modules: Vec::new(),
+    }),
+    file: const_mutex(None),
+};
+
+struct Levels {
+    global: LevelFilter,
+    // Per module overrides set through the control socket
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Levels {
+    // The most specific override matching the target wins
+    fn level(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| matches_module(target, module))
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.global, |(_, level)| *level)
+    }
+
+    fn max(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.global, LevelFilter::max)
+    }
+}
+
+// Human readable lines go to stderr as before, structured key=value lines to a size rotated
+// file under the module directory for bug reports
+struct Logger {
+    levels: Mutex<Levels>,
+    file: Mutex<Option<RotatingFile>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.levels.lock().level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
+        let _ = writeln!(
+            io::stderr(),
+            "[{time}] [{}] [{}] {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        if let Some(file) = self.file.lock().as_mut() {
+            let mut line = format!(
+                "ts=\"{time}\" level={} target={} thread={}",
+                record.level().as_str().to_lowercase(),
+                record.target(),
+                thread::current().name().unwrap_or("unnamed")
+            );
+            let message = record.args().to_string();
+            let _ = writeln!(line, " msg=\"{}\"", message.escape_default());
+            file.write(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().as_mut() {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn write(&mut self, line: &[u8]) {
+        if self.size + line.len() as u64 > MAX_SIZE && self.rotate().is_err() {
+            return;
+        }
+
+        if self.file.write_all(line).is_ok() {
+            self.size += line.len() as u64;
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..MAX_ROTATED).rev() {
+            let _ = fs::rename(rotated(&self.path, index), rotated(&self.path, index + 1));
+        }
+        fs::rename(&self.path, rotated(&self.path, 1))?;
+
+        *self = Self::open(self.path.clone())?;
+        Ok(())
+    }
+}
+
+fn rotated(path: &Path, index: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(format!(".{index}"));
+    PathBuf::from(path)
+}
+
+// "looper" matches fas_rs_next::framework::scheduler::looper and everything below it
+fn matches_module(target: &str, module: &str) -> bool {
+    target == module
+        || target.starts_with(&format!("{module}::"))
+        || target.ends_with(&format!("::{module}"))
+        || target.contains(&format!("::{module}::"))
+}
+
+pub fn init() {
+    let file = env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(LOG_DIR)))
+        .and_then(|dir| {
+            fs::create_dir_all(&dir).ok()?;
+            RotatingFile::open(dir.join(LOG_FILE)).ok()
+        });
+    *LOGGER.file.lock() = file;
+
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(LevelFilter::Info);
+}
+
+// The level of modules without an override, from the config
+pub fn set_level(level: LevelFilter) {
+    let mut levels = LOGGER.levels.lock();
+    if levels.global != level {
+        levels.global = level;
+        log::set_max_level(levels.max());
+    }
+}
+
+// log_level <module> <off|error|warn|info|debug|trace|default>
+pub fn set_module_level(module: &str, level: &str) -> String {
+    let level = if level == "default" {
+        None
+    } else {
+        match LevelFilter::from_str(level) {
+            Ok(level) => Some(level),
+            Err(_) => return format!("invalid level: {level}"),
+        }
+    };
+
+    let mut levels = LOGGER.levels.lock();
+    levels.modules.retain(|(name, _)| name != module);
+    if let Some(level) = level {
+        levels.modules.push((module.to_string(), level));
+    }
+    log::set_max_level(levels.max());
+    String::from("ok")
+}
+
+// One "<module> <level>" per line, "*" is the default level
+pub fn module_levels() -> String {
+    let levels = LOGGER.levels.lock();
+    let mut lines = vec![format!("* {}", levels.global.as_str().to_lowercase())];
+    lines.extend(
+        levels
+            .modules
+            .iter()
+            .map(|(module, level)| format!("{module} {}", level.as_str().to_lowercase())),
+    );
+    lines.join("\n")
+}
@@ -28,12 +28,25 @@
 mod cpu_common;
 mod file_handler;
 mod framework;
+mod logger;
+mod props;
+mod quirks;
+mod root_env;
+mod safe_mode;
+mod snapshot;
+mod sysfs;
+mod watchdog;
 
-use std::{env, fs, io::prelude::*, process};
+use std::{
+    env, fs,
+    io::{self, prelude::*},
+    path::Path,
+    process::{self, Command},
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
-use env_logger::Builder;
-use log::{debug, error, warn};
+use anyhow::{Result, bail};
+use log::{debug, error, info, warn};
 use mimalloc::MiMalloc;
 
 use cpu_common::Controller;
@@ -43,6 +56,12 @@ use framework::prelude::*;
 static GLOBAL: MiMalloc = MiMalloc;
 
 const USER_CONFIG: &str = "/sdcard/Android/fas-rs/games.toml";
+const BENCH_TIME: u64 = 30;
+const BENCH_FPS: f64 = 60.0;
+// A daemon exiting sooner most likely failed to start, relaunching it is backed off
+const DAEMON_HEALTHY_TIME: Duration = Duration::from_secs(10);
+const RELAUNCH_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_RELAUNCH_BACKOFF: Duration = Duration::from_secs(300);
 
 fn main() -> Result<()> {
     let args: Vec<_> = env::args().collect();
@@ -55,53 +74,255 @@ fn main() -> Result<()> {
         println!("{new}");
 
         return Ok(());
-    } else if args[1] == "run" {
-        run(&args[2]).unwrap_or_else(|e| {
-            for cause in e.chain() {
-                error!("{cause:#?}");
+    } else if args[1] == "export" {
+        let local = fs::read_to_string(USER_CONFIG)?;
+        let learned = Learned::load().values(&args[2]);
+        let profile = Config::export_profile(&local, &args[2], learned)?;
+        fs::write(&args[3], profile)?;
+
+        return Ok(());
+    } else if args[1] == "import" {
+        let local = fs::read_to_string(USER_CONFIG)?;
+        let profile = fs::read_to_string(&args[2])?;
+
+        let new = Config::import_profile(&local, &profile, confirm_overwrite)?;
+        fs::write(USER_CONFIG, new)?;
+
+        let (pkg, values) = Config::profile_learned(&profile)?;
+        if !values.is_empty() {
+            Learned::load().import(&pkg, values, confirm_overwrite);
+        }
+
+        return Ok(());
+    } else if args[1] == "discover" {
+        let local = fs::read_to_string(USER_CONFIG)?;
+        let games = Config::discover_games(&local)?;
+        if games.is_empty() {
+            println!("No installed games missing from the game list");
+            return Ok(());
+        }
+
+        for (pkg, target_fps) in &games {
+            println!("\"{pkg}\" = {target_fps}");
+        }
+        print!("Add these {} games to the game list? [y/N] ", games.len());
+        let _ = io::stdout().flush();
+
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y") {
+            fs::write(USER_CONFIG, Config::add_games(&local, &games)?)?;
+            println!("Added {} games", games.len());
+        }
+
+        return Ok(());
+    } else if args[1] == "confirm" {
+        let mut learned = Learned::load();
+
+        if learned.confirm(&args[2]) {
+            learned.save();
+            println!("Restored the rolled back values of {}", args[2]);
+        } else {
+            println!("No rolled back values for {}", args[2]);
+        }
+
+        return Ok(());
+    } else if args[1] == "history" {
+        let days = args.get(2).map_or(Ok(7), |s| s.parse())?;
+        print!(
+            "{}",
+            framework::telemetry::query(days, args.get(3).map(String::as_str))
+        );
+
+        return Ok(());
+    } else if args[1] == "clear_safe_mode" {
+        if safe_mode::clear() {
+            println!("Left safe mode, fas engages again on the next start");
+        } else {
+            println!("Not in safe mode");
+        }
+
+        return Ok(());
+    } else if args[1] == "restore" {
+        println!("Restored {} nodes", snapshot::restore());
+
+        return Ok(());
+    } else if args[1] == "status" {
+        print!("{}", Control::request("status")?);
+
+        return Ok(());
+    } else if args[1] == "dump" || args[1] == "state" {
+        print!("{}", Control::request(&args[1])?);
+
+        return Ok(());
+    } else if args[1] == "log_level" {
+        print!("{}", Control::request(args[1..].join(" "))?);
+
+        return Ok(());
+    } else if args[1] == "mode" || args[1] == "tune" || args[1] == "profile" {
+        print!("{}", Control::request(args[1..].join(" "))?);
+
+        return Ok(());
+    } else if args[1] == "threads" || args[1] == "hooks" {
+        print!("{}", Control::request(&args[1])?);
+
+        return Ok(());
+    } else if args[1] == "ignore" {
+        print!(
+            "{}",
+            Control::request(format!("ignore {} {}", args[2], args[3]))?
+        );
+
+        return Ok(());
+    } else if args[1] == "bench" {
+        let pattern = args.get(2).map_or("steady", String::as_str).parse()?;
+        let seconds = args.get(3).map_or(Ok(BENCH_TIME), |s| s.parse())?;
+        let target_fps: f64 = args.get(4).map_or(Ok(BENCH_FPS), |s| s.parse())?;
+        // The frametimes are derived from it
+        if !target_fps.is_finite() || target_fps <= 0.0 {
+            bail!("target fps must be a positive number, got {target_fps}");
+        }
+        print!("{}", bench(pattern, seconds, target_fps)?);
+
+        return Ok(());
+    } else if args[1] == "train" {
+        #[cfg(feature = "predictor")]
+        {
+            let traces: Vec<_> = args[2..]
+                .iter()
+                .map(|trace| Path::new(trace).to_path_buf())
+                .collect();
+            print!("{}", train_predictor(&traces)?);
+        }
+        #[cfg(not(feature = "predictor"))]
+        println!("This build has no predictor, build it with the predictor feature");
+
+        return Ok(());
+    } else if args[1] == "run" || args[1] == "daemon" {
+        match run(&args[2], args[1] == "daemon") {
+            Ok(()) => safe_mode::end(),
+            Err(e) => {
+                for cause in e.chain() {
+                    error!("{cause:#?}");
+                }
+                error!("{:#?}", e.backtrace());
+                // The loop may have bailed out with clusters still clamped
+                snapshot::restore();
+                safe_mode::record_crash();
             }
-            error!("{:#?}", e.backtrace());
-        });
+        }
     }
 
     Ok(())
 }
 
-fn run<S>(std_path: S) -> Result<()>
+fn run<S>(std_path: S, on_demand_daemon: bool) -> Result<()>
 where
     S: AsRef<str>,
 {
-    let mut builder = Builder::new();
-
-    builder.format(|buf, record| {
-        let local_time = chrono::Local::now();
-        let time_str = local_time.format("%Y-%m-%d %H:%M:%S%.3f").to_string();
-
-        writeln!(
-            buf,
-            "[{}] [{}] [{}] {}",
-            time_str,
-            record.level(),
-            record.target(),
-            record.args()
-        )
-    });
-    builder.filter_level(log::LevelFilter::Info).init();
+    logger::init();
+    watchdog::init();
+    root_env::init();
 
     let std_path = std_path.as_ref();
 
-    let self_pid = process::id();
-    let _ = fs::write("/dev/cpuset/background/cgroup.procs", self_pid.to_string());
+    CAPABILITIES.log();
 
-    let config = Config::new(USER_CONFIG, std_path)?;
-    let cpu = Controller::new()?;
+    let restored = snapshot::restore();
+    if restored > 0 {
+        warn!("Restored {restored} nodes left behind by an unclean exit");
+    }
+
+    let mut config = Config::new(USER_CONFIG, std_path)?;
+    let stub = !on_demand_daemon && config.on_demand();
+
+    if !stub {
+        safe_mode::mark_running();
+    }
+    if safe_mode::begin() {
+        return Ok(());
+    }
+
+    quirks::load(&Path::new(std_path).with_file_name("quirks.toml"));
+
+    if CAPABILITIES.cpuset {
+        let self_pid = process::id();
+        let _ = fs::write("/dev/cpuset/background/cgroup.procs", self_pid.to_string());
+    }
+
+    if stub {
+        return run_stub(std_path, config);
+    }
+
+    let idle_exit_time = config.idle_exit_time();
+    let cpu = Controller::new(sysfs::root(), config.freq_step(), config.policy_topology())?;
 
     debug!("{cpu:#?}");
 
-    Scheduler::new()
-        .config(config)
-        .controller(cpu)
-        .start_run()?;
+    let mut scheduler = Scheduler::new().config(config).controller(cpu);
+    if on_demand_daemon {
+        scheduler = scheduler.idle_exit(idle_exit_time);
+    }
+    scheduler.start_run()?;
+
+    Ok(())
+}
+
+fn confirm_overwrite(key: &str, old: &toml::Value, new: &toml::Value) -> bool {
+    print!("{key}: local = {old}, profile = {new}, overwrite? [y/N] ");
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y")
+}
+
+fn bench(pattern: BenchPattern, seconds: u64, target_fps: f64) -> Result<String> {
+    logger::init();
+
+    // The module's own games.toml sits next to the binary
+    let std_path = env::current_exe()?.with_file_name("games.toml");
+    quirks::load(&std_path.with_file_name("quirks.toml"));
+    let mut config = Config::new(Path::new(USER_CONFIG), &std_path)?;
+    let cpu = Controller::new(sysfs::root(), config.freq_step(), config.policy_topology())?;
+
+    let report = Scheduler::new().config(config).controller(cpu).bench(
+        pattern,
+        Duration::from_secs(seconds),
+        target_fps,
+    )?;
+
+    Ok(report.to_string())
+}
+
+fn run_stub(std_path: &str, mut config: Config) -> Result<()> {
+    info!("Running as on-demand stub");
+    let exe = env::current_exe()?;
+    let mut on_demand = OnDemand::new();
+    let mut backoff = RELAUNCH_BACKOFF;
+
+    while on_demand.wait_for_game(&mut config) {
+        info!("Game launched, starting fas daemon");
+        let started = Instant::now();
+        let mut child = Command::new(&exe).args(["daemon", std_path]).spawn()?;
+        let status = OnDemand::supervise(&mut child);
+        info!("fas daemon exited: {status:?}");
+
+        if OnDemand::shutdown_requested() {
+            break;
+        }
+        if safe_mode::active() {
+            warn!("In safe mode, not launching the fas daemon again");
+            break;
+        }
+
+        if status.is_some_and(|status| status.success()) && started.elapsed() >= DAEMON_HEALTHY_TIME
+        {
+            backoff = RELAUNCH_BACKOFF;
+        } else {
+            warn!("fas daemon failed, launching it again in {backoff:?} at the earliest");
+            OnDemand::sleep(backoff);
+            backoff = (backoff * 2).min(MAX_RELAUNCH_BACKOFF);
+        }
+    }
 
     Ok(())
 }
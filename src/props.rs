@@ -0,0 +1,195 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    process::Command,
+    sync::{
+        LazyLock, Once,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{debug, info, warn};
+use parking_lot::{Mutex, const_mutex};
+
+use crate::file_handler;
+
+// Reads within this long of each other share one getprop, read-only props never change
+const CACHE_TIME: Duration = Duration::from_secs(1);
+const WATCH_POLL_TIME: Duration = Duration::from_secs(1);
+
+static CACHE: LazyLock<Mutex<HashMap<String, Cached>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static SUBSCRIBERS: Mutex<Vec<Subscriber>> = const_mutex(Vec::new());
+static WATCHER: Once = Once::new();
+static SETTER: LazyLock<Option<Sender<(String, String)>>> = LazyLock::new(|| {
+    let (sx, rx) = mpsc::channel::<(String, String)>();
+    thread::Builder::new()
+        .name("SetPropThread".into())
+        .spawn(move || {
+            for (name, value) in rx {
+                set(&name, &value);
+            }
+        })
+        .ok()?;
+    Some(sx)
+});
+
+struct Cached {
+    value: String,
+    time: Instant,
+}
+
+struct Subscriber {
+    name: String,
+    last: String,
+    sx: Sender<String>,
+}
+
+// Android system properties, empty when unset or off device
+pub fn get(name: &str) -> String {
+    if let Some(cached) = CACHE.lock().get(name)
+        && (name.starts_with("ro.") || cached.time.elapsed() < CACHE_TIME)
+    {
+        return cached.value.clone();
+    }
+
+    let value = read(name);
+    cache(name, &value);
+    value
+}
+
+// Blocks until setprop is done, returns whether it took
+pub fn set(name: &str, value: &str) -> bool {
+    if file_handler::dry_run() {
+        info!("dry run: setprop {name} {value:?}");
+        return true;
+    }
+    debug!("setprop {name} {value:?}");
+    match Command::new("setprop").args([name, value]).status() {
+        Ok(status) if status.success() => {
+            cache(name, value);
+            true
+        }
+        Ok(status) => {
+            warn!("Failed to set {name}: setprop exited with {status}");
+            false
+        }
+        Err(e) => {
+            warn!("Failed to set {name}: {e}");
+            false
+        }
+    }
+}
+
+// For callers on the control loop, setprop takes a while. Run in call order on one thread,
+// so the last value set for a prop is the one that sticks
+pub fn set_async(name: String, value: String) {
+    let Some(sx) = SETTER.as_ref() else {
+        set(&name, &value);
+        return;
+    };
+
+    if let Err(mpsc::SendError((name, value))) = sx.send((name, value)) {
+        set(&name, &value);
+    }
+}
+
+// Every prop starting with `prefix` with its value, sorted by name
+pub fn list(prefix: &str) -> Vec<(String, String)> {
+    let Ok(output) = Command::new("getprop").output() else {
+        return Vec::new();
+    };
+
+    // "[name]: [value]" per line
+    let mut props: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once("]: [")?;
+            let name = name.strip_prefix('[')?;
+            let value = value.strip_suffix(']')?;
+            name.starts_with(prefix)
+                .then(|| (name.to_string(), value.to_string()))
+        })
+        .collect();
+    props.sort_unstable();
+    props
+}
+
+// Receives the new value whenever the prop changes, polled on a shared thread. Dropping the
+// receiver unsubscribes
+pub fn subscribe(name: &str) -> Receiver<String> {
+    WATCHER.call_once(|| {
+        let _ = thread::Builder::new()
+            .name("PropWatchThread".into())
+            .spawn(watch);
+    });
+
+    let (sx, rx) = mpsc::channel();
+    SUBSCRIBERS.lock().push(Subscriber {
+        name: name.to_string(),
+        last: get(name),
+        sx,
+    });
+    rx
+}
+
+fn watch() {
+    loop {
+        thread::sleep(WATCH_POLL_TIME);
+
+        let mut subscribers = SUBSCRIBERS.lock();
+        let mut values: HashMap<String, String> = HashMap::new();
+        subscribers.retain_mut(|subscriber| {
+            let value = values
+                .entry(subscriber.name.clone())
+                .or_insert_with(|| read(&subscriber.name));
+            if *value == subscriber.last {
+                return true;
+            }
+
+            subscriber.last.clone_from(value);
+            subscriber.sx.send(value.clone()).is_ok()
+        });
+        drop(subscribers);
+
+        for (name, value) in values {
+            cache(&name, &value);
+        }
+    }
+}
+
+fn read(name: &str) -> String {
+    Command::new("getprop")
+        .arg(name)
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+fn cache(name: &str, value: &str) {
+    CACHE.lock().insert(
+        name.to_string(),
+        Cached {
+            value: value.to_string(),
+            time: Instant::now(),
+        },
+    );
+}
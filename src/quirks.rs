@@ -0,0 +1,241 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::{props, sysfs};
+
+const USER_QUIRKS: &str = "/sdcard/Android/fas-rs/quirks.toml";
+
+static QUIRKS: OnceLock<Quirks> = OnceLock::new();
+
+// Order of the scaling_min_freq and scaling_max_freq writes, some kernels reject a min above
+// the current max or the other way around
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteOrder {
+    // Whatever keeps min <= max at every step
+    #[default]
+    Auto,
+    MinFirst,
+    MaxFirst,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AlternatePath {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+// Overrides the write order of one policy, and waits between its min and max writes
+#[derive(Debug, Deserialize, Clone)]
+pub struct PolicyWrite {
+    pub policy: i32,
+    #[serde(default)]
+    pub write_order: WriteOrder,
+    // Milliseconds
+    #[serde(default)]
+    pub write_delay: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct Quirk {
+    // ro.board.platform
+    platform: String,
+    // ro.product.device, narrows the quirk down to one device of the platform
+    #[serde(default)]
+    device: Option<String>,
+    #[serde(default)]
+    write_order: WriteOrder,
+    #[serde(default)]
+    avoid_nodes: Vec<PathBuf>,
+    #[serde(default)]
+    alternate_paths: Vec<AlternatePath>,
+    #[serde(default)]
+    policies: Vec<PolicyWrite>,
+}
+
+impl Quirk {
+    fn matches(&self, platform: &str, device: &str) -> bool {
+        self.platform.eq_ignore_ascii_case(platform)
+            && self
+                .device
+                .as_ref()
+                .is_none_or(|quirk_device| quirk_device.eq_ignore_ascii_case(device))
+    }
+
+    fn same_key(&self, other: &Self) -> bool {
+        self.platform.eq_ignore_ascii_case(&other.platform) && self.device == other.device
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct QuirksFile {
+    #[serde(default)]
+    quirk: Vec<Quirk>,
+}
+
+// Device specific workarounds, looked up by platform instead of being hardcoded
+#[derive(Debug, Default)]
+pub struct Quirks {
+    write_order: WriteOrder,
+    avoid_nodes: Vec<PathBuf>,
+    alternate_paths: Vec<AlternatePath>,
+    policies: Vec<PolicyWrite>,
+}
+
+impl Quirks {
+    // The policy's own order wins over the platform wide one
+    pub fn write_order(&self, policy: i32) -> WriteOrder {
+        self.policy(policy)
+            .map(|write| write.write_order)
+            .filter(|order| *order != WriteOrder::Auto)
+            .unwrap_or(self.write_order)
+    }
+
+    // Between the first and the second limit write of the policy
+    pub fn write_delay(&self, policy: i32) -> Duration {
+        self.policy(policy).map_or(Duration::ZERO, |write| {
+            Duration::from_millis(write.write_delay)
+        })
+    }
+
+    fn policy(&self, policy: i32) -> Option<&PolicyWrite> {
+        self.policies.iter().find(|write| write.policy == policy)
+    }
+
+    // Where to write instead, None if the node must not be written at all. Quirks name the
+    // kernel paths, placed under root like every other node
+    pub fn resolve(&self, root: &Path, path: &Path) -> Option<PathBuf> {
+        if self
+            .avoid_nodes
+            .iter()
+            .any(|node| sysfs::resolve(root, node) == path)
+        {
+            return None;
+        }
+
+        Some(
+            self.alternate_paths
+                .iter()
+                .find(|alternate| sysfs::resolve(root, &alternate.from) == path)
+                .map_or_else(
+                    || path.to_path_buf(),
+                    |alternate| sysfs::resolve(root, &alternate.to),
+                ),
+        )
+    }
+}
+
+// The quirks shipped with the module, with the user's file replacing entries of the same
+// platform and device
+pub fn load(shipped: &Path) {
+    let platform = props::get("ro.board.platform");
+    let device = props::get("ro.product.device");
+
+    let mut quirks = read(shipped);
+    for user_quirk in read(Path::new(USER_QUIRKS)) {
+        quirks.retain(|quirk| !quirk.same_key(&user_quirk));
+        quirks.push(user_quirk);
+    }
+
+    let mut active = Quirks::default();
+    for quirk in quirks
+        .into_iter()
+        .filter(|quirk| quirk.matches(&platform, &device))
+    {
+        info!(
+            "Applying quirks for {}{}",
+            quirk.platform,
+            quirk
+                .device
+                .as_ref()
+                .map(|device| format!(" ({device})"))
+                .unwrap_or_default()
+        );
+        if quirk.write_order != WriteOrder::Auto {
+            active.write_order = quirk.write_order;
+        }
+        active.avoid_nodes.extend(quirk.avoid_nodes);
+        active.alternate_paths.extend(quirk.alternate_paths);
+        for write in quirk.policies {
+            active
+                .policies
+                .retain(|active| active.policy != write.policy);
+            active.policies.push(write);
+        }
+    }
+
+    let _ = QUIRKS.set(active);
+}
+
+pub fn get() -> &'static Quirks {
+    QUIRKS.get_or_init(Quirks::default)
+}
+
+fn read(path: &Path) -> Vec<Quirk> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    match toml::from_str::<QuirksFile>(&content) {
+        Ok(file) => file.quirk,
+        Err(e) => {
+            warn!("Failed to parse {}: {e}", path.display());
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_quirk_paths_under_root() {
+        let quirks = Quirks {
+            avoid_nodes: vec![PathBuf::from("/sys/avoided")],
+            alternate_paths: vec![AlternatePath {
+                from: PathBuf::from("/sys/from"),
+                to: PathBuf::from("/sys/to"),
+            }],
+            ..Quirks::default()
+        };
+        let root = Path::new("/tmp/fake");
+
+        assert_eq!(
+            quirks.resolve(root, Path::new("/tmp/fake/sys/avoided")),
+            None
+        );
+        assert_eq!(
+            quirks.resolve(root, Path::new("/tmp/fake/sys/from")),
+            Some(PathBuf::from("/tmp/fake/sys/to"))
+        );
+        assert_eq!(
+            quirks.resolve(root, Path::new("/tmp/fake/sys/other")),
+            Some(PathBuf::from("/tmp/fake/sys/other"))
+        );
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use std::{
+    env, fmt, fs,
+    path::Path,
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use crate::props;
+
+const WORK_DIR: &str = "/data/adb/fas_rs";
+const USER_DIR: &str = "/sdcard/Android/fas-rs";
+const SELINUX_ENFORCE: &str = "/sys/fs/selinux/enforce";
+const WAIT_TIMEOUT: Duration = Duration::from_secs(300);
+const WAIT_INTERVAL: Duration = Duration::from_secs(1);
+
+static ROOT_ENV: OnceLock<RootEnv> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootImpl {
+    Magisk,
+    KernelSu,
+    APatch,
+    Unknown,
+}
+
+impl fmt::Display for RootImpl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Magisk => write!(f, "Magisk"),
+            Self::KernelSu => write!(f, "KernelSU"),
+            Self::APatch => write!(f, "APatch"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl RootImpl {
+    // KernelSU and APatch export these to module scripts, the daemon inherits them from
+    // service.sh. The data directories are the fallback when started by hand
+    fn detect() -> (Self, Option<String>) {
+        if env::var("KSU").is_ok_and(|ksu| ksu == "true") {
+            return (Self::KernelSu, env::var("KSU_VER_CODE").ok());
+        }
+        if env::var("APATCH").is_ok_and(|apatch| apatch == "true") {
+            return (Self::APatch, env::var("APATCH_VER_CODE").ok());
+        }
+        if let Ok(version) = env::var("MAGISK_VER_CODE") {
+            return (Self::Magisk, Some(version));
+        }
+
+        // APatch and KernelSU may leave a magisk directory around for compatibility
+        let root = if Path::new("/data/adb/ap").exists() {
+            Self::APatch
+        } else if Path::new("/data/adb/ksu").exists() {
+            Self::KernelSu
+        } else if Path::new("/data/adb/magisk").exists() {
+            Self::Magisk
+        } else {
+            Self::Unknown
+        };
+        (root, None)
+    }
+
+    // The domains module/sepolicy.rule grants the vulkan layer socket to
+    const fn domain(self) -> &'static str {
+        match self {
+            Self::KernelSu => "su",
+            Self::Magisk | Self::APatch | Self::Unknown => "magisk",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RootEnv {
+    pub root: RootImpl,
+    pub version: Option<String>,
+    pub selinux: Option<bool>,
+    pub context: String,
+    // How long startup waited for the boot to complete and user storage to show up
+    pub waited: Duration,
+}
+
+impl RootEnv {
+    pub fn describe(&self) -> String {
+        format!(
+            "root: {}{}\nselinux: {}\ncontext: {}\nstartup wait: {:.1}s",
+            self.root,
+            self.version
+                .as_ref()
+                .map(|version| format!(" ({version})"))
+                .unwrap_or_default(),
+            match self.selinux {
+                Some(true) => "enforcing",
+                Some(false) => "permissive",
+                None => "disabled",
+            },
+            self.context,
+            self.waited.as_secs_f64()
+        )
+    }
+}
+
+// Detects the root implementation and holds startup until the system is ready for fas
+pub fn init() {
+    let (root, version) = RootImpl::detect();
+    let selinux = fs::read_to_string(SELINUX_ENFORCE)
+        .ok()
+        .map(|enforce| enforce.trim() == "1");
+    let context = fs::read_to_string("/proc/self/attr/current")
+        .map(|context| context.trim_end_matches(['\0', '\n']).to_string())
+        .unwrap_or_default();
+
+    info!(
+        "Running under {root}{}, selinux context {context}",
+        version
+            .as_ref()
+            .map(|version| format!(" ({version})"))
+            .unwrap_or_default()
+    );
+
+    // Under an enforcing policy the sepolicy.rule of the module only covers the expected domain
+    if selinux == Some(true)
+        && let Some(domain) = context.split(':').nth(2)
+        && domain != root.domain()
+        && root != RootImpl::Unknown
+    {
+        warn!(
+            "Running in selinux domain {domain} instead of {}, the sepolicy rules of the module may not apply",
+            root.domain()
+        );
+    }
+
+    if let Err(e) = fs::create_dir_all(WORK_DIR) {
+        warn!("Failed to create {WORK_DIR}: {e}");
+    }
+
+    let start = Instant::now();
+    wait_for("boot to complete", || {
+        props::get("sys.boot_completed") == "1"
+    });
+    // KernelSU and APatch may start the module before user storage is decrypted and mounted
+    if root != RootImpl::Magisk {
+        wait_for("user storage", || Path::new(USER_DIR).exists());
+    }
+
+    let _ = ROOT_ENV.set(RootEnv {
+        root,
+        version,
+        selinux,
+        context,
+        waited: start.elapsed(),
+    });
+}
+
+pub fn get() -> Option<&'static RootEnv> {
+    ROOT_ENV.get()
+}
+
+fn wait_for<F>(what: &str, ready: F)
+where
+    F: Fn() -> bool,
+{
+    if ready() {
+        return;
+    }
+
+    info!("Waiting for {what}");
+    let start = Instant::now();
+    while !ready() {
+        if start.elapsed() > WAIT_TIMEOUT {
+            warn!(
+                "Gave up waiting for {what} after {}s",
+                WAIT_TIMEOUT.as_secs()
+            );
+            return;
+        }
+        thread::sleep(WAIT_INTERVAL);
+    }
+    info!("Waited {:.1}s for {what}", start.elapsed().as_secs_f64());
+}
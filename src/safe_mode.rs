@@ -0,0 +1,105 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::{error, warn};
+
+const SAFE_MODE_MARKER: &str = "/data/adb/fas_rs/safe_mode";
+const CRASH_LOG: &str = "/data/adb/fas_rs/crashes";
+// Left behind by a run that didn't exit cleanly, e.g. killed by a native crash. Holds the
+// boot id, a marker of an earlier boot is from a reboot rather than a crash
+const RUNNING_MARKER: &str = "/data/adb/fas_rs/running";
+const BOOT_ID: &str = "/proc/sys/kernel/random/boot_id";
+const MAX_CRASHES: usize = 5;
+const CRASH_WINDOW: Duration = Duration::from_secs(600);
+
+// Only for the process driving the nodes, an on-demand stub outliving its daemon isn't a run
+pub fn mark_running() {
+    let boot_id = fs::read_to_string(BOOT_ID).unwrap_or_default();
+    if fs::read_to_string(RUNNING_MARKER).is_ok_and(|marker| marker == boot_id) {
+        record_crash();
+    }
+    let _ = fs::write(RUNNING_MARKER, boot_id);
+}
+
+// Too many crashes in a short time, most likely an incompatible kernel. Only the snapshot is
+// restored until the user clears the marker
+pub fn begin() -> bool {
+    let now = now();
+    let crashes: Vec<u64> = fs::read_to_string(CRASH_LOG)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .filter(|time| now.saturating_sub(*time) < CRASH_WINDOW.as_secs())
+        .collect();
+    let _ = fs::write(
+        CRASH_LOG,
+        crashes
+            .iter()
+            .map(|time| format!("{time}\n"))
+            .collect::<String>(),
+    );
+
+    if crashes.len() >= MAX_CRASHES && !Path::new(SAFE_MODE_MARKER).exists() {
+        let reason = format!(
+            "[{}] fas-rs-next crashed {} times within {} minutes, entered safe mode: nodes are only restored and fas stays off.\nRun 'fas-rs-next clear_safe_mode' to leave it.\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            crashes.len(),
+            CRASH_WINDOW.as_secs() / 60
+        );
+        error!("{}", reason.trim());
+        let _ = fs::write(SAFE_MODE_MARKER, reason);
+    }
+
+    if active() {
+        warn!("In safe mode, see {SAFE_MODE_MARKER}");
+        return true;
+    }
+    false
+}
+
+pub fn active() -> bool {
+    Path::new(SAFE_MODE_MARKER).exists()
+}
+
+// The run is over, cleanly or with a crash recorded by the caller
+pub fn end() {
+    let _ = fs::remove_file(RUNNING_MARKER);
+}
+
+pub fn record_crash() {
+    let crashes = fs::read_to_string(CRASH_LOG).unwrap_or_default();
+    let _ = fs::write(CRASH_LOG, format!("{crashes}{}\n", now()));
+    end();
+}
+
+// Returns whether it was in safe mode
+pub fn clear() -> bool {
+    let _ = fs::remove_file(CRASH_LOG);
+    fs::remove_file(SAFE_MODE_MARKER).is_ok()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,295 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs::{self, set_permissions},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::LazyLock,
+};
+
+use libc::{PRIO_PROCESS, pid_t};
+use log::{error, warn};
+use nix::{
+    sched::{CpuSet, sched_setaffinity},
+    unistd::Pid,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sys_mount::{UnmountFlags, unmount};
+
+const SNAPSHOT_PATH: &str = "/data/adb/fas_rs/snapshot.toml";
+const TASKS_PATH: &str = "/data/adb/fas_rs/snapshot_tasks.toml";
+const SCHED_PATH: &str = "/data/adb/fas_rs/snapshot_sched.toml";
+const SETTINGS_PATH: &str = "/data/adb/fas_rs/snapshot_settings.toml";
+
+// Original values of every node written since startup, persisted on each new entry so a
+// crashed daemon can still be undone by the next start or `fas-rs restore`
+static SNAPSHOT: LazyLock<Mutex<Snapshot>> = LazyLock::new(|| Mutex::new(Snapshot::default()));
+
+#[derive(Default)]
+struct Snapshot {
+    values: BTreeMap<String, String>,
+    seen: HashSet<PathBuf>,
+    // Threads moved out of a cgroup, by the tasks node of the cgroup they came from
+    tasks: BTreeMap<String, BTreeSet<pid_t>>,
+    // Original scheduling of reprioritized threads, by tid
+    sched: BTreeMap<String, Sched>,
+    // Changed system settings, by key
+    settings: BTreeMap<String, Setting>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Sched {
+    nice: Option<i32>,
+    cpus: Option<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Setting {
+    original: String,
+    // What it was changed to, a different value means someone else changed it since
+    applied: String,
+}
+
+// Must be called before the first write to a node
+pub fn record<P>(path: P)
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let mut snapshot = SNAPSHOT.lock();
+    if !snapshot.seen.insert(path.to_path_buf()) {
+        return;
+    }
+
+    // Write only nodes can't be restored anyway
+    let Ok(value) = fs::read_to_string(path) else {
+        return;
+    };
+    snapshot.values.insert(
+        path.display().to_string(),
+        selected(value.trim()).to_string(),
+    );
+
+    if let Err(e) = toml::to_string(&snapshot.values)
+        .map_err(anyhow::Error::from)
+        .and_then(|raw| fs::write(SNAPSHOT_PATH, raw).map_err(anyhow::Error::from))
+    {
+        error!("Failed to persist node snapshot: {e:?}");
+    }
+}
+
+// Must be called before the threads are moved out of the cgroup `origin`
+pub fn record_tasks<P>(origin: P, tids: &[pid_t])
+where
+    P: AsRef<Path>,
+{
+    let path = origin.as_ref().join("tasks").display().to_string();
+    let mut snapshot = SNAPSHOT.lock();
+    let recorded = snapshot.tasks.entry(path).or_default();
+    let len = recorded.len();
+    recorded.extend(tids);
+    if recorded.len() == len {
+        return;
+    }
+
+    if let Err(e) = toml::to_string(&snapshot.tasks)
+        .map_err(anyhow::Error::from)
+        .and_then(|raw| fs::write(TASKS_PATH, raw).map_err(anyhow::Error::from))
+    {
+        error!("Failed to persist cgroup snapshot: {e:?}");
+    }
+}
+
+// Moves the recorded threads back into the cgroups they came from, exited ones are skipped
+pub fn restore_tasks() {
+    let mut snapshot = SNAPSHOT.lock();
+    let mut tasks: BTreeMap<String, BTreeSet<pid_t>> = fs::read_to_string(TASKS_PATH)
+        .ok()
+        .and_then(|raw| toml::from_str(&raw).ok())
+        .unwrap_or_default();
+    tasks.append(&mut snapshot.tasks);
+
+    // A tasks node takes one tid per write
+    for (path, tids) in &tasks {
+        for tid in tids {
+            let _ = fs::write(path, tid.to_string());
+        }
+    }
+
+    let _ = fs::remove_file(TASKS_PATH);
+}
+
+// Must be called before changing the thread's nice value or affinity, the first record of a
+// tid wins
+pub fn record_sched(tid: pid_t, nice: Option<i32>, affinity: Option<&CpuSet>) {
+    let cpus = affinity.map(|affinity| {
+        (0..CpuSet::count())
+            .filter(|cpu| affinity.is_set(*cpu).unwrap_or(false))
+            .collect()
+    });
+    let mut snapshot = SNAPSHOT.lock();
+    if snapshot.sched.contains_key(&tid.to_string()) {
+        return;
+    }
+    snapshot.sched.insert(tid.to_string(), Sched { nice, cpus });
+
+    if let Err(e) = toml::to_string(&snapshot.sched)
+        .map_err(anyhow::Error::from)
+        .and_then(|raw| fs::write(SCHED_PATH, raw).map_err(anyhow::Error::from))
+    {
+        error!("Failed to persist scheduling snapshot: {e:?}");
+    }
+}
+
+// Gives the recorded threads their nice value and affinity back, exited ones are skipped
+pub fn restore_sched() {
+    let mut snapshot = SNAPSHOT.lock();
+    let mut sched: BTreeMap<String, Sched> = fs::read_to_string(SCHED_PATH)
+        .ok()
+        .and_then(|raw| toml::from_str(&raw).ok())
+        .unwrap_or_default();
+    sched.append(&mut snapshot.sched);
+
+    for (tid, sched) in &sched {
+        let Ok(tid) = tid.parse::<pid_t>() else {
+            continue;
+        };
+        if let Some(nice) = sched.nice {
+            unsafe {
+                libc::setpriority(PRIO_PROCESS, tid as u32, nice);
+            }
+        }
+        if let Some(cpus) = &sched.cpus {
+            let mut affinity = CpuSet::new();
+            for cpu in cpus {
+                let _ = affinity.set(*cpu);
+            }
+            let _ = sched_setaffinity(Pid::from_raw(tid), &affinity);
+        }
+    }
+
+    let _ = fs::remove_file(SCHED_PATH);
+}
+
+// Must be called before changing the system setting `key`, the original value of the first
+// record stays until the setting is forgotten
+pub fn record_setting(key: &str, original: &str, applied: &str) {
+    let mut snapshot = SNAPSHOT.lock();
+    let original = snapshot
+        .settings
+        .get(key)
+        .map_or_else(|| original.to_string(), |setting| setting.original.clone());
+    snapshot.settings.insert(
+        key.to_string(),
+        Setting {
+            original,
+            applied: applied.to_string(),
+        },
+    );
+    persist_settings(&snapshot.settings);
+}
+
+// The setting was given back, or was changed by someone else meanwhile
+pub fn forget_setting(key: &str) {
+    let mut snapshot = SNAPSHOT.lock();
+    if snapshot.settings.remove(key).is_some() {
+        persist_settings(&snapshot.settings);
+    }
+}
+
+fn persist_settings(settings: &BTreeMap<String, Setting>) {
+    if let Err(e) = toml::to_string(settings)
+        .map_err(anyhow::Error::from)
+        .and_then(|raw| fs::write(SETTINGS_PATH, raw).map_err(anyhow::Error::from))
+    {
+        error!("Failed to persist settings snapshot: {e:?}");
+    }
+}
+
+// Gives the recorded system settings their original values back, unless someone else changed
+// them since
+pub fn restore_settings() {
+    let mut snapshot = SNAPSHOT.lock();
+    let mut settings: BTreeMap<String, Setting> = fs::read_to_string(SETTINGS_PATH)
+        .ok()
+        .and_then(|raw| toml::from_str(&raw).ok())
+        .unwrap_or_default();
+    settings.append(&mut snapshot.settings);
+
+    for (key, setting) in &settings {
+        let current = Command::new("settings")
+            .args(["get", "system", key])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+        if current.ok().as_deref() != Some(setting.applied.as_str()) {
+            continue;
+        }
+
+        let status = if setting.original == "null" {
+            Command::new("settings")
+                .args(["delete", "system", key])
+                .status()
+        } else {
+            Command::new("settings")
+                .args(["put", "system", key, &setting.original])
+                .status()
+        };
+        if let Err(e) = status {
+            warn!("Failed to restore setting {key}: {e}");
+        }
+    }
+
+    let _ = fs::remove_file(SETTINGS_PATH);
+}
+
+// Writes back everything recorded by this or a previous crashed run, then forgets it
+pub fn restore() -> usize {
+    restore_tasks();
+    restore_sched();
+    restore_settings();
+
+    let mut snapshot = SNAPSHOT.lock();
+    let mut values: BTreeMap<String, String> = fs::read_to_string(SNAPSHOT_PATH)
+        .ok()
+        .and_then(|raw| toml::from_str(&raw).ok())
+        .unwrap_or_default();
+    values.append(&mut snapshot.values);
+
+    for (path, value) in &values {
+        let _ = unmount(path, UnmountFlags::DETACH);
+        let _ = set_permissions(path, PermissionsExt::from_mode(0o644));
+        if let Err(e) = fs::write(path, value) {
+            warn!("Failed to restore {path}: {e}");
+        }
+    }
+
+    snapshot.seen.clear();
+    let _ = fs::remove_file(SNAPSHOT_PATH);
+    values.len()
+}
+
+// Choice nodes read like "[mq-deadline] kyber none" but only take the choice back
+fn selected(value: &str) -> &str {
+    value
+        .split_whitespace()
+        .find_map(|choice| choice.strip_prefix('[')?.strip_suffix(']'))
+        .unwrap_or(value)
+}
@@ -0,0 +1,199 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    env,
+    fs::{self, File},
+    io,
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    thread,
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+
+const ROOT_ENV: &str = "FAS_RS_SYSFS_ROOT";
+// Reads of a node racing a hotplug or a driver reinit fail spuriously, tried this often
+const READ_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(5);
+
+// Kernel interfaces are looked up under this root. It is "/" unless FAS_RS_SYSFS_ROOT points
+// at a fake tree, which lets the control pipeline run against a temp dir off device
+static ROOT: LazyLock<PathBuf> =
+    LazyLock::new(|| env::var_os(ROOT_ENV).map_or_else(|| PathBuf::from("/"), PathBuf::from));
+
+pub fn root() -> &'static Path {
+    &ROOT
+}
+
+// Places an absolute kernel path like "/sys/class/devfreq" under root
+pub fn resolve<P>(root: &Path, path: P) -> PathBuf
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    root.join(path.strip_prefix("/").unwrap_or(path))
+}
+
+// For the one-off reads at startup, where one spurious failure would drop a whole policy
+pub fn read_retrying<P>(path: P) -> io::Result<String>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let mut attempt = 1;
+    loop {
+        match fs::read_to_string(path) {
+            Err(e) if attempt < READ_ATTEMPTS && e.kind() != io::ErrorKind::NotFound => {
+                attempt += 1;
+                thread::sleep(RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+// A node read often, kept open and read with pread rather than reopened every time. Sysfs and
+// procfs regenerate the content whenever it is read from the start.
+#[derive(Debug)]
+pub struct PreadNode {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+impl PreadNode {
+    pub fn new(path: PathBuf) -> Self {
+        let file = File::open(&path).ok();
+        Self {
+            path,
+            file: Mutex::new(file),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Reopened once when the read fails, hotplug removes and recreates nodes
+    pub fn read(&self) -> Option<String> {
+        let mut file = self.file.lock();
+        if let Some(content) = file.as_ref().and_then(read_from_start) {
+            return Some(content);
+        }
+
+        *file = File::open(&self.path).ok();
+        file.as_ref().and_then(read_from_start)
+    }
+
+    // Drops the open file, for when the node may have been recreated
+    pub fn invalidate(&self) {
+        *self.file.lock() = None;
+    }
+}
+
+fn read_from_start(file: &File) -> Option<String> {
+    let mut content = Vec::new();
+    let mut buf = [0; 4096];
+    loop {
+        let read = file.read_at(&mut buf, content.len() as u64).ok()?;
+        if read == 0 {
+            break;
+        }
+        content.extend_from_slice(&buf[..read]);
+    }
+
+    String::from_utf8(content).ok()
+}
+
+// A throwaway tree under the temp dir standing in for root, removed once dropped
+#[cfg(test)]
+pub struct FakeRoot(PathBuf);
+
+#[cfg(test)]
+impl FakeRoot {
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("fas-rs-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    // Creates the node at its kernel path under the fake root
+    pub fn write(&self, node: &str, content: &str) -> PathBuf {
+        let path = resolve(&self.0, node);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+}
+
+#[cfg(test)]
+impl Drop for FakeRoot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_places_kernel_paths_under_root() {
+        let root = Path::new("/tmp/fake");
+        assert_eq!(
+            resolve(root, "/sys/class/devfreq"),
+            PathBuf::from("/tmp/fake/sys/class/devfreq")
+        );
+        assert_eq!(
+            resolve(Path::new("/"), "/sys/class/devfreq"),
+            PathBuf::from("/sys/class/devfreq")
+        );
+    }
+
+    #[test]
+    fn read_retrying_gives_up_on_missing_nodes() {
+        let root = FakeRoot::new("read-retrying");
+        let path = root.write("/sys/devices/system/cpu/online", "0-7\n");
+
+        assert_eq!(read_retrying(&path).unwrap(), "0-7\n");
+        let missing = read_retrying(resolve(root.path(), "/sys/missing")).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn pread_node_follows_changes_and_recreation() {
+        let root = FakeRoot::new("pread-node");
+        let path = root.write("/sys/class/kgsl/kgsl-3d0/gpu_busy_percentage", "10 %");
+        let node = PreadNode::new(path.clone());
+        assert_eq!(node.read().as_deref(), Some("10 %"));
+
+        fs::write(&path, "42 %").unwrap();
+        assert_eq!(node.read().as_deref(), Some("42 %"));
+
+        fs::remove_file(&path).unwrap();
+        root.write("/sys/class/kgsl/kgsl-3d0/gpu_busy_percentage", "7 %");
+        node.invalidate();
+        assert_eq!(node.read().as_deref(), Some("7 %"));
+    }
+}
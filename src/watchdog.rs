@@ -0,0 +1,247 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    env,
+    fmt::Write as _,
+    fs, panic,
+    process::{self, Command},
+    sync::{OnceLock, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{error, warn};
+use parking_lot::{Mutex, const_mutex};
+
+use crate::{safe_mode, snapshot};
+
+const CRASH_REPORT: &str = "/data/adb/fas_rs/crash_report.txt";
+const POLL_TIME: Duration = Duration::from_secs(1);
+// The control loop feeds at least every few seconds even while suspended
+pub const STALL_TIME: Duration = Duration::from_secs(15);
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(2);
+const HISTORY_LEN: usize = 120;
+const ERRORS_LEN: usize = 20;
+// Restarts in a row before giving up, so a persistent fault can't restart forever
+const MAX_RESTARTS: u32 = 3;
+const RESTARTS_ENV: &str = "FAS_RS_RESTARTS";
+// Running this long counts as healthy again
+const HEALTHY_TIME: Duration = Duration::from_secs(600);
+
+static HEARTBEAT: Mutex<Option<Instant>> = const_mutex(None);
+static HISTORY: Mutex<VecDeque<Record>> = const_mutex(VecDeque::new());
+static ERRORS: Mutex<VecDeque<ErrorRecord>> = const_mutex(VecDeque::new());
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    frametime: Duration,
+    target_fps: Option<f64>,
+    control: isize,
+}
+
+#[derive(Debug)]
+struct ErrorRecord {
+    time: chrono::DateTime<chrono::Local>,
+    reason: String,
+    count: u32,
+}
+
+// Watches the control loop and panics: restores the nodes fas wrote, leaves a crash report
+// and restarts the daemon instead of leaving the device stuck at whatever fas last wrote
+pub fn init() {
+    STARTED.get_or_init(Instant::now);
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let thread = thread::current();
+        let name = thread.name().unwrap_or("unnamed");
+        report(&format!("thread {name} panicked: {info}"));
+        default_hook(info);
+
+        // The node writer is restarted by the file handler. Other worker threads only feed
+        // hints (refresh rate, top-app events, fallback frames) and stay stopped, their
+        // owners keep going on the last or default values. The control loop takes the daemon
+        // down
+        if name == "main" {
+            recover();
+        }
+    }));
+
+    let _ = thread::Builder::new()
+        .name("WatchdogThread".into())
+        .spawn(|| {
+            loop {
+                thread::sleep(POLL_TIME);
+                let stalled = HEARTBEAT
+                    .lock()
+                    .is_some_and(|heartbeat| heartbeat.elapsed() > STALL_TIME);
+                if stalled {
+                    report(&format!("control loop stalled for over {STALL_TIME:?}"));
+                    recover();
+                }
+            }
+        });
+}
+
+pub fn feed() {
+    *HEARTBEAT.lock() = Some(Instant::now());
+}
+
+// The loop is going away on purpose, e.g. for shutdown
+pub fn disarm() {
+    *HEARTBEAT.lock() = None;
+}
+
+pub fn record(frametime: Duration, target_fps: Option<f64>, control: isize) {
+    let mut history = HISTORY.lock();
+    if history.len() >= HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(Record {
+        frametime,
+        target_fps,
+        control,
+    });
+}
+
+// A recoverable error the control loop carried on past, kept for the crash report. Repeats
+// of the last one are only counted, so a persistently failing node doesn't flood the log
+pub fn error(reason: String) {
+    let mut errors = ERRORS.lock();
+    if let Some(last) = errors.back_mut()
+        && last.reason == reason
+    {
+        last.count += 1;
+        return;
+    }
+
+    warn!("{reason}");
+    if errors.len() >= ERRORS_LEN {
+        errors.pop_front();
+    }
+    errors.push_back(ErrorRecord {
+        time: chrono::Local::now(),
+        reason,
+        count: 1,
+    });
+}
+
+pub fn report(reason: &str) {
+    error!("{reason}, writing crash report to {CRASH_REPORT}");
+
+    let crash_report = format!(
+        "[{}] {reason}\n\nrecent errors:\n{}\nlast frames (frametime ms, target fps, control khz):\n{}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+        errors(),
+        history()
+    );
+
+    if let Err(e) = fs::write(CRASH_REPORT, crash_report) {
+        warn!("Failed to write crash report: {e}");
+    }
+}
+
+// One "<frametime ms> <target fps> <control khz>" per line, oldest first
+pub fn history() -> String {
+    let mut lines = String::new();
+    // Don't wait on a lock held by whatever got stuck
+    if let Some(history) = HISTORY.try_lock_for(Duration::from_millis(100)) {
+        for record in history.iter() {
+            let _ = writeln!(
+                lines,
+                "{:.2} {} {}",
+                record.frametime.as_secs_f64() * 1000.0,
+                record
+                    .target_fps
+                    .map_or_else(|| "-".to_string(), |fps| fps.to_string()),
+                record.control
+            );
+        }
+    }
+    lines
+}
+
+// One "[time] <reason> (xN)" per line, oldest first
+pub fn errors() -> String {
+    let mut lines = String::new();
+    if let Some(errors) = ERRORS.try_lock_for(Duration::from_millis(100)) {
+        for record in errors.iter() {
+            let _ = write!(
+                lines,
+                "[{}] {}",
+                record.time.format("%H:%M:%S%.3f"),
+                record.reason
+            );
+            if record.count > 1 {
+                let _ = write!(lines, " (x{})", record.count);
+            }
+            lines.push('\n');
+        }
+    }
+    lines
+}
+
+fn recover() -> ! {
+    disarm();
+
+    // The stuck thread may hold the snapshot, the restarted daemon restores it from disk anyway
+    let (sx, rx) = mpsc::channel();
+    let _ = thread::Builder::new()
+        .name("RecoverThread".into())
+        .spawn(move || {
+            let _ = sx.send(snapshot::restore());
+        });
+    match rx.recv_timeout(RESTORE_TIMEOUT) {
+        Ok(restored) => warn!("Restored {restored} nodes"),
+        Err(_) => warn!("Timed out restoring nodes, leaving it to the next start"),
+    }
+    safe_mode::record_crash();
+
+    // The on-demand stub relaunches its daemon itself, a second one would fight it for the nodes
+    if env::args().nth(1).is_some_and(|mode| mode == "daemon") {
+        warn!("Leaving the restart to the on-demand stub");
+        process::exit(1);
+    }
+
+    let restarts: u32 = env::var(RESTARTS_ENV)
+        .ok()
+        .and_then(|restarts| restarts.parse().ok())
+        .filter(|_| {
+            STARTED
+                .get()
+                .is_some_and(|started| started.elapsed() < HEALTHY_TIME)
+        })
+        .unwrap_or(0);
+    if restarts >= MAX_RESTARTS {
+        error!("Restarted {restarts} times in a row, giving up");
+        process::exit(1);
+    }
+
+    match env::current_exe().and_then(|exe| {
+        Command::new(exe)
+            .args(env::args().skip(1))
+            .env(RESTARTS_ENV, (restarts + 1).to_string())
+            .spawn()
+    }) {
+        Ok(_) => warn!("Restarted fas-rs-next"),
+        Err(e) => error!("Failed to restart fas-rs-next: {e}"),
+    }
+    process::exit(1);
+}
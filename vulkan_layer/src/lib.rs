@@ -0,0 +1,374 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+//! A Vulkan layer that reports the time between `vkQueuePresentKHR` calls to fas-rs-next
+//! over an abstract unix datagram socket, so Vulkan titles get precise frametimes without
+//! the libgui hooks.
+
+#![allow(non_snake_case, clippy::missing_safety_doc)]
+
+use std::{
+    collections::HashMap,
+    ffi::{CStr, c_char, c_void},
+    os::{
+        android::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixDatagram},
+    },
+    process, ptr,
+    sync::{LazyLock, Mutex},
+    time::Instant,
+};
+
+// Must match the daemon side in src/framework/scheduler/frame_source/vulkan.rs
+const SOCKET_NAME: &[u8] = b"fas_rs_vk";
+const LAYER_NAME: &[u8] = b"VK_LAYER_FAS_RS_frame_report";
+const LAYER_DESCRIPTION: &[u8] = b"Reports present timestamps to fas-rs-next";
+
+type VkResult = i32;
+type VkHandle = *mut c_void;
+type VoidFunction = Option<unsafe extern "system" fn()>;
+type GetInstanceProcAddr = unsafe extern "system" fn(VkHandle, *const c_char) -> VoidFunction;
+type GetDeviceProcAddr = unsafe extern "system" fn(VkHandle, *const c_char) -> VoidFunction;
+type CreateInstance =
+    unsafe extern "system" fn(*const c_void, *const c_void, *mut VkHandle) -> VkResult;
+type CreateDevice =
+    unsafe extern "system" fn(VkHandle, *const c_void, *const c_void, *mut VkHandle) -> VkResult;
+type QueuePresent = unsafe extern "system" fn(VkHandle, *const c_void) -> VkResult;
+
+const VK_SUCCESS: VkResult = 0;
+const VK_ERROR_INITIALIZATION_FAILED: VkResult = -3;
+const VK_STRUCTURE_TYPE_LOADER_INSTANCE_CREATE_INFO: i32 = 47;
+const VK_STRUCTURE_TYPE_LOADER_DEVICE_CREATE_INFO: i32 = 48;
+const VK_LAYER_LINK_INFO: i32 = 0;
+
+#[repr(C)]
+struct BaseInStructure {
+    s_type: i32,
+    p_next: *const BaseInStructure,
+}
+
+#[repr(C)]
+struct LayerInstanceLink {
+    p_next: *mut LayerInstanceLink,
+    next_get_instance_proc_addr: GetInstanceProcAddr,
+    next_get_physical_device_proc_addr: *const c_void,
+}
+
+#[repr(C)]
+struct LayerDeviceLink {
+    p_next: *mut LayerDeviceLink,
+    next_get_instance_proc_addr: GetInstanceProcAddr,
+    next_get_device_proc_addr: GetDeviceProcAddr,
+}
+
+#[repr(C)]
+struct LayerCreateInfo<L> {
+    s_type: i32,
+    p_next: *const c_void,
+    function: i32,
+    layer_info: *mut L,
+}
+
+#[repr(C)]
+pub struct LayerProperties {
+    layer_name: [c_char; 256],
+    spec_version: u32,
+    implementation_version: u32,
+    description: [c_char; 256],
+}
+
+#[derive(Clone, Copy)]
+struct DeviceDispatch {
+    get_device_proc_addr: GetDeviceProcAddr,
+    queue_present: Option<QueuePresent>,
+}
+
+struct Reporter {
+    socket: Option<UnixDatagram>,
+    address: Option<SocketAddr>,
+    last_present: Option<Instant>,
+}
+
+static INSTANCES: LazyLock<Mutex<HashMap<usize, GetInstanceProcAddr>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static DEVICES: LazyLock<Mutex<HashMap<usize, DeviceDispatch>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static REPORTER: LazyLock<Mutex<Reporter>> = LazyLock::new(|| {
+    Mutex::new(Reporter {
+        socket: UnixDatagram::unbound().ok(),
+        address: SocketAddr::from_abstract_name(SOCKET_NAME).ok(),
+        last_present: None,
+    })
+});
+
+// Dispatchable handles start with a pointer to the loader's dispatch table, which is
+// shared between a device and its queues
+unsafe fn dispatch_key(handle: VkHandle) -> usize {
+    unsafe { *handle.cast::<usize>() }
+}
+
+unsafe fn find_link<L>(create_info: *const c_void, s_type: i32) -> *mut LayerCreateInfo<L> {
+    let mut info = create_info.cast::<BaseInStructure>();
+    while !info.is_null() {
+        let layer_info = info.cast::<LayerCreateInfo<L>>().cast_mut();
+        unsafe {
+            if (*info).s_type == s_type && (*layer_info).function == VK_LAYER_LINK_INFO {
+                return layer_info;
+            }
+            info = (*info).p_next;
+        }
+    }
+    ptr::null_mut()
+}
+
+fn report_present() {
+    let Ok(mut reporter) = REPORTER.lock() else {
+        return;
+    };
+
+    let now = Instant::now();
+    let Some(last_present) = reporter.last_present.replace(now) else {
+        return;
+    };
+
+    if let (Some(socket), Some(address)) = (&reporter.socket, &reporter.address) {
+        let frametime = now.duration_since(last_present).as_nanos() as u64;
+        let mut message = [0; 12];
+        message[..4].copy_from_slice(&process::id().to_le_bytes());
+        message[4..].copy_from_slice(&frametime.to_le_bytes());
+        let _ = socket.send_to_addr(&message, address);
+    }
+}
+
+unsafe extern "system" fn create_instance(
+    create_info: *const c_void,
+    allocator: *const c_void,
+    instance: *mut VkHandle,
+) -> VkResult {
+    unsafe {
+        let link = find_link::<LayerInstanceLink>(
+            create_info,
+            VK_STRUCTURE_TYPE_LOADER_INSTANCE_CREATE_INFO,
+        );
+        if link.is_null() {
+            return VK_ERROR_INITIALIZATION_FAILED;
+        }
+
+        let next_gipa = (*(*link).layer_info).next_get_instance_proc_addr;
+        (*link).layer_info = (*(*link).layer_info).p_next;
+
+        let Some(next_create) = next_gipa(ptr::null_mut(), c"vkCreateInstance".as_ptr()) else {
+            return VK_ERROR_INITIALIZATION_FAILED;
+        };
+        let next_create: CreateInstance = std::mem::transmute(next_create);
+
+        let result = next_create(create_info, allocator, instance);
+        if result == VK_SUCCESS
+            && let Ok(mut instances) = INSTANCES.lock()
+        {
+            instances.insert(dispatch_key(*instance), next_gipa);
+        }
+        result
+    }
+}
+
+unsafe extern "system" fn create_device(
+    physical_device: VkHandle,
+    create_info: *const c_void,
+    allocator: *const c_void,
+    device: *mut VkHandle,
+) -> VkResult {
+    unsafe {
+        let link =
+            find_link::<LayerDeviceLink>(create_info, VK_STRUCTURE_TYPE_LOADER_DEVICE_CREATE_INFO);
+        if link.is_null() {
+            return VK_ERROR_INITIALIZATION_FAILED;
+        }
+
+        let next_gipa = (*(*link).layer_info).next_get_instance_proc_addr;
+        let next_gdpa = (*(*link).layer_info).next_get_device_proc_addr;
+        (*link).layer_info = (*(*link).layer_info).p_next;
+
+        let Some(next_create) = next_gipa(ptr::null_mut(), c"vkCreateDevice".as_ptr()) else {
+            return VK_ERROR_INITIALIZATION_FAILED;
+        };
+        let next_create: CreateDevice = std::mem::transmute(next_create);
+
+        let result = next_create(physical_device, create_info, allocator, device);
+        if result == VK_SUCCESS
+            && let Ok(mut devices) = DEVICES.lock()
+        {
+            let queue_present = next_gdpa(*device, c"vkQueuePresentKHR".as_ptr())
+                .map(|f| std::mem::transmute::<_, QueuePresent>(f));
+            devices.insert(
+                dispatch_key(*device),
+                DeviceDispatch {
+                    get_device_proc_addr: next_gdpa,
+                    queue_present,
+                },
+            );
+        }
+        result
+    }
+}
+
+unsafe extern "system" fn queue_present(queue: VkHandle, present_info: *const c_void) -> VkResult {
+    let dispatch = unsafe {
+        DEVICES
+            .lock()
+            .ok()
+            .and_then(|devices| devices.get(&dispatch_key(queue)).copied())
+    };
+    let Some(next_present) = dispatch.and_then(|dispatch| dispatch.queue_present) else {
+        return VK_ERROR_INITIALIZATION_FAILED;
+    };
+
+    report_present();
+    unsafe { next_present(queue, present_info) }
+}
+
+unsafe fn intercept(name: &CStr) -> VoidFunction {
+    let function: *const () = match name.to_bytes() {
+        b"vkGetInstanceProcAddr" => vkGetInstanceProcAddr as *const (),
+        b"vkGetDeviceProcAddr" => vkGetDeviceProcAddr as *const (),
+        b"vkCreateInstance" => create_instance as *const (),
+        b"vkCreateDevice" => create_device as *const (),
+        b"vkQueuePresentKHR" => queue_present as *const (),
+        b"vkEnumerateInstanceLayerProperties" => vkEnumerateInstanceLayerProperties as *const (),
+        b"vkEnumerateDeviceLayerProperties" => vkEnumerateDeviceLayerProperties as *const (),
+        b"vkEnumerateInstanceExtensionProperties" => {
+            vkEnumerateInstanceExtensionProperties as *const ()
+        }
+        b"vkEnumerateDeviceExtensionProperties" => {
+            vkEnumerateDeviceExtensionProperties as *const ()
+        }
+        _ => return None,
+    };
+    unsafe { Some(std::mem::transmute::<*const (), unsafe extern "system" fn()>(function)) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "system" fn vkGetInstanceProcAddr(
+    instance: VkHandle,
+    name: *const c_char,
+) -> VoidFunction {
+    unsafe {
+        let c_name = CStr::from_ptr(name);
+        if let Some(function) = intercept(c_name) {
+            return Some(function);
+        }
+
+        if instance.is_null() {
+            return None;
+        }
+
+        let next_gipa = INSTANCES
+            .lock()
+            .ok()
+            .and_then(|instances| instances.get(&dispatch_key(instance)).copied())?;
+        next_gipa(instance, name)
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "system" fn vkGetDeviceProcAddr(
+    device: VkHandle,
+    name: *const c_char,
+) -> VoidFunction {
+    unsafe {
+        let c_name = CStr::from_ptr(name);
+        match c_name.to_bytes() {
+            b"vkGetDeviceProcAddr" | b"vkQueuePresentKHR" => return intercept(c_name),
+            _ => (),
+        }
+
+        let dispatch = DEVICES
+            .lock()
+            .ok()
+            .and_then(|devices| devices.get(&dispatch_key(device)).copied())?;
+        (dispatch.get_device_proc_addr)(device, name)
+    }
+}
+
+fn copy_str(dest: &mut [c_char; 256], src: &[u8]) {
+    for (dest, src) in dest.iter_mut().zip(src) {
+        *dest = *src as c_char;
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "system" fn vkEnumerateInstanceLayerProperties(
+    count: *mut u32,
+    properties: *mut LayerProperties,
+) -> VkResult {
+    unsafe {
+        if properties.is_null() {
+            *count = 1;
+            return VK_SUCCESS;
+        }
+        if *count < 1 {
+            return VK_SUCCESS;
+        }
+
+        let mut layer = LayerProperties {
+            layer_name: [0; 256],
+            spec_version: 1 << 22,
+            implementation_version: 1,
+            description: [0; 256],
+        };
+        copy_str(&mut layer.layer_name, LAYER_NAME);
+        copy_str(&mut layer.description, LAYER_DESCRIPTION);
+        properties.write(layer);
+        *count = 1;
+        VK_SUCCESS
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "system" fn vkEnumerateDeviceLayerProperties(
+    _physical_device: VkHandle,
+    count: *mut u32,
+    properties: *mut LayerProperties,
+) -> VkResult {
+    unsafe { vkEnumerateInstanceLayerProperties(count, properties) }
+}
+
+// The layer doesn't provide any extension
+#[unsafe(no_mangle)]
+pub unsafe extern "system" fn vkEnumerateInstanceExtensionProperties(
+    _layer_name: *const c_char,
+    count: *mut u32,
+    _properties: *mut c_void,
+) -> VkResult {
+    unsafe {
+        *count = 0;
+    }
+    VK_SUCCESS
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "system" fn vkEnumerateDeviceExtensionProperties(
+    _physical_device: VkHandle,
+    _layer_name: *const c_char,
+    count: *mut u32,
+    _properties: *mut c_void,
+) -> VkResult {
+    unsafe {
+        *count = 0;
+    }
+    VK_SUCCESS
+}
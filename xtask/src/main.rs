@@ -38,6 +38,9 @@ struct Cli {
     /// Enable extension features
     #[clap(long, default_value = "false")]
     extension: bool,
+    /// Enable the frametime predictor
+    #[clap(long, default_value = "false")]
+    predictor: bool,
 }
 
 #[derive(Subcommand)]
@@ -98,7 +101,7 @@ fn main() -> Result<()> {
             check(release, verbose)?;
         }
         Commands::Build { release, verbose } => {
-            build(release, verbose, cli.extension)?;
+            build(release, verbose, cli.extension, cli.predictor)?;
         }
         Commands::Clean => {
             clean()?;
@@ -117,35 +120,29 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn build(release: bool, verbose: bool, extension: bool) -> Result<()> {
+fn build(release: bool, verbose: bool, extension: bool, predictor: bool) -> Result<()> {
     let temp_dir = temp_dir(release);
 
     let _ = fs::remove_dir_all(&temp_dir);
     fs::create_dir_all(&temp_dir)?;
 
     let mut cargo = cargo_ndk();
-    if extension {
-        cargo.args([
-            "build",
-            "--target",
-            "aarch64-linux-android",
-            "-Z",
-            "build-std",
-            "-Z",
-            "trim-paths",
-            "--features",
-            "extension",
-        ]);
-    } else {
-        cargo.args([
-            "build",
-            "--target",
-            "aarch64-linux-android",
-            "-Z",
-            "build-std",
-            "-Z",
-            "trim-paths",
-        ]);
+    cargo.args([
+        "build",
+        "--target",
+        "aarch64-linux-android",
+        "-Z",
+        "build-std",
+        "-Z",
+        "trim-paths",
+    ]);
+
+    let features: Vec<_> = [(extension, "extension"), (predictor, "predictor")]
+        .into_iter()
+        .filter_map(|(enabled, feature)| enabled.then_some(feature))
+        .collect();
+    if !features.is_empty() {
+        cargo.args(["--features", &features.join(",")]);
     }
 
     if release {
@@ -158,6 +155,29 @@ fn build(release: bool, verbose: bool, extension: bool) -> Result<()> {
 
     cargo.spawn()?.wait()?;
 
+    let mut cargo = cargo_ndk();
+    cargo.args([
+        "build",
+        "-p",
+        "fas-rs-vulkan-layer",
+        "--target",
+        "aarch64-linux-android",
+        "-Z",
+        "build-std",
+        "-Z",
+        "trim-paths",
+    ]);
+
+    if release {
+        cargo.arg("--release");
+    }
+
+    if verbose {
+        cargo.arg("--verbose");
+    }
+
+    cargo.spawn()?.wait()?;
+
     let module_dir = module_dir();
     dir::copy(
         &module_dir,
@@ -172,6 +192,13 @@ fn build(release: bool, verbose: bool, extension: bool) -> Result<()> {
         &file::CopyOptions::new().overwrite(true),
     )
     .unwrap();
+    fs::create_dir_all(temp_dir.join("vulkan"))?;
+    file::copy(
+        layer_path(release),
+        temp_dir.join("vulkan").join("libVkLayer_fas_rs.so"),
+        &file::CopyOptions::new().overwrite(true),
+    )
+    .unwrap();
 
     let build_type = if release { "release" } else { "debug" };
     let package_path = Path::new("output").join(format!("fas-rs-next({build_type}).zip"));
@@ -280,6 +307,13 @@ fn bin_path(release: bool) -> PathBuf {
         .join("fas-rs-next")
 }
 
+fn layer_path(release: bool) -> PathBuf {
+    Path::new("target")
+        .join("aarch64-linux-android")
+        .join(if release { "release" } else { "debug" })
+        .join("libVkLayer_fas_rs.so")
+}
+
 fn cargo_ndk() -> Command {
     let mut command = Command::new("cargo");
     command